@@ -1,22 +1,17 @@
+use advent_common::parsing::{in_file, parse_lines, read_lines, uint};
 use humantime::format_duration;
 use regex::Regex;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
 
-fn parse(lines: &[String]) -> Vec<f32> {
-    lines
-        .iter()
-        .map(|m| m.parse::<f32>().expect("a whole number"))
-        .collect()
+fn parse(lines: &[String]) -> AResult<Vec<f32>> {
+    // Masses are whole numbers, but the fuel calculations are done as floats
+    Ok(parse_lines(lines, uint)?)
 }
 
-fn part_a(lines: &[String]) -> f32 {
-    parse(lines).iter().map(|m| (m / 3.0).floor() - 2.0).sum()
+fn part_a(lines: &[String]) -> AResult<f32> {
+    Ok(parse(lines)?.iter().map(|m| (m / 3.0).floor() - 2.0).sum())
 }
 
 fn fuel_with_fuel(m: f32) -> f32 {
@@ -29,12 +24,12 @@ fn fuel_with_fuel(m: f32) -> f32 {
     fuel
 }
 
-fn part_b(lines: &[String]) -> f32 {
-    parse(lines)
+fn part_b(lines: &[String]) -> AResult<f32> {
+    Ok(parse(lines)?
         .iter()
         .map(|m| (m / 3.0).floor() - 2.0)
         .map(fuel_with_fuel)
-        .sum()
+        .sum())
 }
 
 fn main() -> AResult<()> {
@@ -48,13 +43,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -73,15 +74,32 @@ mod tests {
     100756";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let e = parse(&["12".to_string(), "1.5".to_string()]).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "line 2, column 2: expected end of line, found \".5\""
+        );
+
+        let e = parse(&["-14".to_string()]).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "line 1, column 1: expected unsigned integer, found \"-14\""
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()) as usize, 2 + 2 + 654 + 33583);
+        assert_eq!(part_a(lines.as_slice())? as usize, 2 + 2 + 654 + 33583);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
-        assert_eq!(part_b(&["14".to_string()]) as usize, 2);
-        assert_eq!(part_b(&["1969".to_string()]) as usize, 966);
-        assert_eq!(part_b(&["100756".to_string()]) as usize, 50346,);
+    fn test_b() -> AResult<()> {
+        assert_eq!(part_b(&["14".to_string()])? as usize, 2);
+        assert_eq!(part_b(&["1969".to_string()])? as usize, 966);
+        assert_eq!(part_b(&["100756".to_string()])? as usize, 50346,);
+        Ok(())
     }
 }
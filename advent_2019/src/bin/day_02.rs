@@ -1,48 +1,45 @@
+use advent_common::parsing::{in_file, read_lines};
+use anyhow::anyhow;
 use humantime::format_duration;
 use regex::Regex;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::time::Instant;
 
-use advent_2019::run_program;
+use advent_2019::{parse_program, run_program};
 
 type AResult<T> = anyhow::Result<T>;
 
-fn parse(lines: &[String]) -> Vec<usize> {
-    lines[0]
-        .split(',')
-        .map(str::parse)
-        .map(Result::unwrap)
-        .collect()
+/// Run the program with `noun` and `verb` in addresses 1 and 2, returning what ends
+/// up in address 0
+fn run_with(code: &[isize], noun: isize, verb: isize) -> AResult<isize> {
+    if code.len() < 3 {
+        return Err(anyhow!(
+            "the program needs at least 3 values to set a noun and verb, not {}",
+            code.len()
+        ));
+    }
+    let mut memory = code.to_vec();
+    memory[1] = noun;
+    memory[2] = verb;
+    run_program(&mut memory, &[])?;
+    Ok(memory[0])
 }
 
-fn part_a(lines: &[String]) -> usize {
-    let mut code = parse(lines);
-    code[1] = 12;
-    code[2] = 2;
-    run_program(&mut code);
-    code[0]
+fn part_a(lines: &[String]) -> AResult<isize> {
+    run_with(&parse_program(lines)?, 12, 2)
 }
 
-fn part_b(lines: &[String]) -> usize {
-    let code = parse(lines);
+fn part_b(lines: &[String]) -> AResult<isize> {
+    let code = parse_program(lines)?;
 
     for noun in 0..100 {
         for verb in 0..100 {
-            let mut memory = code.clone();
-            memory[1] = noun;
-            memory[2] = verb;
-            run_program(&mut memory);
-
-            if memory[0] == 19_690_720 {
-                return 100 * noun + verb;
+            if run_with(&code, noun, verb)? == 19_690_720 {
+                return Ok(100 * noun + verb);
             }
         }
     }
 
-    panic!("no solution found :(")
+    Err(anyhow!("no solution found :("))
 }
 
 fn main() -> AResult<()> {
@@ -56,16 +53,53 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_errors() {
+        let e = part_a(&["1,0,0,3,x".to_string()]).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "line 1, column 9: expected integer, found \"x\""
+        );
+
+        let e = part_a(&["1,0".to_string()]).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "the program needs at least 3 values to set a noun and verb, not 2"
+        );
+
+        // Address 12 is past the end of this program
+        let e = part_a(&["1,0,0,0,99".to_string()]).unwrap_err();
+        assert_eq!(e.to_string(), "address 12 is outside memory");
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
+        let code = [1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+        assert_eq!(run_with(&code, 9, 10)?, 3500);
+        Ok(())
+    }
+}
@@ -1,34 +1,40 @@
+use advent_common::parsing::{exact_lines, in_file, parse_all, read_lines, uint, PResult};
 use humantime::format_duration;
-use regex::Regex;
-use std::{
-    collections::{HashMap, HashSet},
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
+use nom::{
+    character::complete::{char, one_of},
+    combinator::{cut, map},
+    error::context,
+    multi::separated_list1,
+    sequence::pair,
 };
+use regex::Regex;
+use std::{collections::HashSet, time::Instant};
 
 type AResult<T> = anyhow::Result<T>;
 
+#[derive(Debug)]
 struct Instr {
     dir: char,
     dist: usize,
 }
 
-impl From<&str> for Instr {
-    fn from(value: &str) -> Self {
-        let mut chars = value.chars();
-        Instr {
-            dir: chars.next().unwrap(),
-            dist: chars.collect::<String>().parse().unwrap(),
-        }
-    }
+fn instr(input: &str) -> PResult<'_, Instr> {
+    map(
+        pair(context("a direction", one_of("RULD")), uint),
+        |(dir, dist)| Instr { dir, dist },
+    )(input)
+}
+
+fn wire(input: &str) -> PResult<'_, Vec<Instr>> {
+    separated_list1(char(','), cut(instr))(input)
 }
 
-fn parse(lines: &[String]) -> (Vec<Instr>, Vec<Instr>) {
-    (
-        lines[0].split(',').map(Instr::from).collect(),
-        lines[1].split(',').map(Instr::from).collect(),
-    )
+fn parse(lines: &[String]) -> AResult<(Vec<Instr>, Vec<Instr>)> {
+    let [w1, w2] = exact_lines(lines, "a second wire")?;
+    Ok((
+        parse_all(w1, wire)?,
+        parse_all(w2, wire).map_err(|e| e.offset_by(1))?,
+    ))
 }
 
 fn generate_coords(wire: &[Instr]) -> Vec<(isize, isize)> {
@@ -51,29 +57,29 @@ fn generate_coords(wire: &[Instr]) -> Vec<(isize, isize)> {
     coords
 }
 
-fn part_a(lines: &[String]) -> usize {
-    let (w1, w2) = parse(lines);
+fn part_a(lines: &[String]) -> AResult<usize> {
+    let (w1, w2) = parse(lines)?;
 
     let c1: HashSet<_> = HashSet::from_iter(generate_coords(&w1));
     let c2: HashSet<_> = HashSet::from_iter(generate_coords(&w2));
 
-    c1.intersection(&c2)
+    Ok(c1
+        .intersection(&c2)
         .map(|(row, col)| row.abs() + col.abs())
         .min()
         .unwrap()
-        .try_into()
-        .unwrap()
+        .try_into()?)
 }
 
-fn part_b(lines: &[String]) -> usize {
-    let (w1, w2) = parse(lines);
+fn part_b(lines: &[String]) -> AResult<usize> {
+    let (w1, w2) = parse(lines)?;
 
     let c1 = generate_coords(&w1);
     let c2 = generate_coords(&w2);
     let c1_set: HashSet<_> = c1.iter().copied().collect();
     let c2_set: HashSet<_> = c2.iter().copied().collect();
 
-    c1_set
+    Ok(c1_set
         .intersection(&c2_set)
         .map(|a| {
             let dist1 = c1.iter().position(|b| a == b).unwrap();
@@ -82,7 +88,7 @@ fn part_b(lines: &[String]) -> usize {
         })
         .map(|(d1, d2)| d1 + d2)
         .min()
-        .unwrap()
+        .unwrap())
 }
 
 fn main() -> AResult<()> {
@@ -96,13 +102,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -136,18 +148,41 @@ mod tests {
     ];
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let e = parse(&["R8,U5".to_string(), "U7,X6".to_string()]).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "line 2, column 4: expected a direction, found \"X6\""
+        );
+
+        let e = parse(&["R8,U5,L".to_string(), "U7".to_string()]).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "line 1, column 8: expected unsigned integer, found end of line"
+        );
+
+        let e = parse(&["R8,U5".to_string()]).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "line 2, column 1: expected a second wire, found end of input"
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         for (inp, actual, _) in TEST_INPUTS {
             let lines: Vec<_> = inp.lines().map(|l| l.trim().to_string()).collect();
-            assert_eq!(part_a(lines.as_slice()), *actual);
+            assert_eq!(part_a(lines.as_slice())?, *actual);
         }
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         for (inp, _, actual) in TEST_INPUTS {
             let lines: Vec<_> = inp.lines().map(|l| l.trim().to_string()).collect();
-            assert_eq!(part_b(lines.as_slice()), *actual);
+            assert_eq!(part_b(lines.as_slice())?, *actual);
         }
+        Ok(())
     }
 }
@@ -1,10 +1,15 @@
+use advent_common::parsing::{parse_all, uint, ParseError};
 use humantime::format_duration;
 use lazy_static::lazy_static;
+use nom::{character::complete::char, sequence::separated_pair};
 use regex::Regex;
 use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
 
+// The puzzle input is just this range
+const INPUT: &str = "165432-707912";
+
 lazy_static! {
     static ref DOUBLE_PATTERN: Regex = Regex::new("11|22|33|44|55|66|77|88|99|00").unwrap();
 }
@@ -36,23 +41,46 @@ fn is_valid_b(num_str: &str) -> Option<usize> {
     None
 }
 
-fn part_a() -> usize {
-    let start = 165_432;
-    let end = 707_912;
+/// The range of six digit passwords, as `start-end`
+fn parse(input: &str) -> AResult<(usize, usize)> {
+    let (start, end): (usize, usize) = parse_all(input, separated_pair(uint, char('-'), uint))?;
+    for (value, column) in [(start, 1), (end, input.find('-').unwrap_or(0) + 2)] {
+        if !(100_000..=999_999).contains(&value) {
+            Err(ParseError::new(
+                1,
+                column,
+                "a six digit number",
+                format!("{:?}", value.to_string()),
+            ))?;
+        }
+    }
+    if start > end {
+        Err(ParseError::new(
+            1,
+            1,
+            "a range that doesn't run backwards",
+            format!("{input:?}"),
+        ))?;
+    }
+    Ok((start, end))
+}
+
+fn part_a(input: &str) -> AResult<usize> {
+    let (start, end) = parse(input)?;
 
-    let DIGITS = "123456789".chars();
+    let digits = "123456789".chars();
     let mut valid = vec![];
 
-    for c1 in DIGITS.clone() {
-        for c2 in DIGITS.clone().filter(|x| x >= &c1) {
-            for c3 in DIGITS.clone().filter(|x| x >= &c2) {
-                for c4 in DIGITS.clone().filter(|x| x >= &c3) {
-                    for c5 in DIGITS.clone().filter(|x| x >= &c4) {
-                        for c6 in DIGITS.clone().filter(|x| x >= &c5) {
+    for c1 in digits.clone() {
+        for c2 in digits.clone().filter(|x| x >= &c1) {
+            for c3 in digits.clone().filter(|x| x >= &c2) {
+                for c4 in digits.clone().filter(|x| x >= &c3) {
+                    for c5 in digits.clone().filter(|x| x >= &c4) {
+                        for c6 in digits.clone().filter(|x| x >= &c5) {
                             if let Some(the_num) = is_valid_a(&format!("{c1}{c2}{c3}{c4}{c5}{c6}"))
                             {
                                 if the_num > end {
-                                    return valid.len();
+                                    return Ok(valid.len());
                                 }
 
                                 if the_num >= start {
@@ -66,26 +94,25 @@ fn part_a() -> usize {
         }
     }
 
-    panic!("Nothing found")
+    Ok(valid.len())
 }
 
-fn part_b() -> usize {
-    let start = 165_432;
-    let end = 707_912;
+fn part_b(input: &str) -> AResult<usize> {
+    let (start, end) = parse(input)?;
 
-    let DIGITS = "123456789".chars();
+    let digits = "123456789".chars();
     let mut valid = vec![];
 
-    for c1 in DIGITS.clone() {
-        for c2 in DIGITS.clone().filter(|x| x >= &c1) {
-            for c3 in DIGITS.clone().filter(|x| x >= &c2) {
-                for c4 in DIGITS.clone().filter(|x| x >= &c3) {
-                    for c5 in DIGITS.clone().filter(|x| x >= &c4) {
-                        for c6 in DIGITS.clone().filter(|x| x >= &c5) {
+    for c1 in digits.clone() {
+        for c2 in digits.clone().filter(|x| x >= &c1) {
+            for c3 in digits.clone().filter(|x| x >= &c2) {
+                for c4 in digits.clone().filter(|x| x >= &c3) {
+                    for c5 in digits.clone().filter(|x| x >= &c4) {
+                        for c6 in digits.clone().filter(|x| x >= &c5) {
                             if let Some(the_num) = is_valid_b(&format!("{c1}{c2}{c3}{c4}{c5}{c6}"))
                             {
                                 if the_num > end {
-                                    return valid.len();
+                                    return Ok(valid.len());
                                 }
 
                                 if the_num >= start {
@@ -99,7 +126,7 @@ fn part_b() -> usize {
         }
     }
 
-    panic!("Nothing found")
+    Ok(valid.len())
 }
 
 fn main() -> AResult<()> {
@@ -114,11 +141,48 @@ fn main() -> AResult<()> {
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a());
-    println!("Part B result = {}", part_b());
+    println!("Part A result = {}", part_a(INPUT)?);
+    println!("Part B result = {}", part_b(INPUT)?);
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse("165432:707912").unwrap_err().to_string(),
+            "line 1, column 7: expected '-', found \":707912\""
+        );
+        assert_eq!(
+            parse("165432-7079").unwrap_err().to_string(),
+            "line 1, column 8: expected a six digit number, found \"7079\""
+        );
+        assert_eq!(
+            parse("707912-165432").unwrap_err().to_string(),
+            "line 1, column 1: expected a range that doesn't run backwards, found \"707912-165432\""
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
+        assert_eq!(is_valid_a("111111"), Some(111_111));
+        assert_eq!(is_valid_a("123789"), None);
+        assert_eq!(part_a("111110-111122")?, 10);
+        Ok(())
+    }
+
+    #[test]
+    fn test_b() -> AResult<()> {
+        assert_eq!(is_valid_b("112233"), Some(112_233));
+        assert_eq!(is_valid_b("123444"), None);
+        assert_eq!(is_valid_b("111122"), Some(111_122));
+        Ok(())
+    }
+}
@@ -1,36 +1,36 @@
-use advent_2019::run_program;
+use advent_2019::{parse_program, run_program};
+use advent_common::parsing::{in_file, read_lines};
+use anyhow::anyhow;
 use humantime::format_duration;
 use regex::Regex;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
 
-fn parse(lines: &[String]) -> Vec<isize> {
-    return lines[0]
-        .split(',')
-        .map(str::parse)
-        .map(Result::unwrap)
-        .collect();
+/// Run the diagnostic program for `system`, returning the diagnostic code it ends
+/// with as long as every test before that passed (output zero)
+fn diagnose(lines: &[String], system: isize) -> AResult<isize> {
+    let mut memory = parse_program(lines)?;
+    let output = run_program(&mut memory, &[system])?;
+    let (&code, tests) = output
+        .split_last()
+        .ok_or_else(|| anyhow!("the program didn't output a diagnostic code"))?;
+    if let Some(i) = tests.iter().position(|&t| t != 0) {
+        return Err(anyhow!(
+            "diagnostic test {} failed with {}",
+            i + 1,
+            tests[i]
+        ));
+    }
+    Ok(code)
 }
 
-fn part_a(lines: &[String]) -> isize {
-    let mut memory = parse(lines);
-    let output = run_program(&mut memory, &[1]);
-    let dc = *output.iter().last().unwrap();
-    assert_eq!(output.iter().sum::<isize>(), dc, "Error in execution");
-    dc
+fn part_a(lines: &[String]) -> AResult<isize> {
+    diagnose(lines, 1)
 }
 
-fn part_b(lines: &[String]) -> isize {
-    let mut memory = parse(lines);
-    let output = run_program(&mut memory, &[5]);
-    let dc = *output.iter().last().unwrap();
-    assert_eq!(output.iter().sum::<isize>(), dc, "Error in execution");
-    dc
+fn part_b(lines: &[String]) -> AResult<isize> {
+    diagnose(lines, 5)
 }
 
 fn main() -> AResult<()> {
@@ -44,16 +44,58 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_errors() {
+        let e = part_a(&["3,0,4,0,99,".to_string()]).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "line 1, column 12: expected integer, found end of line"
+        );
+
+        let e = part_a(&["3,0,4,0,99".to_string(), "99".to_string()]).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "line 2, column 1: expected end of input, found \"99\""
+        );
+
+        let e = part_a(&["3,0,4,0,42".to_string()]).unwrap_err();
+        assert_eq!(e.to_string(), "unknown opcode 42 at index 4");
+    }
+
+    #[test]
+    fn test_diagnose() -> AResult<()> {
+        // Echo the system ID back as the diagnostic code
+        assert_eq!(part_a(&["3,0,4,0,99".to_string()])?, 1);
+        assert_eq!(part_b(&["104,0,104,0,3,0,4,0,99".to_string()])?, 5);
+
+        let e = part_a(&["104,0,104,3,104,7,99".to_string()]).unwrap_err();
+        assert_eq!(e.to_string(), "diagnostic test 2 failed with 3");
+        let e = part_a(&["3,0,99".to_string()]).unwrap_err();
+        assert_eq!(e.to_string(), "the program didn't output a diagnostic code");
+        Ok(())
+    }
+}
@@ -1,134 +1,174 @@
-#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
-fn get_param_value(memory: &mut [isize], p_modes: &[char], ip: usize, offset: usize) -> isize {
-    match p_modes.get(offset - 1).unwrap_or(&'0') {
-        '0' => memory[memory[ip + offset] as usize], // Position Mode
-        '1' => memory[ip + offset],                  // Immediate Mode
-        x => panic!("unknown parameter mode {x}"),
+use advent_common::parsing::{exact_lines, int, parse_all, ParseError};
+use anyhow::anyhow;
+use nom::{character::complete::char, combinator::cut, multi::separated_list1};
+
+/// Read an Intcode program - a single line of comma separated integers
+///
+/// # Errors
+///
+/// Returns an error if the input isn't exactly one line of integers
+pub fn parse_program(lines: &[String]) -> Result<Vec<isize>, ParseError> {
+    let [program] = exact_lines(lines, "an Intcode program")?;
+    parse_all(program, separated_list1(char(','), cut(int)))
+}
+
+/// The value at `at`, if it's inside memory
+fn read(memory: &[isize], at: usize) -> anyhow::Result<isize> {
+    memory
+        .get(at)
+        .copied()
+        .ok_or_else(|| anyhow!("address {at} is outside memory"))
+}
+
+/// A parameter used as an address
+fn address(value: isize) -> anyhow::Result<usize> {
+    usize::try_from(value).map_err(|_| anyhow!("{value} is not a valid address"))
+}
+
+/// Parameter `n` (from 1) of the instruction at `ip`, in position or immediate mode
+fn get_param_value(memory: &[isize], modes: isize, ip: usize, n: u32) -> anyhow::Result<isize> {
+    let param = read(memory, ip + n as usize)?;
+    match modes / 10_isize.pow(n - 1) % 10 {
+        0 => read(memory, address(param)?), // Position Mode
+        1 => Ok(param),                     // Immediate Mode
+        x => Err(anyhow!("unknown parameter mode {x} at index {ip}")),
     }
 }
 
+/// Store `value` at the address in parameter `n` (from 1), which is never in
+/// immediate mode
+fn write(memory: &mut [isize], ip: usize, n: usize, value: isize) -> anyhow::Result<()> {
+    let at = address(read(memory, ip + n)?)?;
+    *memory
+        .get_mut(at)
+        .ok_or_else(|| anyhow!("address {at} is outside memory"))? = value;
+    Ok(())
+}
+
 /// Intcode computer for Advent 2019
 ///
 /// ip = Instruction Pointer
 ///
-/// # Panics
+/// # Errors
 ///
-/// Will panic if the opcode is unknown
-#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
-pub fn run_program(memory: &mut [isize], input: &[isize]) -> Vec<isize> {
+/// If the program has an unknown opcode or parameter mode, reads or writes outside
+/// memory, runs out of input or runs off the end without halting
+pub fn run_program(memory: &mut [isize], input: &[isize]) -> anyhow::Result<Vec<isize>> {
     let mut ip = 0usize;
     let mut in_it = input.iter();
     let mut output: Vec<isize> = vec![];
 
-    while memory[ip] != 99 {
-        let step;
-
-        // Unpack the opcode
-        let op_str = format!("{:02}", memory[ip]);
-        let op_vec: Vec<_> = op_str.chars().collect();
+    loop {
+        // The opcode is the last two digits, and the parameter modes the rest
+        let instruction = read(memory, ip)?;
+        let (op, modes) = (instruction % 100, instruction / 100);
+        let param = |n| get_param_value(memory, modes, ip, n);
 
-        // Op is the last two chars of the op_str
-        let op: usize = format!("{}{}", op_vec[op_vec.len() - 2], op_vec[op_vec.len() - 1])
-            .parse()
-            .unwrap();
-        let p_modes: Vec<char> = op_vec.into_iter().rev().skip(2).collect();
-
-        match op {
+        ip = match op {
             // Addition
             1 => {
-                let a = get_param_value(memory, &p_modes, ip, 1);
-                let b = get_param_value(memory, &p_modes, ip, 2);
-                let t = memory[ip + 3]; // Never in immediate mode
-
-                memory[t as usize] = a + b;
-                step = 4;
+                let v = param(1)? + param(2)?;
+                write(memory, ip, 3, v)?;
+                ip + 4
             }
             // Multiplication
             2 => {
-                let a = get_param_value(memory, &p_modes, ip, 1);
-                let b = get_param_value(memory, &p_modes, ip, 2);
-                let t = memory[ip + 3]; // Never in immediate mode
-
-                memory[t as usize] = a * b;
-                step = 4;
+                let v = param(1)? * param(2)?;
+                write(memory, ip, 3, v)?;
+                ip + 4
             }
             // Input
             3 => {
-                let t = memory[ip + 1]; // Never in immediate mode
-                let v = *in_it.next().expect("an input value");
-                memory[t as usize] = v;
-                step = 2;
+                let v = *in_it
+                    .next()
+                    .ok_or_else(|| anyhow!("ran out of input at index {ip}"))?;
+                write(memory, ip, 1, v)?;
+                ip + 2
             }
             // Output
             4 => {
-                let v = get_param_value(memory, &p_modes, ip, 1);
-                output.push(v);
-                step = 2;
+                output.push(param(1)?);
+                ip + 2
             }
             // Jump-if-true
-            5 => {
-                let cond = get_param_value(memory, &p_modes, ip, 1);
-                let t = get_param_value(memory, &p_modes, ip, 2);
-                if cond == 0 {
-                    step = 3; // Don't Jump if 0
-                } else {
-                    ip = t as usize; // jump!
-                    step = 0;
-                }
-            }
+            5 => match param(1)? {
+                0 => ip + 3, // Don't Jump if 0
+                _ => address(param(2)?)?,
+            },
             // jump-if-false
-            6 => {
-                let cond = get_param_value(memory, &p_modes, ip, 1);
-                let t = get_param_value(memory, &p_modes, ip, 2);
-                if cond == 0 {
-                    ip = t as usize; // jump if 0
-                    step = 0;
-                } else {
-                    step = 3; // Don't Jump
-                }
-            }
+            6 => match param(1)? {
+                0 => address(param(2)?)?, // jump if 0
+                _ => ip + 3,
+            },
             // less than
             7 => {
-                let a = get_param_value(memory, &p_modes, ip, 1);
-                let b = get_param_value(memory, &p_modes, ip, 2);
-                let t = memory[ip + 3] as usize;
-
-                memory[t] = (a < b).into();
-                step = 4;
+                let v = (param(1)? < param(2)?).into();
+                write(memory, ip, 3, v)?;
+                ip + 4
             }
             // Equals
             8 => {
-                let a = get_param_value(memory, &p_modes, ip, 1);
-                let b = get_param_value(memory, &p_modes, ip, 2);
-                let t = memory[ip + 3] as usize;
-
-                memory[t] = (a == b).into();
-                step = 4;
+                let v = (param(1)? == param(2)?).into();
+                write(memory, ip, 3, v)?;
+                ip + 4
             }
-
-            x => panic!("Unknown op code {x} at index {ip}"),
-        }
-
-        ip += step;
+            99 => return Ok(output),
+            _ => return Err(anyhow!("unknown opcode {instruction} at index {ip}")),
+        };
     }
-
-    output
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_program() {
+        assert_eq!(parse_program(&["1,-2,99".to_string()]), Ok(vec![1, -2, 99]));
+
+        let e = parse_program(&["1,2,,99".to_string()]).unwrap_err();
+        assert_eq!((e.line, e.column, e.expected.as_str()), (1, 5, "integer"));
+
+        let e = parse_program(&[]).unwrap_err();
+        assert_eq!(e.expected, "an Intcode program");
+
+        let e = parse_program(&["1,2".to_string(), "3".to_string()]).unwrap_err();
+        assert_eq!((e.line, e.column), (2, 1));
+    }
+
+    #[test]
+    fn test_bad_programs() {
+        let run = |mut memory: Vec<isize>, input: &[isize]| {
+            run_program(&mut memory, input).unwrap_err().to_string()
+        };
+        assert_eq!(
+            run(vec![1, 0, 0, 0, 42], &[]),
+            "unknown opcode 42 at index 4"
+        );
+        assert_eq!(run(vec![1, 0, 0, 0], &[]), "address 4 is outside memory");
+        assert_eq!(
+            run(vec![1, 0, 9, 0, 99], &[]),
+            "address 9 is outside memory"
+        );
+        assert_eq!(run(vec![1, -1, 0, 0, 99], &[]), "-1 is not a valid address");
+        assert_eq!(run(vec![3, 0, 99], &[]), "ran out of input at index 0");
+        assert_eq!(
+            run(vec![201, 0, 0, 0, 99], &[]),
+            "unknown parameter mode 2 at index 0"
+        );
+    }
+
     #[test]
     fn test_day02_intcode() {
         let mut memory = vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
-        run_program(&mut memory, &[]);
+        run_program(&mut memory, &[]).unwrap();
         assert_eq!(memory[0], 3500);
     }
 
     #[test]
     fn test_day05_input() {
         let mut memory = vec![3, 0, 4, 0, 99];
-        let output = run_program(&mut memory, &[50]);
+        let output = run_program(&mut memory, &[50]).unwrap();
         assert_eq!(memory[0], 50);
         assert_eq!(output, vec![50]);
     }
@@ -136,52 +176,52 @@ mod tests {
     #[test]
     fn test_day_05_pmode() {
         let mut memory = vec![1002, 4, 3, 4, 33];
-        let output = run_program(&mut memory, &[]);
+        run_program(&mut memory, &[]).unwrap();
         assert_eq!(memory[4], 99);
     }
 
     #[test]
     fn test_day_05_negatives() {
         let mut memory = vec![1101, 100, -1, 4, 0];
-        let output = run_program(&mut memory, &[]);
+        run_program(&mut memory, &[]).unwrap();
         assert_eq!(memory[4], 99);
     }
 
     #[test]
     fn test_day_05_eq() {
         let mut memory = vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
-        let mut output = run_program(&mut memory, &[8]);
+        let mut output = run_program(&mut memory, &[8]).unwrap();
         assert_eq!(output[0], 1, "Equals test failed"); // 8 == 8
 
         memory = vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
-        output = run_program(&mut memory, &[7]);
+        output = run_program(&mut memory, &[7]).unwrap();
         assert_eq!(output[0], 0, "Non-equals test failed"); // 7 != 8
 
         memory = vec![3, 3, 1108, -1, 8, 3, 4, 3, 99];
-        output = run_program(&mut memory, &[8]);
+        output = run_program(&mut memory, &[8]).unwrap();
         assert_eq!(output[0], 1, "Equals test failed"); // 8 == 8
 
         memory = vec![3, 3, 1108, -1, 8, 3, 4, 3, 99];
-        output = run_program(&mut memory, &[7]);
+        output = run_program(&mut memory, &[7]).unwrap();
         assert_eq!(output[0], 0, "Non-equals test failed"); // 7 != 8
     }
 
     #[test]
     fn test_day_05_less_than() {
         let mut memory = vec![3, 9, 7, 9, 10, 9, 4, 9, 99, -1, 8];
-        let mut output = run_program(&mut memory, &[8]);
+        let mut output = run_program(&mut memory, &[8]).unwrap();
         assert_eq!(output[0], 0, "wrong 8 !< 8 pos mode"); // 8 not < 8
 
         memory = vec![3, 9, 7, 9, 10, 9, 4, 9, 99, -1, 8];
-        output = run_program(&mut memory, &[7]);
+        output = run_program(&mut memory, &[7]).unwrap();
         assert_eq!(output[0], 1, "wrong 7 < 8 pos mode"); // 7 is < 8
 
         memory = vec![3, 3, 1107, -1, 8, 3, 4, 3, 99];
-        output = run_program(&mut memory, &[8]);
+        output = run_program(&mut memory, &[8]).unwrap();
         assert_eq!(output[0], 0, "wrong 8 !< 8 immediate mode"); // 8 not < 8
 
         memory = vec![3, 3, 1107, -1, 8, 3, 4, 3, 99];
-        output = run_program(&mut memory, &[7]);
+        output = run_program(&mut memory, &[7]).unwrap();
         assert_eq!(output[0], 1, "wrong 7 < 8 immediate mode"); // 7 is < 8
     }
 
@@ -192,11 +232,11 @@ mod tests {
             0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4,
             20, 1105, 1, 46, 98, 99,
         ];
-        let output = run_program(&mut memory.clone(), &[3]);
+        let output = run_program(&mut memory.clone(), &[3]).unwrap();
         assert_eq!(output[0], 999);
-        let output = run_program(&mut memory.clone(), &[8]);
+        let output = run_program(&mut memory.clone(), &[8]).unwrap();
         assert_eq!(output[0], 1000);
-        let output = run_program(&mut memory.clone(), &[13]);
+        let output = run_program(&mut memory.clone(), &[13]).unwrap();
         assert_eq!(output[0], 1001);
     }
 }
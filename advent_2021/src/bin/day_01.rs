@@ -1,19 +1,12 @@
+use advent_common::parsing::{in_file, parse_lines, read_lines, uint};
 use humantime::format_duration;
 use regex::Regex;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
 
 fn parse(lines: &[String]) -> AResult<Vec<u16>> {
-    Ok(lines
-        .iter()
-        .map(|l| (*l).parse::<u16>())
-        .map(Result::unwrap)
-        .collect())
+    Ok(parse_lines(lines, uint)?)
 }
 
 fn part_a(lines: &[String]) -> AResult<u16> {
@@ -59,15 +52,13 @@ fn main() -> AResult<()> {
     println!("Running code for Day {}.", ex);
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}_a.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines()
-        .map(Result::unwrap)
-        .collect();
+    let path = format!("./data/day_{ex}_a.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice())?);
-    println!("Part B result = {}", part_b(lines.as_slice())?);
+    println!("Part A result = {}", part_a(lines.as_slice()).map_err(in_file(&path))?);
+    println!("Part B result = {}", part_b(lines.as_slice()).map_err(in_file(&path))?);
     let end = Instant::now();
 
     println!("Run took {}ms", format_duration(end - start));
@@ -90,6 +81,21 @@ mod tests {
         260
         263";
 
+    #[test]
+    fn test_parse_errors() {
+        let lines: Vec<_> = "199\n20O\n208".lines().map(String::from).collect();
+        assert_eq!(
+            parse(lines.as_slice()).unwrap_err().to_string(),
+            "line 2, column 3: expected end of line, found \"O\""
+        );
+
+        let lines: Vec<_> = "199\n\n208".lines().map(String::from).collect();
+        assert_eq!(
+            parse(lines.as_slice()).unwrap_err().to_string(),
+            "line 2, column 1: expected unsigned integer, found end of line"
+        );
+    }
+
     #[test]
     fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
@@ -1,23 +1,24 @@
+use advent_common::parsing::{in_file, parse_lines, read_lines, uint, PResult};
 use humantime::format_duration;
-use regex::Regex;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
+use nom::{
+    branch::alt, bytes::complete::tag, character::complete::space1, error::context,
+    sequence::separated_pair,
 };
+use regex::Regex;
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
 
-fn parse(lines: &[String]) -> AResult<Vec<(&str, u32)>> {
-    let mut res = vec!();
-    for l in lines {
-        let mut it = l.split_whitespace();
-        let dir = it.next().expect("no direction");
-        let dst = it.next().expect("no distance").parse()?;
+fn command(input: &str) -> PResult<'_, (&str, u32)> {
+    separated_pair(
+        context("forward, down or up", alt((tag("forward"), tag("down"), tag("up")))),
+        space1,
+        uint,
+    )(input)
+}
 
-        res.push((dir, dst));
-    }
-    Ok(res)
+fn parse(lines: &[String]) -> AResult<Vec<(&str, u32)>> {
+    Ok(parse_lines(lines, command)?)
 }
 
 fn part_a(lines: &[String]) -> AResult<u32> {
@@ -68,13 +69,13 @@ fn main() -> AResult<()> {
     println!("Running code for Day {}.", ex);
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}_a.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}_a.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice())?);
-    println!("Part B result = {}", part_b(lines.as_slice())?);
+    println!("Part A result = {}", part_a(lines.as_slice()).map_err(in_file(&path))?);
+    println!("Part B result = {}", part_b(lines.as_slice()).map_err(in_file(&path))?);
     let end = Instant::now();
 
     println!("Run took {}ms", format_duration(end - start));
@@ -93,6 +94,21 @@ mod tests {
     down 8
     forward 2";
 
+    #[test]
+    fn test_parse_errors() {
+        let lines: Vec<_> = "forward 5\nbackward 5".lines().map(String::from).collect();
+        assert_eq!(
+            parse(lines.as_slice()).unwrap_err().to_string(),
+            "line 2, column 1: expected forward, down or up, found \"backward 5\""
+        );
+
+        let lines: Vec<_> = "forward 5\ndown".lines().map(String::from).collect();
+        assert_eq!(
+            parse(lines.as_slice()).unwrap_err().to_string(),
+            "line 2, column 5: expected space, found end of line"
+        );
+    }
+
     #[test]
     fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
@@ -1,20 +1,14 @@
+use advent_common::parsing::{grid_of, in_file, read_lines};
 use humantime::format_duration;
 use regex::Regex;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
 
 fn parse(lines: &[String]) -> AResult<Vec<Vec<char>>> {
-    let mut rows: Vec<Vec<char>> = vec![];
-    for l in lines {
-        let chars: Vec<char> = l.chars().collect();
-        rows.push(chars);
-    }
-    Ok(rows)
+    Ok(grid_of(lines, "0 or 1", |c| {
+        matches!(c, '0' | '1').then_some(c)
+    })?)
 }
 
 fn part_a(lines: &[String]) -> AResult<u32> {
@@ -119,13 +113,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {}.", ex);
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}_a.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}_a.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice())?);
-    println!("Part B result = {}", part_b(lines.as_slice())?);
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}ms", format_duration(end - start));
@@ -150,6 +150,21 @@ mod tests {
     00010
     01010";
 
+    #[test]
+    fn test_parse_errors() {
+        let lines: Vec<_> = "00100\n11120".lines().map(String::from).collect();
+        assert_eq!(
+            parse(lines.as_slice()).unwrap_err().to_string(),
+            "line 2, column 4: expected 0 or 1, found '2'"
+        );
+
+        let lines: Vec<_> = "00100\n1111".lines().map(String::from).collect();
+        assert_eq!(
+            parse(lines.as_slice()).unwrap_err().to_string(),
+            "line 2, column 5: expected a row of 5 cells, found 4 cells"
+        );
+    }
+
     #[test]
     fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
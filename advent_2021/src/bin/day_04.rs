@@ -1,29 +1,33 @@
+use advent_common::parsing::{
+    blocks, exact_lines, in_file, parse_all, read_lines, uint, Block, ParseError,
+};
 use anyhow::format_err;
 use humantime::format_duration;
-use regex::Regex;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
+use nom::{
+    character::complete::{char, space0},
+    combinator::map_res,
+    multi::{count, separated_list1},
+    sequence::preceded,
 };
+use regex::Regex;
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
 
+#[derive(Debug)]
 struct Card {
     elem: [[i8; 5]; 5],
 }
 
-impl From<&[String]> for Card {
-    fn from(lines: &[String]) -> Self {
-        let mut elem = [[0; 5]; 5];
-        for row in 0..5 {
-            let nums: Vec<i8> = lines[row]
-                .split_whitespace()
-                .map(|x| x.parse().unwrap())
-                .collect();
-            elem[row].copy_from_slice(&nums);
-        }
-        Card { elem }
+impl TryFrom<&Block<'_>> for Card {
+    type Error = ParseError;
+
+    fn try_from(block: &Block) -> Result<Self, Self::Error> {
+        let row = |i| map_res(count(preceded(space0, uint), 5), <[i8; 5]>::try_from)(i);
+        let rows = exact_lines::<5>(block.lines, "a row of 5 numbers")
+            .map_err(|e| e.offset_by(block.start))?;
+        let elem = Block { start: block.start, lines: rows }.parse_lines(row)?;
+        Ok(Card { elem: elem.try_into().expect("exactly 5 rows") })
     }
 }
 
@@ -70,16 +74,15 @@ impl Card {
 }
 
 fn parse(lines: &[String]) -> AResult<(Vec<i8>, Vec<Card>)> {
-    let draw = lines[0]
-        .split(',')
-        .map(|e| e.parse().expect("a positive integer"))
-        .collect();
-
-    let mut cards = vec![];
-    for chunk in lines[2..].chunks(6) {
-        cards.push(Card::from(&chunk[..5]));
-    }
-    
+    let blocks = blocks(lines);
+    let Some((draw, cards)) = blocks.split_first() else {
+        return Err(ParseError::end_of_input(0, "the numbers to draw").into());
+    };
+
+    let [draw] = exact_lines(draw.lines, "the numbers to draw")?;
+    let draw = parse_all(draw, separated_list1(char(','), uint))?;
+    let cards = cards.iter().map(Card::try_from).collect::<Result<_, _>>()?;
+
     Ok((draw, cards))
 }
 
@@ -119,13 +122,13 @@ fn main() -> AResult<()> {
     println!("Running code for Day {}.", ex);
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}_a.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}_a.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice())?);
-    println!("Part B result = {}", part_b(lines.as_slice())?);
+    println!("Part A result = {}", part_a(lines.as_slice()).map_err(in_file(&path))?);
+    println!("Part B result = {}", part_b(lines.as_slice()).map_err(in_file(&path))?);
     let end = Instant::now();
 
     println!("Run took {}ms", format_duration(end - start));
@@ -158,6 +161,28 @@ mod tests {
     22 11 13  6  5
      2  0 12  3  7";
 
+    #[test]
+    fn test_parse_errors() {
+        let lines: Vec<_> = "7,4,9;5\n\n1 2 3 4 5".lines().map(String::from).collect();
+        assert_eq!(
+            parse(lines.as_slice()).unwrap_err().to_string(),
+            "line 1, column 6: expected end of line, found \";5\""
+        );
+
+        let mut lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        lines[4] = "8  2 23  4".to_string();
+        assert_eq!(
+            parse(lines.as_slice()).unwrap_err().to_string(),
+            "line 5, column 11: expected unsigned integer, found end of line"
+        );
+
+        lines.truncate(4);
+        assert_eq!(
+            parse(lines.as_slice()).unwrap_err().to_string(),
+            "line 5, column 1: expected a row of 5 numbers, found end of input"
+        );
+    }
+
     #[test]
     fn test_parse() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
@@ -1,31 +1,31 @@
+use advent_common::parsing::{in_file, parse_lines, read_lines, uint, PResult};
 use humantime::format_duration;
+use nom::{
+    bytes::complete::tag, character::complete::char, combinator::map, error::context,
+    sequence::separated_pair,
+};
 use regex::Regex;
 use std::{
     cmp::{max},
-    fs::File,
-    io::{BufRead, BufReader},
     time::Instant,
     vec::Vec
 };
 
 type AResult<T> = anyhow::Result<T>;
 
-fn parse(lines: &[String]) -> AResult<Vec<(i32, i32, i32, i32)>> {
-    let re = Regex::new("([0-9]+),([0-9]+) -> ([0-9]+),([0-9]+)")?;
-
-    let mut out = vec![];
-    for line in lines {
-        let caps = re.captures(line).unwrap();
-        let mut it = caps.iter().map(Option::unwrap);
-        it.next(); // Skip capture 0 as it's the whole string
+fn point(input: &str) -> PResult<'_, (i32, i32)> {
+    separated_pair(uint, char(','), uint)(input)
+}
 
-        let coords: Vec<_> = it
-            .map(|m| -> i32 { m.as_str().parse().unwrap() })
-            .collect();
+fn vent(input: &str) -> PResult<'_, (i32, i32, i32, i32)> {
+    map(
+        separated_pair(point, context("\" -> \"", tag(" -> ")), point),
+        |((x1, y1), (x2, y2))| (x1, y1, x2, y2),
+    )(input)
+}
 
-        out.push((coords[0], coords[1], coords[2], coords[3]))
-    }
-    Ok(out)
+fn parse(lines: &[String]) -> AResult<Vec<(i32, i32, i32, i32)>> {
+    Ok(parse_lines(lines, vent)?)
 }
 
 fn solve(lines: &[String], no_diags: bool) -> AResult<u32> {
@@ -90,13 +90,13 @@ fn main() -> AResult<()> {
     println!("Running code for Day {}.", ex);
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}_a.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}_a.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice())?);
-    println!("Part B result = {}", part_b(lines.as_slice())?);
+    println!("Part A result = {}", part_a(lines.as_slice()).map_err(in_file(&path))?);
+    println!("Part B result = {}", part_b(lines.as_slice()).map_err(in_file(&path))?);
     let end = Instant::now();
 
     println!("Run took {}ms", format_duration(end - start));
@@ -119,6 +119,21 @@ mod tests {
     0,0 -> 8,8
     5,5 -> 8,2";
 
+    #[test]
+    fn test_parse_errors() {
+        let lines = ["0,9 -> 5,9".to_string(), "8,0 => 0,8".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 4: expected \" -> \", found \" => 0,8\""
+        );
+
+        let lines = ["0,9 -> 5,9".to_string(), "8,0 -> 0".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 9: expected ',', found end of line"
+        );
+    }
+
     #[test]
     fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
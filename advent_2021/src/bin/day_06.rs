@@ -1,15 +1,14 @@
+use advent_common::parsing::{exact_lines, in_file, parse_all, read_lines, uint};
 use humantime::format_duration;
+use nom::{character::complete::char, combinator::cut, multi::separated_list1};
 use regex::Regex;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
 
 fn parse(lines: &[String]) -> AResult<Vec<u64>> {
-    Ok(lines[0].split(',').map(|s| s.parse().unwrap()).collect())
+    let [line] = exact_lines(lines, "the fish timers")?;
+    Ok(parse_all(line, separated_list1(char(','), cut(uint)))?)
 }
 
 fn solve(lines: &[String], days: u32) -> AResult<u64> {
@@ -23,7 +22,7 @@ fn solve(lines: &[String], days: u32) -> AResult<u64> {
     for _day in 0..days {
         let zero_fish = ages[0];
         for a in 1..ages.len() {
-            ages[(a-1) as usize] = ages[a] as u64;
+            ages[a - 1] = ages[a];
         }
         ages[6] += zero_fish;
         ages[8] = zero_fish;
@@ -52,13 +51,13 @@ fn main() -> AResult<()> {
     println!("Running code for Day {}.", ex);
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}_a.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}_a.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice())?);
-    println!("Part B result = {}", part_b(lines.as_slice())?);
+    println!("Part A result = {}", part_a(lines.as_slice()).map_err(in_file(&path))?);
+    println!("Part B result = {}", part_b(lines.as_slice()).map_err(in_file(&path))?);
     let end = Instant::now();
 
     println!("Run took {}ms", format_duration(end - start));
@@ -72,6 +71,18 @@ mod tests {
 
     const TEST_INPUT: &str = "3,4,3,1,2";
 
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse(&["3,4,x,1".to_string()]).unwrap_err().to_string(),
+            "line 1, column 5: expected unsigned integer, found \"x,1\""
+        );
+        assert_eq!(
+            parse(&[]).unwrap_err().to_string(),
+            "line 1, column 1: expected the fish timers, found end of input"
+        );
+    }
+
     #[test]
     fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
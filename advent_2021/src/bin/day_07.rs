@@ -1,16 +1,14 @@
+use advent_common::parsing::{exact_lines, in_file, parse_all, read_lines, uint};
 use humantime::format_duration;
+use nom::{character::complete::char, combinator::cut, multi::separated_list1};
 use regex::Regex;
-use std::{
-    collections::HashMap,
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::{collections::HashMap, time::Instant};
 
 type AResult<T> = anyhow::Result<T>;
 
 fn parse(lines: &[String]) -> AResult<Vec<u32>> {
-    Ok(lines[0].split(',').map(|s| s.parse().unwrap()).collect())
+    let [line] = exact_lines(lines, "the crab positions")?;
+    Ok(parse_all(line, separated_list1(char(','), cut(uint)))?)
 }
 
 fn part_a(lines: &[String]) -> AResult<u32> {
@@ -61,13 +59,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {}.", ex);
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}_a.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}_a.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice())?);
-    println!("Part B result = {}", part_b(lines.as_slice())?);
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}ms", format_duration(end - start));
@@ -81,6 +85,18 @@ mod tests {
 
     const TEST_INPUT: &str = "16,1,2,0,4,2,7,1,2,14";
 
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse(&["16,1,-2,0".to_string()]).unwrap_err().to_string(),
+            "line 1, column 6: expected unsigned integer, found \"-2,0\""
+        );
+        assert_eq!(
+            parse(&[]).unwrap_err().to_string(),
+            "line 1, column 1: expected the crab positions, found end of input"
+        );
+    }
+
     #[test]
     fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
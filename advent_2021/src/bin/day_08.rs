@@ -1,8 +1,11 @@
+use advent_common::parsing::{in_file, parse_lines, read_lines, PResult};
 use humantime::format_duration;
+use nom::{
+    bytes::complete::{is_a, tag}, character::complete::char, error::context,
+    multi::separated_list1, sequence::separated_pair,
+};
 use regex::Regex;
 use std::{
-    fs::File,
-    io::{BufRead, BufReader},
     time::Instant, collections::{HashMap, HashSet},
 };
 
@@ -32,21 +35,13 @@ struct Pattern {
     top: bool, ul: bool, ur: bool, mid: bool, ll: bool, lr: bool, bot: bool,
 }
 
-fn parse(lines: &[String]) -> AResult<Vec<(Vec<&str>, Vec<&str>)>> {
-    let mut output = Vec::with_capacity(lines.len());
-
-    for line in lines {
-        let mut line_parts = line.split('|');
-        let pattern_string = line_parts.next().unwrap();
-        let number_string = line_parts.next().unwrap();
-
-        let patterns = pattern_string.trim().split(' ').collect();
-        let digits = number_string.trim().split(' ').collect();
-
-        output.push((patterns, digits));
-    }
+fn segments(input: &str) -> PResult<'_, Vec<&str>> {
+    separated_list1(char(' '), context("segments a to g", is_a("abcdefg")))(input)
+}
 
-    Ok(output)
+fn parse(lines: &[String]) -> AResult<Vec<(Vec<&str>, Vec<&str>)>> {
+    let divider = context("\" | \"", tag(" | "));
+    Ok(parse_lines(lines, separated_pair(segments, divider, segments))?)
 }
 
 fn part_a(lines: &[String]) -> AResult<usize> {
@@ -195,13 +190,13 @@ fn main() -> AResult<()> {
     println!("Running code for Day {}.", ex);
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}_a.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}_a.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice())?);
-    println!("Part B result = {}", part_b(lines.as_slice())?);
+    println!("Part A result = {}", part_a(lines.as_slice()).map_err(in_file(&path))?);
+    println!("Part B result = {}", part_b(lines.as_slice()).map_err(in_file(&path))?);
     let end = Instant::now();
 
     println!("Run took {}ms", format_duration(end - start));
@@ -225,6 +220,21 @@ mod tests {
     egadfb cdbfeg cegd fecab cgb gbdefca cg fgcdab egfdb bfceg | gbdfcae bgc cg cgb
     gcafb gcf dcaebfg ecagb gf abcdeg gaef cafbge fdbac fegbdc | fgae cfgab fg bagce";
 
+    #[test]
+    fn test_parse_errors() {
+        let lines = ["be cfbegad | fdgacbe cefdb".to_string(), "edbfga begcd cbg".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 17: expected \" | \", found end of line"
+        );
+
+        let lines = ["be cfbegaz | fdgacbe".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 10: expected \" | \", found \"z | fdgacbe\""
+        );
+    }
+
     #[test]
     fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
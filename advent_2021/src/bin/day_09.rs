@@ -1,17 +1,14 @@
+use advent_common::parsing::{digit_grid, in_file, read_lines};
 use humantime::format_duration;
 use regex::Regex;
 use std::{
-    fs::File,
-    io::{BufRead, BufReader},
     time::Instant, collections::HashSet,
 };
 
 type AResult<T> = anyhow::Result<T>;
 
 fn parse(lines: &[String]) -> AResult<Vec<Vec<u8>>> {
-    Ok(lines.iter().map(
-        |line| line.chars().map(|c| (c as u8) - 48).collect()
-    ).collect())
+    Ok(digit_grid(lines)?)
 }
 
 fn part_a(lines: &[String]) -> AResult<(u32, Vec<(usize,usize)>)> {
@@ -98,14 +95,14 @@ fn main() -> AResult<()> {
     println!("Running code for Day {}.", ex);
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}_a.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}_a.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    let (a_res, lp) = part_a(lines.as_slice())?;
+    let (a_res, lp) = part_a(lines.as_slice()).map_err(in_file(&path))?;
     println!("Part A result = {}", a_res);
-    println!("Part B result = {}", part_b(lines.as_slice(), lp)?);
+    println!("Part B result = {}", part_b(lines.as_slice(), lp).map_err(in_file(&path))?);
     let end = Instant::now();
 
     println!("Run took {}ms", format_duration(end - start));
@@ -123,6 +120,15 @@ mod tests {
     8767896789
     9899965678";
 
+    #[test]
+    fn test_parse_errors() {
+        let lines = ["2199943210".to_string(), "39878949-1".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 9: expected a digit, found '-'"
+        );
+    }
+
     #[test]
     fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
@@ -1,18 +1,19 @@
+use advent_common::parsing::{in_file, read_lines, rows_of};
 use humantime::format_duration;
 use regex::Regex;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
 
+fn parse(lines: &[String]) -> AResult<Vec<Vec<char>>> {
+    Ok(rows_of(lines, "a bracket", |c| "()[]{}<>".contains(c).then_some(c))?)
+}
+
 fn part_a(lines: &[String]) -> AResult<u32> {
     let mut acc = 0;
-    for line in lines {
+    for line in parse(lines)? {
         let mut stack: Vec<char> = Vec::with_capacity(line.len() / 2);
-        for char in line.chars() {
+        for char in line {
             match char {
                 '('|'['|'{'|'<' => { stack.push(char); },
                 ')'|']'|'}'|'>' => {
@@ -30,7 +31,7 @@ fn part_a(lines: &[String]) -> AResult<u32> {
                         _ => panic!("wtf")
                     }
                 },
-                _ => panic!("Unknown character in input")
+                _ => unreachable!("parse only allows brackets")
             }
         }
     }
@@ -40,12 +41,12 @@ fn part_a(lines: &[String]) -> AResult<u32> {
 
 fn part_b(lines: &[String]) -> AResult<u64> {
     let mut acc = Vec::new();
-    for line in lines {
+    for line in parse(lines)? {
         let mut stack: Vec<char> = Vec::with_capacity(line.len() / 2);
         let mut corrupt = false;
         let mut line_acc = 0u64;
 
-        for char in line.chars() {
+        for char in line {
             match char {
                 '('|'['|'{'|'<' => { stack.push(char); },
                 ')'|']'|'}'|'>' => {
@@ -63,7 +64,7 @@ fn part_b(lines: &[String]) -> AResult<u64> {
                         _ => panic!("wtf")
                     }
                 },
-                _ => panic!("Unknown character in input")
+                _ => unreachable!("parse only allows brackets")
             }
         }
 
@@ -98,13 +99,13 @@ fn main() -> AResult<()> {
     println!("Running code for Day {}.", ex);
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}_a.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}_a.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice())?);
-    println!("Part B result = {}", part_b(lines.as_slice())?);
+    println!("Part A result = {}", part_a(lines.as_slice()).map_err(in_file(&path))?);
+    println!("Part B result = {}", part_b(lines.as_slice()).map_err(in_file(&path))?);
     let end = Instant::now();
 
     println!("Run took {}ms", format_duration(end - start));
@@ -127,6 +128,18 @@ mod tests {
         <{([([[(<>()){}]>(<<{{
         <{([{{}}[<[[[<>{}]]]>[]]";
 
+    #[test]
+    fn test_parse_errors() {
+        let lines = ["[({(<(())[]>[[{[]{<()<>>".to_string(), "[(()[<>])]({[<{<<[]>>(".to_string()];
+        assert!(parse(&lines).is_ok());
+
+        let lines = ["[({(<(())[]>[[{[]{<()<>>".to_string(), "[(()[<>]) ]".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 10: expected a bracket, found ' '"
+        );
+    }
+
     #[test]
     fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
@@ -1,21 +1,25 @@
+use advent_common::parsing::{digit_grid, exact_lines, in_file, read_lines, ParseError};
 use humantime::format_duration;
 use regex::Regex;
 use std::{
     cmp::min,
-    fs::File,
-    io::{BufRead, BufReader},
     time::Instant,
 };
 
 type AResult<T> = anyhow::Result<T>;
 
 fn parse(lines: &[String]) -> AResult<[[u8; 10]; 10]> {
-    let mut arr = [[0u8; 10]; 10];
+    let rows = exact_lines::<10>(lines, "a row of 10 digits")?;
+    let grid = digit_grid::<u8>(rows)?;
 
-    for (ir, line) in lines.iter().enumerate() {
-        for (ic, char) in line.chars().enumerate() {
-            arr[ir][ic] = (char as u8) - 48;
-        }
+    if grid[0].len() != 10 {
+        let found = format!("{} digits", grid[0].len());
+        return Err(ParseError::new(1, 1, "a row of 10 digits", found).into());
+    }
+
+    let mut arr = [[0u8; 10]; 10];
+    for (ir, row) in grid.into_iter().enumerate() {
+        arr[ir].copy_from_slice(&row);
     }
 
     Ok(arr)
@@ -111,13 +115,13 @@ fn main() -> AResult<()> {
     println!("Running code for Day {}.", ex);
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}_a.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}_a.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice())?);
-    println!("Part B result = {}", part_b(lines.as_slice())?);
+    println!("Part A result = {}", part_a(lines.as_slice()).map_err(in_file(&path))?);
+    println!("Part B result = {}", part_b(lines.as_slice()).map_err(in_file(&path))?);
     let end = Instant::now();
 
     println!("Run took {}ms", format_duration(end - start));
@@ -140,6 +144,28 @@ mod tests {
     4846848554
     5283751526";
 
+    #[test]
+    fn test_parse_errors() {
+        let mut lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        lines.pop();
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 10, column 1: expected a row of 10 digits, found end of input"
+        );
+
+        lines.push("52837515260".to_string());
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 10, column 11: expected a row of 10 cells, found 11 cells"
+        );
+
+        let lines: Vec<_> = (0..10).map(|_| "12345".to_string()).collect();
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 1: expected a row of 10 digits, found 5 digits"
+        );
+    }
+
     #[test]
     fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
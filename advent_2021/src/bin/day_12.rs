@@ -1,21 +1,23 @@
+use advent_common::{
+    parsing::{in_file, parse_lines, read_lines},
+    search::all_paths_by,
+};
 use humantime::format_duration;
-use regex::Regex;
-use std::{
-    collections::{HashMap, HashSet},
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
+use nom::{
+    character::complete::{alpha1, char},
+    error::context,
+    sequence::separated_pair,
 };
+use regex::Regex;
+use std::{collections::HashMap, time::Instant};
 
 type AResult<T> = anyhow::Result<T>;
 
 fn parse(lines: &[String]) -> AResult<HashMap<&str, Vec<&str>>> {
     let mut adj: HashMap<&str, Vec<&str>> = HashMap::new();
 
-    for line in lines {
-        let mut it = line.split('-');
-        let (c1, c2) = (it.next().unwrap(), it.next().unwrap());
-
+    let cave = || context("a cave name", alpha1);
+    for (c1, c2) in parse_lines(lines, separated_pair(cave(), char('-'), cave()))? {
         adj.entry(c1)
             .and_modify(|list| list.push(c2))
             .or_insert_with(|| Vec::from_iter([c2]));
@@ -31,108 +33,45 @@ fn parse(lines: &[String]) -> AResult<HashMap<&str, Vec<&str>>> {
     Ok(adj)
 }
 
-fn extend_path<'a>(
-    existing: Vec<Vec<&'a str>>,
-    adj: &HashMap<&'a str, Vec<&'a str>>,
-) -> Vec<Vec<&'a str>> {
-    let mut out = Vec::new();
-
-    for path in existing {
-        if path[path.len() - 1] == "end" {
-            out.push(path); // reached the end of this path - no extension to do
-            continue;
-        }
-
-        let last_elem = path[path.len() - 1];
-        for dest in &adj[last_elem] {
-            let small: HashSet<_> = path
-                .iter()
-                .filter(|s| &&s.to_lowercase().as_str() == s)
-                .collect();
-
-            if small.contains(dest) {
-                continue; // no double visiting small caves
-            }
-
-            let mut clone = path.clone();
-            clone.push(dest);
-            out.push(clone);
-        }
-    }
+fn is_small(cave: &str) -> bool {
+    cave.chars().all(|c| c.is_ascii_lowercase())
+}
 
-    out
+fn count_paths<'a, F>(adj: &HashMap<&'a str, Vec<&'a str>>, can_visit: F) -> usize
+where
+    F: FnMut(&[&'a str], &&'a str) -> bool,
+{
+    let neighbours = |cave: &&'a str| adj.get(cave).cloned().unwrap_or_default();
+    all_paths_by("start", neighbours, |&cave| cave == "end", can_visit).len()
 }
 
 fn part_a(lines: &[String]) -> AResult<u64> {
     let adj = parse(lines)?;
 
-    let mut paths = Vec::from_iter(adj.get("start").unwrap().iter().map(|x| vec![*x]));
-
-    let mut prev_paths: Vec<_> = vec![];
-    let mut pp_len = prev_paths.len();
-    while pp_len != paths.len() {
-        prev_paths = paths;
-        pp_len = prev_paths.len();
-        paths = extend_path(prev_paths, &adj);
-    }
+    // no double visiting small caves
+    let paths = count_paths(&adj, |path, dest| !is_small(dest) || !path.contains(dest));
 
-    Ok(paths.len() as u64)
+    Ok(paths as u64)
 }
 
-fn extend_path_b<'a>(
-    existing: Vec<Vec<&'a str>>,
-    adj: &HashMap<&'a str, Vec<&'a str>>,
-) -> Vec<Vec<&'a str>> {
-    let mut out = Vec::new();
+fn part_b(lines: &[String]) -> AResult<u64> {
+    let adj = parse(lines)?;
 
-    for path in existing {
-        if path[path.len() - 1] == "end" {
-            out.push(path); // reached the end of this path - no extension to do
-            continue;
+    let paths = count_paths(&adj, |path, dest| {
+        if !is_small(dest) || !path.contains(dest) {
+            return true; // Big cave, or a small cave we've not been in before
         }
 
-        let mut small_counts: HashMap<&str, u8> = HashMap::new();
-        path.iter()
-            .filter(|s| &&s.to_lowercase().as_str() == s)
-            .for_each(|v| {
-                small_counts
-                    .entry(v)
-                    .and_modify(|c| *c += 1u8)
-                    .or_insert(1u8);
-            });
-        let small_max = small_counts.values().max().unwrap_or(&0);
-
-        let last_elem = path[path.len() - 1];
-        for dest in &adj[last_elem] {
-            let prev = small_counts.get(dest).unwrap_or(&0);
-            if (&(dest.to_lowercase().as_str()) != dest) || // Big cave - valid extension
-                (*prev == 0) || // Small cave we've not been in before
-                ((*small_max == 1) && (*prev == 1))
-            // dest is a small cave, but we can visit it a second time
-            {
-                let mut clone = path.clone();
-                clone.push(dest);
-                out.push(clone);
-            }
-        }
-    }
+        // dest is a small cave, but we can visit it a second time if no other
+        // small cave has been visited twice
+        let mut small: Vec<_> = path.iter().filter(|c| is_small(c)).collect();
+        let total = small.len();
+        small.sort_unstable();
+        small.dedup();
+        small.len() == total
+    });
 
-    out
-}
-
-fn part_b(lines: &[String]) -> AResult<u64> {
-    let adj = parse(lines)?;
-    let mut paths = Vec::from_iter(adj.get("start").unwrap().iter().map(|x| vec![*x]));
-
-    let mut prev_paths: Vec<_> = vec![];
-    let mut pp_len = prev_paths.len();
-    while pp_len != paths.len() {
-        prev_paths = paths;
-        pp_len = prev_paths.len();
-        paths = extend_path_b(prev_paths, &adj);
-    }
-
-    Ok(paths.len() as u64)
+    Ok(paths as u64)
 }
 
 fn main() -> AResult<()> {
@@ -146,13 +85,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {}.", ex);
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}_a.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}_a.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice())?);
-    println!("Part B result = {}", part_b(lines.as_slice())?);
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}ms", format_duration(end - start));
@@ -183,6 +128,21 @@ mod tests {
     pj-fs
     start-RW";
 
+    #[test]
+    fn test_parse_errors() {
+        let lines = ["start-A".to_string(), "A-b-end".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 4: expected end of line, found \"-end\""
+        );
+
+        let lines = ["start-A".to_string(), "A b".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 2: expected '-', found \" b\""
+        );
+    }
+
     #[test]
     fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
@@ -1,10 +1,15 @@
+use advent_common::parsing::{exact_blocks, in_file, read_lines, uint, PResult};
 use humantime::format_duration;
+use nom::{
+    bytes::complete::tag,
+    character::complete::{char, one_of},
+    error::context,
+    sequence::{preceded, separated_pair},
+};
 use regex::Regex;
 use std::{
     cmp::min,
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant, iter::repeat
+    time::Instant
 };
 
 type AResult<T> = anyhow::Result<T>;
@@ -18,29 +23,18 @@ fn _print_grid(grid: &[Vec<char>]) {
     println!("================================");
 }
 
+fn point(input: &str) -> PResult<'_, (usize, usize)> {
+    separated_pair(uint, char(','), uint)(input)
+}
+
+fn fold_line(input: &str) -> PResult<'_, (char, usize)> {
+    preceded(tag("fold along "), separated_pair(context("x or y", one_of("xy")), char('='), uint))(input)
+}
+
 fn parse(lines: &[String]) -> AResult<Instructions> {
-    let mut points = Vec::new();
-    let mut folds = Vec::new();
-
-    let point_pattern = Regex::new("^([0-9]+),([0-9]+)$")?;
-    let fold_pattern = Regex::new("([xy])=([0-9]+)$")?;
-
-
-    for line in lines {
-        if let Some(caps) = point_pattern.captures(line) {
-            points.push((
-                caps.get(1).map(|v| v.as_str().parse().unwrap()).unwrap(),
-                caps.get(2).map(|v| v.as_str().parse().unwrap()).unwrap(),
-            ))
-        } else if let Some(caps) = fold_pattern.captures(line) {
-            folds.push((
-                caps.get(1).unwrap().as_str().chars().next().unwrap(),
-                caps.get(2).map(|v| v.as_str().parse().unwrap()).unwrap()
-            ))
-        }
-    }
+    let [points, folds] = exact_blocks(lines, "the fold instructions")?;
 
-    Ok((points, folds))
+    Ok((points.parse_lines(point)?, folds.parse_lines(fold_line)?))
 }
 
 #[allow(clippy::needless_range_loop)]  // reason: code is more readable with the loop
@@ -75,10 +69,10 @@ fn fold(grid: &mut Vec<Vec<char>>, fold_ax: char, fold_idx: usize) {
 
 fn part_a(lines: &[String]) -> AResult<u64> {
     let (points, folds) = parse(lines)?;
-    let row_count = *points.iter().map(|(_,y)| y).max().unwrap() as usize;
-    let col_count = *points.iter().map(|(x,_)| x).max().unwrap() as usize;
+    let row_count = *points.iter().map(|(_,y)| y).max().unwrap();
+    let col_count = *points.iter().map(|(x,_)| x).max().unwrap();
 
-    let mut grid: Vec<Vec<char>> = (0..=row_count).map(|_| Vec::from_iter(repeat('.').take(col_count + 1))).collect();
+    let mut grid: Vec<Vec<char>> = (0..=row_count).map(|_| vec!['.'; col_count + 1]).collect();
 
     for (col, row) in points {
         grid[row][col] = '#';
@@ -102,10 +96,10 @@ fn part_a(lines: &[String]) -> AResult<u64> {
 
 fn part_b(lines: &[String]) -> AResult<&str> {
     let (points, folds) = parse(lines)?;
-    let row_count = *points.iter().map(|(_,y)| y).max().unwrap() as usize;
-    let col_count = *points.iter().map(|(x,_)| x).max().unwrap() as usize;
+    let row_count = *points.iter().map(|(_,y)| y).max().unwrap();
+    let col_count = *points.iter().map(|(x,_)| x).max().unwrap();
 
-    let mut grid: Vec<Vec<char>> = (0..=row_count).map(|_| Vec::from_iter(repeat('.').take(col_count + 1))).collect();
+    let mut grid: Vec<Vec<char>> = (0..=row_count).map(|_| vec!['.'; col_count + 1]).collect();
 
     for (col, row) in points {
         grid[row][col] = '#';
@@ -132,13 +126,13 @@ fn main() -> AResult<()> {
     println!("Running code for Day {}.", ex);
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}_a.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}_a.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice())?);
-    println!("Part B result = {}", part_b(lines.as_slice())?);
+    println!("Part A result = {}", part_a(lines.as_slice()).map_err(in_file(&path))?);
+    println!("Part B result = {}", part_b(lines.as_slice()).map_err(in_file(&path))?);
     let end = Instant::now();
 
     println!("Run took {}ms", format_duration(end - start));
@@ -172,6 +166,29 @@ mod tests {
     fold along y=7
     fold along x=5";
 
+    #[test]
+    fn test_parse_errors() {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        assert_eq!(
+            parse(&lines[..18]).unwrap_err().to_string(),
+            "line 19, column 1: expected the fold instructions, found end of input"
+        );
+
+        let mut bad = lines.clone();
+        bad[20] = "fold along z=5".to_string();
+        assert_eq!(
+            parse(&bad).unwrap_err().to_string(),
+            "line 21, column 12: expected x or y, found \"z=5\""
+        );
+
+        let mut bad = lines;
+        bad[3] = "0;3".to_string();
+        assert_eq!(
+            parse(&bad).unwrap_err().to_string(),
+            "line 4, column 2: expected ',', found \";3\""
+        );
+    }
+
     #[test]
     fn test_part_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
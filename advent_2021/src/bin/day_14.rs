@@ -1,9 +1,12 @@
+use advent_common::parsing::{exact_blocks, exact_lines, in_file, parse_all, read_lines, PResult};
 use humantime::format_duration;
+use nom::{
+    bytes::complete::tag, character::complete::satisfy, combinator::recognize, error::context,
+    multi::many1, sequence::{pair, separated_pair},
+};
 use regex::Regex;
 use std::{
     collections::HashMap,
-    fs::File,
-    io::{BufRead, BufReader},
     iter::zip,
     time::Instant,
 };
@@ -11,19 +14,19 @@ use std::{
 type AResult<T> = anyhow::Result<T>;
 type Instructions = (String, HashMap<(char, char), char>);
 
+fn element(input: &str) -> PResult<'_, char> {
+    context("an element", satisfy(|c| c.is_ascii_uppercase()))(input)
+}
+
+fn rule(input: &str) -> PResult<'_, ((char, char), char)> {
+    separated_pair(pair(element, element), context("\" -> \"", tag(" -> ")), element)(input)
+}
+
 fn parse(lines: &[String]) -> AResult<Instructions> {
-    let template = lines[0].clone();
-
-    let rules: HashMap<_, _> = lines[2..]
-        .iter()
-        .map(|l| -> Option<((char, char), char)> {
-            let mut it = l.split(" -> ");
-            let mut k_it = it.next()?.chars();
-            let key = (k_it.next()?, k_it.next()?);
-            Some((key, it.next()?.chars().next()?))
-        })
-        .map(Option::unwrap)
-        .collect();
+    let [template, rules] = exact_blocks(lines, "the insertion rules")?;
+    let [template] = exact_lines(template.lines, "the polymer template")?;
+    let template = parse_all(template, recognize(many1(element)))?.to_string();
+    let rules = rules.parse_lines(rule)?.into_iter().collect();
 
     Ok((template, rules))
 }
@@ -75,13 +78,13 @@ fn main() -> AResult<()> {
     println!("Running code for Day {}.", ex);
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}_a.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}_a.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice())?);
-    println!("Part B result = {}", part_b(lines.as_slice())?);
+    println!("Part A result = {}", part_a(lines.as_slice()).map_err(in_file(&path))?);
+    println!("Part B result = {}", part_b(lines.as_slice()).map_err(in_file(&path))?);
     let end = Instant::now();
 
     println!("Run took {}ms", format_duration(end - start));
@@ -112,6 +115,30 @@ mod tests {
     CC -> N
     CN -> C";
 
+    #[test]
+    fn test_parse_errors() {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+
+        let mut bad = lines.clone();
+        bad[4] = "HH => N".to_string();
+        assert_eq!(
+            parse(&bad).unwrap_err().to_string(),
+            "line 5, column 3: expected \" -> \", found \" => N\""
+        );
+
+        let mut bad = lines.clone();
+        bad[0] = "NNcB".to_string();
+        assert_eq!(
+            parse(&bad).unwrap_err().to_string(),
+            "line 1, column 3: expected end of line, found \"cB\""
+        );
+
+        assert_eq!(
+            parse(&lines[..1]).unwrap_err().to_string(),
+            "line 2, column 1: expected the insertion rules, found end of input"
+        );
+    }
+
     #[test]
     fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
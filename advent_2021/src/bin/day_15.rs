@@ -1,46 +1,15 @@
+use advent_common::{
+    parsing::{digit_grid, in_file, read_lines},
+    search::dijkstra,
+};
 use humantime::format_duration;
 use regex::Regex;
-use std::{
-    cmp::Ordering,
-    collections::{HashMap, BTreeSet},
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
 
-#[derive(Debug, Clone, Copy)]
-struct Cell(usize, usize, u64);
-
-impl Ord for Cell {
-    fn cmp(&self, other: &Self) -> Ordering {
-        match self.2.cmp(&other.2) {
-            Ordering::Equal => { (self.0, self.1).cmp(&(other.0, other.1)) },
-            x => x
-        }
-    }
-}
-
-impl PartialOrd for Cell {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-impl PartialEq for Cell {
-    fn eq(&self, other: &Self) -> bool {
-        self.0 == other.0 && self.1 == other.1
-    }
-}
-
-impl Eq for Cell {}
-
 fn parse(lines: &[String]) -> AResult<Vec<Vec<u64>>> {
-    Ok(lines
-        .iter()
-        .map(|l| l.chars().map(|c| (c as u64) - 48u64).collect())
-        .collect())
+    Ok(digit_grid(lines)?)
 }
 
 fn expand(grid: &mut Vec<Vec<u64>>) {
@@ -81,42 +50,22 @@ fn expand(grid: &mut Vec<Vec<u64>>) {
 }
 
 fn solve(map: &[Vec<u64>]) -> AResult<u64> {
-    let dist: &mut HashMap<(usize, usize), u64> =
-        &mut HashMap::with_capacity(map.len() * map.len());
-    dist.insert((0, 0), 0);
-
-    let mut q = BTreeSet::new();
-    for (ri, row) in map.iter().enumerate() {
-        for (ci, _) in row.iter().enumerate() {
-            if (ri, ci) != (0, 0) {
-                dist.insert((ri, ci), u64::MAX);
-                q.insert(Cell(ri, ci, u64::MAX));
-            } else {
-                q.insert(Cell(0, 0, 0));
-            }
-        }
-    }
-
-    while let Some(&u) = q.iter().next() {
-        // seperate remove as pop_first(..) is a nightly feature
-        q.remove(&u);
-        
-        let Cell(row, col, distance) = u;
-
-        for (d_row, d_col) in [ (-1isize, 0isize), (1, 0), (0, -1), (0, 1) ] {
-            let n_row: usize =  (row as isize + d_row) as usize;
-            let n_col: usize = (col as isize + d_col) as usize;
-            if let Some(next_cost) = map.get(n_row).and_then(|r| r.get(n_col)) {
-                let alt = if distance < u64::MAX { distance + next_cost } else { u64::MAX };
-                if alt < dist[&(n_row, n_col)] {
-                    dist.insert((n_row, n_col), alt);
-                    q.replace(Cell(n_row, n_col, alt));
-                }
-            }
-        }
-    }
-
-    Ok(*dist.get(&(map.len()-1, map[0].len()-1)).unwrap())
+    let target = (map.len() - 1, map[0].len() - 1);
+
+    let neighbours = |&(row, col): &(usize, usize)| {
+        [(-1isize, 0isize), (1, 0), (0, -1), (0, 1)]
+            .into_iter()
+            .filter_map(move |(d_row, d_col)| {
+                let n_row = row.checked_add_signed(d_row)?;
+                let n_col = col.checked_add_signed(d_col)?;
+                let cost = map.get(n_row).and_then(|r| r.get(n_col))?;
+                Some(((n_row, n_col), *cost))
+            })
+    };
+
+    dijkstra((0, 0), neighbours, |&p| p == target)
+        .map(|(_, risk)| risk)
+        .ok_or_else(|| anyhow::format_err!("no path to {target:?}"))
 }
 
 fn part_a(lines: &[String]) -> AResult<u64> {
@@ -141,13 +90,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {}.", ex);
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}_a.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}_a.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice())?);
-    println!("Part B result = {}", part_b(lines.as_slice())?);
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}ms", format_duration(end - start));
@@ -170,6 +125,15 @@ mod tests {
     1293138521
     2311944581";
 
+    #[test]
+    fn test_parse_errors() {
+        let lines = ["1163751742".to_string(), "138137367".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 10: expected a row of 10 cells, found 9 cells"
+        );
+    }
+
     #[test]
     fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
@@ -1,29 +1,15 @@
+use advent_common::parsing::{blocks, in_file, read_lines, uint};
 use humantime::format_duration;
 use regex::Regex;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
 
 fn parse(lines: &[String]) -> AResult<Vec<u64>> {
-    let mut o = Vec::new();
-    let mut this_elf: u64 = 0;
-    for line in lines {
-        match line.len() {
-            0 => {
-                o.push(this_elf);
-                this_elf = 0;
-            }
-            _ => {
-                this_elf += line.parse::<u64>()?;
-            }
-        }
-    }
-    o.push(this_elf);
-    Ok(o)
+    blocks(lines)
+        .iter()
+        .map(|elf| Ok(elf.parse_lines(uint::<u64>)?.into_iter().sum()))
+        .collect()
 }
 
 fn part_a(lines: &[String]) -> AResult<u64> {
@@ -48,13 +34,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice())?);
-    println!("Part B result = {}", part_b(lines.as_slice())?);
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -81,6 +73,16 @@ mod tests {
     
     10000";
 
+    #[test]
+    fn test_parse_errors() {
+        let mut lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        lines[5] = "5000 6000".to_string();
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 6, column 5: expected end of line, found \" 6000\""
+        );
+    }
+
     #[test]
     fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
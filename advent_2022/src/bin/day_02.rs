@@ -1,30 +1,42 @@
+use advent_common::parsing::{in_file, parse_lines, read_lines, PResult};
 use humantime::format_duration;
-use regex::Regex;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
+use nom::{
+    character::complete::{char, one_of},
+    combinator::map,
+    error::context,
+    sequence::separated_pair,
 };
+use regex::Regex;
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
 
-fn parse(lines: &[String]) -> Vec<(i32, i32)> {
-    let f = |x: &str| match x.chars().next().unwrap() {
+fn shape(c: char) -> i32 {
+    match c {
         'A' | 'X' => 0,
         'B' | 'Y' => 1,
         'C' | 'Z' => 2,
-        _ => panic!(),
-    };
-
-    lines
-        .iter()
-        .map(|l| -> Vec<&str> { l.split_whitespace().collect() })
-        .map(|v| (f(v[0]), f(v[1])))
-        .collect()
+        _ => unreachable!(),
+    }
+}
+
+fn round(input: &str) -> PResult<'_, (i32, i32)> {
+    map(
+        separated_pair(
+            context("A, B or C", one_of("ABC")),
+            char(' '),
+            context("X, Y or Z", one_of("XYZ")),
+        ),
+        |(a, b)| (shape(a), shape(b)),
+    )(input)
 }
 
-fn part_a(lines: &[String]) -> i64 {
-    let rounds = parse(lines);
+fn parse(lines: &[String]) -> AResult<Vec<(i32, i32)>> {
+    Ok(parse_lines(lines, round)?)
+}
+
+fn part_a(lines: &[String]) -> AResult<i64> {
+    let rounds = parse(lines)?;
 
     let mut acc = 0i32;
     for (a, b) in rounds {
@@ -36,11 +48,11 @@ fn part_a(lines: &[String]) -> i64 {
         acc += wld + me;
     }
 
-    acc.into()
+    Ok(acc.into())
 }
 
-fn part_b(lines: &[String]) -> i64 {
-    let rounds = parse(lines);
+fn part_b(lines: &[String]) -> AResult<i64> {
+    let rounds = parse(lines)?;
     let mut acc = 0;
 
     for (a, b) in rounds {
@@ -53,7 +65,7 @@ fn part_b(lines: &[String]) -> i64 {
         acc += res;
     }
 
-    acc.into()
+    Ok(acc.into())
 }
 
 fn main() -> AResult<()> {
@@ -67,13 +79,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -90,14 +108,31 @@ mod tests {
     C Z";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines = ["A Y".to_string(), "B W".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 3: expected X, Y or Z, found \"W\""
+        );
+
+        let lines = ["A Y".to_string(), "BX".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 2: expected ' ', found \"X\""
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 15);
+        assert_eq!(part_a(lines.as_slice())?, 15);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(lines.as_slice()), 12);
+        assert_eq!(part_b(lines.as_slice())?, 12);
+        Ok(())
     }
 }
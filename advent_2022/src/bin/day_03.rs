@@ -1,40 +1,64 @@
+use advent_common::parsing::{in_file, parse_lines, read_lines, PResult, ParseError};
+use anyhow::anyhow;
 use humantime::format_duration;
+use nom::{character::complete::alpha1, combinator::verify, error::context};
 use regex::Regex;
 use std::{
     collections::{HashMap, HashSet},
-    fs::File,
-    io::{BufRead, BufReader},
     time::Instant,
 };
 
 type AResult<T> = anyhow::Result<T>;
 
-fn part_a(lines: &[String]) -> u64 {
+fn rucksack(input: &str) -> PResult<'_, &str> {
+    context(
+        "an even number of items",
+        verify(alpha1, |items: &str| items.len().is_multiple_of(2)),
+    )(input)
+}
+
+fn parse(lines: &[String]) -> AResult<Vec<&str>> {
+    Ok(parse_lines(lines, rucksack)?)
+}
+
+fn priority(item: char) -> u64 {
+    match item {
+        'A'..='Z' => 27 + (item as u64) - ('A' as u64),
+        'a'..='z' => 1 + (item as u64) - ('a' as u64),
+        _ => unreachable!(),
+    }
+}
+
+fn part_a(lines: &[String]) -> AResult<u64> {
     let mut acc = 0u64;
-    for line in lines {
+    for (idx, line) in parse(lines)?.into_iter().enumerate() {
         let mid = line.len() / 2;
         let (a, b): (HashSet<char>, HashSet<char>) = (
             (line[0..mid]).chars().collect(),
             (line[mid..]).chars().collect(),
         );
 
-        let common: Vec<&char> = a.intersection(&b).collect();
-        let val = match common.get(0) {
-            Some('A'..='Z') => 27 + (*common[0] as u64) - ('A' as u64),
-            Some('a'..='z') => 1 + (*common[0] as u64) - ('a' as u64),
-            _ => {
-                panic!();
-            }
-        };
-        acc += val;
+        let common = a
+            .intersection(&b)
+            .next()
+            .ok_or_else(|| anyhow!("line {}: no item is in both compartments", idx + 1))?;
+        acc += priority(*common);
     }
 
-    acc
+    Ok(acc)
 }
 
-fn part_b(lines: &[String]) -> u64 {
+fn part_b(lines: &[String]) -> AResult<u64> {
+    let rucksacks = parse(lines)?;
+    if !rucksacks.len().is_multiple_of(3) {
+        Err(ParseError::end_of_input(
+            lines.len(),
+            "the rest of the group of three",
+        ))?;
+    }
+
     let mut acc = 0u64;
-    for group in lines.chunks_exact(3) {
+    for (idx, group) in rucksacks.chunks_exact(3).enumerate() {
         let mut items: HashMap<char, u32> = HashMap::new();
 
         for line in group {
@@ -44,17 +68,14 @@ fn part_b(lines: &[String]) -> u64 {
             }
         }
 
-        let (badge, _) = items.into_iter().find(|(_, v)| *v == 3).unwrap();
-        acc += match badge {
-            'A'..='Z' => 27 + (badge as u64) - ('A' as u64),
-            'a'..='z' => 1 + (badge as u64) - ('a' as u64),
-            _ => {
-                panic!();
-            }
-        };
+        let (badge, _) = items
+            .into_iter()
+            .find(|(_, v)| *v == 3)
+            .ok_or_else(|| anyhow!("line {}: the group has no common badge", 3 * idx + 1))?;
+        acc += priority(badge);
     }
 
-    acc
+    Ok(acc)
 }
 
 fn main() -> AResult<()> {
@@ -68,13 +89,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -94,14 +121,37 @@ mod tests {
     CrZsJsPPZsGzwwsLwLmpwMDw";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines = ["abcd".to_string(), "abc".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 1: expected an even number of items, found \"abc\""
+        );
+
+        let lines = ["ab1d".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 3: expected end of line, found \"1d\""
+        );
+
+        let lines = ["abcd".to_string(), "efgh".to_string()];
+        assert_eq!(
+            part_b(&lines).unwrap_err().to_string(),
+            "line 3, column 1: expected the rest of the group of three, found end of input"
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 157);
+        assert_eq!(part_a(lines.as_slice())?, 157);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(lines.as_slice()), 70);
+        assert_eq!(part_b(lines.as_slice())?, 70);
+        Ok(())
     }
 }
@@ -1,41 +1,36 @@
+use advent_common::parsing::{in_file, parse_lines, read_lines, uint, PResult};
 use humantime::format_duration;
+use nom::{character::complete::char, sequence::separated_pair};
 use regex::Regex;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
 
-fn parse(lines: &[String]) -> Vec<(u32, u32, u32, u32)> {
-    let mut out: Vec<(u32, u32, u32, u32)> = Vec::with_capacity(lines.len());
-    for line in lines {
-        let nums: Vec<_> = line
-            .split(',')
-            .map(|l| -> Vec<_> { l.split('-').collect() })
-            .map(|ll| -> Vec<_> { ll.iter().map(|v| v.parse::<u32>().unwrap()).collect() })
-            .collect();
-
-        out.push((nums[0][0], nums[0][1], nums[1][0], nums[1][1]));
-    }
+fn range(input: &str) -> PResult<'_, (u32, u32)> {
+    separated_pair(uint, char('-'), uint)(input)
+}
 
-    out
+fn parse(lines: &[String]) -> AResult<Vec<(u32, u32, u32, u32)>> {
+    let pairs = parse_lines(lines, separated_pair(range, char(','), range))?;
+    Ok(pairs
+        .into_iter()
+        .map(|((a, b), (c, d))| (a, b, c, d))
+        .collect())
 }
 
-fn part_a(lines: &[String]) -> u32 {
-    let parts = parse(lines);
+fn part_a(lines: &[String]) -> AResult<u32> {
+    let parts = parse(lines)?;
     let mut acc = 0u32;
     for (a, b, c, d) in parts {
         if (a <= c && b >= d) | (c <= a && d >= b) {
             acc += 1;
         }
     }
-    acc
+    Ok(acc)
 }
 
-fn part_b(lines: &[String]) -> u32 {
-    let parts = parse(lines);
+fn part_b(lines: &[String]) -> AResult<u32> {
+    let parts = parse(lines)?;
     let mut acc = 0u32;
     for (a, b, c, d) in parts {
         if (a <= c && c <= b && b <= d)  // a c b d
@@ -49,7 +44,7 @@ fn part_b(lines: &[String]) -> u32 {
         }
     }
 
-    acc
+    Ok(acc)
 }
 
 fn main() -> AResult<()> {
@@ -63,13 +58,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -89,14 +90,31 @@ mod tests {
     2-6,4-8";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines = ["2-4,6-8".to_string(), "2-4,6x8".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 6: expected '-', found \"x8\""
+        );
+
+        let lines = ["2-4;6-8".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 4: expected ',', found \";6-8\""
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 2);
+        assert_eq!(part_a(lines.as_slice())?, 2);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(lines.as_slice()), 4);
+        assert_eq!(part_b(lines.as_slice())?, 4);
+        Ok(())
     }
 }
@@ -1,46 +1,43 @@
-use core::panic;
+use advent_common::parsing::{exact_blocks, in_file, read_lines, uint, PResult, ParseError};
+use anyhow::anyhow;
 use humantime::format_duration;
-use regex::Regex;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
+use nom::{
+    bytes::complete::tag,
+    combinator::map,
+    error::context,
+    sequence::{preceded, tuple},
 };
+use regex::Regex;
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
-type State<'a> = Vec<Vec<char>>;
+type State = Vec<Vec<char>>;
+#[derive(Debug)]
 struct Move {
     quantity: usize,
     from: usize,
     to: usize,
 }
 
-fn parse(lines: &[String]) -> AResult<(State, Vec<Move>)> {
-    let crane_positions = (1..lines[0].len()).step_by(4);
-    let mut state: State = crane_positions.clone().map(|_| Vec::new()).collect();
-
-    let mut moves: Vec<_> = Vec::with_capacity(lines.len());
-    let mut state_finished = false;
-    let i_pattern = Regex::new("move ([0-9]+) from ([0-9]) to ([0-9])")?;
+fn crane_move(input: &str) -> PResult<'_, Move> {
+    map(
+        tuple((
+            preceded(context("\"move \"", tag("move ")), uint),
+            preceded(context("\" from \"", tag(" from ")), uint),
+            preceded(context("\" to \"", tag(" to ")), uint),
+        )),
+        |(quantity, from, to)| Move { quantity, from, to },
+    )(input)
+}
 
-    for line in lines {
-        if line.trim().is_empty() {
-            state_finished = true;
-            continue;
-        }
+fn parse(lines: &[String]) -> AResult<(State, Vec<Move>)> {
+    let [drawing, procedure] = exact_blocks::<2>(lines, "the rearrangement procedure")?;
 
-        // instructions
-        if state_finished {
-            let m = i_pattern.captures(line).unwrap();
-            moves.push(Move {
-                quantity: m.get(1).unwrap().as_str().parse()?,
-                from: m.get(2).unwrap().as_str().parse()?,
-                to: m.get(3).unwrap().as_str().parse()?,
-            });
-            continue;
-        }
+    let crane_positions = (1..drawing.lines[0].len()).step_by(4);
+    let mut state: State = crane_positions.clone().map(|_| Vec::new()).collect();
 
-        // Initial state
+    // Initial state
+    for (row, line) in drawing.lines.iter().enumerate() {
         let chars: Vec<char> = line.chars().collect();
         for col in crane_positions.clone() {
             let crane = col / 4;
@@ -49,12 +46,32 @@ fn parse(lines: &[String]) -> AResult<(State, Vec<Move>)> {
                     let l = state.get_mut(crane).unwrap();
                     l.insert(0, chars[col]);
                 }
-                Some('1'..='9' | ' ') => { /* ignore patterns */ }
-                _ => panic!(),
+                Some('1'..='9' | ' ') | None => { /* ignore patterns */ }
+                Some(c) => Err(ParseError::new(
+                    drawing.start + row + 1,
+                    col + 1,
+                    "a crate letter or stack number",
+                    format!("{c:?}"),
+                ))?,
             };
         }
     }
 
+    // instructions
+    let moves = procedure.parse_lines(crane_move)?;
+    let stacks = 1..=state.len();
+    if let Some(idx) = moves
+        .iter()
+        .position(|m| !stacks.contains(&m.from) || !stacks.contains(&m.to))
+    {
+        Err(ParseError::new(
+            procedure.start + idx + 1,
+            1,
+            format!("a move between stacks 1 and {}", state.len()),
+            format!("{:?}", procedure.lines[idx]),
+        ))?;
+    }
+
     Ok((state, moves))
 }
 
@@ -69,8 +86,10 @@ fn part_a(lines: &[String]) -> AResult<String> {
     let (mut state, moves) = parse(lines)?;
     for Move { quantity, from, to } in moves {
         for _ in 0..quantity {
-            let temp = state.get_mut(from - 1).unwrap().pop().unwrap();
-            state.get_mut(to - 1).unwrap().push(temp);
+            let temp = state[from - 1]
+                .pop()
+                .ok_or_else(|| anyhow!("stack {from} ran out of crates"))?;
+            state[to - 1].push(temp);
         }
     }
 
@@ -80,7 +99,10 @@ fn part_a(lines: &[String]) -> AResult<String> {
 fn part_b(lines: &[String]) -> AResult<String> {
     let (mut state, moves) = parse(lines)?;
     for Move { quantity, from, to } in moves {
-        let split = state[from - 1].len() - quantity;
+        let split = state[from - 1]
+            .len()
+            .checked_sub(quantity)
+            .ok_or_else(|| anyhow!("stack {from} ran out of crates"))?;
         let temp = state.get_mut(from - 1).unwrap().split_off(split);
         state.get_mut(to - 1).unwrap().extend(temp);
     }
@@ -99,13 +121,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice())?);
-    println!("Part B result = {}", part_b(lines.as_slice())?);
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -131,6 +159,29 @@ mod tests {
         ]
     }
 
+    #[test]
+    fn test_parse_errors() {
+        let the_input = test_input();
+        assert_eq!(
+            parse(&the_input[..4]).unwrap_err().to_string(),
+            "line 5, column 1: expected the rearrangement procedure, found end of input"
+        );
+
+        let mut the_input = test_input();
+        the_input[7] = "move 2 from 4 to 1".to_string();
+        assert_eq!(
+            parse(&the_input).unwrap_err().to_string(),
+            "line 8, column 1: expected a move between stacks 1 and 3, found \"move 2 from 4 to 1\""
+        );
+
+        let mut the_input = test_input();
+        the_input[7] = "move 2 from 2 t 1".to_string();
+        assert_eq!(
+            parse(&the_input).unwrap_err().to_string(),
+            "line 8, column 14: expected \" to \", found \" t 1\""
+        );
+    }
+
     #[test]
     fn test_a() -> AResult<()> {
         let the_input = test_input();
@@ -1,14 +1,16 @@
+use advent_common::parsing::{exact_lines, in_file, parse_all, read_lines};
 use humantime::format_duration;
+use nom::{character::complete::alpha1, error::context};
 use regex::Regex;
-use std::{
-    collections::HashSet,
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::{collections::HashSet, time::Instant};
 
 type AResult<T> = anyhow::Result<T>;
 
+fn parse(lines: &[String]) -> AResult<&str> {
+    let [line] = exact_lines::<1>(lines, "the datastream")?;
+    Ok(parse_all(line, context("a lowercase letter", alpha1))?)
+}
+
 fn part_a(line: &str) -> AResult<usize> {
     solve(line, 4)
 }
@@ -25,7 +27,9 @@ fn solve(line: &str, window_size: usize) -> AResult<usize> {
             return Ok(i + window_size);
         }
     }
-    Err(anyhow::format_err!("Not found"))
+    Err(anyhow::format_err!(
+        "no run of {window_size} different characters"
+    ))
 }
 
 fn main() -> AResult<()> {
@@ -39,13 +43,14 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
+    let line = parse(&lines).map_err(in_file(&path))?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.first().unwrap())?);
-    println!("Part B result = {}", part_b(lines.first().unwrap())?);
+    println!("Part A result = {}", part_a(line)?);
+    println!("Part B result = {}", part_b(line)?);
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -72,6 +77,25 @@ mod tests {
         ("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 26),
     ];
 
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse(&[]).unwrap_err().to_string(),
+            "line 1, column 1: expected the datastream, found end of input"
+        );
+
+        let lines = ["mjqjpq mgbl".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 7: expected end of line, found \" mgbl\""
+        );
+
+        assert_eq!(
+            part_a("abcabc").unwrap_err().to_string(),
+            "no run of 4 different characters"
+        );
+    }
+
     #[test]
     fn test_a() -> AResult<()> {
         for (line, exp) in TEST_INPUT {
@@ -1,70 +1,253 @@
+use advent_common::parsing::{in_file, parse_lines, read_lines, PResult, ParseError};
+use anyhow::anyhow;
 use humantime::format_duration;
-use regex::Regex;
-use std::{
-    collections::HashMap,
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{char, digit1, not_line_ending},
+    combinator::{map, map_res, value},
+    error::context,
+    sequence::{preceded, separated_pair},
 };
+use regex::Regex;
+use std::{collections::BTreeMap, time::Instant};
 
 type AResult<T> = anyhow::Result<T>;
 
-fn walk(lines: &[String]) -> HashMap<String, u64> {
-    // First collect file locations & sizes
-    let mut files: HashMap<String, u64> = HashMap::new();
-    let cwd: &mut Vec<&str> = &mut Vec::new();
-    for line in lines {
-        match line.as_str() {
-            "$ cd .." => {
-                cwd.pop();
-            }
-            l if l.starts_with("$ cd ") => {
-                cwd.push(&l[5..]);
-            }
-            l if l.chars().next().unwrap().is_ascii_digit() => {
-                let bits: Vec<_> = l.split(' ').collect();
-                let mut name: String = String::from("/");
-                if !cwd.is_empty() {
-                    name.push_str(cwd.join("/").as_str());
-                    name.push('/');
+#[derive(Debug, Clone)]
+enum Output<'a> {
+    Cd(&'a str),
+    Ls,
+    Dir(&'a str),
+    File(u64, &'a str),
+}
+
+fn output(input: &str) -> PResult<'_, Output<'_>> {
+    context(
+        "a command or a listing",
+        alt((
+            map(preceded(tag("$ cd "), not_line_ending), Output::Cd),
+            value(Output::Ls, tag("$ ls")),
+            map(preceded(tag("dir "), not_line_ending), Output::Dir),
+            map(
+                separated_pair(map_res(digit1, str::parse), char(' '), not_line_ending),
+                |(size, name)| Output::File(size, name),
+            ),
+        )),
+    )(input)
+}
+
+fn parse(lines: &[String]) -> AResult<Vec<Output<'_>>> {
+    Ok(parse_lines(lines, output)?)
+}
+
+type NodeId = usize;
+
+/// The root directory is always the first node
+const ROOT: NodeId = 0;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Kind {
+    // Entries by name
+    Dir(BTreeMap<String, NodeId>),
+    File(u64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Node {
+    name: String,
+    // Only the root has no parent
+    parent: Option<NodeId>,
+    kind: Kind,
+}
+
+/// The directory tree pieced together from a shell transcript
+#[derive(Debug)]
+struct FileSystem {
+    // Nodes are only ever added after their parent
+    nodes: Vec<Node>,
+}
+
+impl FileSystem {
+    fn new() -> Self {
+        FileSystem {
+            nodes: vec![Node {
+                name: "/".to_string(),
+                parent: None,
+                kind: Kind::Dir(BTreeMap::new()),
+            }],
+        }
+    }
+
+    /// Replay the `cd` and `ls` commands, checking they agree with each other
+    fn replay(lines: &[String]) -> AResult<Self> {
+        let mut fs = FileSystem::new();
+        let mut cwd = ROOT;
+        let mut listing = false;
+
+        for (i, out) in parse(lines)?.into_iter().enumerate() {
+            let err = |column, expected: String, found: &str| {
+                ParseError::new(i + 1, column, expected, format!("{found:?}"))
+            };
+
+            let (name, kind) = match out {
+                Output::Cd(dir) => {
+                    cwd = match dir {
+                        "/" => ROOT,
+                        // As in a shell, the root is its own parent
+                        ".." => fs.nodes[cwd].parent.unwrap_or(ROOT),
+                        _ => fs
+                            .entry(cwd, dir)
+                            .filter(|&id| matches!(fs.nodes[id].kind, Kind::Dir(_)))
+                            .ok_or_else(|| {
+                                err(6, format!("a directory in {}", fs.path(cwd)), dir)
+                            })?,
+                    };
+                    listing = false;
+                    continue;
+                }
+                Output::Ls => {
+                    listing = true;
+                    continue;
+                }
+                _ if !listing => Err(err(1, "$ ls before a listing".to_string(), &lines[i]))?,
+                Output::Dir(name) => (name, Kind::Dir(BTreeMap::new())),
+                Output::File(size, name) => (name, Kind::File(size)),
+            };
+
+            // Listing a directory again must agree with what was listed before
+            match fs.entry(cwd, name) {
+                None => fs.add(cwd, name, kind),
+                Some(id) => {
+                    let same = match (&fs.nodes[id].kind, &kind) {
+                        (Kind::Dir(_), Kind::Dir(_)) => true,
+                        (Kind::File(before), Kind::File(now)) => before == now,
+                        _ => false,
+                    };
+                    if !same {
+                        Err(err(
+                            1,
+                            format!("{:?} as listed before", fs.listing(id)),
+                            &lines[i],
+                        ))?;
+                    }
                 }
-                name.push_str(bits[1]);
-                files.insert(name, bits[0].parse().unwrap());
             }
-            _ => { /* ignore everything else */ }
         }
+
+        Ok(fs)
     }
 
-    // Now iterate over files to build the directory sizes
-    let mut dirs: HashMap<_, _> = HashMap::new();
-    for (f_key, f_size) in files {
-        let path: Vec<_> = f_key.split('/').collect();
-        for i in 1..path.len() {
-            let d_key = path[0..i].join("/");
-            dirs.entry(d_key)
-                .and_modify(|d_size| *d_size += f_size)
-                .or_insert(f_size);
+    fn add(&mut self, dir: NodeId, name: &str, kind: Kind) {
+        let id = self.nodes.len();
+        self.nodes.push(Node {
+            name: name.to_string(),
+            parent: Some(dir),
+            kind,
+        });
+        if let Kind::Dir(entries) = &mut self.nodes[dir].kind {
+            entries.insert(name.to_string(), id);
         }
     }
 
-    dirs
+    /// The entry called `name` in the directory `dir`, if there is one
+    fn entry(&self, dir: NodeId, name: &str) -> Option<NodeId> {
+        match &self.nodes[dir].kind {
+            Kind::Dir(entries) => entries.get(name).copied(),
+            Kind::File(_) => None,
+        }
+    }
+
+    fn path(&self, id: NodeId) -> String {
+        match self.nodes[id].parent {
+            None => "/".to_string(),
+            Some(ROOT) => format!("/{}", self.nodes[id].name),
+            Some(parent) => format!("{}/{}", self.path(parent), self.nodes[id].name),
+        }
+    }
+
+    /// The line `ls` shows for a node
+    fn listing(&self, id: NodeId) -> String {
+        let node = &self.nodes[id];
+        match node.kind {
+            Kind::Dir(_) => format!("dir {}", node.name),
+            Kind::File(size) => format!("{size} {}", node.name),
+        }
+    }
+
+    /// The total size of every node, indexed by id
+    fn du(&self) -> Vec<u64> {
+        let mut sizes: Vec<_> = self
+            .nodes
+            .iter()
+            .map(|n| match n.kind {
+                Kind::Dir(_) => 0,
+                Kind::File(size) => size,
+            })
+            .collect();
+
+        // Children come after their parents, so each is complete before it's added on
+        for id in (0..self.nodes.len()).rev() {
+            if let Some(parent) = self.nodes[id].parent {
+                sizes[parent] += sizes[id];
+            }
+        }
+        sizes
+    }
+
+    /// Every directory whose total size matches `pred`, along with that size
+    fn find(&self, pred: impl Fn(u64) -> bool) -> Vec<(NodeId, u64)> {
+        self.du()
+            .into_iter()
+            .enumerate()
+            .filter(|&(id, size)| matches!(self.nodes[id].kind, Kind::Dir(_)) && pred(size))
+            .collect()
+    }
+
+    /// Draw the tree the way the puzzle does, with entries sorted by name
+    fn tree(&self) -> String {
+        let mut tree = String::new();
+        let mut stack = vec![(ROOT, 0)];
+        while let Some((id, depth)) = stack.pop() {
+            let node = &self.nodes[id];
+            let indent = "  ".repeat(depth);
+            match &node.kind {
+                Kind::Dir(entries) => {
+                    tree.push_str(&format!("{indent}- {} (dir)\n", node.name));
+                    stack.extend(entries.values().rev().map(|&e| (e, depth + 1)));
+                }
+                Kind::File(size) => {
+                    tree.push_str(&format!("{indent}- {} (file, size={size})\n", node.name));
+                }
+            }
+        }
+        tree
+    }
 }
 
-fn part_a(lines: &[String]) -> u64 {
-    let mut dirs = walk(lines);
-    dirs.retain(|_, v| *v <= 100_000);
-    dirs.values().sum()
+fn part_a(lines: &[String]) -> AResult<u64> {
+    let fs = FileSystem::replay(lines)?;
+    Ok(fs
+        .find(|size| size <= 100_000)
+        .into_iter()
+        .map(|(_, size)| size)
+        .sum())
 }
 
-fn part_b(lines: &[String]) -> u64 {
-    let dirs = walk(lines);
+fn part_b(lines: &[String]) -> AResult<u64> {
+    let fs = FileSystem::replay(lines)?;
 
-    let free_space = 70_000_000 - dirs.get("").unwrap();
-    let extra_needed = 30_000_000 - free_space;
+    let used = fs.du()[ROOT];
+    let free_space = 70_000_000u64
+        .checked_sub(used)
+        .ok_or_else(|| anyhow!("{used} bytes of files will not fit on the disk"))?;
+    let extra_needed = 30_000_000u64.saturating_sub(free_space);
 
-    let mut dirs: Vec<_> = Vec::from_iter(dirs);
-    dirs.sort_by_key(|(_, v)| *v);
-    dirs.iter().find(|(_, v)| *v >= extra_needed).unwrap().1
+    fs.find(|size| size >= extra_needed)
+        .into_iter()
+        .map(|(_, size)| size)
+        .min()
+        .ok_or_else(|| anyhow!("no directory frees up {extra_needed} bytes"))
 }
 
 fn main() -> AResult<()> {
@@ -75,16 +258,36 @@ fn main() -> AResult<()> {
         .find(name)
         .expect("binary name should contain a number")
         .as_str();
-    println!("Running code for Day {ex}.");
+    // `day_07 --tree` prints the reconstructed filesystem instead of solving
+    let tree = std::env::args().any(|arg| arg == "--tree");
+    if !tree {
+        println!("Running code for Day {ex}.");
+    }
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
+
+    if tree {
+        print!(
+            "{}",
+            FileSystem::replay(lines.as_slice())
+                .map_err(in_file(&path))?
+                .tree()
+        );
+        return Ok(());
+    }
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -121,14 +324,115 @@ mod tests {
     7214296 k";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines = ["$ cd /".to_string(), "$ dir".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 1: expected a command or a listing, found \"$ dir\""
+        );
+
+        let lines = ["$ cd /".to_string(), "1234".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 5: expected a command or a listing, found end of line"
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 95437);
+        assert_eq!(part_a(lines.as_slice())?, 95437);
+        Ok(())
+    }
+
+    #[test]
+    fn test_b() -> AResult<()> {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        assert_eq!(part_b(lines.as_slice())?, 24_933_642);
+        Ok(())
+    }
+
+    #[test]
+    fn test_replay() -> AResult<()> {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        let fs = FileSystem::replay(&lines)?;
+        assert_eq!(fs.du()[ROOT], 48_381_165);
+        let small: Vec<_> = fs
+            .find(|size| size <= 100_000)
+            .into_iter()
+            .map(|(id, size)| (fs.path(id), size))
+            .collect();
+        assert_eq!(
+            small,
+            [("/a".to_string(), 94853), ("/a/e".to_string(), 584)]
+        );
+
+        // Jumping back to the root and listing directories again changes nothing
+        let mut again = lines.clone();
+        again.extend(["$ cd /", "$ ls", "dir d", "$ cd a", "$ ls", "29116 f"].map(String::from));
+        again.extend(["$ cd ..", "$ cd ..", "$ cd d", "$ ls", "7214296 k"].map(String::from));
+        assert_eq!(FileSystem::replay(&again)?.nodes, fs.nodes);
+        assert_eq!(part_a(&again)?, 95437);
+        assert_eq!(part_b(&again)?, 24_933_642);
+        Ok(())
+    }
+
+    #[test]
+    fn test_replay_errors() {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        let replay = |extra: &[&str]| {
+            let mut lines = lines.clone();
+            lines.extend(extra.iter().map(|l| l.to_string()));
+            FileSystem::replay(&lines).unwrap_err().to_string()
+        };
+
+        assert_eq!(
+            replay(&["$ cd /", "$ cd x"]),
+            "line 25, column 6: expected a directory in /, found \"x\""
+        );
+        assert_eq!(
+            replay(&["$ cd /", "$ cd b.txt"]),
+            "line 25, column 6: expected a directory in /, found \"b.txt\""
+        );
+        assert_eq!(
+            replay(&["$ cd /", "$ cd a", "$ cd e", "$ cd a"]),
+            "line 27, column 6: expected a directory in /a/e, found \"a\""
+        );
+        assert_eq!(
+            replay(&["$ cd ..", "12 m"]),
+            "line 25, column 1: expected $ ls before a listing, found \"12 m\""
+        );
+        assert_eq!(
+            replay(&["$ cd /", "$ ls", "dir b.txt"]),
+            "line 26, column 1: expected \"14848514 b.txt\" as listed before, found \"dir b.txt\""
+        );
+        assert_eq!(
+            replay(&["$ cd /", "$ ls", "12 a"]),
+            "line 26, column 1: expected \"dir a\" as listed before, found \"12 a\""
+        );
     }
 
     #[test]
-    fn test_b() {
+    fn test_tree() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(lines.as_slice()), 24_933_642);
+        assert_eq!(
+            FileSystem::replay(&lines)?.tree(),
+            "- / (dir)
+  - a (dir)
+    - e (dir)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir)
+    - d.ext (file, size=5626152)
+    - d.log (file, size=8033020)
+    - j (file, size=4060174)
+    - k (file, size=7214296)
+"
+        );
+        Ok(())
     }
 }
@@ -1,22 +1,19 @@
+use advent_common::parsing::{digit_grid, in_file, read_lines, ParseError};
 use humantime::format_duration;
 use regex::Regex;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
 
-fn parse(lines: &[String]) -> Vec<Vec<usize>> {
-    lines
-        .iter()
-        .map(|l| l.chars().map(|c| (c as usize) - ('0' as usize)).collect())
-        .collect()
+fn parse(lines: &[String]) -> AResult<Vec<Vec<usize>>> {
+    if lines.is_empty() {
+        Err(ParseError::end_of_input(0, "a row of trees"))?;
+    }
+    Ok(digit_grid(lines)?)
 }
 
-fn part_a(lines: &[String]) -> usize {
-    let grid = parse(lines);
+fn part_a(lines: &[String]) -> AResult<usize> {
+    let grid = parse(lines)?;
 
     // Perimiter trees are always visible
     let mut visible = (lines.len() * 2) + ((lines[0].len() - 2) * 2);
@@ -53,7 +50,7 @@ fn part_a(lines: &[String]) -> usize {
         }
     }
 
-    visible
+    Ok(visible)
 }
 
 fn scenic_score(grid: &[Vec<usize>], ri: usize, ci: usize) -> usize {
@@ -103,8 +100,8 @@ fn scenic_score(grid: &[Vec<usize>], ri: usize, ci: usize) -> usize {
     left_score * right_score * up_score * down_score
 }
 
-fn part_b(lines: &[String]) -> usize {
-    let grid = parse(lines);
+fn part_b(lines: &[String]) -> AResult<usize> {
+    let grid = parse(lines)?;
     let mut scores = Vec::with_capacity(grid.len() * grid[0].len());
 
     for (ri, row) in grid.iter().enumerate() {
@@ -113,7 +110,7 @@ fn part_b(lines: &[String]) -> usize {
         }
     }
 
-    *scores.iter().max().unwrap()
+    Ok(*scores.iter().max().unwrap())
 }
 
 fn main() -> AResult<()> {
@@ -127,13 +124,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -152,22 +155,40 @@ mod tests {
     35390";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines = ["303".to_string(), "2x5".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 2: expected a digit, found 'x'"
+        );
+
+        let lines = ["303".to_string(), "25".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 3: expected a row of 3 cells, found 2 cells"
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 21);
+        assert_eq!(part_a(lines.as_slice())?, 21);
+        Ok(())
     }
 
     #[test]
-    fn test_scenic_score() {
+    fn test_scenic_score() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        let grid = parse(&lines);
+        let grid = parse(&lines)?;
         assert_eq!(scenic_score(&grid, 1, 2), 4);
         assert_eq!(scenic_score(&grid, 3, 2), 8);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(lines.as_slice()), 8);
+        assert_eq!(part_b(lines.as_slice())?, 8);
+        Ok(())
     }
 }
@@ -1,28 +1,29 @@
+use advent_common::parsing::{in_file, parse_lines, read_lines, uint, PResult};
 use humantime::format_duration;
-use regex::Regex;
-use std::{
-    collections::HashSet,
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
+use nom::{
+    character::complete::{char, one_of},
+    error::context,
+    sequence::separated_pair,
 };
+use regex::Regex;
+use std::{collections::HashSet, time::Instant};
 
 type AResult<T> = anyhow::Result<T>;
 
-fn parse(lines: &[String]) -> Vec<(char, i32)> {
-    lines
-        .iter()
-        .map(|l| l.split_whitespace().collect::<Vec<_>>())
-        .map(|l| (l[0].chars().next().unwrap(), l[1].parse().unwrap()))
-        .collect()
+fn motion(input: &str) -> PResult<'_, (char, i32)> {
+    separated_pair(context("U, D, L or R", one_of("UDLR")), char(' '), uint)(input)
+}
+
+fn parse(lines: &[String]) -> AResult<Vec<(char, i32)>> {
+    Ok(parse_lines(lines, motion)?)
 }
 
 fn calculate_diff(h: (i32, i32), t: (i32, i32)) -> (i32, i32) {
     (h.0 - t.0, h.1 - t.1)
 }
 
-fn solve(lines: &[String], knots: usize) -> usize {
-    let instr = parse(lines);
+fn solve(lines: &[String], knots: usize) -> AResult<usize> {
+    let instr = parse(lines)?;
     let mut positions: Vec<_> = (0..knots).map(|_| (0, 0)).collect();
     let mut t_visited: HashSet<(i32, i32)> = HashSet::from_iter([(0, 0)]);
 
@@ -33,7 +34,7 @@ fn solve(lines: &[String], knots: usize) -> usize {
                 ('D', (x, y)) => (x, y - 1),
                 ('L', (x, y)) => (x - 1, y),
                 ('R', (x, y)) => (x + 1, y),
-                _ => panic!(),
+                _ => unreachable!(),
             };
 
             // Update the rest of the knots in turn
@@ -54,14 +55,14 @@ fn solve(lines: &[String], knots: usize) -> usize {
             }
         }
     }
-    t_visited.len()
+    Ok(t_visited.len())
 }
 
-fn part_a(lines: &[String]) -> usize {
+fn part_a(lines: &[String]) -> AResult<usize> {
     solve(lines, 2)
 }
 
-fn part_b(lines: &[String]) -> usize {
+fn part_b(lines: &[String]) -> AResult<usize> {
     solve(lines, 10)
 }
 
@@ -76,13 +77,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -113,23 +120,41 @@ mod tests {
                                U 20";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines = ["R 4".to_string(), "X 4".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 1: expected U, D, L or R, found \"X 4\""
+        );
+
+        let lines = ["R 4".to_string(), "U -4".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 3: expected unsigned integer, found \"-4\""
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 13);
+        assert_eq!(part_a(lines.as_slice())?, 13);
+        Ok(())
     }
 
     #[test]
-    fn test_solve() {
+    fn test_solve() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(solve(lines.as_slice(), 2), 13);
+        assert_eq!(solve(lines.as_slice(), 2)?, 13);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(lines.as_slice()), 1);
+        assert_eq!(part_b(lines.as_slice())?, 1);
 
         let lines: Vec<_> = TEST_LONGER.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(lines.as_slice()), 36);
+        assert_eq!(part_b(lines.as_slice())?, 36);
+        Ok(())
     }
 }
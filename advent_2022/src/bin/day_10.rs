@@ -1,14 +1,19 @@
+use advent_common::parsing::{in_file, int, parse_lines, read_lines, PResult};
+use anyhow::anyhow;
 use humantime::format_duration;
-use regex::Regex;
-use std::{
-    collections::VecDeque,
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    combinator::{map, value},
+    error::context,
+    sequence::preceded,
 };
+use regex::Regex;
+use std::{collections::VecDeque, time::Instant};
 
 type AResult<T> = anyhow::Result<T>;
 
+#[derive(Debug, Clone)]
 enum Instruction {
     Noop,
     Addx(i32),
@@ -16,20 +21,22 @@ enum Instruction {
 
 use Instruction::{Addx, Noop};
 
-fn parse(lines: &[String]) -> Vec<Instruction> {
-    lines
-        .iter()
-        .map(|l| l.split_whitespace().collect::<Vec<_>>())
-        .map(|x| match x.as_slice() {
-            ["noop"] => Instruction::Noop,
-            ["addx", ns] => Instruction::Addx(ns.parse().unwrap()),
-            _ => panic!("unknown instruction {x:?}"),
-        })
-        .collect()
+fn instruction(input: &str) -> PResult<'_, Instruction> {
+    context(
+        "noop or addx",
+        alt((
+            value(Noop, tag("noop")),
+            map(preceded(tag("addx "), int), Addx),
+        )),
+    )(input)
 }
 
-fn part_a(lines: &[String]) -> i32 {
-    let instructions = parse(lines);
+fn parse(lines: &[String]) -> AResult<Vec<Instruction>> {
+    Ok(parse_lines(lines, instruction)?)
+}
+
+fn part_a(lines: &[String]) -> AResult<i32> {
+    let instructions = parse(lines)?;
     let mut it = instructions.iter();
     let mut acc = 0;
     let mut clk = 0;
@@ -65,11 +72,11 @@ fn part_a(lines: &[String]) -> i32 {
         x += q.pop_front().unwrap();
     }
 
-    acc
+    Ok(acc)
 }
 
-fn part_b(lines: &[String]) -> String {
-    let instructions = parse(lines);
+fn part_b(lines: &[String]) -> AResult<String> {
+    let instructions = parse(lines)?;
     let mut it = instructions.iter();
     let mut clk: usize = 0;
     let mut q: VecDeque<isize> = VecDeque::new();
@@ -96,6 +103,9 @@ fn part_b(lines: &[String]) -> String {
         // Update CRT
         let crt_row = clk / 40;
         let crt_col = clk.rem_euclid(40);
+        if crt_row >= crt.len() {
+            return Err(anyhow!("the program runs for more than 240 cycles"));
+        }
         if (x - 1..=x + 1).contains(&(crt_col.try_into().unwrap())) {
             // Sprite is overlapping the crt position - mark the crt
             crt[crt_row][crt_col] = '#';
@@ -108,7 +118,7 @@ fn part_b(lines: &[String]) -> String {
     let mut lines: Vec<String> = crt.iter().map(|r| r.iter().collect()).collect();
     lines.insert(0, "\n=======================================".to_string());
     lines.push("=======================================".to_string());
-    lines.join("\n")
+    Ok(lines.join("\n"))
 }
 
 fn main() -> AResult<()> {
@@ -122,13 +132,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -288,15 +304,31 @@ mod tests {
     noop";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines = ["noop".to_string(), "addx".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 1: expected noop or addx, found \"addx\""
+        );
+
+        let lines = ["noop".to_string(), "addx x3".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 6: expected integer, found \"x3\""
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 13140);
+        assert_eq!(part_a(lines.as_slice())?, 13140);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        let out_lines = part_b(lines.as_slice());
+        let out_lines = part_b(lines.as_slice())?;
         assert!(out_lines.contains(
             "##  ##  ##  ##  ##  ##  ##  ##  ##  ##  
 ###   ###   ###   ###   ###   ###   ### 
@@ -305,5 +337,6 @@ mod tests {
 ######      ######      ######      ####
 #######       #######       #######     "
         ));
+        Ok(())
     }
 }
@@ -1,146 +1,287 @@
+use advent_common::{
+    math::lcm_all,
+    parsing::{blocks, in_file, read_lines, uint, PResult, ParseError},
+};
+use anyhow::anyhow;
 use humantime::format_duration;
-use lazy_static::lazy_static;
-use regex::{Captures, Regex, RegexBuilder};
-use std::{collections::VecDeque, fs, path::Path, time::Instant};
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{char, line_ending, multispace1, one_of, space0, space1},
+    combinator::{cut, map, peek, value},
+    error::context,
+    multi::{fold_many0, many1, separated_list1},
+    sequence::{delimited, pair, preceded, separated_pair, terminated, tuple},
+};
+use regex::Regex;
+use std::{collections::VecDeque, time::Instant};
 
 type AResult<T> = anyhow::Result<T>;
 
+/// An arithmetic expression in the old worry level
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Old,
+    Num(i128),
+    // Op(lhs, operator, rhs)
+    Op(Box<Expr>, char, Box<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, old: i128) -> AResult<i128> {
+        match self {
+            Expr::Old => Ok(old),
+            Expr::Num(n) => Ok(*n),
+            Expr::Op(l, op, r) => {
+                let (l, r) = (l.eval(old)?, r.eval(old)?);
+                match op {
+                    '+' => l.checked_add(r),
+                    '-' => l.checked_sub(r),
+                    '*' => l.checked_mul(r),
+                    '/' if r == 0 => return Err(anyhow!("division by zero")),
+                    '/' => l.checked_div(r),
+                    _ => unreachable!(),
+                }
+                .ok_or_else(|| anyhow!("{l} {op} {r} overflows"))
+            }
+        }
+    }
+
+    fn divides(&self) -> bool {
+        match self {
+            Expr::Old | Expr::Num(_) => false,
+            Expr::Op(l, op, r) => *op == '/' || l.divides() || r.divides(),
+        }
+    }
+}
+
+/// Throw to `dest` if the worry level is divisible by `divisor`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Throw {
+    divisor: i128,
+    dest: usize,
+}
+
 #[derive(Debug, PartialEq, Eq)]
 struct Monkey {
     id: usize,
-    items: VecDeque<usize>,
-    op: char,
-    operand: Option<usize>,
-    test_div: usize,
-    dest_t: usize,
-    dest_f: usize,
+    items: VecDeque<i128>,
+    op: Expr,
+    // Tried in order, falling back to `otherwise`
+    throws: Vec<Throw>,
+    otherwise: usize,
     inspects: usize,
 }
 
 impl Monkey {
-    fn new(c: &Captures) -> Option<Monkey> {
-        Some(Monkey {
-            id: c.name("monkey")?.as_str().parse().ok()?,
-            items: c
-                .name("items")?
-                .as_str()
-                .split(", ")
-                .flat_map(str::parse)
-                .collect(),
-            op: c.name("op")?.as_str().chars().next()?,
-            operand: c.name("operand")?.as_str().parse().ok(),
-            test_div: c.name("div")?.as_str().parse().ok()?,
-            dest_t: c.name("dest_t")?.as_str().parse().ok()?,
-            dest_f: c.name("dest_f")?.as_str().parse().ok()?,
-            inspects: 0,
-        })
+    fn target(&self, item: i128) -> usize {
+        self.throws
+            .iter()
+            .find(|t| item.rem_euclid(t.divisor) == 0)
+            .map_or(self.otherwise, |t| t.dest)
     }
 }
 
-fn parse(lines: &str) -> Vec<Monkey> {
-    lazy_static! {
-        static ref RE: Regex = RegexBuilder::new(
-            r##"Monkey (?P<monkey>[0-9]+):
-  Starting items: (?P<items>[0-9, ]+)
-  Operation: new = old (?P<op>[+*]) (?P<operand>old|[0-9]+)
-  Test: divisible by (?P<div>[0-9]+)
-    If true: throw to monkey (?P<dest_t>[0-9]+)
-    If false: throw to monkey (?P<dest_f>[0-9]+)"##
-        )
-        .multi_line(true)
-        .build()
-        .unwrap();
+/// How worry levels are kept in check after each inspection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Relief {
+    /// The monkey gets bored and worry is divided down
+    Divide(i128),
+    /// Worry is only kept modulo the LCM of every divisor, so every test is unaffected
+    Lcm,
+    /// No relief at all - an error once worry overflows
+    Unbounded,
+}
+
+// Each line of a monkey's description starts on a new (indented) line
+fn field<'a, O, F>(label: &'static str, value: F) -> impl FnMut(&'a str) -> PResult<'a, O>
+where
+    F: FnMut(&'a str) -> PResult<'a, O>,
+{
+    preceded(
+        tuple((multispace1, context(label, tag(label)), space1)),
+        value,
+    )
+}
+
+// Usual precedence: `expr := term (+|- term)*`, `term := factor (*|/ factor)*`
+fn expr(input: &str) -> PResult<'_, Expr> {
+    binary(term, "+-")(input)
+}
+
+fn term(input: &str) -> PResult<'_, Expr> {
+    binary(factor, "*/")(input)
+}
+
+fn binary<'a>(
+    mut operand: impl FnMut(&'a str) -> PResult<'a, Expr>,
+    ops: &'static str,
+) -> impl FnMut(&'a str) -> PResult<'a, Expr> {
+    move |input| {
+        let (input, first) = operand(input)?;
+        fold_many0(
+            pair(delimited(space0, one_of(ops), space0), cut(&mut operand)),
+            move || first.clone(),
+            |l, (op, r)| Expr::Op(Box::new(l), op, Box::new(r)),
+        )(input)
     }
+}
 
-    RE.captures_iter(lines)
-        .map(|c| Monkey::new(&c))
-        .map(Option::unwrap)
-        .collect()
+fn factor(input: &str) -> PResult<'_, Expr> {
+    context(
+        "old, a number or a bracket",
+        alt((
+            value(Expr::Old, tag("old")),
+            map(uint, Expr::Num),
+            delimited(
+                pair(char('('), space0),
+                cut(expr),
+                cut(pair(space0, context("')'", char(')')))),
+            ),
+        )),
+    )(input)
 }
 
-fn part_a(lines: &str) -> usize {
-    let mut monkeys = parse(lines);
+fn throws(input: &str) -> PResult<'_, (Vec<Throw>, usize)> {
+    alt((
+        map(
+            tuple((
+                field("Test: divisible by", uint),
+                field("If true: throw to monkey", uint),
+                field("If false: throw to monkey", uint),
+            )),
+            |(divisor, dest, otherwise)| (vec![Throw { divisor, dest }], otherwise),
+        ),
+        pair(
+            many1(map(
+                field(
+                    "If divisible by",
+                    separated_pair(uint, tag(": throw to monkey "), uint),
+                ),
+                |(divisor, dest)| Throw { divisor, dest },
+            )),
+            field("Otherwise: throw to monkey", uint),
+        ),
+    ))(input)
+}
 
-    for _r in 0..20 {
-        for i in 0..monkeys.len() {
-            let m = &mut monkeys[i];
+fn monkey(input: &str) -> PResult<'_, Monkey> {
+    map(
+        tuple((
+            delimited(
+                context("Monkey", tag("Monkey ")),
+                uint,
+                context("':'", tag(":")),
+            ),
+            field("Starting items:", separated_list1(tag(", "), cut(uint))),
+            field(
+                "Operation: new =",
+                terminated(
+                    expr,
+                    preceded(space0, context("+, -, * or /", peek(line_ending))),
+                ),
+            ),
+            throws,
+        )),
+        |(id, items, op, (throws, otherwise))| Monkey {
+            id,
+            items: items.into(),
+            op,
+            throws,
+            otherwise,
+            inspects: 0,
+        },
+    )(input)
+}
 
-            // We need to collect the moves and apply them after the while
-            // loop because the borrow checker won't let us have multiple
-            // mutable references from the monkeys Vec in play at once
-            let mut moves: Vec<_> = Vec::with_capacity(m.items.len());
+fn parse(lines: &[String]) -> AResult<Vec<Monkey>> {
+    let blocks = blocks(lines);
+    let monkeys = blocks
+        .iter()
+        .map(|b| b.parse_joined(monkey))
+        .collect::<Result<Vec<_>, _>>()?;
 
-            while let Some(mut item) = m.items.pop_front() {
-                // Monkey inspects item
-                m.inspects += 1;
-                item = match m {
-                    Monkey {
-                        op: '*', operand, ..
-                    } => item * operand.unwrap_or(item),
-                    Monkey {
-                        op: '+', operand, ..
-                    } => item + operand.unwrap_or(item),
-                    _ => panic!("Unknown op {} {:?}", m.op, m.operand),
-                };
+    // Check the monkeys are in order and only throw to each other
+    for (idx, (block, m)) in blocks.iter().zip(&monkeys).enumerate() {
+        let err = |line: usize, expected: String| {
+            ParseError::new(
+                block.start + line + 1,
+                1,
+                expected,
+                format!("{:?}", block.lines[line]),
+            )
+        };
 
-                // Monkey gets bored
-                item /= 3;
+        if m.id != idx {
+            Err(err(0, format!("Monkey {idx}")))?;
+        }
 
-                // apply the test and collect the results
-                if item.rem_euclid(m.test_div) == 0 {
-                    moves.push((m.dest_t, item));
-                } else {
-                    moves.push((m.dest_f, item));
-                }
+        // Either a Test line followed by If true / If false, or one line per throw
+        let standard = block.lines[3].trim_start().starts_with("Test:");
+        let mut dests: Vec<_> = m.throws.iter().map(|t| t.dest).collect();
+        dests.push(m.otherwise);
+        for (i, t) in m.throws.iter().enumerate() {
+            if t.divisor <= 0 {
+                Err(err(3 + i, "a divisor above zero".to_string()))?;
             }
-
-            // Now apply the moves
-            for (dest, item) in moves {
-                monkeys[dest].items.push_back(item);
+        }
+        for (i, dest) in dests.into_iter().enumerate() {
+            if dest == idx || dest >= monkeys.len() {
+                Err(err(
+                    if standard { 4 + i } else { 3 + i },
+                    format!("another monkey from 0 to {}", monkeys.len() - 1),
+                ))?;
             }
         }
     }
 
-    let mut counts: Vec<_> = monkeys.iter().map(|m| m.inspects).collect();
-    counts.sort_unstable();
-    counts.iter().rev().take(2).product()
+    Ok(monkeys)
 }
 
-fn part_b(lines: &str) -> usize {
-    let mut monkeys = parse(lines);
-
+/// The product of the two highest inspection counts after `rounds` rounds
+fn monkey_business(mut monkeys: Vec<Monkey>, rounds: usize, relief: Relief) -> AResult<usize> {
     // Worry levels can be contained within the range 0..common_factor-1
     // because we only need to work on the relative offset within this
-    // range - not the absolute worry value, and we're only using + and *
-    let common_factor = monkeys.iter().map(|m| m.test_div).product();
+    // range - not the absolute worry value - as long as we're only using +, - and *
+    let common_factor = match relief {
+        Relief::Lcm if monkeys.iter().any(|m| m.op.divides()) => {
+            return Err(anyhow!(
+                "worry levels can't be kept modulo the LCM when monkeys divide"
+            ))
+        }
+        Relief::Lcm => lcm_all(
+            monkeys
+                .iter()
+                .flat_map(|m| m.throws.iter().map(|t| t.divisor)),
+        )
+        .ok_or_else(|| anyhow!("the monkeys' divisors are too large to bound worry levels"))?,
+        Relief::Divide(0) => return Err(anyhow!("worry can't be divided by zero")),
+        Relief::Divide(_) | Relief::Unbounded => 0,
+    };
 
-    for _r in 0..10000 {
+    for _r in 0..rounds {
         for i in 0..monkeys.len() {
             let m = &mut monkeys[i];
+
+            // We need to collect the moves and apply them after the while
+            // loop because the borrow checker won't let us have multiple
+            // mutable references from the monkeys Vec in play at once
             let mut moves: Vec<_> = Vec::with_capacity(m.items.len());
 
             while let Some(mut item) = m.items.pop_front() {
                 // Monkey inspects item
                 m.inspects += 1;
-                item = match m {
-                    Monkey {
-                        op: '*', operand, ..
-                    } => item * operand.unwrap_or(item),
-                    Monkey {
-                        op: '+', operand, ..
-                    } => item + operand.unwrap_or(item),
-                    _ => panic!("Unknown op {} {:?}", m.op, m.operand),
-                };
+                item = m.op.eval(item)?;
 
                 // Apply the worry level control
-                item = item.rem_euclid(common_factor);
+                item = match relief {
+                    Relief::Divide(by) => item.div_euclid(by),
+                    Relief::Lcm => item.rem_euclid(common_factor),
+                    Relief::Unbounded => item,
+                };
 
-                // apply the test and collect the results
-                if item.rem_euclid(m.test_div) == 0 {
-                    moves.push((m.dest_t, item));
-                } else {
-                    moves.push((m.dest_f, item));
-                }
+                // apply the tests and collect the results
+                moves.push((m.target(item), item));
             }
 
             // Now apply the moves
@@ -152,7 +293,15 @@ fn part_b(lines: &str) -> usize {
 
     let mut counts: Vec<_> = monkeys.iter().map(|m| m.inspects).collect();
     counts.sort_unstable();
-    counts.iter().rev().take(2).product()
+    Ok(counts.iter().rev().take(2).product())
+}
+
+fn part_a(lines: &[String]) -> AResult<usize> {
+    monkey_business(parse(lines)?, 20, Relief::Divide(3))
+}
+
+fn part_b(lines: &[String]) -> AResult<usize> {
+    monkey_business(parse(lines)?, 10000, Relief::Lcm)
 }
 
 fn main() -> AResult<()> {
@@ -163,16 +312,44 @@ fn main() -> AResult<()> {
         .find(name)
         .expect("binary name should contain a number")
         .as_str();
-    println!("Running code for Day {ex}.");
+    // `day_11 --no-relief N` runs N rounds with worry never reduced at all, which
+    // only works until the worry levels overflow
+    let args: Vec<_> = std::env::args().collect();
+    let no_relief = match args.iter().position(|arg| arg == "--no-relief") {
+        Some(i) => Some(
+            args.get(i + 1)
+                .and_then(|n| n.parse::<usize>().ok())
+                .ok_or_else(|| anyhow!("--no-relief needs a number of rounds"))?,
+        ),
+        None => None,
+    };
+    if no_relief.is_none() {
+        println!("Running code for Day {ex}.");
+    }
 
     // Load the appropriate input text
-    let file = format!("./data/day_{ex}.txt");
-    let lines = fs::read_to_string(Path::new(file.as_str()))?;
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
+
+    if let Some(rounds) = no_relief {
+        let monkeys = parse(lines.as_slice()).map_err(in_file(&path))?;
+        println!(
+            "Monkey business after {rounds} rounds = {}",
+            monkey_business(monkeys, rounds, Relief::Unbounded)?
+        );
+        return Ok(());
+    }
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(&lines));
-    println!("Part B result = {}", part_b(&lines));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -183,6 +360,7 @@ fn main() -> AResult<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use advent_common::parsing::parse_all;
 
     const TEST_INPUT: &str = "Monkey 0:
   Starting items: 79, 98
@@ -213,19 +391,66 @@ Monkey 3:
     If false: throw to monkey 1";
 
     #[test]
-    fn test_parse() {
-        let monkeys = parse(TEST_INPUT);
+    fn test_parse_errors() {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+
+        let mut broken = lines.clone();
+        broken[2] = "Operation: new = old * (19 +)".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 3, column 29: expected old, a number or a bracket, found \")\""
+        );
+
+        let mut broken = lines.clone();
+        broken[2] = "Operation: new = old % 19".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 3, column 22: expected +, -, * or /, found \"% 19\""
+        );
+
+        let mut broken = lines.clone();
+        broken[1] = "Starting items: 79, x".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 2, column 21: expected unsigned integer, found \"x\""
+        );
+
+        let mut broken = lines.clone();
+        broken[12] = "If false: throw to monkey 4".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 13, column 1: expected another monkey from 0 to 3, found \"If false: throw to monkey 4\""
+        );
+
+        // The real input is indented, which shouldn't change which line is blamed
+        let mut broken: Vec<_> = TEST_INPUT.lines().map(str::to_string).collect();
+        broken[12] = "    If false: throw to monkey 4".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 13, column 1: expected another monkey from 0 to 3, found \"    If false: throw to monkey 4\""
+        );
+
+        let mut broken = lines;
+        broken[7] = "Monkey 2:".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 8, column 1: expected Monkey 1, found \"Monkey 2:\""
+        );
+    }
+
+    #[test]
+    fn test_parse() -> AResult<()> {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        let monkeys = parse(lines.as_slice())?;
         assert_eq!(monkeys.len(), 4);
         assert_eq!(
             monkeys[0],
             Monkey {
                 id: 0,
                 items: VecDeque::from_iter(vec![79, 98]),
-                op: '*',
-                operand: Some(19),
-                test_div: 23,
-                dest_t: 2,
-                dest_f: 3,
+                op: Expr::Op(Box::new(Expr::Old), '*', Box::new(Expr::Num(19))),
+                throws: vec![Throw { divisor: 23, dest: 2 }],
+                otherwise: 3,
                 inspects: 0
             }
         );
@@ -234,23 +459,99 @@ Monkey 3:
             Monkey {
                 id: 2,
                 items: VecDeque::from_iter(vec![79, 60, 97]),
-                op: '*',
-                operand: None,
-                test_div: 13,
-                dest_t: 1,
-                dest_f: 3,
+                op: Expr::Op(Box::new(Expr::Old), '*', Box::new(Expr::Old)),
+                throws: vec![Throw { divisor: 13, dest: 1 }],
+                otherwise: 3,
                 inspects: 0
             }
         );
+        Ok(())
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        assert_eq!(part_a(lines.as_slice())?, 10605);
+        Ok(())
     }
 
     #[test]
-    fn test_a() {
-        assert_eq!(part_a(TEST_INPUT), 10605);
+    fn test_b() -> AResult<()> {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        assert_eq!(part_b(lines.as_slice())?, 2_713_310_158);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
-        assert_eq!(part_b(TEST_INPUT), 2_713_310_158);
+    fn test_expr() -> AResult<()> {
+        let eval = |text: &str, old| -> AResult<i128> { parse_all(text, expr)?.eval(old) };
+        assert_eq!(eval("old * 19", 2)?, 38);
+        assert_eq!(eval("2 + old * 3", 4)?, 14);
+        assert_eq!(eval("(2 + old) * 3", 4)?, 18);
+        assert_eq!(eval("old - 10 - 3", 4)?, -9);
+        assert_eq!(eval("(old + 2) * old - 3 * 2", 4)?, 18);
+        assert_eq!(eval("old / (old - 2)", 10)?, 1);
+        assert_eq!(
+            eval("old / (old - 2)", 2).unwrap_err().to_string(),
+            "division by zero"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_variants() -> AResult<()> {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+
+        // Keeping worry modulo the LCM doesn't change where anything is thrown
+        for rounds in 1..=3 {
+            assert_eq!(
+                monkey_business(parse(&lines)?, rounds, Relief::Lcm)?,
+                monkey_business(parse(&lines)?, rounds, Relief::Unbounded)?
+            );
+        }
+        assert!(monkey_business(parse(&lines)?, 1000, Relief::Unbounded)
+            .unwrap_err()
+            .to_string()
+            .ends_with("overflows"));
+
+        // Any number of throw clauses, and monkeys that divide
+        let variant = "Monkey 0:
+            Starting items: 10, 11, 12, 15
+            Operation: new = (old + 1) * 2 / 3
+            If divisible by 2: throw to monkey 1
+            If divisible by 3: throw to monkey 2
+            Otherwise: throw to monkey 1
+
+            Monkey 1:
+            Starting items: 5
+            Operation: new = old - 1
+            Test: divisible by 5
+            If true: throw to monkey 2
+            If false: throw to monkey 0
+
+            Monkey 2:
+            Starting items: 7
+            Operation: new = old
+            If divisible by 7: throw to monkey 0
+            Otherwise: throw to monkey 1";
+        let lines: Vec<_> = variant.lines().map(|l| l.trim().to_string()).collect();
+        let monkeys = parse(&lines)?;
+        assert_eq!(monkeys[0].throws.len(), 2);
+        // The first matching clause wins
+        assert_eq!(monkeys[0].target(6), 1);
+        assert_eq!(monkeys[0].target(9), 2);
+        assert_eq!(monkeys[0].target(7), 1);
+        // 0 throws everything to 1, which throws everything back
+        assert_eq!(
+            monkey_business(parse(&lines)?, 1, Relief::Unbounded)?,
+            5 * 4
+        );
+        assert_eq!(
+            monkey_business(parse(&lines)?, 1, Relief::Lcm)
+                .unwrap_err()
+                .to_string(),
+            "worry levels can't be kept modulo the LCM when monkeys divide"
+        );
+        Ok(())
     }
 }
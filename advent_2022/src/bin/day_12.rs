@@ -1,42 +1,18 @@
+use advent_common::{
+    parsing::{grid_of, in_file, read_lines},
+    search::bfs,
+};
 use humantime::format_duration;
 use regex::Regex;
-use std::{
-    cmp::Ordering,
-    collections::{BTreeSet, HashMap},
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
 type Coord = (usize, usize);
 
-#[derive(Debug, Eq, Clone, Copy)]
-struct PathPart(u64, Coord);
-
-impl PartialEq for PathPart {
-    fn eq(&self, other: &Self) -> bool {
-        self.cmp(other) == Ordering::Equal
-    }
-}
-
-impl PartialOrd for PathPart {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-impl Ord for PathPart {
-    fn cmp(&self, other: &Self) -> Ordering {
-        match self.0.cmp(&other.0) {
-            Ordering::Equal => self.1.cmp(&other.1),
-            x => x,
-        }
-    }
-}
-
-fn parse(lines: &[String]) -> Vec<Vec<char>> {
-    lines.iter().map(|l| l.chars().collect()).collect()
+fn parse(lines: &[String]) -> AResult<Vec<Vec<char>>> {
+    Ok(grid_of(lines, "a height from a to z, S or E", |c| {
+        matches!(c, 'a'..='z' | 'S' | 'E').then_some(c)
+    })?)
 }
 
 fn find_neighbours<F: Fn(char, char) -> bool>(
@@ -64,24 +40,16 @@ fn find_neighbours<F: Fn(char, char) -> bool>(
     neighbours
 }
 
-#[allow(non_snake_case)]
+fn find(grid: &[Vec<char>], target: char) -> AResult<Coord> {
+    grid.iter()
+        .enumerate()
+        .find_map(|(ri, row)| row.iter().position(|&c| c == target).map(|ci| (ri, ci)))
+        .ok_or_else(|| anyhow::format_err!("{target} not found in grid"))
+}
+
 fn part_a(lines: &[String]) -> AResult<u64> {
-    let grid = parse(lines);
-
-    // it's dijkstra time!
-    let mut dist: HashMap<Coord, u64> = HashMap::new();
-    let mut Q: BTreeSet<PathPart> = BTreeSet::new();
-
-    'outer: for (ri, row) in grid.iter().enumerate() {
-        for (ci, _) in row.iter().enumerate() {
-            let coords = (ri, ci);
-            if grid[ri][ci] == 'S' {
-                dist.insert(coords, 0);
-                Q.insert(PathPart(0, coords));
-                break 'outer;
-            }
-        }
-    }
+    let grid = parse(lines)?;
+    let start = find(&grid, 'S')?;
 
     // Define the function for valid moves
     let can_move = |old: char, new: char| match (old, new) {
@@ -95,72 +63,36 @@ fn part_a(lines: &[String]) -> AResult<u64> {
         (o, n) => (n <= ((o as u8) + 1) as char) && n >= 'a',
     };
 
-    while let Some(u_pp) = Q.pop_first() {
-        let PathPart(u_dist, u) = u_pp;
-        if grid[u.0][u.1] == 'E' {
-            return Ok(u_dist); // reached the target
-        }
-
-        let neighbours = find_neighbours(&grid, u, can_move);
-        for v in neighbours {
-            let alt = u_dist + 1;
-            let v_dist = *dist.get(&v).unwrap_or(&u64::MAX);
-            if alt < v_dist {
-                dist.insert(v, alt);
-                Q.remove(&PathPart(v_dist, v));
-                Q.insert(PathPart(alt, v));
-            }
-        }
-    }
+    // Every step costs the same so a breadth first search will do
+    let path = bfs(
+        start,
+        |&u| find_neighbours(&grid, u, can_move),
+        |&(ri, ci)| grid[ri][ci] == 'E',
+    )
+    .ok_or_else(|| anyhow::format_err!("Not found"))?;
 
-    Err(anyhow::format_err!("Not found"))
+    Ok(path.len() as u64 - 1)
 }
 
-#[allow(non_snake_case)]
 fn part_b(lines: &[String]) -> AResult<u64> {
-    let grid = parse(lines);
-
-    // it's dijkstra time again only this time in reverse
-    let mut dist: HashMap<Coord, u64> = HashMap::new();
-    let mut Q: BTreeSet<PathPart> = BTreeSet::new();
-
-    'outer: for (ri, row) in grid.iter().enumerate() {
-        for (ci, _) in row.iter().enumerate() {
-            let coords = (ri, ci);
-            if grid[ri][ci] == 'E' {
-                dist.insert(coords, 0);
-                Q.insert(PathPart(0, coords));
-                break 'outer;
-            }
-        }
-    }
+    let grid = parse(lines)?;
+    let start = find(&grid, 'E')?;
 
-    // Define the function for valid moves
+    // Search again only this time in reverse
     let can_move = |old: char, new: char| match (old, new) {
         ('E', 'z' | 'y') | ('b', 'S') => true,
         ('E', _) => false,
         (n, o) => (n <= ((o as u8) + 1) as char) && n >= 'a',
     };
 
-    while let Some(u_pp) = Q.pop_first() {
-        let PathPart(u_dist, u) = u_pp;
-        if grid[u.0][u.1] == 'a' {
-            return Ok(u_dist); // reached the target
-        }
-
-        let neighbours = find_neighbours(&grid, u, can_move);
-        for v in neighbours {
-            let alt = u_dist + 1;
-            let v_dist = *dist.get(&v).unwrap_or(&u64::MAX);
-            if alt < v_dist {
-                dist.insert(v, alt);
-                Q.remove(&PathPart(v_dist, v));
-                Q.insert(PathPart(alt, v));
-            }
-        }
-    }
+    let path = bfs(
+        start,
+        |&u| find_neighbours(&grid, u, can_move),
+        |&(ri, ci)| grid[ri][ci] == 'a',
+    )
+    .ok_or_else(|| anyhow::format_err!("Not found"))?;
 
-    Err(anyhow::format_err!("Not found"))
+    Ok(path.len() as u64 - 1)
 }
 
 fn main() -> AResult<()> {
@@ -174,13 +106,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice())?);
-    println!("Part B result = {}", part_b(lines.as_slice())?);
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -198,6 +136,21 @@ mod tests {
     acctuvwj
     abdefghi";
 
+    #[test]
+    fn test_parse_errors() {
+        let lines = ["Sabq".to_string(), "abEx".to_string(), "ac1z".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 3, column 3: expected a height from a to z, S or E, found '1'"
+        );
+
+        let lines = ["Sabq".to_string(), "abE".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 4: expected a row of 4 cells, found 3 cells"
+        );
+    }
+
     #[test]
     fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
@@ -1,121 +1,58 @@
+use advent_2022::packet::{packet, Packet};
+use advent_common::parsing::{blocks, exact_lines, in_file, parse_all, read_lines};
 use humantime::format_duration;
-use nom::{
-    branch::alt,
-    bytes::complete::tag,
-    character::complete::digit1,
-    combinator::map,
-    multi::{fold_many1, separated_list0},
-    sequence::delimited,
-    IResult,
-};
 use regex::Regex;
-use std::{
-    cmp::Ordering,
-    collections::BTreeSet,
-    fs::File,
-    io::{BufRead, BufReader},
-    iter::zip,
-    str::FromStr,
-    time::Instant,
-};
-use Element::{List, Num};
+use std::{cmp::Ordering, time::Instant};
 
 type AResult<T> = anyhow::Result<T>;
 
-#[derive(Debug, PartialEq, Eq)]
-enum Element {
-    List(Vec<Element>),
-    Num(usize),
-}
-
-impl Ord for Element {
-    fn cmp(&self, other: &Self) -> Ordering {
-        match (&self, &other) {
-            (Num(l), Num(r)) => l.cmp(r),
-            (List(l), List(r)) => {
-                // Calculate order of the common child items
-                let common = zip(l, r)
-                    .map(|(l, r)| l.cmp(r))
-                    .find(|&v| v != Ordering::Equal);
-
-                if let Some(x) = common {
-                    x // One of the pairs of children are ordered - return that order
-                } else {
-                    // All of the children are in order - check lengths
-                    l.len().cmp(&r.len())
-                }
-            }
-            (Element::List(_), Num(r)) => self.cmp(&List(vec![Num(*r)])),
-            (Num(l), Element::List(_)) => List(vec![Num(*l)]).cmp(other),
-        }
-    }
-}
-
-impl PartialOrd for Element {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-// Using nom for parsing - define the functions here
-
-fn number(input: &str) -> IResult<&str, Element> {
-    map(fold_many1(digit1, String::new, |acc, s| acc + s), |x| {
-        Element::Num(usize::from_str(x.as_str()).unwrap())
-    })(input)
-}
-
-fn list(input: &str) -> IResult<&str, Element> {
-    map(
-        delimited(tag("["), separated_list0(tag(","), element), tag("]")),
-        Element::List,
-    )(input)
-}
-
-fn element(input: &str) -> IResult<&str, Element> {
-    alt((number, list))(input)
-}
-
-// End of nom parsing functions
-
-fn parse(lines: &[String]) -> Vec<(Element, Element)> {
-    lines
-        .chunks(3)
-        .map(|chunk| (element(&chunk[0]).unwrap().1, element(&chunk[1]).unwrap().1))
+fn parse(lines: &[String]) -> AResult<Vec<(Packet, Packet)>> {
+    blocks(lines)
+        .into_iter()
+        .map(|block| {
+            let [left, right] = exact_lines::<2>(block.lines, "a pair of packets")
+                .map_err(|e| e.offset_by(block.start))?;
+            let packet = |line: &str, idx: usize| {
+                parse_all(line, packet).map_err(|e| e.offset_by(block.start + idx))
+            };
+            Ok((packet(left, 0)?, packet(right, 1)?))
+        })
         .collect()
 }
 
-fn part_a(lines: &[String]) -> usize {
-    let pairs = parse(lines);
+fn part_a(lines: &[String]) -> AResult<usize> {
+    let pairs = parse(lines)?;
     let mut acc = 0;
 
     for (idx, (e1, e2)) in pairs.into_iter().enumerate() {
-        if e1 < e2 {
+        if e1.compare(&e2) == Ordering::Less {
             acc += idx + 1;
         }
     }
 
-    acc
+    Ok(acc)
 }
 
-fn part_b(lines: &[String]) -> usize {
-    let pairs = parse(lines);
+fn part_b(lines: &[String]) -> AResult<usize> {
+    let pairs = parse(lines)?;
 
     // Create the specified divider packets
-    let two = &List(vec![List(vec![Num(2)])]);
-    let six = &List(vec![List(vec![Num(6)])]);
-
-    // Create the flattened list of packets (using a sorted set)
-    let mut flat: BTreeSet<_> = BTreeSet::from_iter(vec![two, six]);
-    let (a, b): (Vec<_>, Vec<_>) = pairs.into_iter().unzip();
-    flat.extend(a.iter());
-    flat.extend(b.iter());
-
-    // Locate the dividers
-    let i2 = flat.iter().enumerate().find(|(_, v)| *v == &two).unwrap().0 + 1;
-    let i6 = flat.iter().enumerate().find(|(_, v)| *v == &six).unwrap().0 + 1;
-
-    i2 * i6
+    let two: Packet = "[[2]]".parse()?;
+    let six: Packet = "[[6]]".parse()?;
+
+    // Each divider's position in the sorted packets is one more than the number of
+    // packets before it - and [[6]] also comes after [[2]]
+    let packets: Vec<_> = pairs.into_iter().flat_map(|(a, b)| [a, b]).collect();
+    let before = |divider: &Packet| {
+        packets
+            .iter()
+            .filter(|p| p.compare(divider) == Ordering::Less)
+            .count()
+    };
+    let i2 = before(&two) + 1;
+    let i6 = before(&six) + 2;
+
+    Ok(i2 * i6)
 }
 
 fn main() -> AResult<()> {
@@ -129,13 +66,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -171,37 +114,71 @@ mod tests {
         [1,[2,[3,[4,[5,6,7]]]],8,9]
         [1,[2,[3,[4,[5,6,0]]]],8,9]";
 
+    #[test]
+    fn test_parse_errors() {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+
+        let mut broken = lines.clone();
+        broken[4] = "[[1],4".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 5, column 7: expected ',' or ']', found end of line"
+        );
+
+        let mut broken = lines.clone();
+        broken[4] = "[[1],,4]".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 5, column 5: expected ',' or ']', found \",,4]\""
+        );
+
+        let mut broken = lines;
+        broken.remove(4);
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 5, column 1: expected a pair of packets, found end of input"
+        );
+    }
+
     #[test]
     fn test_parse() {
-        assert_eq!(Num(1), element("1").unwrap().1);
-        assert_eq!(List(vec![]), element("[]").unwrap().1);
-        assert_eq!(List(vec![Num(1)]), element("[1]").unwrap().1);
+        use Packet::{List, Num};
+
+        assert_eq!(Num(1), parse_all("1", packet).unwrap());
+        assert_eq!(List(vec![]), parse_all("[]", packet).unwrap());
+        assert_eq!(List(vec![Num(1)]), parse_all("[1]", packet).unwrap());
         assert_eq!(
             List(vec![Num(1), Num(2), Num(3)]),
-            element("[1,2,3]").unwrap().1
+            parse_all("[1,2,3]", packet).unwrap()
         );
         assert_eq!(
             List(vec![
                 List(vec![Num(1), Num(2), Num(3)]),
                 List(vec![Num(1), Num(2), Num(3)])
             ]),
-            element("[[1,2,3],[1,2,3]]").unwrap().1
+            parse_all("[[1,2,3],[1,2,3]]", packet).unwrap()
         );
         assert_eq!(
             List(vec![List(vec![]), List(vec![])]),
-            element("[[],[]]").unwrap().1
+            parse_all("[[],[]]", packet).unwrap()
         );
     }
 
     #[test]
-    fn test_a() {
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 13);
+        assert_eq!(part_a(lines.as_slice())?, 13);
+
+        // Packets the puzzle counts as equal are neither in nor out of order
+        let lines = ["[1]", "[[1]]", "", "[[1]]", "[1]"].map(str::to_string);
+        assert_eq!(part_a(&lines)?, 0);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(lines.as_slice()), 140);
+        assert_eq!(part_b(lines.as_slice())?, 140);
+        Ok(())
     }
 }
@@ -1,148 +1,287 @@
+use advent_common::parsing::{in_file, parse_lines, read_lines, uint, PResult, ParseError};
+use anyhow::anyhow;
 use humantime::format_duration;
+use nom::{
+    bytes::complete::tag, character::complete::char, combinator::cut, error::context,
+    multi::separated_list1, sequence::separated_pair,
+};
 use regex::Regex;
 use std::{
-    collections::HashMap,
-    fs::File,
-    io::{BufRead, BufReader},
+    collections::{HashMap, HashSet},
+    fmt::Write,
     time::Instant,
 };
 
 type AResult<T> = anyhow::Result<T>;
+type Pos = (usize, usize);
+
+const SPAWN: Pos = (500, 0);
 
-const SPAWN: (usize, usize) = (500, 0);
+fn rock_path(input: &str) -> PResult<'_, Vec<Pos>> {
+    separated_list1(
+        context("\" -> \"", tag(" -> ")),
+        cut(separated_pair(uint, char(','), uint)),
+    )(input)
+}
 
-fn parse(lines: &[String]) -> (HashMap<(usize, usize), char>, usize) {
-    let rocks: Vec<_> = lines
+fn parse(lines: &[String]) -> AResult<Vec<Vec<Pos>>> {
+    let rocks = parse_lines(lines, rock_path)?;
+    if let Some(idx) = rocks
         .iter()
-        .map(|l| -> Vec<(usize, usize)> {
-            l.split(" -> ")
-                .map(|c| -> (usize, usize) {
-                    let n: Vec<_> = c.split(',').map(|v| v.parse().unwrap()).collect();
-                    (n[0], n[1])
-                })
-                .collect()
-        })
-        .collect();
-
-    let mut max_y = 0;
-    let mut grid = HashMap::new();
-    for segments in rocks {
-        for segment in segments.windows(2) {
-            let x1 = segment.iter().map(|s| s.0).min().unwrap();
-            let x2 = segment.iter().map(|s| s.0).max().unwrap();
-            let y1 = segment.iter().map(|s| s.1).min().unwrap();
-            let y2 = segment.iter().map(|s| s.1).max().unwrap();
-
-            for x in x1..=x2 {
-                for y in y1..=y2 {
-                    grid.insert((x, y), '#');
-                    max_y = std::cmp::max(y, max_y);
+        .position(|r| r.windows(2).any(|s| s[0].0 != s[1].0 && s[0].1 != s[1].1))
+    {
+        Err(ParseError::new(
+            idx + 1,
+            1,
+            "horizontal and vertical lines",
+            format!("{:?}", lines[idx]),
+        ))?;
+    }
+    Ok(rocks)
+}
+
+/// What sand finds once it falls past the lowest rock
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bottom {
+    // It keeps falling forever
+    Abyss,
+    // It lands on an endless floor two below the lowest rock
+    Floor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tile {
+    Rock,
+    Sand,
+}
+
+/// The rocks, the sand come to rest so far and where sand pours in from
+#[derive(Debug, Clone)]
+struct Cave {
+    tiles: HashMap<Pos, Tile>,
+    sources: Vec<Pos>,
+    lowest: usize,
+    bottom: Bottom,
+}
+
+impl Cave {
+    fn new(rocks: &[Vec<Pos>], sources: &[Pos], bottom: Bottom) -> AResult<Cave> {
+        let mut cave = Cave {
+            tiles: HashMap::new(),
+            sources: Vec::new(),
+            lowest: 0,
+            bottom,
+        };
+        for path in rocks {
+            for segment in path.windows(2) {
+                let ((x1, y1), (x2, y2)) = (segment[0], segment[1]);
+                for x in x1.min(x2)..=x1.max(x2) {
+                    for y in y1.min(y2)..=y1.max(y2) {
+                        cave.block((x, y))?;
+                    }
                 }
             }
+            if let [single] = path[..] {
+                cave.block(single)?;
+            }
         }
+        for &source in sources {
+            cave.add_source(source)?;
+        }
+        Ok(cave)
     }
 
-    (grid, max_y)
-}
+    /// Put an obstacle at `pos`, which counts as rock
+    fn block(&mut self, pos: Pos) -> AResult<()> {
+        if self.sources.contains(&pos) {
+            return Err(anyhow!("{pos:?} can't be blocked, sand pours in there"));
+        }
+        self.tiles.insert(pos, Tile::Rock);
+        self.lowest = self.lowest.max(pos.1);
+        self.sources.iter().try_for_each(|&s| self.check_source(s))
+    }
 
-fn _display(grid: &HashMap<(usize, usize), char>) {
-    let min_x = grid.keys().map(|k| k.0).min().unwrap();
-    let min_y = 0; // Always 0 because of the sand start position
-    let max_x = grid.keys().map(|k| k.0).max().unwrap();
-    let max_y = grid.keys().map(|k| k.1).max().unwrap();
-
-    let max_x = std::cmp::max(max_x, 500);
-
-    println!("=========================");
-    for y in min_y..=max_y {
-        print!("{y:02}  ");
-        for x in min_x..=max_x {
-            match grid.get(&(x, y)) {
-                Some(c) => print!("{c}"),
-                None if (x, y) == SPAWN => print!("+"),
-                None => print!(" "),
-            }
+    fn add_source(&mut self, source: Pos) -> AResult<()> {
+        self.check_source(source)?;
+        self.sources.push(source);
+        Ok(())
+    }
+
+    /// Sand has to pour in from open air above the floor, if there is one
+    fn check_source(&self, source: Pos) -> AResult<()> {
+        if self.tiles.contains_key(&source) {
+            Err(anyhow!(
+                "sand can't pour in from {source:?}, it's inside rock"
+            ))
+        } else if self.bottom == Bottom::Floor && source.1 >= self.floor() {
+            Err(anyhow!(
+                "sand can't pour in from {source:?}, the floor is at y = {}",
+                self.floor()
+            ))
+        } else {
+            Ok(())
         }
-        println!();
     }
-    println!("=========================");
-}
 
-fn part_a(lines: &[String]) -> usize {
-    let (mut grid, floor) = parse(lines);
-    let starting_len = grid.len();
-
-    'outer: loop {
-        let (mut sx, mut sy) = SPAWN;
-        loop {
-            let mut moved = false;
-            if grid.get(&(sx, sy + 1)).is_none() {
-                // Can move directly down
-                sy += 1;
-                moved = true;
-            } else if !moved && grid.get(&(sx - 1, sy + 1)).is_none() {
-                // Down is blocked, try down-left
-                sx -= 1;
-                sy += 1;
-                moved = true;
-            } else if !moved && grid.get(&(sx + 1, sy + 1)).is_none() {
-                sx += 1;
-                sy += 1;
-                moved = true;
-            }
+    fn floor(&self) -> usize {
+        self.lowest + 2
+    }
 
-            if !moved {
-                // if we've not moved - then this is the final position of this sand
-                // and we can try an put another block of sand in
-                grid.insert((sx, sy), 'o');
-                break;
-            }
+    fn is_free(&self, pos: Pos) -> bool {
+        self.tile(pos).is_none()
+    }
 
-            if sy > floor {
-                // Stop the sim - some sand is in the abyss
-                break 'outer;
-            }
+    /// Where a grain at `pos` moves next, if anywhere
+    fn fall(&self, (x, y): Pos) -> Option<Pos> {
+        [Some(x), x.checked_sub(1), Some(x + 1)]
+            .into_iter()
+            .flatten()
+            .map(|x| (x, y + 1))
+            .find(|&p| self.is_free(p))
+    }
+
+    fn sand(&self) -> usize {
+        self.tiles.values().filter(|&&t| t == Tile::Sand).count()
+    }
+
+    /// How much sand there will be once no more can come to rest on the floor,
+    /// counting row by row instead of dropping each grain. With a floor every tile
+    /// that sand could fall into from a source fills up in the end, and sand can
+    /// reach a tile if it's not rock and one of the three above it is sand.
+    fn fill_count(&self) -> usize {
+        let floor = self.floor();
+        let top = self.sources.iter().map(|s| s.1).min().unwrap_or(floor);
+        let mut row: HashSet<usize> = HashSet::new();
+        let mut count = 0;
+        for y in top..floor {
+            let mut next: HashSet<usize> = row
+                .iter()
+                .flat_map(|&x| [x.checked_sub(1), Some(x), Some(x + 1)])
+                .flatten()
+                .collect();
+            next.extend(self.sources.iter().filter(|s| s.1 == y).map(|s| s.0));
+            next.retain(|&x| self.tiles.get(&(x, y)) != Some(&Tile::Rock));
+            count += next.len();
+            row = next;
         }
+        count
+    }
+
+    /// The smallest rectangle holding every rock, grain and source, as
+    /// `(top left, bottom right)`
+    fn bounds(&self) -> (Pos, Pos) {
+        let all = || self.tiles.keys().chain(&self.sources);
+        let min_x = all().map(|p| p.0).min().unwrap_or(0);
+        let max_x = all().map(|p| p.0).max().unwrap_or(0);
+        let min_y = all().map(|p| p.1).min().unwrap_or(0);
+        let max_y = all().map(|p| p.1).max().unwrap_or(0);
+        ((min_x, min_y), (max_x, max_y))
     }
 
-    grid.len() - starting_len
+    fn tile(&self, pos: Pos) -> Option<Tile> {
+        match self.tiles.get(&pos) {
+            None if self.bottom == Bottom::Floor && pos.1 == self.floor() => Some(Tile::Rock),
+            t => t.copied(),
+        }
+    }
+
+    /// The cave within `bounds`, drawn as in the puzzle
+    fn ascii(&self, ((x1, y1), (x2, y2)): (Pos, Pos)) -> String {
+        (y1..=y2)
+            .map(|y| {
+                (x1..=x2)
+                    .map(|x| match self.tile((x, y)) {
+                        Some(Tile::Rock) => '#',
+                        Some(Tile::Sand) => 'o',
+                        None if self.sources.contains(&(x, y)) => '+',
+                        None => '.',
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The cave within `bounds` as a plain PPM image, one pixel per tile
+    fn ppm(&self, ((x1, y1), (x2, y2)): (Pos, Pos)) -> String {
+        let mut out = format!("P3\n{} {}\n255\n", x2 - x1 + 1, y2 - y1 + 1);
+        for y in y1..=y2 {
+            let pixels: Vec<_> = (x1..=x2)
+                .map(|x| match self.tile((x, y)) {
+                    Some(Tile::Rock) => "110 100 90",
+                    Some(Tile::Sand) => "230 190 90",
+                    None if self.sources.contains(&(x, y)) => "220 40 40",
+                    None => "16 16 32",
+                })
+                .collect();
+            let _ = writeln!(out, "{}", pixels.join(" "));
+        }
+        out
+    }
 }
 
-fn part_b(lines: &[String]) -> usize {
-    let (mut grid, max_y) = parse(lines);
-    let floor = max_y + 1;
-    let starting_len = grid.len();
-
-    'outer: loop {
-        let (mut sx, mut sy) = SPAWN;
-        loop {
-            let mut moved = false;
-            if grid.get(&(sx, sy + 1)).is_none() && sy < floor {
-                // Can move directly down
-                sy += 1;
-                moved = true;
-            } else if !moved && grid.get(&(sx - 1, sy + 1)).is_none() && sy < floor {
-                // Down is blocked, try down-left
-                sx -= 1;
-                sy += 1;
-                moved = true;
-            } else if !moved && grid.get(&(sx + 1, sy + 1)).is_none() && sy < floor {
-                sx += 1;
-                sy += 1;
-                moved = true;
-            }
+/// Drops grains from each source in turn. Each source remembers the path its last
+/// grain took, so the next one can pick up from the last tile of it that's still
+/// free instead of falling all the way from the top again.
+struct Simulation {
+    cave: Cave,
+    paths: Vec<Vec<Pos>>,
+    // Sources that are buried, or that lost a grain to the abyss
+    done: Vec<bool>,
+    next: usize,
+}
 
-            if !moved {
-                grid.insert((sx, sy), 'o');
-                if SPAWN == (sx, sy) {
-                    break 'outer;
-                }
-                break;
+impl Simulation {
+    fn new(cave: Cave) -> Simulation {
+        let paths = cave.sources.iter().map(|&s| vec![s]).collect();
+        let done = vec![false; cave.sources.len()];
+        Simulation { cave, paths, done, next: 0 }
+    }
+
+    /// Drop the next grain, returning where it came to rest or `None` once every
+    /// source is done
+    fn drop_grain(&mut self) -> Option<Pos> {
+        let count = self.done.len();
+        let source = (0..count)
+            .map(|i| (self.next + i) % count)
+            .find(|&i| !self.done[i])?;
+        self.next = (source + 1) % count;
+
+        // Another source's sand may have landed anywhere on the path, and the grain
+        // would have gone the same way up to there
+        let path = &mut self.paths[source];
+        if let Some(taken) = path.iter().position(|&p| !self.cave.is_free(p)) {
+            path.truncate(taken);
+        }
+        let Some(&(mut at)) = path.last() else {
+            self.done[source] = true;
+            return self.drop_grain();
+        };
+        while let Some(next) = self.cave.fall(at) {
+            if self.cave.bottom == Bottom::Abyss && next.1 > self.cave.lowest {
+                self.done[source] = true;
+                return self.drop_grain();
             }
+            path.push(next);
+            at = next;
         }
+        self.cave.tiles.insert(at, Tile::Sand);
+        Some(at)
+    }
+
+    fn run(&mut self) -> usize {
+        while self.drop_grain().is_some() {}
+        self.cave.sand()
     }
+}
+
+fn part_a(lines: &[String]) -> AResult<usize> {
+    let cave = Cave::new(&parse(lines)?, &[SPAWN], Bottom::Abyss)?;
+    Ok(Simulation::new(cave).run())
+}
 
-    grid.len() - starting_len
+fn part_b(lines: &[String]) -> AResult<usize> {
+    Ok(Cave::new(&parse(lines)?, &[SPAWN], Bottom::Floor)?.fill_count())
 }
 
 fn main() -> AResult<()> {
@@ -153,16 +292,92 @@ fn main() -> AResult<()> {
         .find(name)
         .expect("binary name should contain a number")
         .as_str();
-    println!("Running code for Day {ex}.");
+    // `day_14 --draw` prints the cave once the sand has stopped coming to rest in part
+    // A, `day_14 --frames DIR` writes part B's sand filling the cave out to DIR as PPM
+    // images, and `--source X,Y` (which can be given more than once) pours the sand in
+    // from somewhere other than 500,0
+    let args: Vec<_> = std::env::args().collect();
+    let draw = args.iter().any(|arg| arg == "--draw");
+    let frames = match args.iter().position(|arg| arg == "--frames") {
+        Some(i) => Some(
+            args.get(i + 1)
+                .ok_or_else(|| anyhow!("--frames needs a directory"))?,
+        ),
+        None => None,
+    };
+    let sources = args
+        .windows(2)
+        .filter(|w| w[0] == "--source")
+        .map(|w| {
+            w[1].split_once(',')
+                .and_then(|(x, y)| Some((x.parse().ok()?, y.parse().ok()?)))
+                .ok_or_else(|| anyhow!("--source needs X,Y, not {:?}", w[1]))
+        })
+        .collect::<AResult<Vec<Pos>>>()?;
+    let sources = if sources.is_empty() {
+        vec![SPAWN]
+    } else {
+        sources
+    };
+    if !draw && frames.is_none() {
+        println!("Running code for Day {ex}.");
+    }
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
+
+    if draw {
+        let rocks = parse(lines.as_slice()).map_err(in_file(&path))?;
+        let mut sim = Simulation::new(Cave::new(&rocks, &sources, Bottom::Abyss)?);
+        sim.run();
+        println!("{}", sim.cave.ascii(sim.cave.bounds()));
+        return Ok(());
+    }
+
+    if let Some(dir) = frames {
+        let rocks = parse(lines.as_slice()).map_err(in_file(&path))?;
+        let cave = Cave::new(&rocks, &sources, Bottom::Floor)?;
+
+        // Run through once to see how far the sand spreads, so every frame is the
+        // same size
+        let mut sim = Simulation::new(cave.clone());
+        sim.run();
+        let ((x1, y1), (x2, _)) = sim.cave.bounds();
+        let bounds = ((x1, y1), (x2, sim.cave.floor()));
+
+        let mut sim = Simulation::new(cave);
+        for frame in 0.. {
+            std::fs::write(format!("{dir}/sand_{frame:04}.ppm"), sim.cave.ppm(bounds))?;
+            if (0..100).any(|_| sim.drop_grain().is_none()) {
+                std::fs::write(
+                    format!("{dir}/sand_{:04}.ppm", frame + 1),
+                    sim.cave.ppm(bounds),
+                )?;
+                break;
+            }
+        }
+        return Ok(());
+    }
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    if sources == [SPAWN] {
+        println!(
+            "Part A result = {}",
+            part_a(lines.as_slice()).map_err(in_file(&path))?
+        );
+        println!(
+            "Part B result = {}",
+            part_b(lines.as_slice()).map_err(in_file(&path))?
+        );
+    } else {
+        let rocks = parse(lines.as_slice()).map_err(in_file(&path))?;
+        let abyss = Cave::new(&rocks, &sources, Bottom::Abyss)?;
+        let floor = Cave::new(&rocks, &sources, Bottom::Floor)?;
+        println!("Part A result = {}", Simulation::new(abyss).run());
+        println!("Part B result = {}", floor.fill_count());
+    }
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -177,15 +392,127 @@ mod tests {
     const TEST_INPUT: &str = "498,4 -> 498,6 -> 496,6
     503,4 -> 502,4 -> 502,9 -> 494,9";
 
+    fn rocks() -> Vec<Vec<Pos>> {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        parse(&lines).unwrap()
+    }
+
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines = ["498,4 -> 498,6".to_string(), "503,4 -> 502,x".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 14: expected unsigned integer, found \"x\""
+        );
+
+        let lines = ["498,4 -> 498,6".to_string(), "503,4 - 502,4".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 6: expected end of line, found \" - 502,4\""
+        );
+
+        let lines = ["498,4 -> 498,6".to_string(), "503,4 -> 502,5".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 1: expected horizontal and vertical lines, found \"503,4 -> 502,5\""
+        );
+
+        assert_eq!(
+            Cave::new(&rocks(), &[(498, 5)], Bottom::Abyss)
+                .unwrap_err()
+                .to_string(),
+            "sand can't pour in from (498, 5), it's inside rock"
+        );
+
+        // Nothing can pour in from the floor or below it, but the abyss is fine
+        assert_eq!(
+            Cave::new(&rocks(), &[(500, 11)], Bottom::Floor)
+                .unwrap_err()
+                .to_string(),
+            "sand can't pour in from (500, 11), the floor is at y = 11"
+        );
+        assert!(Cave::new(&rocks(), &[(500, 11)], Bottom::Abyss).is_ok());
+        let mut cave = Cave::new(&rocks(), &[(500, 10)], Bottom::Floor).unwrap();
+        assert_eq!(
+            cave.block((500, 10)).unwrap_err().to_string(),
+            "(500, 10) can't be blocked, sand pours in there"
+        );
+        assert!(cave.block((480, 20)).is_ok());
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 24);
+        assert_eq!(part_a(lines.as_slice())?, 24);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(lines.as_slice()), 93);
+        assert_eq!(part_b(lines.as_slice())?, 93);
+        Ok(())
+    }
+
+    #[test]
+    fn test_simulation() -> AResult<()> {
+        let mut sim = Simulation::new(Cave::new(&rocks(), &[SPAWN], Bottom::Abyss)?);
+        assert_eq!(sim.drop_grain(), Some((500, 8)));
+        assert_eq!(sim.drop_grain(), Some((499, 8)));
+        assert_eq!(sim.run(), 24);
+        assert_eq!(
+            sim.cave.ascii(sim.cave.bounds()),
+            "......+...
+..........
+......o...
+.....ooo..
+....#ooo##
+...o#ooo#.
+..###ooo#.
+....oooo#.
+.o.ooooo#.
+#########."
+        );
+
+        // Dropping every grain on the floor agrees with counting row by row
+        let mut sim = Simulation::new(Cave::new(&rocks(), &[SPAWN], Bottom::Floor)?);
+        assert_eq!(sim.run(), 93);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sources_and_obstacles() -> AResult<()> {
+        let sources = [(495, 0), (500, 0), (503, 2)];
+        let mut cave = Cave::new(&rocks(), &sources, Bottom::Floor)?;
+        cave.block((499, 3))?;
+        cave.block((500, 3))?;
+        cave.block((501, 3))?;
+
+        let count = cave.fill_count();
+        let mut sim = Simulation::new(cave.clone());
+        assert_eq!(sim.run(), count);
+        for source in sources {
+            assert_eq!(sim.cave.tile(source), Some(Tile::Sand));
+        }
+
+        // Sand piles up on the obstacles and no longer reaches the rock below them
+        let mut sim = Simulation::new(cave);
+        assert_eq!(sim.drop_grain(), Some((495, 8)));
+        assert_eq!(sim.drop_grain(), Some((500, 2)));
+        assert_eq!(sim.drop_grain(), Some((504, 10)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ppm() -> AResult<()> {
+        let mut sim = Simulation::new(Cave::new(&rocks(), &[SPAWN], Bottom::Abyss)?);
+        sim.drop_grain();
+        let image = sim.cave.ppm(((499, 0), (501, 9)));
+        let lines: Vec<_> = image.lines().collect();
+        assert_eq!(lines[..3], ["P3", "3 10", "255"]);
+        assert_eq!(lines[3], "16 16 32 220 40 40 16 16 32");
+        assert_eq!(lines[3 + 8], "16 16 32 230 190 90 16 16 32");
+        assert_eq!(lines.len(), 13);
+        Ok(())
     }
 }
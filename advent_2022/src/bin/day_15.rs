@@ -1,17 +1,21 @@
+use advent_common::parsing::{in_file, int, parse_lines, read_lines, PResult};
 use humantime::format_duration;
-use lazy_static::lazy_static;
+use nom::{
+    bytes::complete::tag,
+    combinator::map,
+    error::context,
+    sequence::{preceded, tuple},
+};
 use regex::Regex;
 use std::{
     cmp::{max, min},
     collections::BTreeSet,
-    fs::File,
-    io::{BufRead, BufReader},
-    isize,
     time::Instant,
 };
 
 type AResult<T> = anyhow::Result<T>;
 
+#[derive(Debug)]
 struct Sensor {
     sx: isize,
     sy: isize,
@@ -30,34 +34,26 @@ struct Coverage {
     beacon_count: isize,
 }
 
-fn parse(lines: &[String]) -> Vec<Sensor> {
-    lazy_static! {
-        static ref PATTERN: Regex = Regex::new(
-            r##"^Sensor at x=(-?[0-9]+), y=(-?[0-9]+): closest beacon is at x=(-?[0-9]+), y=(-?[0-9]+)$"##
-        ).unwrap();
-    }
-
-    let mut out: Vec<Sensor> = vec![];
-
-    for line in lines {
-        let v: Vec<isize> = PATTERN
-            .captures(line)
-            .unwrap_or_else(|| panic!("Line doesn't match the pattern - \n{line:?}"))
-            .iter()
-            .skip(1)
-            .map(Option::unwrap)
-            .map(|c| c.as_str().parse())
-            .map(Result::unwrap)
-            .collect();
-
-        if let [sx, sy, bx, by] = v[0..4] {
-            out.push(Sensor { sx, sy, bx, by });
-        } else {
-            panic!();
-        }
-    }
+fn sensor(input: &str) -> PResult<'_, Sensor> {
+    map(
+        tuple((
+            preceded(context("\"Sensor at x=\"", tag("Sensor at x=")), int),
+            preceded(context("\", y=\"", tag(", y=")), int),
+            preceded(
+                context(
+                    "\": closest beacon is at x=\"",
+                    tag(": closest beacon is at x="),
+                ),
+                int,
+            ),
+            preceded(context("\", y=\"", tag(", y=")), int),
+        )),
+        |(sx, sy, bx, by)| Sensor { sx, sy, bx, by },
+    )(input)
+}
 
-    out
+fn parse(lines: &[String]) -> AResult<Vec<Sensor>> {
+    Ok(parse_lines(lines, sensor)?)
 }
 
 fn merge(r1: &(isize, isize), r2: (isize, isize)) -> Option<(isize, isize)> {
@@ -113,18 +109,18 @@ fn row_coverage(sensors: &[Sensor], target_y: isize) -> Coverage {
     }
 }
 
-fn part_a(lines: &[String], target_y: isize) -> isize {
-    let sensors = parse(lines);
+fn part_a(lines: &[String], target_y: isize) -> AResult<isize> {
+    let sensors = parse(lines)?;
     let Coverage { row, beacon_count } = row_coverage(&sensors, target_y);
     assert_eq!(row.len(), 1);
-    (1 + (row[0].1 - row[0].0)) - beacon_count
     // The 1+ is because the ranges are inclusive
+    Ok((1 + (row[0].1 - row[0].0)) - beacon_count)
 }
 
 fn part_b(lines: &[String], y_max: isize) -> AResult<isize> {
     // If we assume the distress beacon is inside the area delimited by the
     // sensors ranges we need to look for a gap inside one of the rows
-    let sensors = parse(lines);
+    let sensors = parse(lines)?;
 
     for y in 0..=y_max {
         let row = row_coverage(&sensors, y).row;
@@ -148,13 +144,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice(), 2_000_000));
-    println!("Part B result = {}", part_b(lines.as_slice(), 4_000_000)?);
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice(), 2_000_000).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice(), 4_000_000).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -182,20 +184,37 @@ mod tests {
     Sensor at x=20, y=1: closest beacon is at x=15, y=3";
 
     #[test]
-    fn test_small() {
+    fn test_parse_errors() {
+        let lines = ["Sensor at x=2, y=18: closest beacon at x=-2, y=15".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 20: expected \": closest beacon is at x=\", found \": closest beacon at \"..."
+        );
+
+        let lines = ["Sensor at x=2, y=18: closest beacon is at x=-2, y=".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 51: expected integer, found end of line"
+        );
+    }
+
+    #[test]
+    fn test_small() -> AResult<()> {
         assert_eq!(
             part_a(
                 &["Sensor at x=8, y=7: closest beacon is at x=2, y=10".to_string()],
                 10
-            ),
+            )?,
             12
         );
+        Ok(())
     }
 
     #[test]
-    fn test_a() {
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice(), 10), 26);
+        assert_eq!(part_a(lines.as_slice(), 10)?, 26);
+        Ok(())
     }
 
     #[test]
@@ -1,9 +1,22 @@
+use advent_common::{
+    parsing::{in_file, parse_lines, read_lines, uint, PResult, ParseError},
+    search::floyd_warshall,
+};
+use anyhow::anyhow;
 use humantime::format_duration;
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_while_m_n},
+    combinator::{cut, map},
+    error::context,
+    multi::separated_list1,
+    sequence::{preceded, tuple},
+};
 use regex::Regex;
 use std::{
-    collections::{BTreeSet, HashMap, HashSet},
-    fs::File,
-    io::{BufRead, BufReader},
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    fmt,
     time::Instant,
 };
 
@@ -16,305 +29,240 @@ struct Valve {
     tunnels: Vec<String>,
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
-struct Path(usize, usize, Vec<String>);
-
-#[derive(Debug)]
-struct Distances<'a> {
-    dist: HashMap<(&'a String, &'a String), usize>,
-    next: HashMap<(&'a String, &'a String), &'a String>,
+fn valve_id(input: &str) -> PResult<'_, String> {
+    context(
+        "a valve name",
+        map(
+            take_while_m_n(2, 2, |c: char| c.is_ascii_uppercase()),
+            String::from,
+        ),
+    )(input)
 }
 
-impl<'a> Distances<'a> {
-    fn new(valves: &'a HashMap<String, Valve>) -> Self {
-        let mut dist = HashMap::new();
-        let mut next = HashMap::new();
-
-        for u in valves.values() {
-            for v in &u.tunnels {
-                dist.insert((&u.id, v), 1);
-                next.insert((&u.id, v), v);
-            }
-
-            dist.insert((&u.id, &u.id), 0);
-            next.insert((&u.id, &u.id), &u.id);
-        }
-
-        for k in valves.keys() {
-            for i in valves.keys() {
-                for j in valves.keys() {
-                    let o_dik = dist.get(&(i, k));
-                    let o_dkj = dist.get(&(k, j));
-                    let dij = dist.get(&(i, j)).unwrap_or(&usize::MAX);
-
-                    if let (Some(dik), Some(dkj)) = (o_dik, o_dkj) {
-                        let alt = dik + dkj;
-                        if dij > &alt {
-                            dist.insert((i, j), alt);
-                            next.insert((i, j), next[&(i, k)]);
-                        }
-                    }
-                }
-            }
-        }
-
-        Distances { dist, next }
-    }
-
-    fn route(&self, src: String, dest: &String) -> Vec<String> {
-        let mut r = vec![src];
-        while r.last().unwrap() != dest {
-            let k = &(&r.last().unwrap()[..2].to_string(), dest);
-            let next = self.next[k].clone();
-            r.push(next);
-        }
-        r.remove(0); // remove the start otherwise it'll be duplicated
-        r.push(format!("{dest}_O")); // Open the valve when we get to it
-        r
-    }
+fn valve(input: &str) -> PResult<'_, Valve> {
+    map(
+        tuple((
+            preceded(context("\"Valve \"", tag("Valve ")), valve_id),
+            preceded(context("\" has flow rate=\"", tag(" has flow rate=")), uint),
+            preceded(
+                context(
+                    "the tunnels",
+                    alt((
+                        tag("; tunnels lead to valves "),
+                        tag("; tunnel leads to valve "),
+                    )),
+                ),
+                separated_list1(tag(", "), cut(valve_id)),
+            ),
+        )),
+        |(id, rate, tunnels)| Valve { id, rate, tunnels },
+    )(input)
 }
 
 fn parse(lines: &[String]) -> AResult<HashMap<String, Valve>> {
-    let pattern = Regex::new(
-        "^Valve ([A-Z]{2}) has flow rate=([0-9]+); tunnel(?:s)? lead(?:s)? to valve(?:s)? (.*)$",
-    )?;
-    Ok(lines
-        .iter()
-        .map(|l| {
-            let c = pattern
-                .captures(l)
-                .unwrap_or_else(|| panic!("regex does not match - {l}"));
-            let id = c.get(1).unwrap().as_str().to_string();
-            let v = Valve {
-                id: id.clone(),
-                rate: c.get(2).unwrap().as_str().parse().unwrap(),
-                tunnels: c
-                    .get(3)
-                    .unwrap()
-                    .as_str()
-                    .split(", ")
-                    .map(Into::into)
-                    .collect(),
-            };
-            (id, v)
-        })
-        .collect())
-}
-
-fn bounds(path: &[String], valves: &HashMap<String, Valve>) -> (usize, usize) {
-    let current = {
-        let mut acc = 0;
-        for (t, a) in path.iter().enumerate() {
-            if a.ends_with("_O") {
-                let rem_time = 30 - t;
-                acc += rem_time * valves[&a[0..2]].rate;
-            }
+    let valves = parse_lines(lines, valve)?;
+
+    // Every tunnel has to lead somewhere, and we always start from AA
+    let known: HashSet<_> = valves.iter().map(|v| v.id.as_str()).collect();
+    for (idx, v) in valves.iter().enumerate() {
+        if let Some(t) = v.tunnels.iter().find(|t| !known.contains(t.as_str())) {
+            Err(ParseError::new(
+                idx + 1,
+                lines[idx].rfind(t.as_str()).unwrap_or_default() + 1,
+                "a tunnel to a known valve",
+                format!("{t:?}"),
+            ))?;
         }
-        acc
-    };
-
-    if path.len() == 31 {
-        // Quick exit if the path is 30 minutes long as we can't possibly do more
-        return (current, current);
     }
-
-    // Upper bound is if all valves were instantly activtated - not possible in
-    // the actual scenario but it is easy to calculate
-    let remaining = {
-        let remaining_mins: usize = 31 - path.len();
-        let all: HashSet<_> = valves.keys().filter(|&v| valves[v].rate > 0).collect();
-        let open_valves: Vec<_> = path[1..]
-            .iter()
-            .filter_map(|x| x.strip_suffix("_O"))
-            .map(ToOwned::to_owned)
-            .collect();
-
-        let path_set = open_valves.iter().collect();
-        let mut unopened_valves: Vec<_> = all.difference(&path_set).collect();
-        unopened_valves.sort_by_key(|&&v| valves[v].rate);
-
-        let tot: usize = unopened_valves
-            .iter()
-            .map(|&&v| valves[v].rate)
-            .take(remaining_mins)
-            .sum();
-
-        tot * remaining_mins
-    };
-
-    (current, current + remaining)
-}
-
-#[allow(clippy::similar_names)]
-fn part_a(lines: &[String]) -> AResult<usize> {
-    let valves = parse(lines)?;
-    let all_valves: HashSet<String> = valves
-        .iter()
-        .filter_map(|(k, v)| if v.rate > 0 { Some(k) } else { None })
-        .cloned()
-        .collect();
-
-    // Create a distance matrix for all valve pairs
-    let distances = Distances::new(&valves);
-
-    let mut queue: BTreeSet<Path> = BTreeSet::new();
-    let start = vec!["AA".to_string()];
-    let (lb, ub) = bounds(&start, &valves);
-
-    queue.insert(Path(
-        ub, // upper bound - what happens if we activate all valves now
-        lb, // lower bound - the current pressure
-        start,
-    ));
-
-    // path is the option with the highest possible upper bound
-    while let Some(Path(ub, lb, path)) = queue.pop_last() {
-        // Exit when we have a fully realised path that is the maximal path
-        if ub == lb {
-            return Ok(ub);
-        }
-
-        let open_valves: HashSet<String> = path
-            .iter()
-            .filter_map(|x| x.strip_suffix("_O"))
-            .map(str::to_string)
-            .collect();
-
-        let unopened_valves: Vec<_> = all_valves.difference(&open_valves).collect();
-
-        for n in unopened_valves {
-            let n = n.clone();
-            let mut new_path: Vec<_> = path.clone();
-            new_path.extend(distances.route(path.last().unwrap().clone(), &n));
-
-            // calculate new bounds for new path
-            let (new_lb, new_ub) = bounds(&new_path, &valves);
-            // Enqueue if it's not too long
-            if new_path.len() <= 31 {
-                let new_path = Path(new_ub, new_lb, new_path);
-                queue.insert(new_path);
-            }
-        }
-
-        // Remove elements from the queue if the upper bound is < the the original
-        // lb for this potential solution
-        queue.retain(|x| x.0 >= lb);
+    if !known.contains("AA") {
+        Err(ParseError::end_of_input(lines.len(), "valve AA"))?;
     }
 
-    Err(anyhow::format_err!("Solution is not found"))
+    Ok(valves.into_iter().map(|v| (v.id.clone(), v)).collect())
 }
-
-#[derive(PartialEq, Eq, Clone)]
-struct BPath {
-    path: Vec<String>,
-    score: usize,
-    opened: HashSet<String>,
+/// The valves worth opening, numbered so that a set of them fits in a `u64`
+#[derive(Debug)]
+struct Network {
+    names: Vec<String>,
+    rates: Vec<usize>,
+    /// Minutes to walk from one valve to another, with the start (AA) as the
+    /// last row
+    dist: Vec<Vec<Option<usize>>>,
 }
 
-impl PartialOrd for BPath {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
-    }
+/// One agent's walk: the valves it opens, as `(minute, valve)`, and the
+/// pressure they release
+#[derive(Debug, Clone, Default)]
+struct Route {
+    pressure: usize,
+    opens: Vec<(usize, usize)>,
 }
 
-impl Ord for BPath {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        (self.score, &self.path).cmp(&(other.score, &other.path))
-    }
+/// The best the agents can do together, and which valve each opens when
+#[derive(Debug)]
+struct Plan {
+    pressure: usize,
+    schedule: Vec<Vec<(usize, String)>>,
 }
 
-fn path_score(p: &[String], valves: &HashMap<String, Valve>) -> usize {
-    let mut acc: usize = 0;
-    for (t, a) in p.iter().enumerate() {
-        if a.ends_with("_O") {
-            let rem_time = 25 - t;
-            acc += rem_time * valves[&a[0..2]].rate;
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.pressure)?;
+        for (agent, opens) in self.schedule.iter().enumerate() {
+            write!(f, "\n  agent {} opens ", agent + 1)?;
+            if opens.is_empty() {
+                write!(f, "nothing")?;
+            }
+            for (i, (minute, valve)) in opens.iter().enumerate() {
+                let sep = if i == 0 { "" } else { ", " };
+                write!(f, "{sep}{valve} at minute {minute}")?;
+            }
         }
+        Ok(())
     }
-    acc
 }
 
-impl BPath {
-    fn new(path: Vec<String>, valves: &HashMap<String, Valve>, opened: &HashSet<String>) -> BPath {
-        BPath {
-            score: path_score(&path, valves),
-            path,
-            opened: opened.clone(),
+impl Network {
+    fn new(valves: &HashMap<String, Valve>) -> AResult<Network> {
+        let mut useful: Vec<_> = valves.values().filter(|v| v.rate > 0).collect();
+        useful.sort_by(|a, b| a.id.cmp(&b.id));
+        if useful.len() > 64 {
+            return Err(anyhow!(
+                "{} valves have a flow rate, but at most 64 can be tracked",
+                useful.len()
+            ));
         }
-    }
-}
-
-fn part_b(lines: &[String]) -> AResult<usize> {
-    let valves = parse(lines)?;
-    let all_valves: HashSet<String> = valves
-        .iter()
-        .filter_map(|(k, v)| if v.rate > 0 { Some(k) } else { None })
-        .cloned()
-        .collect();
-
-    // Create a distance matrix for all valve pairs
-    let distances = &Distances::new(&valves);
-
-    // Generate the paths possible in the time limit (26 minutes)
-    // Search for the two largest (non-overlapping) paths in that set
-
-    let mut paths: BTreeSet<BPath> = BTreeSet::new();
-    let mut queue: Vec<Vec<String>> = vec![];
-    let mut first = true;
-
-    while !queue.is_empty() || first {
-        first = false;
-
-        let path = queue.pop().unwrap_or_default();
-        let loc = path.last().unwrap_or(&"AA".to_string()).clone()[..2].to_string();
 
-        let visited = &path
+        let all = floyd_warshall(valves.keys().cloned(), |v: &String| {
+            valves[v].tunnels.iter().map(|t| (t.clone(), 1))
+        });
+        let start = "AA".to_string();
+        let dist = useful
             .iter()
-            .filter_map(|x| x.strip_suffix("_O").map(str::to_string))
+            .map(|v| &v.id)
+            .chain([&start])
+            .map(|from| useful.iter().map(|to| all.distance(from, &to.id)).collect())
             .collect();
 
-        let unopened = all_valves.difference(visited);
+        Ok(Network {
+            names: useful.iter().map(|v| v.id.clone()).collect(),
+            rates: useful.iter().map(|v| v.rate).collect(),
+            dist,
+        })
+    }
 
-        for next in unopened {
-            let dist = distances.dist[&(&loc, next)];
-            if path.len() + dist < 26 {
-                let route = distances.route(loc.clone(), next);
-                queue.push(path.iter().cloned().chain(route.iter().cloned()).collect());
-            }
+    /// The best single-agent route for every set of valves that can be opened
+    /// within `minutes`
+    fn routes(&self, minutes: usize) -> HashMap<u64, Route> {
+        let mut best = HashMap::new();
+        let start = self.names.len();
+        self.explore(start, minutes, minutes, 0, &mut Route::default(), &mut best);
+        best
+    }
+
+    fn explore(
+        &self,
+        at: usize,
+        minutes: usize,
+        left: usize,
+        opened: u64,
+        route: &mut Route,
+        best: &mut HashMap<u64, Route>,
+    ) {
+        if best
+            .get(&opened)
+            .is_none_or(|b: &Route| b.pressure < route.pressure)
+        {
+            best.insert(opened, route.clone());
         }
 
-        if !path.is_empty() {
-            paths.insert(BPath::new(path, &valves, visited));
+        for next in 0..self.names.len() {
+            let Some(d) = self.dist[at][next] else {
+                continue;
+            };
+            // Only worth the trip if the valve is then open for a minute or more
+            if opened & 1 << next != 0 || d + 1 >= left {
+                continue;
+            }
+            let left = left - d - 1;
+            route.pressure += self.rates[next] * left;
+            route.opens.push((minutes - left, next));
+            self.explore(next, minutes, left, opened | 1 << next, route, best);
+            route.opens.pop();
+            route.pressure -= self.rates[next] * left;
         }
     }
 
-    let best_segment_score = paths.last().unwrap().score;
-    let mut best_sofar = best_segment_score;
+    /// The most pressure `agents` can release in `minutes`, with each valve
+    /// opened by at most one of them
+    fn plan(&self, minutes: usize, agents: usize) -> Plan {
+        if agents == 0 {
+            return Plan { pressure: 0, schedule: vec![] };
+        }
 
-    for left in paths.iter().rev() {
-        if left.score + best_segment_score < best_sofar {
-            break;
+        let routes = self.routes(minutes);
+        let mut singles: Vec<_> = routes.iter().map(|(&m, r)| (r.pressure, m)).collect();
+        singles.sort_unstable_by(|a, b| b.cmp(a));
+
+        // The best split of each set of valves among all but the last agent
+        let mut teams: HashMap<u64, (usize, Vec<u64>)> = HashMap::from([(0, (0, vec![]))]);
+        for _ in 1..agents {
+            let mut next: HashMap<u64, (usize, Vec<u64>)> = HashMap::new();
+            for (mask, (pressure, members)) in &teams {
+                for &(p, m) in singles.iter().filter(|(_, m)| mask & m == 0) {
+                    let total = pressure + p;
+                    if next.get(&(mask | m)).is_none_or(|t| t.0 < total) {
+                        let members = members.iter().copied().chain([m]).collect();
+                        next.insert(mask | m, (total, members));
+                    }
+                }
+            }
+            teams = next;
         }
 
-        // Iterate over the other paths (in descending score order) where the right
-        // path is completely disjoint with the left path
-        for right in paths
-            .iter()
-            .rev()
-            .filter(|o| left.opened.is_disjoint(&o.opened))
-        {
-            if left.score + right.score <= best_sofar {
+        // The last agent takes the best route that doesn't clash, searching
+        // both lists best first so we can stop as soon as nothing can win
+        let mut teams: Vec<_> = teams.into_iter().collect();
+        teams.sort_unstable_by_key(|(_, (pressure, _))| Reverse(*pressure));
+        let mut best = (0, vec![0; agents]);
+        for (mask, (pressure, members)) in teams {
+            if pressure + singles[0].0 <= best.0 {
                 break;
             }
-
-            let combined_score = left.score + right.score;
-            if combined_score > best_sofar {
-                best_sofar = combined_score;
+            for &(p, m) in &singles {
+                if pressure + p <= best.0 {
+                    break;
+                }
+                if mask & m == 0 {
+                    best = (pressure + p, members.iter().copied().chain([m]).collect());
+                    break;
+                }
             }
         }
+
+        Plan {
+            pressure: best.0,
+            schedule: best
+                .1
+                .iter()
+                .map(|m| {
+                    routes[m]
+                        .opens
+                        .iter()
+                        .map(|&(minute, v)| (minute, self.names[v].clone()))
+                        .collect()
+                })
+                .collect(),
+        }
     }
+}
+
+fn part_a(lines: &[String]) -> AResult<Plan> {
+    Ok(Network::new(&parse(lines)?)?.plan(30, 1))
+}
 
-    Ok(best_sofar)
+fn part_b(lines: &[String]) -> AResult<Plan> {
+    Ok(Network::new(&parse(lines)?)?.plan(26, 2))
 }
 
 fn main() -> AResult<()> {
@@ -328,13 +276,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice())?);
-    println!("Part B result = {}", part_b(lines.as_slice())?);
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -358,34 +312,90 @@ mod tests {
     Valve JJ has flow rate=21; tunnel leads to valve II";
 
     #[test]
-    fn test_bounds() -> AResult<()> {
+    fn test_parse_errors() {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        let valves = parse(lines.as_slice())?;
-        let path = [
-            "AA", "DD", "DD_O", "CC", "BB", "BB_O", "AA", "II", "JJ", "JJ_O", "II", "AA", "DD",
-            "EE", "FF", "GG", "HH", "HH_O", "GG", "FF", "EE", "EE_O", "DD", "CC", "CC_O",
-        ]
-        .into_iter()
-        .map(str::to_string)
-        .collect::<Vec<String>>();
-
-        assert_eq!(path.len(), 25);
-
-        // Cost of this path should be exactly 1651
-        assert_eq!((1651, 1651), bounds(path.as_slice(), &valves));
-
-        for i in 1..path.len() {
-            let (lb, ub) = bounds(&path[..i], &valves);
-            assert!(lb < 1651);
-            assert!(ub > 1651);
-        }
+
+        let mut broken = lines.clone();
+        broken[1] = "Valve BB has flow rate=13; tunnels lead to valves CC, a".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 2, column 55: expected a valve name, found \"a\""
+        );
+
+        let mut broken = lines.clone();
+        broken[1] = "Valve BB has flow rate=13; tunnels lead to valves CC, ZZ".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 2, column 55: expected a tunnel to a known valve, found \"ZZ\""
+        );
+
+        let mut broken = lines;
+        broken[1] = "Valve BB has flow rate 13; tunnels lead to valves CC, AA".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 2, column 9: expected \" has flow rate=\", found \" has flow rate 13; t\"..."
+        );
+    }
+
+    fn network() -> AResult<Network> {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        Network::new(&parse(&lines)?)
+    }
+
+    fn opens(schedule: &[(usize, String)]) -> Vec<(usize, &str)> {
+        schedule.iter().map(|(t, v)| (*t, v.as_str())).collect()
+    }
+
+    #[test]
+    fn test_schedule() -> AResult<()> {
+        let network = network()?;
+
+        let plan = network.plan(30, 1);
+        assert_eq!(
+            opens(&plan.schedule[0]),
+            [
+                (2, "DD"),
+                (5, "BB"),
+                (9, "JJ"),
+                (17, "HH"),
+                (21, "EE"),
+                (24, "CC")
+            ]
+        );
+
+        let mut plan = network.plan(26, 2);
+        plan.schedule.sort();
+        assert_eq!(opens(&plan.schedule[0]), [(2, "DD"), (7, "HH"), (11, "EE")]);
+        assert_eq!(opens(&plan.schedule[1]), [(3, "JJ"), (7, "BB"), (9, "CC")]);
+        assert_eq!(plan.to_string().lines().next(), Some("1707"),);
+        Ok(())
+    }
+
+    #[test]
+    fn test_limits() -> AResult<()> {
+        let network = network()?;
+
+        // DD is a minute away and takes a minute to open, so it needs a third
+        // minute to release anything
+        assert_eq!(network.plan(2, 1).pressure, 0);
+        assert_eq!(network.plan(2, 1).to_string(), "0\n  agent 1 opens nothing");
+        assert_eq!(network.plan(3, 1).pressure, 20);
+        assert_eq!(network.plan(30, 0).pressure, 0);
+
+        // More agents never hurt, and never share a valve
+        let plan = network.plan(26, 3);
+        assert!(plan.pressure >= 1707);
+        let mut opened: Vec<_> = plan.schedule.iter().flatten().map(|(_, v)| v).collect();
+        opened.sort();
+        opened.dedup();
+        assert_eq!(opened.len(), plan.schedule.iter().map(Vec::len).sum());
         Ok(())
     }
 
     #[test]
     fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice())?, 1651);
+        assert_eq!(part_a(lines.as_slice())?.pressure, 1651);
         Ok(())
     }
 
@@ -393,7 +403,7 @@ mod tests {
     fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
         let r = part_b(lines.as_slice())?;
-        assert_eq!(r, 1707);
+        assert_eq!(r.pressure, 1707);
         Ok(())
     }
 }
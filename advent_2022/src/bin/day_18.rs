@@ -1,209 +1,33 @@
+use advent_2022::voxels::{Shape, Voxel};
+use advent_common::parsing::{in_file, parse_lines, read_lines, uint, PResult, ParseError};
+use anyhow::anyhow;
 use humantime::format_duration;
-use regex::Regex;
-use std::{
-    cmp::{max, min},
-    collections::{HashMap, HashSet},
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
+use nom::{
+    character::complete::char,
+    sequence::{preceded, tuple},
 };
+use regex::Regex;
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
 
-type Coord = (u32, u32, u32);
-
-#[derive(Debug, Hash, PartialEq, Eq)]
-struct Face(Coord, Coord);
-
-impl Face {
-    fn new(c1: Coord, c2: Coord) -> Face {
-        // Order to coords in a face so we have consistent Map keys
-        Face(min(c1, c2), max(c1, c2))
-    }
-}
-
-fn parse(lines: &[String]) -> Vec<Coord> {
-    lines
-        .iter()
-        .map(|l| {
-            l.split(',')
-                .map(|s| s.parse::<u32>().unwrap())
-                .collect::<Vec<_>>()
-        })
-        // shift all of the coordinates by 1 so we don't have to deal with
-        // bounds issues on usizes
-        .map(|s| (s[0] + 1, s[1] + 1, s[2] + 1))
-        .collect()
+fn cube(input: &str) -> PResult<'_, Voxel> {
+    tuple((uint, preceded(char(','), uint), preceded(char(','), uint)))(input)
 }
 
-fn generate_faces(c: Coord) -> [Face; 6] {
-    let (x, y, z) = c;
-    [
-        Face::new((x, y, z), (x, y, z - 1)),
-        Face::new((x, y, z), (x, y, z + 1)),
-        Face::new((x, y, z), (x, y - 1, z)),
-        Face::new((x, y, z), (x, y + 1, z)),
-        Face::new((x, y, z), (x - 1, y, z)),
-        Face::new((x, y, z), (x + 1, y, z)),
-    ]
-}
-
-fn part_a(lines: &[String]) -> usize {
-    let cubes = parse(lines);
-    let mut faces = HashMap::new();
-
-    for c in cubes {
-        for face in generate_faces(c) {
-            faces.entry(face).and_modify(|v| *v += 1).or_insert(0);
-        }
+fn parse(lines: &[String]) -> AResult<Shape> {
+    if lines.is_empty() {
+        Err(ParseError::end_of_input(0, "a cube"))?;
     }
-
-    faces.len() - faces.values().filter(|&&v| v > 0).count()
+    Ok(parse_lines(lines, cube)?.into_iter().collect())
 }
 
-fn is_outside(
-    coord: Coord,
-    max: Coord,
-    cubes: &[Coord],
-    external_blocks: &mut HashSet<Coord>,
-    history: &mut HashSet<Coord>,
-) -> bool {
-    let (cx, cy, cz) = coord;
-    let (mx, my, mz) = max;
-    if cx == 0
-        || cy == 0
-        || cz == 0
-        || cx == mx
-        || cy == my
-        || cz == mz
-        || external_blocks.contains(&coord)
-    {
-        return true;
-    }
-    if cubes.contains(&coord) {
-        return false; // we're a cube
-    }
-
-    history.insert(coord);
-
-    // If there are no cubes to my left I'm outside
-    let mut outside = !cubes
-        .iter()
-        .any(|&(ox, oy, oz)| ox < cx && oy == cy && oz == cz)
-
-    || {
-        !cubes
-            .iter()
-            .any(|&(ox, oy, oz)| ox == cx && oy < cy && oz == cz)
-    }
-    || {
-        !cubes
-            .iter()
-            .any(|&(ox, oy, oz)| ox == cx && oy == cy && oz < cz)
-    }
-
-    // to the right
-    || {
-        !cubes
-            .iter()
-            .any(|&(ox, oy, oz)| ox > cx && oy == cy && oz == cz)
-    }
-    || {
-        !cubes
-            .iter()
-            .any(|&(ox, oy, oz)| ox == cx && oy > cy && oz == cz)
-    }
-    || {
-        !cubes
-            .iter()
-            .any(|&(ox, oy, oz)| ox == cx && oy == cy && oz > cz)
-    };
-
-    if !outside {
-        // check our neighbours to see if they're outside
-        outside = (!history.contains(&(cx - 1, cy, cz))
-            && is_outside((cx - 1, cy, cz), max, cubes, external_blocks, history))
-            || (!history.contains(&(cx + 1, cy, cz))
-                && is_outside((cx + 1, cy, cz), max, cubes, external_blocks, history))
-            || (!history.contains(&(cx, cy - 1, cz))
-                && is_outside((cx, cy - 1, cz), max, cubes, external_blocks, history))
-            || (!history.contains(&(cx, cy + 1, cz))
-                && is_outside((cx, cy + 1, cz), max, cubes, external_blocks, history))
-            || (!history.contains(&(cx, cy, cz - 1))
-                && is_outside((cx, cy, cz - 1), max, cubes, external_blocks, history))
-            || (!history.contains(&(cx, cy, cz + 1))
-                && is_outside((cx, cy, cz + 1), max, cubes, external_blocks, history));
-    }
-
-    if outside {
-        external_blocks.insert(coord);
-    }
-
-    outside
+fn part_a(lines: &[String]) -> AResult<usize> {
+    Ok(parse(lines)?.surface_area())
 }
 
-fn part_b(lines: &[String]) -> usize {
-    let mut cubes = parse(lines);
-    cubes.sort_unstable();
-    let cubes = &cubes;
-
-    let mut external_faces = 0;
-    let mut external_blocks = HashSet::new();
-    let max = (
-        cubes.iter().map(|x| x.0).max().unwrap() + 1,
-        cubes.iter().map(|x| x.1).max().unwrap() + 1,
-        cubes.iter().map(|x| x.2).max().unwrap() + 1,
-    );
-
-    // Check each cube - if it can route to 0,0,0 without colliding with
-    // another cube then it's external
-    for &(x, y, z) in cubes {
-        // Left face
-        external_faces += usize::from(is_outside(
-            (x - 1, y, z),
-            max,
-            cubes,
-            &mut external_blocks,
-            &mut HashSet::new(),
-        ));
-        external_faces += usize::from(is_outside(
-            (x + 1, y, z),
-            max,
-            cubes,
-            &mut external_blocks,
-            &mut HashSet::new(),
-        ));
-        external_faces += usize::from(is_outside(
-            (x, y - 1, z),
-            max,
-            cubes,
-            &mut external_blocks,
-            &mut HashSet::new(),
-        ));
-        external_faces += usize::from(is_outside(
-            (x, y + 1, z),
-            max,
-            cubes,
-            &mut external_blocks,
-            &mut HashSet::new(),
-        ));
-        external_faces += usize::from(is_outside(
-            (x, y, z - 1),
-            max,
-            cubes,
-            &mut external_blocks,
-            &mut HashSet::new(),
-        ));
-        external_faces += usize::from(is_outside(
-            (x, y, z + 1),
-            max,
-            cubes,
-            &mut external_blocks,
-            &mut HashSet::new(),
-        ));
-    }
-
-    external_faces
+fn part_b(lines: &[String]) -> AResult<usize> {
+    Ok(parse(lines)?.exterior_surface_area())
 }
 
 fn main() -> AResult<()> {
@@ -214,16 +38,54 @@ fn main() -> AResult<()> {
         .find(name)
         .expect("binary name should contain a number")
         .as_str();
-    println!("Running code for Day {ex}.");
+    // `day_18 --pockets` lists the droplet's parts and the air pockets trapped inside
+    // it, and `day_18 --stl FILE` or `--obj FILE` writes its surface out as a mesh
+    let args: Vec<_> = std::env::args().collect();
+    let pockets = args.iter().any(|arg| arg == "--pockets");
+    let mesh = match args.iter().position(|arg| arg == "--stl" || arg == "--obj") {
+        Some(i) => Some((
+            args[i].as_str(),
+            args.get(i + 1)
+                .ok_or_else(|| anyhow!("{} needs a file name", args[i]))?,
+        )),
+        None => None,
+    };
+    if !pockets && mesh.is_none() {
+        println!("Running code for Day {ex}.");
+    }
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
+
+    if pockets || mesh.is_some() {
+        let droplet = parse(lines.as_slice()).map_err(in_file(&path))?;
+        if pockets {
+            let parts = droplet.components();
+            println!("{} cubes in {} parts", droplet.len(), parts.len());
+            for pocket in droplet.air_pockets() {
+                let (lo, hi) = pocket.bounds().expect("pockets aren't empty");
+                println!("air pocket of {} from {lo:?} to {hi:?}", pocket.len());
+            }
+        }
+        match mesh {
+            Some(("--stl", file)) => std::fs::write(file, droplet.to_stl("droplet"))?,
+            Some((_, file)) => std::fs::write(file, droplet.to_obj())?,
+            None => {}
+        }
+        return Ok(());
+    }
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -250,26 +112,48 @@ mod tests {
     2,3,5";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines = ["2,2,2".to_string(), "1,2".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 4: expected ',', found end of line"
+        );
+
+        let lines = ["2,2,2".to_string(), "1,-2,2".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 3: expected unsigned integer, found \"-2,2\""
+        );
+
+        assert_eq!(
+            parse(&[]).unwrap_err().to_string(),
+            "line 1, column 1: expected a cube, found end of input"
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(&lines[..2]), 10);
-        assert_eq!(part_a(lines.as_slice()), 64);
+        assert_eq!(part_a(&lines[..2])?, 10);
+        assert_eq!(part_a(lines.as_slice())?, 64);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(&lines[..1]), 6);
+        assert_eq!(part_b(&lines[..1])?, 6);
         assert_eq!(
-            part_b(&lines[..2]),
+            part_b(&lines[..2])?,
             10,
             "pair has no void - so all non-adj faces are external"
         );
         assert_eq!(
-            part_b(&lines[..3]),
+            part_b(&lines[..3])?,
             14,
             "pair has no void - so all non-adj faces are external"
         );
-        assert_eq!(part_b(lines.as_slice()), 58);
+        assert_eq!(part_b(lines.as_slice())?, 58);
+        Ok(())
     }
 }
@@ -1,226 +1,288 @@
+use advent_common::parsing::{in_file, parse_lines, read_lines, uint, PResult, ParseError};
+use anyhow::anyhow;
 use humantime::format_duration;
-use lazy_static::lazy_static;
+use nom::{
+    bytes::complete::tag,
+    character::complete::alpha1,
+    combinator::cut,
+    error::context,
+    multi::{many1, separated_list1},
+    sequence::{delimited, preceded, separated_pair, terminated, tuple},
+    Offset,
+};
 use rayon::prelude::*;
 use regex::Regex;
-use std::{
-    cmp::min,
-    collections::HashSet,
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::{fmt::Write, time::Instant};
 
 type AResult<T> = anyhow::Result<T>;
 
-lazy_static! {
-    static ref PATTERN: Regex =
-        Regex::new(r##"^Blueprint ([0-9]+): Each ore robot costs ([0-9]+) ore. Each clay robot costs ([0-9]+) ore. Each obsidian robot costs ([0-9]+) ore and ([0-9]+) clay. Each geode robot costs ([0-9]+) ore and ([0-9]+) obsidian.$"##)
-            .unwrap();
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// What each kind of robot costs to build. Robot `i` collects `resources[i]`,
+/// and the first robot listed is the one the factory starts with.
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct Blueprint {
     id: usize,
-    ore_ore_cost: usize,
-    clay_ore_cost: usize,
-    obs_ore_cost: usize,
-    obs_clay_cost: usize,
-    geo_ore_cost: usize,
-    geo_obs_cost: usize,
+    resources: Vec<String>,
+    /// `costs[robot][resource]`
+    costs: Vec<Vec<usize>>,
 }
 
-impl From<&String> for Blueprint {
-    fn from(value: &String) -> Self {
-        let matches = PATTERN.captures(value).unwrap();
-        Blueprint {
-            id: matches.get(1).unwrap().as_str().parse().unwrap(),
-            ore_ore_cost: matches.get(2).unwrap().as_str().parse().unwrap(),
-            clay_ore_cost: matches.get(3).unwrap().as_str().parse().unwrap(),
-            obs_ore_cost: matches.get(4).unwrap().as_str().parse().unwrap(),
-            obs_clay_cost: matches.get(5).unwrap().as_str().parse().unwrap(),
-            geo_ore_cost: matches.get(6).unwrap().as_str().parse().unwrap(),
-            geo_obs_cost: matches.get(7).unwrap().as_str().parse().unwrap(),
-        }
-    }
+/// A robot recipe as written, borrowing its names from the line
+type Recipe<'a> = (&'a str, Vec<(usize, &'a str)>);
+
+fn recipe(input: &str) -> PResult<'_, Recipe<'_>> {
+    preceded(
+        tag(" Each "),
+        cut(tuple((
+            context("a resource name", alpha1),
+            preceded(
+                context("\" robot costs \"", tag(" robot costs ")),
+                terminated(
+                    separated_list1(
+                        tag(" and "),
+                        separated_pair(uint, tag(" "), context("a resource name", alpha1)),
+                    ),
+                    context("\".\"", tag(".")),
+                ),
+            ),
+        ))),
+    )(input)
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
-struct State {
-    time: usize,
-    // resources available
-    ore: usize,
-    clay: usize,
-    obs: usize,
-    // robots available
-    r_ore: usize,
-    r_clay: usize,
-    r_obs: usize,
-    r_geode: usize,
-    // output
-    geos: usize,
+fn blueprint(input: &str) -> PResult<'_, (usize, Vec<Recipe<'_>>)> {
+    tuple((
+        context(
+            "the blueprint number",
+            delimited(tag("Blueprint "), uint, tag(":")),
+        ),
+        context("a robot recipe", many1(recipe)),
+    ))(input)
 }
 
-impl State {
-    fn upper(self, time_remaining: usize) -> usize {
-        // Return the upper bound on sub-solutions of this state given the time remaining
-        // (with relaxed constraints)
-        // Assume - we create 1 new geode robot each tick, and all of them return geodes
+fn parse(lines: &[String]) -> AResult<Vec<Blueprint>> {
+    let recipes = parse_lines(lines, blueprint)?;
+
+    let mut blueprints = Vec::with_capacity(recipes.len());
+    for (idx, (id, recipes)) in recipes.into_iter().enumerate() {
+        let line = &lines[idx];
+        let error = |at: &str, expected: String| {
+            ParseError::new(idx + 1, line.offset(at) + 1, expected, format!("{at:?}"))
+        };
 
-        let mut r_count = self.r_geode;
-        let mut total = self.geos;
+        let mut resources: Vec<String> = Vec::new();
+        for (robot, _) in &recipes {
+            if resources.iter().any(|r| r == robot) {
+                Err(error(robot, "a robot not listed before".to_string()))?;
+            }
+            resources.push((*robot).to_string());
+        }
 
-        for _ in 0..time_remaining {
-            total += r_count;
-            r_count += 1;
+        let mut costs = vec![vec![0; resources.len()]; resources.len()];
+        for (robot, cost) in recipes.iter().enumerate() {
+            for &(amount, resource) in &cost.1 {
+                let Some(r) = resources.iter().position(|r| r == resource) else {
+                    Err(error(
+                        resource,
+                        format!("one of the collected resources {}", resources.join(", ")),
+                    ))?
+                };
+                costs[robot][r] += amount;
+            }
         }
 
-        total
+        blueprints.push(Blueprint { id, resources, costs });
+    }
+    Ok(blueprints)
+}
+
+/// The most of one resource a blueprint can collect, and the robots to build
+/// for it as `(minute, robot)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Build {
+    collected: usize,
+    order: Vec<(usize, usize)>,
+}
+
+impl Blueprint {
+    fn resource(&self, name: &str) -> AResult<usize> {
+        self.resources
+            .iter()
+            .position(|r| r == name)
+            .ok_or_else(|| anyhow!("blueprint {} has no {name} robot", self.id))
+    }
+
+    /// The best build for collecting `goal` in `minutes`
+    fn best(&self, goal: usize, minutes: usize) -> Build {
+        let n = self.resources.len();
+
+        // A factory can only build one robot a minute, so there's no point
+        // collecting more of a resource per minute than any robot costs
+        let caps = (0..n)
+            .map(|r| {
+                if r == goal {
+                    usize::MAX
+                } else {
+                    self.costs.iter().map(|c| c[r]).max().unwrap_or(0)
+                }
+            })
+            .collect();
+
+        let mut search = Search {
+            bp: self,
+            goal,
+            minutes,
+            caps,
+            order: Vec::new(),
+            best: Build {
+                collected: 0,
+                order: Vec::new(),
+            },
+        };
+        let mut robots = vec![0; n];
+        robots[0] = 1;
+        search.dfs(minutes, &mut robots, &mut vec![0; n]);
+        search.best
     }
 
-    fn lower(self, time_remaining: usize) -> usize {
-        // Lower bound is simply how many geodes have already been harvested
-        // + how many geodes the existing robots will harvest in the remaining time
-        self.geos + (time_remaining * self.r_geode)
+    /// Describe a build using the resource names
+    fn describe(&self, build: &Build) -> String {
+        let mut out = String::new();
+        for (i, &(minute, robot)) in build.order.iter().enumerate() {
+            let sep = if i == 0 { "" } else { ", " };
+            let _ = write!(
+                out,
+                "{sep}{} robot at minute {minute}",
+                self.resources[robot]
+            );
+        }
+        out
     }
 }
 
-fn parse(lines: &[String]) -> Vec<Blueprint> {
-    lines.iter().map(Blueprint::from).collect()
+/// Depth-first branch and bound over which robot to build next
+struct Search<'a> {
+    bp: &'a Blueprint,
+    goal: usize,
+    minutes: usize,
+    caps: Vec<usize>,
+    order: Vec<(usize, usize)>,
+    best: Build,
 }
 
-fn tick(bp: &Blueprint, states: &HashSet<State>, time_remaining: usize) -> HashSet<State> {
-    // In each tick we generate all possible successor states - filtering on bounds
-    // is done outside this method
-    states
-        .iter()
-        .flat_map(|state| {
-            let max_ore_cost = [
-                bp.clay_ore_cost,
-                bp.geo_ore_cost,
-                bp.obs_ore_cost,
-                bp.geo_ore_cost,
-            ]
-            .into_iter()
-            .max()
-            .unwrap();
-            let max_clay_cost = bp.obs_clay_cost;
-            let max_obs_cost = bp.geo_obs_cost;
-
-            // Clamp the resources in the noop state to the maximum number of each type
-            // that could be consumed (this allows the containing Set to do deduplication)
-            let noop = State {
-                time: state.time + 1,
-                ore: min(state.ore + state.r_ore, max_ore_cost * (1 + time_remaining)),
-                clay: min(
-                    state.clay + state.r_clay,
-                    max_clay_cost * (1 + time_remaining),
-                ),
-                obs: min(state.obs + state.r_obs, max_obs_cost * (1 + time_remaining)),
-                geos: state.geos + state.r_geode,
-                ..*state
+impl Search<'_> {
+    fn dfs(&mut self, left: usize, robots: &mut [usize], stock: &mut [usize]) {
+        let idle = stock[self.goal] + robots[self.goal] * left;
+        if idle > self.best.collected {
+            self.best = Build {
+                collected: idle,
+                order: self.order.clone(),
             };
+        }
+        if self.bound(left, robots, stock) <= self.best.collected {
+            return;
+        }
 
-            let mut successors = Vec::with_capacity(5);
-            // Only push the noop state if we don't already have the maximum number
-            // of effective robots (more than this will just lead to the stockpiles
-            // growing out of control)
-            if state.r_clay < max_clay_cost
-                && state.r_ore < max_ore_cost
-                && state.r_obs < max_obs_cost
+        // Try the goal robot first, then the most advanced of the rest, so
+        // good builds turn up early and prune more
+        let goal = self.goal;
+        let others = (0..robots.len()).rev().filter(|&r| r != goal);
+        for robot in std::iter::once(goal).chain(others) {
+            // Enough robots (or stock) already to spend the most it could
+            // every remaining minute
+            let cap = self.caps[robot];
+            if robots[robot] >= cap
+                || robot != goal && stock[robot] + robots[robot] * left >= cap * left
             {
-                successors.push(noop);
+                continue;
             }
-
-            // Create a new robots of each type IFF
-            // 1) We have the resources to build one
-            // 2) We don't already have the maximum effective number of them
-
-            // New Ore
-            if state.ore >= bp.ore_ore_cost && state.r_ore < max_ore_cost {
-                successors.push(State {
-                    ore: noop.ore - bp.ore_ore_cost,
-                    r_ore: noop.r_ore + 1,
-                    ..noop
-                });
+            // Skip straight to the minute we can afford it, as long as the
+            // robot then has time to collect something
+            let Some(wait) = self.wait(robot, robots, stock) else {
+                continue;
+            };
+            let step = wait + 1;
+            if step >= left {
+                continue;
             }
 
-            // New Clay
-            if state.ore >= bp.clay_ore_cost && state.r_clay < max_clay_cost {
-                successors.push(State {
-                    ore: noop.ore - bp.clay_ore_cost,
-                    r_clay: noop.r_clay + 1,
-                    ..noop
-                });
+            let cost = &self.bp.costs[robot];
+            for r in 0..robots.len() {
+                stock[r] = stock[r] + robots[r] * step - cost[r];
             }
+            robots[robot] += 1;
+            self.order.push((self.minutes - left + step, robot));
 
-            // New Obsidian
-            if state.ore >= bp.obs_ore_cost
-                && state.clay >= bp.obs_clay_cost
-                && state.r_obs < max_obs_cost
-            {
-                successors.push(State {
-                    ore: noop.ore - bp.obs_ore_cost,
-                    clay: noop.clay - bp.obs_clay_cost,
-                    r_obs: noop.r_obs + 1,
-                    ..noop
-                });
-            }
+            self.dfs(left - step, robots, stock);
 
-            // New Geode
-            if state.ore >= bp.geo_ore_cost && state.obs >= bp.geo_obs_cost {
-                successors.push(State {
-                    ore: noop.ore - bp.geo_ore_cost,
-                    obs: noop.obs - bp.geo_obs_cost,
-                    r_geode: noop.r_geode + 1,
-                    ..noop
-                });
+            self.order.pop();
+            robots[robot] -= 1;
+            for r in 0..robots.len() {
+                stock[r] = stock[r] + cost[r] - robots[r] * step;
             }
+        }
+    }
 
-            successors
-        })
-        .collect()
-}
+    /// Minutes of collecting before `robot` is affordable, if it ever is
+    fn wait(&self, robot: usize, robots: &[usize], stock: &[usize]) -> Option<usize> {
+        let mut wait = 0;
+        for (r, &cost) in self.bp.costs[robot].iter().enumerate() {
+            if cost > stock[r] {
+                if robots[r] == 0 {
+                    return None;
+                }
+                wait = wait.max((cost - stock[r]).div_ceil(robots[r]));
+            }
+        }
+        Some(wait)
+    }
 
-fn evaluate_bp(bp: Blueprint, time_limit: usize) -> usize {
-    let mut states: HashSet<State> = HashSet::new();
-    states.insert(State {
-        time: 0, // to be read as "this is the state at the end of {time} minutes"
-        ore: 0,
-        clay: 0,
-        obs: 0,
-        r_ore: 1,
-        r_clay: 0,
-        r_obs: 0,
-        r_geode: 0,
-        geos: 0,
-    });
-
-    for time_remaining in (1..=time_limit).rev() {
-        states = tick(&bp, &states, time_remaining);
-
-        // Only keep states that might be better than the current best lower bound
-        let best_lb = states
-            .iter()
-            .map(|s| s.lower(time_remaining))
-            .max()
-            .unwrap();
-        states.retain(|s| s.upper(time_remaining) >= best_lb);
+    /// An optimistic bound on what can still be collected. Each kind of robot
+    /// gets its own copy of the stock to pay from, and one of every kind may
+    /// be built each minute.
+    fn bound(&self, left: usize, robots: &[usize], stock: &[usize]) -> usize {
+        let costs = &self.bp.costs;
+        let mut robots = robots.to_vec();
+        let mut pools = vec![stock.to_vec(); robots.len()];
+        let mut collected = stock[self.goal];
+
+        for _ in 0..left {
+            let built: Vec<_> = pools
+                .iter()
+                .zip(costs)
+                .map(|(pool, cost)| pool.iter().zip(cost).all(|(s, c)| s >= c))
+                .collect();
+            for (robot, pool) in pools.iter_mut().enumerate() {
+                for r in 0..robots.len() {
+                    pool[r] += robots[r];
+                    if built[robot] {
+                        pool[r] -= costs[robot][r];
+                    }
+                }
+            }
+            collected += robots[self.goal];
+            for (robot, &b) in built.iter().enumerate() {
+                robots[robot] += usize::from(b);
+            }
+        }
+        collected
     }
+}
 
-    states.iter().map(|s| s.geos).max().unwrap()
+fn geodes(bp: &Blueprint, minutes: usize) -> AResult<Build> {
+    Ok(bp.best(bp.resource("geode")?, minutes))
 }
 
-fn part_a(lines: &[String]) -> usize {
-    parse(lines)
+fn part_a(lines: &[String]) -> AResult<usize> {
+    parse(lines)?
         .into_par_iter()
-        .map(|bp| bp.id * evaluate_bp(bp, 24))
+        .map(|bp| Ok(bp.id * geodes(&bp, 24)?.collected))
         .sum()
 }
 
-fn part_b(lines: &[String]) -> usize {
-    parse(&lines[..3])
+fn part_b(lines: &[String]) -> AResult<usize> {
+    // Only the first three blueprints survived
+    parse(&lines[..lines.len().min(3)])?
         .into_par_iter()
-        .map(|bp| evaluate_bp(bp, 32))
+        .map(|bp| Ok(geodes(&bp, 32)?.collected))
         .product()
 }
 
@@ -232,16 +294,39 @@ fn main() -> AResult<()> {
         .find(name)
         .expect("binary name should contain a number")
         .as_str();
-    println!("Running code for Day {ex}.");
+    // `day_19 --orders` prints the best 24 minute build for each blueprint
+    let orders = std::env::args().any(|arg| arg == "--orders");
+    if !orders {
+        println!("Running code for Day {ex}.");
+    }
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
+
+    if orders {
+        for bp in parse(lines.as_slice()).map_err(in_file(&path))? {
+            let build = geodes(&bp, 24)?;
+            println!(
+                "Blueprint {}: {} geodes from {}",
+                bp.id,
+                build.collected,
+                bp.describe(&build)
+            );
+        }
+        return Ok(());
+    }
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -256,36 +341,118 @@ mod tests {
     const TEST_INPUT: &str = "Blueprint 1: Each ore robot costs 4 ore. Each clay robot costs 2 ore. Each obsidian robot costs 3 ore and 14 clay. Each geode robot costs 2 ore and 7 obsidian.
   Blueprint 2: Each ore robot costs 2 ore. Each clay robot costs 3 ore. Each obsidian robot costs 3 ore and 8 clay. Each geode robot costs 3 ore and 12 obsidian.";
 
+    /// Run a build order minute by minute, checking every robot is affordable
+    fn replay(bp: &Blueprint, build: &Build, goal: usize, minutes: usize) -> usize {
+        let n = bp.resources.len();
+        let (mut robots, mut stock) = (vec![0_usize; n], vec![0_usize; n]);
+        robots[0] = 1;
+        let mut order = build.order.iter().peekable();
+        for minute in 1..=minutes {
+            let next = order.next_if(|(m, _)| *m == minute).map(|&(_, r)| r);
+            if let Some(r) = next {
+                for (s, c) in stock.iter_mut().zip(&bp.costs[r]) {
+                    *s = s.checked_sub(*c).expect("robot should be affordable");
+                }
+            }
+            for (s, r) in stock.iter_mut().zip(&robots) {
+                *s += r;
+            }
+            if let Some(r) = next {
+                robots[r] += 1;
+            }
+        }
+        assert!(order.next().is_none());
+        stock[goal]
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let lines = ["Blueprint 1: Each ore robot costs 4 ore. Each clay robot costs 2 ore. Each obsidian robot costs 3 ore, 14 clay. Each geode robot costs 2 ore and 7 obsidian.".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 102: expected \".\", found \", 14 clay. Each geod\"..."
+        );
+
+        let lines = ["Blueprint 1: Each ore robot costs four ore.".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 35: expected unsigned integer, found \"four ore.\""
+        );
+
+        let lines = [
+            "Blueprint 1: Each ore robot costs 4 ore. Each clay robot costs 2 gold.".to_string(),
+        ];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 66: expected one of the collected resources ore, clay, found \"gold\""
+        );
+
+        let lines =
+            ["Blueprint 1: Each ore robot costs 4 ore. Each ore robot costs 2 ore.".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 47: expected a robot not listed before, found \"ore\""
+        );
+    }
+
     #[test]
-    fn test_eval_bp() {
+    fn test_eval_bp() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        let plan = parse(&lines[..1])[0];
+        let plan = parse(&lines[..1])?.remove(0);
         assert_eq!(
             plan,
             Blueprint {
                 id: 1,
-                ore_ore_cost: 4,
-                clay_ore_cost: 2,
-                obs_ore_cost: 3,
-                obs_clay_cost: 14,
-                geo_ore_cost: 2,
-                geo_obs_cost: 7,
+                resources: ["ore", "clay", "obsidian", "geode"]
+                    .map(String::from)
+                    .to_vec(),
+                costs: vec![
+                    vec![4, 0, 0, 0],
+                    vec![2, 0, 0, 0],
+                    vec![3, 14, 0, 0],
+                    vec![2, 0, 7, 0],
+                ],
             }
         );
 
-        assert_eq!(evaluate_bp(plan, 24), 9);
+        let build = geodes(&plan, 24)?;
+        assert_eq!(build.collected, 9);
+        assert_eq!(replay(&plan, &build, 3, 24), 9);
+        Ok(())
+    }
+
+    #[test]
+    fn test_resources() -> AResult<()> {
+        // Two collected resources, with the crystal robot paying in crystals
+        let lines = ["Blueprint 7: Each sand robot costs 2 sand. Each crystal robot costs 3 sand and 1 crystal.".to_string()];
+        let bp = parse(&lines)?.remove(0);
+        assert!(geodes(&bp, 24).is_err());
+
+        // With no crystal to start with, no crystal robot can ever be built
+        let build = bp.best(1, 24);
+        assert_eq!(build.collected, 0);
+        assert!(build.order.is_empty());
+
+        // A second sand robot would be ready too late to repay its cost
+        let build = bp.best(0, 6);
+        assert_eq!(bp.describe(&build), "sand robot at minute 3");
+        assert_eq!(build.collected, 7);
+        assert_eq!(replay(&bp, &build, 0, 6), 7);
+        Ok(())
     }
 
     #[test]
-    fn test_a() {
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 33);
+        assert_eq!(part_a(lines.as_slice())?, 33);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let mut lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
         lines.push(lines[0].clone());
-        assert_eq!(part_b(lines.as_slice()), 56 * 62 * 56);
+        assert_eq!(part_b(lines.as_slice())?, 56 * 62 * 56);
+        Ok(())
     }
 }
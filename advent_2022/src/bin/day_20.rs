@@ -1,10 +1,8 @@
+use advent_common::parsing::{in_file, int, parse_lines, read_lines};
+use anyhow::anyhow;
 use humantime::format_duration;
 use regex::Regex;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
 
@@ -36,21 +34,23 @@ fn mix(nums: &mut Vec<(usize, isize)>) {
     }
 }
 
-fn part_a(lines: &[String]) -> isize {
+fn parse(lines: &[String]) -> AResult<Vec<isize>> {
+    Ok(parse_lines(lines, int)?)
+}
+
+fn find_zero(nums: &[(usize, isize)]) -> AResult<usize> {
+    nums.iter()
+        .position(|&(_, v)| v == 0)
+        .ok_or_else(|| anyhow!("there is no 0 to count from"))
+}
+
+fn part_a(lines: &[String]) -> AResult<isize> {
     // Record each number with it's original index - so we can deal with duplicates
-    let mut nums: Vec<(usize, isize)> = lines
-        .iter()
-        .map(|x| x.parse().unwrap())
-        .enumerate()
-        .collect();
+    let mut nums: Vec<(usize, isize)> = parse(lines)?.into_iter().enumerate().collect();
 
     mix(&mut nums);
 
-    let zero_pos = nums
-        .iter()
-        .enumerate()
-        .find_map(|(i, &(_, v))| if v == 0 { Some(i) } else { None })
-        .unwrap();
+    let zero_pos = find_zero(&nums)?;
 
     let elems = [
         nums[(zero_pos + 1000) % nums.len()].1,
@@ -58,26 +58,21 @@ fn part_a(lines: &[String]) -> isize {
         nums[(zero_pos + 3000) % nums.len()].1,
     ];
 
-    elems.iter().sum()
+    Ok(elems.iter().sum())
 }
 
-fn part_b(lines: &[String]) -> isize {
+fn part_b(lines: &[String]) -> AResult<isize> {
     // Record each number with it's original index - so we can deal with duplicates
-    let mut nums: Vec<(usize, isize)> = lines
-        .iter()
-        .map(|x| x.parse().unwrap())
-        .map(|x: isize| x * 811_589_153) // apply the "key"
+    let mut nums: Vec<(usize, isize)> = parse(lines)?
+        .into_iter()
+        .map(|x| x * 811_589_153) // apply the "key"
         .enumerate()
         .collect();
 
     // mix 10 times
     (0..10).for_each(|_| mix(&mut nums));
 
-    let zero_pos = nums
-        .iter()
-        .enumerate()
-        .find_map(|(i, &(_, v))| if v == 0 { Some(i) } else { None })
-        .unwrap();
+    let zero_pos = find_zero(&nums)?;
 
     let elems = [
         nums[(zero_pos + 1000) % nums.len()].1,
@@ -85,7 +80,7 @@ fn part_b(lines: &[String]) -> isize {
         nums[(zero_pos + 3000) % nums.len()].1,
     ];
 
-    elems.iter().sum()
+    Ok(elems.iter().sum())
 }
 
 fn main() -> AResult<()> {
@@ -99,13 +94,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -126,14 +127,31 @@ mod tests {
     4";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines = ["1".to_string(), "2.5".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 2: expected end of line, found \".5\""
+        );
+
+        let lines = ["1".to_string(), "2".to_string()];
+        assert_eq!(
+            part_a(&lines).unwrap_err().to_string(),
+            "there is no 0 to count from"
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 3);
+        assert_eq!(part_a(lines.as_slice())?, 3);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(lines.as_slice()), 1_623_178_306);
+        assert_eq!(part_b(lines.as_slice())?, 1_623_178_306);
+        Ok(())
     }
 }
@@ -1,190 +1,236 @@
+use advent_common::{
+    math::gcd,
+    parsing::{in_file, parse_lines, read_lines, uint, PResult, ParseError},
+};
+use anyhow::anyhow;
 use humantime::format_duration;
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_while_m_n},
+    character::complete::{char, one_of},
+    combinator::map,
+    error::context,
+    sequence::{delimited, terminated, tuple},
+};
 use regex::Regex;
 use std::{
-    collections::HashMap,
-    fs::File,
-    io::{BufRead, BufReader},
+    collections::{HashMap, HashSet},
+    fmt::{self, Display},
     time::Instant,
 };
-use Monkey::{Human, Number, Op};
 
 type AResult<T> = anyhow::Result<T>;
 
 #[derive(Debug)]
-enum Monkey<'a> {
-    // Number(id, number)
-    Number(&'a str, usize),
-    // Op(id, l_id, op, r_id, result)
-    Op(&'a str, &'a str, char, &'a str, Option<usize>),
-    // Marker type for part b
-    Human,
+enum Job<'a> {
+    Number(i128),
+    // Op(l_id, op, r_id)
+    Op(&'a str, char, &'a str),
 }
 
-impl Monkey<'_> {
-    fn id(&self) -> &str {
-        match self {
-            Number(id, _) | Op(id, _, _, _, _) => id,
-            Human => "humn",
+fn name(input: &str) -> PResult<'_, &str> {
+    context(
+        "a monkey name",
+        take_while_m_n(4, 4, |c: char| c.is_ascii_lowercase()),
+    )(input)
+}
+
+fn monkey(input: &str) -> PResult<'_, (&str, Job<'_>)> {
+    let (input, id) = terminated(name, context("': '", tag(": ")))(input)?;
+    let (input, job) = context(
+        "a number or a sum",
+        alt((
+            map(uint, Job::Number),
+            map(
+                tuple((
+                    name,
+                    delimited(
+                        char(' '),
+                        context("+, -, * or /", one_of("+-*/")),
+                        char(' '),
+                    ),
+                    name,
+                )),
+                |(l, op, r)| Job::Op(l, op, r),
+            ),
+        )),
+    )(input)?;
+    Ok((input, (id, job)))
+}
+
+fn parse(lines: &[String]) -> AResult<HashMap<&str, Job<'_>>> {
+    let mut jobs = HashMap::new();
+    for (idx, (id, job)) in parse_lines(lines, monkey)?.into_iter().enumerate() {
+        if jobs.insert(id, job).is_some() {
+            Err(ParseError::new(
+                idx + 1,
+                1,
+                "a monkey not already named",
+                format!("{id:?}"),
+            ))?;
         }
     }
+    Ok(jobs)
+}
+
+/// An exact fraction, kept in lowest terms with a positive denominator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Ratio {
+    num: i128,
+    den: i128,
+}
+
+impl Ratio {
+    const ZERO: Ratio = Ratio { num: 0, den: 1 };
+    const ONE: Ratio = Ratio { num: 1, den: 1 };
 
-    // Is this monkey ready to be evaluated
-    fn ready(&self, ctx: &HashMap<&str, usize>) -> bool {
-        match &self {
-            Op(_, l, _, r, None) => ctx.contains_key(l) && ctx.contains_key(r),
-            _ => false,
+    fn new(num: i128, den: i128) -> AResult<Ratio> {
+        if den == 0 {
+            return Err(anyhow!("division by zero"));
         }
+        let g = gcd(num, den) * den.signum();
+        Ok(Ratio { num: num / g, den: den / g })
     }
-}
 
-fn parse(lines: &[String], humans_are_special: bool) -> Vec<Monkey> {
-    lines
-        .iter()
-        .map(|line| -> Monkey {
-            let parts: Vec<_> = line.split_whitespace().collect();
-            match parts.len() {
-                2 if parts[0] == "humn:" && humans_are_special => Human,
-                2 => Number(&parts[0][..4], parts[1].parse().unwrap()),
-                4 if parts[0] == "root:" && humans_are_special => {
-                    Op(&parts[0][..4], parts[1], '=', parts[3], None)
-                }
-                4 => Op(
-                    &parts[0][..4],
-                    parts[1],
-                    parts[2].chars().next().unwrap(),
-                    parts[3],
-                    None,
-                ),
-                _ => panic!("Unknown line pattern: {line}"),
-            }
-        })
-        .collect()
+    fn add(self, rhs: Ratio) -> AResult<Ratio> {
+        let overflow = || anyhow!("{self} + {rhs} overflows");
+        Ratio::new(
+            (self.num.checked_mul(rhs.den))
+                .and_then(|l| l.checked_add(rhs.num.checked_mul(self.den)?))
+                .ok_or_else(overflow)?,
+            self.den.checked_mul(rhs.den).ok_or_else(overflow)?,
+        )
+    }
+
+    fn sub(self, rhs: Ratio) -> AResult<Ratio> {
+        self.add(Ratio { num: -rhs.num, ..rhs })
+    }
+
+    fn mul(self, rhs: Ratio) -> AResult<Ratio> {
+        let overflow = || anyhow!("{self} * {rhs} overflows");
+        Ratio::new(
+            self.num.checked_mul(rhs.num).ok_or_else(overflow)?,
+            self.den.checked_mul(rhs.den).ok_or_else(overflow)?,
+        )
+    }
+
+    fn div(self, rhs: Ratio) -> AResult<Ratio> {
+        self.mul(Ratio::new(rhs.den, rhs.num)?)
+    }
+
+    fn apply(self, op: char, rhs: Ratio) -> AResult<Ratio> {
+        match op {
+            '+' => self.add(rhs),
+            '-' => self.sub(rhs),
+            '*' => self.mul(rhs),
+            '/' => self.div(rhs),
+            _ => unreachable!(),
+        }
+    }
+
+    fn whole(self) -> AResult<i128> {
+        if self.den == 1 {
+            Ok(self.num)
+        } else {
+            Err(anyhow!("{self} is not a whole number"))
+        }
+    }
 }
 
-fn observe_the_monkeys(monkeys: &mut Vec<Monkey>) {
-    // Run the simulation forward as much as possible
-    // (yes, I could create the DAG and do this more efficiently, but this is more than fast enough)
-
-    // Seed the context with the "number" monkeys
-    let mut ctx: HashMap<_, _> = monkeys
-        .iter()
-        .filter_map(|m| match m {
-            &Number(id, num) => Some((id, num)),
-            _ => None,
-        })
-        .collect();
-
-    let mut changed = true;
-    while changed {
-        changed = false;
-
-        for m in &mut *monkeys {
-            let ready = m.ready(&ctx);
-
-            match m {
-                Op(id, l_id, op, r_id, res) if res.is_none() && ready => {
-                    let result = match op {
-                        '+' => ctx[l_id] + ctx[r_id],
-                        '-' => ctx[l_id] - ctx[r_id],
-                        '/' => ctx[l_id] / ctx[r_id],
-                        '*' => ctx[l_id] * ctx[r_id],
-                        _ => panic!("Unknown op: {op}"),
-                    };
-
-                    changed = true;
-                    *res = Some(result);
-                    ctx.insert(id, result);
-                }
-                _ => (),
-            };
+impl Display for Ratio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
         }
     }
 }
 
-fn part_a(lines: &[String]) -> usize {
-    let mut monkeys = parse(lines, false);
-    observe_the_monkeys(&mut monkeys);
-
-    monkeys
-        .iter()
-        .find_map(|m| match m {
-            Op("root", _, _, _, result) => *result,
-            _ => None,
-        })
-        .expect("Did not complete cleanly - root has no result!")
+/// What a monkey yells, with every subtree that doesn't involve the human already
+/// worked out
+#[derive(Debug)]
+enum Expr {
+    Const(Ratio),
+    Human,
+    Op(Box<Expr>, char, Box<Expr>),
 }
 
-fn determine_value(monkey: &Monkey, target: usize, monkeys: &HashMap<&str, &Monkey>) -> usize {
-    if let Op(_, l_id, op, r_id, None) = monkey {
-        let lhs = monkeys.get(l_id);
-        let rhs = monkeys.get(r_id);
+impl Expr {
+    fn build<'a>(
+        id: &'a str,
+        jobs: &HashMap<&'a str, Job<'a>>,
+        human: Option<&str>,
+        seen: &mut HashSet<&'a str>,
+    ) -> AResult<Expr> {
+        if human == Some(id) {
+            return Ok(Expr::Human);
+        }
+        if !seen.insert(id) {
+            return Err(anyhow!("{id} depends on itself"));
+        }
 
-        match (lhs, rhs) {
-            (Some(Op(_, _, _, _, None)), Some(Number(_, r_res) | Op(_, _, _, _, Some(r_res)))) => {
-                // lhs is unknown-op, rhs is a either number or a op with a known result
-                match op {
-                    '/' => determine_value(lhs.unwrap(), target * r_res, monkeys),
-                    '+' => determine_value(lhs.unwrap(), target - r_res, monkeys),
-                    '*' => determine_value(lhs.unwrap(), target / r_res, monkeys),
-                    '-' => determine_value(lhs.unwrap(), target + r_res, monkeys),
-                    _ => panic!("unknown op at location 0"),
-                }
-            }
-            (Some(Number(_, l_res) | Op(_, _, _, _, Some(l_res))), Some(Op(_, _, _, _, None))) => {
-                // lhs is a number or known-op - rhs is a unknown-op
-                match op {
-                    '+' => determine_value(rhs.unwrap(), target - l_res, monkeys),
-                    '*' => determine_value(rhs.unwrap(), target / l_res, monkeys),
-                    '-' => determine_value(rhs.unwrap(), l_res - target, monkeys),
-                    _ => panic!("unknown op at location 1"),
-                }
-            }
-            (Some(Human), Some(Number(_, r_res))) => {
-                // lhs is a human and the rhs is a known value - return the target
-                match op {
-                    '-' => target + r_res,
-                    _ => panic!("unknown op at location 2"),
+        let expr = match jobs.get(id) {
+            None => return Err(anyhow!("no monkey is called {id}")),
+            Some(&Job::Number(n)) => Expr::Const(Ratio::new(n, 1)?),
+            Some(&Job::Op(l, op, r)) => {
+                match (
+                    Expr::build(l, jobs, human, seen)?,
+                    Expr::build(r, jobs, human, seen)?,
+                ) {
+                    (Expr::Const(l), Expr::Const(r)) => Expr::Const(l.apply(op, r)?),
+                    (l, r) => Expr::Op(Box::new(l), op, Box::new(r)),
                 }
             }
-            (Some(Number(_, l_res)), Some(Human)) => {
-                // lhs is a known value and rhs is a human  - return the target
+        };
+
+        seen.remove(id);
+        Ok(expr)
+    }
+
+    /// `(a, b)` where this expression is `a * humn + b`, or an error if that's not
+    /// possible
+    fn linear(&self) -> AResult<(Ratio, Ratio)> {
+        match self {
+            Expr::Const(c) => Ok((Ratio::ZERO, *c)),
+            Expr::Human => Ok((Ratio::ONE, Ratio::ZERO)),
+            Expr::Op(l, op, r) => {
+                let ((la, lb), (ra, rb)) = (l.linear()?, r.linear()?);
                 match op {
-                    '+' => target - l_res,
-                    _ => panic!("unknown op at location 3"),
+                    '+' | '-' => Ok((la.apply(*op, ra)?, lb.apply(*op, rb)?)),
+                    '*' if la == Ratio::ZERO => Ok((lb.mul(ra)?, lb.mul(rb)?)),
+                    '*' if ra == Ratio::ZERO => Ok((la.mul(rb)?, lb.mul(rb)?)),
+                    '/' if ra == Ratio::ZERO => Ok((la.div(rb)?, lb.div(rb)?)),
+                    _ => Err(anyhow!("the equation is not linear in humn")),
                 }
             }
-            _ => {
-                panic!(r#"can't determine the value when neither side is known"#)
-            }
         }
-    } else {
-        panic!("cannot pass non-op to this function");
     }
 }
 
-fn part_b(lines: &[String]) -> usize {
-    let mut monkeys = parse(lines, true);
-    observe_the_monkeys(&mut monkeys);
-
-    // Create dial-a-monkey lookup
-    let monkeys: HashMap<&str, _> = monkeys.iter().map(|m| (m.id(), m)).collect();
+fn part_a(lines: &[String]) -> AResult<i128> {
+    let jobs = parse(lines)?;
+    match Expr::build("root", &jobs, None, &mut HashSet::new())? {
+        Expr::Const(c) => c.whole(),
+        _ => unreachable!("there is no human to leave unknown"),
+    }
+}
 
-    // Find out which part of root is filled - and what the target value is
-    // the recurse back up the tree to humn to determine its value
+fn part_b(lines: &[String]) -> AResult<i128> {
+    let jobs = parse(lines)?;
+    let Some(&Job::Op(l, _, r)) = jobs.get("root") else {
+        return Err(anyhow!("root has to compare two monkeys"));
+    };
 
-    if let Some((_, Op(_, l_id, _, r_id, _))) = monkeys.iter().find(|(&k, _)| k == "root") {
-        if let Some(Op(_, _, _, _, Some(target))) = &monkeys.get(l_id) {
-            // lhs is known - determine the rhs
-            determine_value(monkeys[r_id], *target, &monkeys)
-        } else if let Some(Op(_, _, _, _, Some(target))) = &monkeys.get(r_id) {
-            // rhs is known - determine the lhs
-            determine_value(monkeys[l_id], *target, &monkeys)
-        } else {
-            panic!("can't determine the value when neither monkey is known")
-        }
-    } else {
-        panic!("No root monkey found")
+    // root's two sides are equal, so their difference a * humn + b is zero
+    let (la, lb) = Expr::build(l, &jobs, Some("humn"), &mut HashSet::new())?.linear()?;
+    let (ra, rb) = Expr::build(r, &jobs, Some("humn"), &mut HashSet::new())?.linear()?;
+    let (a, b) = (la.sub(ra)?, lb.sub(rb)?);
+    if a == Ratio::ZERO {
+        return Err(anyhow!("root does not depend on humn"));
     }
+
+    Ratio::ZERO.sub(b)?.div(a)?.whole()
 }
 
 fn main() -> AResult<()> {
@@ -198,13 +244,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -233,14 +285,93 @@ mod tests {
     hmdt: 32";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines = ["root: pppw + sjmn".to_string(), "dbpl 5".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 5: expected ': ', found \" 5\""
+        );
+
+        let lines = ["root: pppw % sjmn".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 12: expected +, -, * or /, found \"% sjmn\""
+        );
+
+        let lines = ["root: pppw + sj".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 14: expected a monkey name, found \"sj\""
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 152);
+        assert_eq!(part_a(lines.as_slice())?, 152);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(lines.as_slice()), 301);
+        assert_eq!(part_b(lines.as_slice())?, 301);
+        Ok(())
+    }
+
+    #[test]
+    fn test_solve() -> AResult<()> {
+        let solve = |input: &str| {
+            let lines: Vec<_> = input.split(", ").map(String::from).collect();
+            part_b(&lines).map_err(|e| e.to_string())
+        };
+
+        // humn on either side of every operator, with fractions on the way
+        assert_eq!(
+            solve("root: aaaa + bbbb, aaaa: humn / cccc, bbbb: 4, cccc: 6"),
+            Ok(24)
+        );
+        assert_eq!(
+            solve("root: bbbb + aaaa, aaaa: cccc - humn, bbbb: 4, cccc: 6"),
+            Ok(2)
+        );
+        assert_eq!(
+            solve("root: aaaa + bbbb, aaaa: cccc - humn, bbbb: 9, cccc: 4"),
+            Ok(-5)
+        );
+        assert_eq!(
+            solve("root: aaaa + bbbb, aaaa: humn + humn, bbbb: 8, humn: 1"),
+            Ok(4)
+        );
+        assert_eq!(
+            solve("root: aaaa + bbbb, aaaa: cccc * humn, bbbb: 6, cccc: dddd / eeee, dddd: 2, eeee: 3"),
+            Ok(9)
+        );
+        assert_eq!(
+            solve("root: aaaa + bbbb, aaaa: humn * cccc, bbbb: 3, cccc: 2"),
+            Err("3/2 is not a whole number".to_string())
+        );
+
+        assert_eq!(
+            solve("root: aaaa + bbbb, aaaa: cccc / humn, bbbb: 4, cccc: 6"),
+            Err("the equation is not linear in humn".to_string())
+        );
+        assert_eq!(
+            solve("root: aaaa + bbbb, aaaa: humn * humn, bbbb: 4"),
+            Err("the equation is not linear in humn".to_string())
+        );
+        assert_eq!(
+            solve("root: aaaa + bbbb, aaaa: humn - humn, bbbb: 4"),
+            Err("root does not depend on humn".to_string())
+        );
+        assert_eq!(
+            solve("root: aaaa + bbbb, aaaa: bbbb + humn, bbbb: aaaa * humn"),
+            Err("aaaa depends on itself".to_string())
+        );
+        assert_eq!(
+            solve("root: aaaa + bbbb, aaaa: humn - cccc, bbbb: 4"),
+            Err("no monkey is called cccc".to_string())
+        );
+        Ok(())
     }
 }
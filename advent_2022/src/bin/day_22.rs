@@ -1,43 +1,72 @@
+use advent_common::parsing::{exact_blocks, in_file, read_lines, PResult, ParseError};
+use anyhow::anyhow;
 use humantime::format_duration;
-use regex::Regex;
-use std::{
-    collections::HashMap,
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::digit1,
+    combinator::{map, map_res, value},
+    error::context,
+    multi::many1,
 };
+use regex::Regex;
+use std::{collections::HashMap, time::Instant};
 
 type AResult<T> = anyhow::Result<T>;
+type Board = HashMap<(isize, isize), char>;
+
+/// One step of the path, which alternates between distances and turns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Forward(usize),
+    Right,
+    Left,
+}
+
+fn path(input: &str) -> PResult<'_, Vec<Step>> {
+    many1(context(
+        "a distance, R or L",
+        alt((
+            map(map_res(digit1, str::parse::<usize>), Step::Forward),
+            value(Step::Right, tag("R")),
+            value(Step::Left, tag("L")),
+        )),
+    ))(input)
+}
 
-#[allow(clippy::similar_names, clippy::no_effect)]
-fn parse(lines: &[String]) -> (HashMap<(isize, isize), char>, Vec<String>) {
-    let board: HashMap<_, char> = lines[..lines.len() - 2]
-        .iter()
+fn parse(lines: &[String]) -> AResult<(Board, Vec<Step>)> {
+    let [board_block, path_block] = exact_blocks::<2>(lines, "the path")?;
+
+    let rows = board_block.map_lines(|line| {
+        line.char_indices()
+            .filter(|&(_, c)| c != ' ')
+            .map(|(ci, c)| match c {
+                '.' | '#' => Ok((ci, c)),
+                _ => Err(ParseError::new(
+                    1,
+                    ci + 1,
+                    "'.', '#' or ' '",
+                    format!("{c:?}"),
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+
+    let board = rows
+        .into_iter()
         .enumerate()
-        .flat_map(|(ri, line)| {
-            line.char_indices()
-                .filter_map(|(ci, c)| {
-                    if c == ' ' {
-                        None
-                    } else {
-                        Some(((ri.try_into().unwrap(), ci.try_into().unwrap()), c))
-                    }
-                })
-                .collect::<Vec<_>>()
+        .flat_map(|(ri, row)| {
+            row.into_iter()
+                .map(move |(ci, c)| ((ri.try_into().unwrap(), ci.try_into().unwrap()), c))
         })
         .collect();
 
-    let p = Regex::new("([0-9]+)|(R)|(L)").unwrap();
-    let instr: Vec<String> = p
-        .find_iter(lines[lines.len() - 1].as_str())
-        .map(|m| m.as_str().to_string())
-        .collect();
+    let instr = path_block.parse_joined(path)?;
 
-    (board, instr)
+    Ok((board, instr))
 }
 
-fn move_right(d: &str, row: isize, col: isize, board: &HashMap<(isize, isize), char>) -> isize {
-    let dist: usize = d.parse().unwrap();
+fn move_right(dist: usize, row: isize, col: isize, board: &Board) -> isize {
     let mut col = col;
 
     for _ in 0..dist {
@@ -59,8 +88,7 @@ fn move_right(d: &str, row: isize, col: isize, board: &HashMap<(isize, isize), c
     col
 }
 
-fn move_left(d: &str, row: isize, col: isize, board: &HashMap<(isize, isize), char>) -> isize {
-    let dist: usize = d.parse().unwrap();
+fn move_left(dist: usize, row: isize, col: isize, board: &Board) -> isize {
     let mut col = col;
 
     for _ in 0..dist {
@@ -82,8 +110,7 @@ fn move_left(d: &str, row: isize, col: isize, board: &HashMap<(isize, isize), ch
     col
 }
 
-fn move_up(d: &str, row: isize, col: isize, board: &HashMap<(isize, isize), char>) -> isize {
-    let dist: usize = d.parse().unwrap();
+fn move_up(dist: usize, row: isize, col: isize, board: &Board) -> isize {
     let mut row = row;
     for _ in 0..dist {
         let mut nr = row - 1;
@@ -103,8 +130,7 @@ fn move_up(d: &str, row: isize, col: isize, board: &HashMap<(isize, isize), char
     row
 }
 
-fn move_down(d: &str, row: isize, col: isize, board: &HashMap<(isize, isize), char>) -> isize {
-    let dist: usize = d.parse().unwrap();
+fn move_down(dist: usize, row: isize, col: isize, board: &Board) -> isize {
     let mut row = row;
 
     for _ in 0..dist {
@@ -123,9 +149,8 @@ fn move_down(d: &str, row: isize, col: isize, board: &HashMap<(isize, isize), ch
     row
 }
 
-#[allow(clippy::match_same_arms)]
-fn part_a(lines: &[String]) -> isize {
-    let (board, instr) = parse(lines);
+fn part_a(lines: &[String]) -> AResult<isize> {
+    let (board, instr) = parse(lines)?;
 
     let mut dir = '>';
     let mut row = 0;
@@ -133,371 +158,251 @@ fn part_a(lines: &[String]) -> isize {
         .keys()
         .filter_map(|&(ro, ci)| if ro == 0 { Some(ci) } else { None })
         .min()
-        .unwrap();
-
-    for ins in instr {
-        match (ins.as_str(), dir) {
-            // Directions
-            ("R", d) => match d {
-                '>' => dir = 'v',
-                'v' => dir = '<',
-                '<' => dir = '^',
-                '^' => dir = '>',
-                _ => panic!(),
-            },
-            ("L", d) => match d {
-                '>' => dir = '^',
-                '^' => dir = '<',
-                '<' => dir = 'v',
-                'v' => dir = '>',
-                _ => panic!(),
-            },
-            // Movement
-            (d, '>') => {
-                col = move_right(d, row, col, &board);
-            }
-            (d, '<') => {
-                col = move_left(d, row, col, &board);
-            }
-            (d, '^') => {
-                row = move_up(d, row, col, &board);
-            }
-            (d, 'v') => {
-                row = move_down(d, row, col, &board);
-            }
-            _ => panic!(),
+        .ok_or_else(|| anyhow!("the board has no top row"))?;
+
+    for step in instr {
+        match (step, dir) {
+            (Step::Right, d) => dir = turn(d, 1),
+            (Step::Left, d) => dir = turn(d, 3),
+            (Step::Forward(dist), '>') => col = move_right(dist, row, col, &board),
+            (Step::Forward(dist), '<') => col = move_left(dist, row, col, &board),
+            (Step::Forward(dist), '^') => row = move_up(dist, row, col, &board),
+            (Step::Forward(dist), _) => row = move_down(dist, row, col, &board),
         }
     }
 
-    ((row + 1) * 1000)
-        + ((col + 1) * 4)
-        + match dir {
-            '>' => 0,
-            'v' => 1,
-            '<' => 2,
-            '^' => 3,
-            _ => panic!(),
-        }
+    Ok(password((row, col, dir)))
 }
 
 //
 // Start of part_b stuff
 //
 
-fn move_right_b(
-    dist: isize,
-    row: isize,
-    col: isize,
-    board: &HashMap<(isize, isize), char>,
-) -> (isize, isize, char) {
-    let mut col = col;
-    for so_far in 0..dist {
-        let nc = col + 1;
-        if board.get(&(row, nc)).is_none() {
-            // Which face are we moving off
-            if row >= 150 {
-                // Moving right off face 1 to move up face 3
-                let nc = row - 100; // 100 = 150 - 50
-                let nr = 149; // last row of face 3 is 149
-
-                // peek to see if there's a foothold possible on the other face
-                // - will steal 1 movement step
-                match board.get(&(nr, nc)) {
-                    Some(c) if *c == '#' => {
-                        // blocked - return from here
-                        break;
-                    }
-                    _ => {
-                        return move_up_b(dist - so_far - 1, nr, nc, board);
-                    }
-                }
-            } else if row >= 100 {
-                // Moving right off Face 3 to start moving left across face 6
-                let nr = 149 - row;
-                let nc = 149;
-
-                // peek to see if there's a foothold possible on the other face
-                // - will steal 1 movement step
-                match board.get(&(nr, nc)) {
-                    Some(c) if *c == '#' => {
-                        // blocked - return from here
-                        break;
-                    }
-                    _ => {
-                        return move_left_b(dist - so_far - 1, nr, nc, board);
-                    }
-                }
-            } else if row >= 50 {
-                // Moving off the rhs of face 4 onto the bottom of face 6
-                let nr = 49;
-                let nc = 100 + (row - 50);
-                match board.get(&(nr, nc)) {
-                    Some(c) if *c == '#' => {
-                        // blocked - return from here
-                        break;
-                    }
-                    _ => {
-                        return move_up_b(dist - so_far - 1, nr, nc, board);
-                    }
-                }
-            } else if row >= 0 {
-                let nr = 100 + (49 - row);
-                let nc = 99;
-                match board.get(&(nr, nc)) {
-                    Some(c) if *c == '#' => {
-                        // blocked - return from here
-                        break;
-                    }
-                    _ => {
-                        return move_left_b(dist - so_far - 1, nr, nc, board);
-                    }
-                }
-            }
-            panic!("not implemented");
-        }
+const DIRS: [char; 4] = ['>', 'v', '<', '^'];
 
-        if board[&(row, nc)] == '#' {
-            break;
-        }
+/// The direction a quarter turn clockwise from `dir`, `by` times
+fn turn(dir: char, by: usize) -> char {
+    DIRS[(DIRS.iter().position(|&d| d == dir).unwrap() + by) % 4]
+}
 
-        col = nc;
-    }
+type Vec3 = [i8; 3];
 
-    (row, col, '>')
+fn neg(v: Vec3) -> Vec3 {
+    v.map(|x| -x)
 }
 
-fn move_left_b(
-    dist: isize,
-    row: isize,
-    col: isize,
-    board: &HashMap<(isize, isize), char>,
-) -> (isize, isize, char) {
-    let mut col = col;
+/// Where a face of the net ends up once folded - the outward normal and the 3D
+/// directions that the board's columns and rows increase along
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Face {
+    normal: Vec3,
+    right: Vec3,
+    down: Vec3,
+}
 
-    for so_far in 0..dist {
-        let nc = col - 1;
-        if board.get(&(row, nc)).is_none() {
-            if row >= 150 {
-                // Moving off face 1 onto the top of face 5
-                let nc = row - 100;
-                let nr = 0;
-                // peek it to check if blocked - will steal 1 movement step
-                match board.get(&(nr, nc)) {
-                    Some(c) if *c == '#' => {
-                        break; // blocked
-                    }
-                    _ => return move_down_b(dist - so_far - 1, nr, nc, board),
-                }
-            } else if row >= 100 {
-                // Moving off face 2 onto the lhs of face 5
-                let nr = 50 - (row - 99);
-                let nc = 50;
-                // peek it
-                match board.get(&(nr, nc)) {
-                    Some(c) if *c == '#' => {
-                        break; // blocked
-                    }
-                    _ => return move_right_b(dist - so_far - 1, nr, nc, board),
-                }
-            } else if row >= 50 {
-                // Moving off left edge of face 4
-                let nc = row - 50;
-                let nr = 100;
-                match board.get(&(nr, nc)) {
-                    Some(c) if *c == '#' => {
-                        break; // blocked
-                    }
-                    _ => return move_down_b(dist - so_far - 1, nr, nc, board),
-                }
-            } else if row >= 0 {
-                // Moving off left edge of face 5 onto f2
-                let nr = 149 - row;
-                let nc = 0;
-                match board.get(&(nr, nc)) {
-                    Some(c) if *c == '#' => {
-                        break; // blocked
-                    }
-                    _ => return move_right_b(dist - so_far - 1, nr, nc, board),
-                }
-            }
-            panic!();
+impl Face {
+    fn towards(&self, dir: char) -> Vec3 {
+        match dir {
+            '>' => self.right,
+            'v' => self.down,
+            '<' => neg(self.right),
+            '^' => neg(self.down),
+            _ => unreachable!(),
         }
+    }
 
-        if board[&(row, nc)] == '#' {
-            break;
+    /// The orientation of the neighbouring face in `dir` after folding over the edge
+    fn roll(&self, dir: char) -> Face {
+        let normal = self.towards(dir);
+        match dir {
+            '>' => Face {
+                normal,
+                right: neg(self.normal),
+                ..*self
+            },
+            '<' => Face {
+                normal,
+                right: self.normal,
+                ..*self
+            },
+            'v' => Face {
+                normal,
+                down: neg(self.normal),
+                ..*self
+            },
+            '^' => Face {
+                normal,
+                down: self.normal,
+                ..*self
+            },
+            _ => unreachable!(),
         }
-
-        col = nc;
     }
 
-    (row, col, '<')
+    /// The direction on this face that points along `v`
+    fn dir_along(&self, v: Vec3) -> char {
+        DIRS.into_iter().find(|&d| self.towards(d) == v).unwrap()
+    }
 }
 
-fn move_up_b(
-    dist: isize,
-    row: isize,
-    col: isize,
-    board: &HashMap<(isize, isize), char>,
-) -> (isize, isize, char) {
-    let mut row = row;
-    for so_far in 0..dist {
-        let nr = row - 1;
-        if board.get(&(nr, col)).is_none() {
-            if nr == 99 {
-                // Going off the top of Face 2
-                let nr = 50 + col;
-                let nc = 50;
-                // peek it - which steals move movement point
-                match board.get(&(nr, nc)) {
-                    Some(c) if *c == '#' => {
-                        break; // blocked
-                    }
-                    _ => return move_right_b(dist - so_far - 1, nr, nc, board),
-                }
-            } else if col < 100 {
-                // Face 5 (100 is the first index of face 6)
-                let nr = col + 100;
-                let nc = 0;
-                match board.get(&(nr, nc)) {
-                    Some(c) if *c == '#' => {
-                        break; // blocked
-                    }
-                    _ => return move_right_b(dist - so_far - 1, nr, nc, board),
-                }
-            } else if col < 150 {
-                // Face 6
-                let nc = col - 100;
-                let nr = 199;
-                match board.get(&(nr, nc)) {
-                    Some(c) if *c == '#' => {
-                        break; // blocked
-                    }
-                    _ => return move_up_b(dist - so_far - 1, nr, nc, board),
-                }
-            }
+#[derive(Debug)]
+struct Cube {
+    size: isize,
+    // Keyed by (row, col) of the face in units of `size`
+    faces: HashMap<(isize, isize), Face>,
+}
 
-            panic!()
-        }
+/// Work out the face size and fold the net up into a cube
+fn fold(board: &Board) -> AResult<Cube> {
+    let size = (1..)
+        .map(|n| (n, 6 * n * n))
+        .find(|&(_, area)| area >= board.len())
+        .filter(|&(_, area)| area == board.len())
+        .map(|(n, _)| n)
+        .ok_or_else(|| anyhow!("{} tiles cannot form the six faces of a cube", board.len()))?;
+    let size = isize::try_from(size)?;
+
+    let mut corners: Vec<_> = board
+        .keys()
+        .map(|&(row, col)| (row.div_euclid(size), col.div_euclid(size)))
+        .collect();
+    corners.sort_unstable();
+    corners.dedup();
+    if corners.len() != 6 {
+        return Err(anyhow!("the board is not a net of {size}x{size} faces"));
+    }
 
-        if board[&(nr, col)] == '#' {
-            break;
+    // Roll the cube across the net from the first face, recording each face's orientation
+    let mut faces = HashMap::from([(
+        corners[0],
+        Face {
+            normal: [0, 0, 1],
+            right: [1, 0, 0],
+            down: [0, 1, 0],
+        },
+    )]);
+    let mut queue = vec![corners[0]];
+    while let Some((fr, fc)) = queue.pop() {
+        for (dir, next) in
+            DIRS.into_iter()
+                .zip([(fr, fc + 1), (fr + 1, fc), (fr, fc - 1), (fr - 1, fc)])
+        {
+            if corners.contains(&next) && !faces.contains_key(&next) {
+                faces.insert(next, faces[&(fr, fc)].roll(dir));
+                queue.push(next);
+            }
         }
+    }
 
-        row = nr;
+    let mut normals: Vec<_> = faces.values().map(|f| f.normal).collect();
+    normals.sort_unstable();
+    normals.dedup();
+    if normals.len() != 6 {
+        return Err(anyhow!("the board does not fold into a cube"));
     }
 
-    (row, col, '^')
+    Ok(Cube { size, faces })
 }
 
-fn move_down_b(
-    dist: isize,
-    mut row: isize,
-    mut col: isize,
-    board: &HashMap<(isize, isize), char>,
-) -> (isize, isize, char) {
-    for so_far in 0..dist {
-        let mut nr = row + 1;
-        if board.get(&(nr, col)).is_none() {
-            if nr >= 200 {
-                // dropped off the bottom of 1 -- loop around to 6 and keep going as normal
-                nr = 0;
-                // peek before updating col/row
-                match board.get(&(nr, col + 100)) {
-                    Some(c) if *c != '#' => {
-                        col += 100; // all good - can continue
-                    }
-                    _ => {
-                        break; // blocked
-                    }
-                }
-            } else if nr >= 150 {
-                let nr = 150 + (col - 50);
-                let nc = 49;
-                match board.get(&(nr, nc)) {
-                    Some(c) if *c != '#' => {
-                        return move_left_b(dist - so_far - 1, nr, nc, board);
-                    }
-                    _ => {
-                        break;
-                    }
-                }
-            } else if nr >= 50 {
-                // Falling of the bottom of face 6
-                let nr = (col - 100) + 50;
-                let nc = 99;
-                match board.get(&(nr, nc)) {
-                    Some(c) if *c == '#' => {
-                        break;
-                    }
-                    _ => {
-                        return move_left_b(dist - so_far - 1, nr, nc, board);
-                    }
-                }
-            } else {
-                panic!();
-            }
-        }
-        if board[&(nr, col)] == '#' {
-            break;
+impl Cube {
+    /// Where stepping off the edge of a face in `dir` lands, and the new heading
+    fn wrap(&self, row: isize, col: isize, dir: char) -> (isize, isize, char) {
+        let n = self.size;
+        let from = self.faces[&(row.div_euclid(n), col.div_euclid(n))];
+        let (&(fr, fc), to) = self
+            .faces
+            .iter()
+            .find(|(_, f)| f.normal == from.towards(dir))
+            .unwrap();
+
+        // Heading away from the shared edge, with the position along it measured in the
+        // same 3D direction on both faces
+        let heading = to.dir_along(neg(from.normal));
+        let along = DIRS[(DIRS.iter().position(|&d| d == dir).unwrap() + 1) % 4];
+        let (lr, lc) = (row.rem_euclid(n), col.rem_euclid(n));
+        let offset = match along {
+            '>' => lc,
+            'v' => lr,
+            '<' => n - 1 - lc,
+            '^' => n - 1 - lr,
+            _ => unreachable!(),
+        };
+
+        let (mut er, mut ec) = match heading {
+            '>' | 'v' => (0, 0),
+            _ => (n - 1, n - 1),
+        };
+        match to.dir_along(from.towards(along)) {
+            '>' => ec = offset,
+            'v' => er = offset,
+            '<' => ec = n - 1 - offset,
+            '^' => er = n - 1 - offset,
+            _ => unreachable!(),
         }
-        row = nr;
+
+        (fr * n + er, fc * n + ec, heading)
     }
 
-    (row, col, 'v')
+    fn step(&self, board: &Board, (row, col, dir): (isize, isize, char)) -> (isize, isize, char) {
+        let (nr, nc) = match dir {
+            '>' => (row, col + 1),
+            'v' => (row + 1, col),
+            '<' => (row, col - 1),
+            '^' => (row - 1, col),
+            _ => unreachable!(),
+        };
+        let next = if board.contains_key(&(nr, nc)) {
+            (nr, nc, dir)
+        } else {
+            self.wrap(row, col, dir)
+        };
+
+        if board[&(next.0, next.1)] == '#' {
+            (row, col, dir)
+        } else {
+            next
+        }
+    }
 }
 
-fn part_b(
-    board: &HashMap<(isize, isize), char>,
-    instr: Vec<String>,
-    start_row: isize,
-    start_col: isize,
-) -> isize {
-    let mut state = (start_row, start_col, '>');
-    for ins in instr {
+fn walk_cube(
+    board: &Board,
+    cube: &Cube,
+    instr: &[Step],
+    start: (isize, isize, char),
+) -> (isize, isize, char) {
+    let mut state = start;
+    for &step in instr {
         let (row, col, dir) = state;
-        state = match (ins.as_str(), dir) {
-            // Directions
-            ("R", d) => (
-                row,
-                col,
-                match d {
-                    '>' => 'v',
-                    'v' => '<',
-                    '<' => '^',
-                    '^' => '>',
-                    _ => panic!(),
-                },
-            ),
-            ("L", d) => (
-                row,
-                col,
-                match d {
-                    '>' => '^',
-                    '^' => '<',
-                    '<' => 'v',
-                    'v' => '>',
-                    _ => panic!(),
-                },
-            ),
-            // Movement
-            (d, '>') => move_right_b(d.parse().unwrap(), row, col, board),
-            (d, '<') => move_left_b(d.parse().unwrap(), row, col, board),
-            (d, '^') => move_up_b(d.parse().unwrap(), row, col, board),
-            (d, 'v') => move_down_b(d.parse().unwrap(), row, col, board),
-            _ => panic!(),
+        state = match step {
+            Step::Right => (row, col, turn(dir, 1)),
+            Step::Left => (row, col, turn(dir, 3)),
+            Step::Forward(dist) => (0..dist).fold(state, |s, _| cube.step(board, s)),
         };
     }
+    state
+}
 
-    let (row, col, dir) = state;
-    ((row + 1) * 1000)
-        + ((col + 1) * 4)
-        + match dir {
-            '>' => 0,
-            'v' => 1,
-            '<' => 2,
-            '^' => 3,
-            _ => panic!(),
-        }
+fn part_b(lines: &[String]) -> AResult<isize> {
+    let (board, instr) = parse(lines)?;
+    let cube = fold(&board)?;
+
+    let start_col = board
+        .keys()
+        .filter_map(|&(ro, ci)| if ro == 0 { Some(ci) } else { None })
+        .min()
+        .ok_or_else(|| anyhow!("the board has no top row"))?;
+    Ok(password(walk_cube(
+        &board,
+        &cube,
+        &instr,
+        (0, start_col, '>'),
+    )))
+}
+
+#[allow(clippy::cast_possible_wrap)]
+fn password((row, col, dir): (isize, isize, char)) -> isize {
+    (row + 1) * 1000 + (col + 1) * 4 + DIRS.iter().position(|&d| d == dir).unwrap() as isize
 }
 
 fn main() -> AResult<()> {
@@ -511,15 +416,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-
-    let (board, instr) = parse(lines.as_slice());
-    println!("Part B result = {}", part_b(&board, instr, 0, 50));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -530,6 +439,7 @@ fn main() -> AResult<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use advent_common::parsing::parse_all;
 
     const TEST_INPUT: &str = "        ...#
         .#..
@@ -562,23 +472,62 @@ mod tests {
 10R5L5L10L4R5L5";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
         let lines: Vec<_> = TEST_INPUT.lines().map(String::from).collect();
-        assert_eq!(part_a(lines.as_slice()), 6032);
+        assert_eq!(
+            parse(&lines[..12]).unwrap_err().to_string(),
+            "line 13, column 1: expected the path, found end of input"
+        );
+
+        let mut broken = lines.clone();
+        broken[2] = "        #.x.".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 3, column 11: expected '.', '#' or ' ', found 'x'"
+        );
+
+        let mut broken = lines;
+        broken[13] = "10R5L5F10L4R5L5".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 14, column 7: expected end of line, found \"F10L4R5L5\""
+        );
+
+        // Distances too far to walk are rejected rather than wrapped or truncated
+        broken[13] = "10R99999999999999999999L5".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 14, column 4: expected end of line, found \"99999999999999999999\"..."
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
+        let lines: Vec<_> = TEST_INPUT.lines().map(String::from).collect();
+        assert_eq!(part_a(lines.as_slice())?, 6032);
 
         let lines: Vec<_> = EXTRA_TEST_INPUT.lines().map(String::from).collect();
-        assert_eq!(part_a(lines.as_slice()), 6042);
+        assert_eq!(part_a(lines.as_slice())?, 6042);
+        Ok(())
     }
 
     #[allow(clippy::too_many_lines)]
     #[test]
     fn test_b() -> AResult<()> {
-        // Will do some small tests directly on the real input because the net
+        let lines: Vec<_> = TEST_INPUT.lines().map(String::from).collect();
+        assert_eq!(part_b(lines.as_slice())?, 5031);
+
+        // Also some small tests directly on the real input because the net
         // is a different shape in the test
 
-        let file = File::open("./data/day_22.txt")?;
-        let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
-        let (board, _instr) = parse(lines.as_slice());
+        let lines = read_lines("./data/day_22.txt")?;
+        let (board, _instr) = parse(lines.as_slice())?;
+        let cube = fold(&board)?;
+        assert_eq!(cube.size, 50);
+        let walk = |instr: &[&str], row, col| -> AResult<isize> {
+            let instr = parse_all(&instr.concat(), path)?;
+            Ok(password(walk_cube(&board, &cube, &instr, (row, col, '>'))))
+        };
 
         // faces numbered as
         //   5 6
@@ -594,96 +543,42 @@ mod tests {
 
         // Face 1
         // Up
-        assert_eq!(
-            part_b(&board, vec!["L".to_string(), "2".to_string()], 150, 37),
-            150_000 + 38 * 4 + 3
-        );
+        assert_eq!(walk(&["L", "2"], 150, 37)?, 150_000 + 38 * 4 + 3);
         println!("===================================");
         // Right - will turn over to the bottom of 3 and start upwards
         // Normal
-        assert_eq!(
-            part_b(&board, vec!["1".to_string()], 150, 49),
-            150_000 + 51 * 4 + 3
-        );
+        assert_eq!(walk(&["1"], 150, 49)?, 150_000 + 51 * 4 + 3);
         println!("===================================");
         // Blocked
-        assert_eq!(
-            part_b(&board, vec!["1".to_string()], 159, 49),
-            160_000 + 50 * 4
-        );
+        assert_eq!(walk(&["1"], 159, 49)?, 160_000 + 50 * 4);
         // Down - will turn over onto face 6 and continue down
         println!("===================================");
-        assert_eq!(
-            part_b(&board, vec!["R".to_string(), "1".to_string()], 199, 0),
-            1_000 + 101 * 4 + 1
-        );
+        assert_eq!(walk(&["R", "1"], 199, 0)?, 1_000 + 101 * 4 + 1);
         // Down with block
         println!("===================================");
-        assert_eq!(
-            part_b(&board, vec!["R".to_string(), "2".to_string()], 199, 1),
-            200_009
-        );
+        assert_eq!(walk(&["R", "2"], 199, 1)?, 200_009);
         // Left - start moving down face 5
         println!("===================================");
-        assert_eq!(
-            part_b(
-                &board,
-                vec!["R".to_string(), "R".to_string(), "5".to_string()],
-                150,
-                0
-            ),
-            5000 + (51 * 4) + 1
-        );
+        assert_eq!(walk(&["R", "R", "5"], 150, 0)?, 5000 + (51 * 4) + 1);
         // Left with block
         println!("===================================");
-        assert_eq!(
-            part_b(
-                &board,
-                vec!["R".to_string(), "R".to_string(), "5".to_string()],
-                152,
-                0
-            ),
-            153_006
-        );
+        assert_eq!(walk(&["R", "R", "5"], 152, 0)?, 153_006);
 
         // Face 2
         // Down and Right are as-normal as so will not be tested here
         //
         // Up - start going right across face 4
         println!("===================================");
-        assert_eq!(
-            part_b(&board, vec!["L".to_string(), "1".to_string()], 100, 0),
-            51_000 + (51 * 4)
-        );
+        assert_eq!(walk(&["L", "1"], 100, 0)?, 51_000 + (51 * 4));
         // Up with block
         println!("===================================");
-        assert_eq!(
-            part_b(&board, vec!["L".to_string(), "1".to_string()], 100, 43),
-            101_000 + (44 * 4) + 3
-        );
+        assert_eq!(walk(&["L", "1"], 100, 43)?, 101_000 + (44 * 4) + 3);
         // Left - start going right across 5 (upside down!)
         println!("===================================");
-        assert_eq!(
-            part_b(
-                &board,
-                vec!["L".to_string(), "L".to_string(), "40".to_string()],
-                119,
-                0
-            ),
-            31208,
-            "f2 left clean"
-        );
+        assert_eq!(walk(&["L", "L", "40"], 119, 0)?, 31208, "f2 left clean");
         // Left with block
         println!("===================================");
-        assert_eq!(
-            part_b(
-                &board,
-                vec!["L".to_string(), "L".to_string(), "40".to_string()],
-                124,
-                0
-            ),
-            26232
-        );
+        assert_eq!(walk(&["L", "L", "40"], 124, 0)?, 26232);
 
         // Face 3
         // Up and right are "normal" moves so are not tested
@@ -691,21 +586,21 @@ mod tests {
         // Right - turns to go left across face 6
         println!("===================================");
         assert_eq!(
-            part_b(&board, vec!["40".to_string()], 149, 149),
+            walk(&["40"], 149, 99)?,
             1_000 + (4 * 147) + 2,
             "f3 right clean"
         );
         // Right blocked
         println!("===================================");
         assert_eq!(
-            part_b(&board, vec!["40".to_string()], 144, 149),
-            145_000 + (4 * 150),
+            walk(&["40"], 144, 99)?,
+            145_000 + (4 * 100),
             "f3 right blocked"
         );
         // Down - coming in right edge of 1 proceeding left
         println!("===================================");
         assert_eq!(
-            part_b(&board, vec!["R".to_string(), "40".to_string()], 149, 50),
+            walk(&["R", "40"], 149, 50)?,
             151_000 + (4 * 49) + 2,
             "f3 down clean"
         );
@@ -714,19 +609,14 @@ mod tests {
         // Left - enter the top of 2 travelling down
         println!("===================================");
         assert_eq!(
-            part_b(
-                &board,
-                vec!["R".to_string(), "R".to_string(), "1".to_string()],
-                99,
-                50
-            ),
+            walk(&["R", "R", "1"], 99, 50)?,
             101_000 + (4 * 50) + 1,
             "f4 left clean"
         );
         // Right
         println!("===================================");
         assert_eq!(
-            part_b(&board, vec!["1".to_string()], 50, 99),
+            walk(&["1"], 50, 99)?,
             50_000 + (4 * 101) + 3,
             "f4 right clean"
         );
@@ -734,52 +624,102 @@ mod tests {
         // Face 5
         // Left - enter lhs of 1 travelling right
         println!("===================================");
-        assert_eq!(
-            part_b(
-                &board,
-                vec!["R".to_string(), "R".to_string(), "1".to_string()],
-                0,
-                50
-            ),
-            150_004,
-            "f5 left clean"
-        );
+        assert_eq!(walk(&["R", "R", "1"], 0, 50)?, 150_004, "f5 left clean");
         // Up - enter lh of 1 travelling right
         println!("===================================");
-        assert_eq!(
-            part_b(&board, vec!["L".to_string(), "1".to_string()], 0, 99),
-            200_000 + 4,
-            "f5 up clean"
-        );
+        assert_eq!(walk(&["L", "1"], 0, 99)?, 200_000 + 4, "f5 up clean");
 
         // Face 6
         // Up
         println!("===================================");
         assert_eq!(
-            part_b(&board, vec!["L".to_string(), "1".to_string()], 0, 147),
+            walk(&["L", "1"], 0, 147)?,
             200_000 + (48 * 4) + 3,
             "f6 up clean"
         );
 
         // Right
         println!("===================================");
-        assert_eq!(
-            part_b(&board, vec!["1".to_string()], 25, 149),
-            125_402,
-            "f6 right clean"
-        );
+        assert_eq!(walk(&["1"], 25, 149)?, 125_402, "f6 right clean");
 
         // Down
         println!("===================================");
         assert_eq!(
-            part_b(&board, vec!["R".to_string(), "1".to_string()], 49, 100),
+            walk(&["R", "1"], 49, 100)?,
             51_000 + 400 + 2,
             "f6 down clean"
         );
 
         // Additional test from debugging - :'(
-        assert_eq!(move_left_b(11, 144, 8, &board), (5, 52, '>'));
+        assert_eq!(
+            walk_cube(&board, &cube, &[Step::Forward(11)], (144, 8, '<')),
+            (5, 52, '>')
+        );
+
+        Ok(())
+    }
+
+    /// An open board with a `size` square face wherever the net has an X
+    fn net_board(net: &str, size: isize) -> Board {
+        net.split('|')
+            .zip(0..)
+            .flat_map(|(row, fr)| {
+                row.chars()
+                    .zip(0..)
+                    .filter(|&(c, _)| c == 'X')
+                    .map(move |(_, fc)| (fr, fc))
+            })
+            .flat_map(|(fr, fc)| {
+                (0..size * size).map(move |i| ((fr * size + i / size, fc * size + i % size), '.'))
+            })
+            .collect()
+    }
 
+    #[test]
+    fn test_fold_nets() -> AResult<()> {
+        // All eleven cube nets
+        let nets = [
+            "X...|XXXX|X...",
+            "X...|XXXX|.X..",
+            "X...|XXXX|..X.",
+            "X...|XXXX|...X",
+            ".X..|XXXX|.X..",
+            ".X..|XXXX|..X.",
+            "XX..|.XXX|.X..",
+            "XX..|.XXX|..X.",
+            "XX..|.XXX|...X",
+            "XX..|.XX.|..XX",
+            "XXX..|..XXX",
+        ];
+        let size = 3;
+        for net in nets {
+            let board = net_board(net, size);
+            let cube = fold(&board)?;
+
+            // Heading straight on from anywhere goes round the cube and back in 4 faces
+            for &(row, col) in board.keys() {
+                for dir in DIRS {
+                    let start = (row, col, dir);
+                    let end = (0..4 * size).fold(start, |s, _| cube.step(&board, s));
+                    assert_eq!(end, start, "{net} from {start:?}");
+                }
+            }
+        }
+
+        // Six faces, but two of them fold onto the same side
+        for net in ["XXXXXX", "X..|XXX|..X|..X"] {
+            assert_eq!(
+                fold(&net_board(net, 2)).unwrap_err().to_string(),
+                "the board does not fold into a cube"
+            );
+        }
+        let lines: Vec<_> = TEST_INPUT.lines().map(String::from).collect();
+        let (mut board, _) = parse(&lines)?;
+        board.remove(&(0, 8));
+        assert_eq!(
+            fold(&board).unwrap_err().to_string(),
+            "95 tiles cannot form the six faces of a cube"
+        );
         Ok(())
     }
 }
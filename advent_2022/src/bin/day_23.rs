@@ -1,154 +1,373 @@
+use advent_common::parsing::{in_file, read_lines, rows_of, ParseError};
+use anyhow::anyhow;
 use humantime::format_duration;
 use regex::Regex;
 use std::{
-    cmp::{max, min},
-    collections::{HashMap, HashSet, VecDeque},
-    fs::File,
-    io::{BufRead, BufReader},
+    collections::{HashMap, HashSet},
+    fmt::Write,
     time::Instant,
 };
 
 type AResult<T> = anyhow::Result<T>;
+// (x, y) with y growing southwards
+type Pos = (isize, isize);
 
-fn parse(lines: &[String]) -> HashSet<(isize, isize)> {
-    lines
+fn parse(lines: &[String]) -> AResult<Vec<Pos>> {
+    let rows = rows_of(lines, "'#' or '.'", |c| match c {
+        '#' => Some(true),
+        '.' => Some(false),
+        _ => None,
+    })?;
+
+    let elves: Vec<_> = rows
         .iter()
         .enumerate()
-        .flat_map(|(ri, line)| {
-            line.char_indices().filter_map(move |(ci, v)| {
-                if v == '#' {
-                    Some((ci.try_into().unwrap(), ri.try_into().unwrap()))
-                } else {
-                    None
-                }
-            })
+        .flat_map(|(ri, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|&(_, &elf)| elf)
+                .map(move |(ci, _)| (ci.try_into().unwrap(), ri.try_into().unwrap()))
         })
-        .collect()
-}
-
-#[allow(clippy::too_many_lines)]
-fn solve(lines: &[String], rounds: usize) -> (isize, Option<usize>) {
-    let mut elves = parse(lines);
-    let n_elves = elves.len();
-    let mut consideration: VecDeque<_> = "NSWE".chars().collect();
-
-    let mut n_rounds = None;
-    for round in 0..rounds {
-        // First Half
-        let mut stationary = HashSet::new();
-        let mut movers = HashSet::new();
-        for (ex, ey) in &elves {
-            let mut neighbours = 0;
-            for dx in -1..=1 {
-                for dy in -1..=1 {
-                    if dx != 0 || dy != 0 {
-                        neighbours += &elves.contains(&(ex + dx, ey + dy)).into();
-                    }
-                }
-            }
+        .collect();
+    if elves.is_empty() {
+        Err(ParseError::end_of_input(lines.len(), "an elf"))?;
+    }
+
+    Ok(elves)
+}
 
-            if neighbours == 0 {
-                stationary.insert((ex, ey));
+/// An inclusive rectangle of ground
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Area {
+    min: Pos,
+    max: Pos,
+}
+
+impl Area {
+    /// The smallest rectangle holding all of `elves`
+    fn of(elves: impl IntoIterator<Item = Pos>) -> Area {
+        elves.into_iter().fold(
+            Area {
+                min: (isize::MAX, isize::MAX),
+                max: (isize::MIN, isize::MIN),
+            },
+            |area, p| area.union(Area { min: p, max: p }),
+        )
+    }
+
+    fn union(self, other: Area) -> Area {
+        Area {
+            min: (self.min.0.min(other.min.0), self.min.1.min(other.min.1)),
+            max: (self.max.0.max(other.max.0), self.max.1.max(other.max.1)),
+        }
+    }
+
+    fn expand(self, by: isize) -> Area {
+        Area {
+            min: (self.min.0 - by, self.min.1 - by),
+            max: (self.max.0 + by, self.max.1 + by),
+        }
+    }
+
+    fn width(self) -> usize {
+        (self.max.0 - self.min.0 + 1).try_into().unwrap_or(0)
+    }
+
+    fn height(self) -> usize {
+        (self.max.1 - self.min.1 + 1).try_into().unwrap_or(0)
+    }
+
+    fn rows(self) -> impl Iterator<Item = impl Iterator<Item = Pos>> {
+        (self.min.1..=self.max.1).map(move |y| (self.min.0..=self.max.0).map(move |x| (x, y)))
+    }
+}
+
+/// Where the elves are, for looking up who's next to who
+trait Field {
+    fn new(elves: &[Pos]) -> Self;
+    fn contains(&self, p: Pos) -> bool;
+    fn insert(&mut self, p: Pos);
+    fn remove(&mut self, p: Pos);
+}
+
+/// Elves as a set of positions - fine however far they spread
+impl Field for HashSet<Pos> {
+    fn new(elves: &[Pos]) -> Self {
+        elves.iter().copied().collect()
+    }
+
+    fn contains(&self, p: Pos) -> bool {
+        HashSet::contains(self, &p)
+    }
+
+    fn insert(&mut self, p: Pos) {
+        HashSet::insert(self, p);
+    }
+
+    fn remove(&mut self, p: Pos) {
+        HashSet::remove(self, &p);
+    }
+}
+
+/// Elves as a bitset over a rectangle of ground, grown whenever one steps off it
+struct Dense {
+    area: Area,
+    // u64 words in each row
+    stride: usize,
+    bits: Vec<u64>,
+}
+
+impl Dense {
+    fn over(area: Area, elves: impl IntoIterator<Item = Pos>) -> Dense {
+        let stride = area.width().div_ceil(64);
+        let mut dense = Dense {
+            area,
+            stride,
+            bits: vec![0; stride * area.height()],
+        };
+        for p in elves {
+            dense.insert(p);
+        }
+        dense
+    }
+
+    fn locate(&self, (x, y): Pos) -> Option<(usize, u64)> {
+        let col = usize::try_from(x - self.area.min.0).ok()?;
+        let row = usize::try_from(y - self.area.min.1).ok()?;
+        (col < self.area.width() && row < self.area.height())
+            .then(|| (row * self.stride + col / 64, 1 << (col % 64)))
+    }
+
+    fn elves(&self) -> impl Iterator<Item = Pos> + '_ {
+        self.area.rows().flatten().filter(|&p| self.contains(p))
+    }
+}
+
+impl Field for Dense {
+    fn new(elves: &[Pos]) -> Self {
+        Dense::over(
+            Area::of(elves.iter().copied()).expand(8),
+            elves.iter().copied(),
+        )
+    }
+
+    fn contains(&self, p: Pos) -> bool {
+        self.locate(p)
+            .is_some_and(|(word, bit)| self.bits[word] & bit != 0)
+    }
+
+    fn insert(&mut self, p: Pos) {
+        let Some((word, bit)) = self.locate(p) else {
+            // Leave plenty of room to spread into, so growing stays rare
+            let area = Area::of(self.elves().chain([p]));
+            let room = isize::try_from(area.width().max(area.height()) / 2).unwrap_or(0);
+            let elves: Vec<_> = self.elves().collect();
+            *self = Dense::over(area.expand(room.max(8)), elves);
+            return self.insert(p);
+        };
+        self.bits[word] |= bit;
+    }
+
+    fn remove(&mut self, p: Pos) {
+        if let Some((word, bit)) = self.locate(p) {
+            self.bits[word] &= !bit;
+        }
+    }
+}
+
+/// A direction an elf might head, and the tiles (relative to it) that have to
+/// be empty for it to propose going that way
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Proposal {
+    step: Pos,
+    clear: Vec<Pos>,
+}
+
+/// What to do when more than one elf proposes the same tile
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Conflicts {
+    NobodyMoves,
+    // The elf earliest in reading order (of where they all started) moves
+    FirstMoves,
+}
+
+#[derive(Debug, Clone)]
+struct Rules {
+    // Tried in order - with `rotate` the first goes to the back after each round
+    proposals: Vec<Proposal>,
+    rotate: bool,
+    // An elf with none of these tiles (relative to it) occupied stays put
+    neighbourhood: Vec<Pos>,
+    conflicts: Conflicts,
+}
+
+impl Rules {
+    /// The rules from the puzzle: look north, south, west then east
+    fn puzzle() -> Rules {
+        let towards = |step: Pos| {
+            let clear = if step.0 == 0 {
+                vec![(-1, step.1), (0, step.1), (1, step.1)]
             } else {
-                movers.insert((ex, ey));
-            }
+                vec![(step.0, -1), (step.0, 0), (step.0, 1)]
+            };
+            Proposal { step, clear }
+        };
+        let around = (-1..=1)
+            .flat_map(|y| (-1..=1).map(move |x| (x, y)))
+            .filter(|&p| p != (0, 0))
+            .collect();
+        Rules {
+            proposals: [(0, -1), (0, 1), (-1, 0), (1, 0)].map(towards).to_vec(),
+            rotate: true,
+            neighbourhood: around,
+            conflicts: Conflicts::NobodyMoves,
         }
+    }
+}
 
-        if stationary.len() == n_elves {
-            n_rounds = Some(round + 1);
-            break;
-        }
-
-        let mut proposals: HashMap<(isize, isize), (isize, isize)> = HashMap::new();
-        for (&ex, &ey) in movers {
-            for d in &consideration {
-                match d {
-                    'N' if !(elves.contains(&(ex, ey - 1))
-                        || elves.contains(&(ex - 1, ey - 1))
-                        || elves.contains(&(ex + 1, ey - 1))) =>
-                    {
-                        let target = (ex, ey - 1);
-                        proposals.insert((ex, ey), target);
-                        break;
-                    }
-                    'S' if !(elves.contains(&(ex, ey + 1))
-                        || elves.contains(&(ex - 1, ey + 1))
-                        || elves.contains(&(ex + 1, ey + 1))) =>
-                    {
-                        let target = (ex, ey + 1);
-                        proposals.insert((ex, ey), target);
-                        break;
-                    }
-                    'W' if !(elves.contains(&(ex - 1, ey))
-                        || elves.contains(&(ex - 1, ey - 1))
-                        || elves.contains(&(ex - 1, ey + 1))) =>
-                    {
-                        let target = (ex - 1, ey);
-                        proposals.insert((ex, ey), target);
-                        break;
-                    }
-                    'E' if !(elves.contains(&(ex + 1, ey))
-                        || elves.contains(&(ex + 1, ey - 1))
-                        || elves.contains(&(ex + 1, ey + 1))) =>
-                    {
-                        let target = (ex + 1, ey);
-                        proposals.insert((ex, ey), target);
-                        break;
-                    }
-                    _ => {}
-                };
-            }
+fn offset((x, y): Pos, (dx, dy): Pos) -> Pos {
+    (x + dx, y + dy)
+}
+
+/// The elves spreading out round by round
+struct Automaton<F> {
+    rules: Rules,
+    elves: Vec<Pos>,
+    field: F,
+    // Which elves moved in the last round
+    moved: Vec<bool>,
+    round: usize,
+}
+
+impl<F: Field> Automaton<F> {
+    fn new(elves: Vec<Pos>, rules: Rules) -> Self {
+        Automaton {
+            field: F::new(&elves),
+            moved: vec![false; elves.len()],
+            elves,
+            rules,
+            round: 0,
         }
+    }
 
-        let clashes: HashSet<_> = proposals
-            .values()
-            .fold(HashMap::new(), |mut m, item| {
-                m.entry(item).and_modify(|v| *v += 1).or_insert(1);
-                m
+    /// Run a round, returning how many elves moved. Nobody moves onto a tile an elf
+    /// started the round on, even if a proposal doesn't check that it's clear.
+    fn step(&mut self) -> usize {
+        let targets: Vec<_> = self
+            .elves
+            .iter()
+            .map(|&elf| {
+                let near = |d| self.field.contains(offset(elf, d));
+                if !self.rules.neighbourhood.iter().any(|&d| near(d)) {
+                    return None;
+                }
+                self.rules
+                    .proposals
+                    .iter()
+                    .find(|p| !p.clear.iter().any(|&d| near(d)))
+                    .map(|p| offset(elf, p.step))
+                    .filter(|&t| !self.field.contains(t))
             })
-            .into_iter()
-            .filter_map(|(target, count)| if count > 1 { Some(target) } else { None })
             .collect();
 
-        // Second Half
-        for (source, target) in &proposals {
-            if !clashes.contains(target) {
-                elves.remove(source);
-                elves.insert(*target);
+        // Who's heading where, in elf order
+        let mut proposers: HashMap<Pos, Vec<usize>> = HashMap::new();
+        for (i, target) in targets.iter().enumerate() {
+            if let Some(t) = target {
+                proposers.entry(*t).or_default().push(i);
             }
         }
 
-        // Rotate consideration
-        let c = consideration.pop_front().unwrap();
-        consideration.push_back(c);
+        self.moved.fill(false);
+        for (target, who) in proposers {
+            let mover = match self.rules.conflicts {
+                Conflicts::NobodyMoves if who.len() > 1 => continue,
+                _ => who[0],
+            };
+            self.field.remove(self.elves[mover]);
+            self.field.insert(target);
+            self.elves[mover] = target;
+            self.moved[mover] = true;
+        }
 
-        assert_eq!(elves.len(), n_elves, "some elves were lost");
+        if self.rules.rotate {
+            self.rules.proposals.rotate_left(1);
+        }
+        self.round += 1;
+        self.moved.iter().filter(|&&m| m).count()
     }
 
-    // find bounding box
-    let (min_x, min_y, max_x, max_y) = elves.iter().fold(
-        (isize::MAX, isize::MAX, isize::MIN, isize::MIN),
-        |acc, &(ex, ey)| {
-            let (min_x, min_y, max_x, max_y) = acc;
-            (
-                min(min_x, ex),
-                min(min_y, ey),
-                max(max_x, ex),
-                max(max_y, ey),
-            )
-        },
-    );
+    /// Keep going until nobody moves, returning that round's number
+    fn settle(&mut self) -> usize {
+        while self.step() > 0 {}
+        self.round
+    }
 
-    let mut acc = ((max_x + 1) - min_x) * ((max_y + 1) - min_y);
-    for x in min_x..=max_x {
-        for y in min_y..=max_y {
-            if elves.contains(&(x, y)) {
-                acc -= 1;
-            }
+    fn area(&self) -> Area {
+        Area::of(self.elves.iter().copied())
+    }
+
+    /// The ground without elves inside the smallest rectangle holding them all
+    fn empty_ground(&self) -> usize {
+        let area = self.area();
+        area.width() * area.height() - self.elves.len()
+    }
+
+    /// The elves in `area`, drawn as in the puzzle
+    fn ascii(&self, area: Area) -> String {
+        area.rows()
+            .map(|row| {
+                row.map(|p| if self.field.contains(p) { '#' } else { '.' })
+                    .collect()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// The elves in `area` as a plain PBM image, one pixel per tile
+    fn pbm(&self, area: Area) -> String {
+        let mut out = format!("P1\n{} {}\n", area.width(), area.height());
+        for row in area.rows() {
+            out.extend(row.map(|p| if self.field.contains(p) { '1' } else { '0' }));
+            out.push('\n');
+        }
+        out
+    }
+
+    /// The elves in `area` as a plain PPM image, with those that just moved in
+    /// orange and the rest in green
+    fn ppm(&self, area: Area) -> String {
+        let moved: HashSet<_> = self
+            .elves
+            .iter()
+            .zip(&self.moved)
+            .filter_map(|(&p, &m)| m.then_some(p))
+            .collect();
+        let mut out = format!("P3\n{} {}\n255\n", area.width(), area.height());
+        for row in area.rows() {
+            let pixels: Vec<_> = row
+                .map(|p| match (self.field.contains(p), moved.contains(&p)) {
+                    (true, true) => "255 140 0",
+                    (true, false) => "40 160 60",
+                    _ => "16 16 32",
+                })
+                .collect();
+            let _ = writeln!(out, "{}", pixels.join(" "));
         }
+        out
     }
+}
+
+fn part_a(lines: &[String]) -> AResult<usize> {
+    let mut elves = Automaton::<Dense>::new(parse(lines)?, Rules::puzzle());
+    for _ in 0..10 {
+        elves.step();
+    }
+    Ok(elves.empty_ground())
+}
 
-    (acc, n_rounds)
+fn part_b(lines: &[String]) -> AResult<usize> {
+    Ok(Automaton::<Dense>::new(parse(lines)?, Rules::puzzle()).settle())
 }
 
 fn main() -> AResult<()> {
@@ -159,18 +378,75 @@ fn main() -> AResult<()> {
         .find(name)
         .expect("binary name should contain a number")
         .as_str();
-    println!("Running code for Day {ex}.");
+    // `day_23 --animate` prints the elves after every round until they settle, and
+    // `day_23 --frames DIR` writes the rounds out to DIR as PPM images (or black and
+    // white PBM ones with `--mono`). Either can add `--first-moves` to let the first
+    // elf through when several propose the same tile.
+    let args: Vec<_> = std::env::args().collect();
+    let animate = args.iter().any(|arg| arg == "--animate");
+    let mono = args.iter().any(|arg| arg == "--mono");
+    let rules = if args.iter().any(|arg| arg == "--first-moves") {
+        Rules {
+            conflicts: Conflicts::FirstMoves,
+            ..Rules::puzzle()
+        }
+    } else {
+        Rules::puzzle()
+    };
+    let frames = match args.iter().position(|arg| arg == "--frames") {
+        Some(i) => Some(
+            args.get(i + 1)
+                .ok_or_else(|| anyhow!("--frames needs a directory"))?,
+        ),
+        None => None,
+    };
+    if !animate && frames.is_none() {
+        println!("Running code for Day {ex}.");
+    }
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
+
+    if animate || frames.is_some() {
+        let elves = parse(lines.as_slice()).map_err(in_file(&path))?;
+
+        // Run through once to find how far they spread, so every frame is the same size
+        let mut sim = Automaton::<Dense>::new(elves.clone(), rules.clone());
+        let mut area = sim.area();
+        while sim.step() > 0 {
+            area = area.union(sim.area());
+        }
+
+        let mut sim = Automaton::<Dense>::new(elves, rules);
+        loop {
+            if animate {
+                println!("== End of Round {} ==\n{}\n", sim.round, sim.ascii(area));
+            }
+            if let Some(dir) = frames {
+                let (image, ext) = if mono {
+                    (sim.pbm(area), "pbm")
+                } else {
+                    (sim.ppm(area), "ppm")
+                };
+                std::fs::write(format!("{dir}/round_{:04}.{ext}", sim.round), image)?;
+            }
+            if sim.step() == 0 {
+                break;
+            }
+        }
+        return Ok(());
+    }
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", solve(lines.as_slice(), 10).0);
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
     println!(
         "Part B result = {}",
-        solve(lines.as_slice(), usize::MAX).1.unwrap()
+        part_b(lines.as_slice()).map_err(in_file(&path))?
     );
     let end = Instant::now();
 
@@ -199,16 +475,122 @@ mod tests {
     .#..#..";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines = [".#.".to_string(), "#o.".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 2: expected '#' or '.', found 'o'"
+        );
+
+        let lines = ["...".to_string(), "...".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 3, column 1: expected an elf, found end of input"
+        );
+    }
+
+    #[test]
+    fn test_rounds() -> AResult<()> {
+        let lines: Vec<_> = SMALL_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        let mut sim = Automaton::<HashSet<Pos>>::new(parse(&lines)?, Rules::puzzle());
+        let frame = Area { min: (0, 0), max: (4, 5) };
+
+        assert_eq!(sim.step(), 3);
+        assert_eq!(sim.ascii(frame), "..##.\n.....\n..#..\n...#.\n..#..\n.....");
+        sim.step();
+        sim.step();
+        assert_eq!(sim.ascii(frame), "..#..\n....#\n#....\n....#\n.....\n..#..");
+        assert_eq!(sim.settle(), 4);
+
+        let small = Area { min: (0, 0), max: (2, 1) };
+        assert_eq!(sim.pbm(small), "P1\n3 2\n001\n000\n");
+        assert_eq!(
+            sim.ppm(small),
+            "P3\n3 2\n255\n16 16 32 16 16 32 40 160 60\n16 16 32 16 16 32 16 16 32\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_backends() -> AResult<()> {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        let elves = parse(&lines)?;
+
+        for conflicts in [Conflicts::NobodyMoves, Conflicts::FirstMoves] {
+            let rules = Rules { conflicts, ..Rules::puzzle() };
+            let mut sparse = Automaton::<HashSet<Pos>>::new(elves.clone(), rules.clone());
+            let mut dense = Automaton::<Dense>::new(elves.clone(), rules);
+            for _ in 0..30 {
+                assert_eq!(sparse.step(), dense.step());
+                assert_eq!(sparse.elves, dense.elves);
+            }
+
+            // Nobody ever shares a tile
+            let tiles: HashSet<_> = dense.elves.iter().collect();
+            assert_eq!(tiles.len(), elves.len());
+            assert_eq!(dense.elves.len(), dense.field.elves().count());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_rules() -> AResult<()> {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        let elves = parse(&lines)?;
+
+        // With no neighbourhood to check, nobody feels crowded
+        let rules = Rules {
+            neighbourhood: vec![],
+            ..Rules::puzzle()
+        };
+        assert_eq!(Automaton::<Dense>::new(elves.clone(), rules).settle(), 1);
+
+        // Only ever heading north, crowded elves shuffle that way when they can
+        let rules = Rules {
+            proposals: vec![Proposal {
+                step: (0, -1),
+                clear: vec![(0, -1)],
+            }],
+            rotate: false,
+            ..Rules::puzzle()
+        };
+        let mut sim = Automaton::<Dense>::new(elves.clone(), rules);
+        assert!(sim.step() > 0);
+        for (&(x, y), &after) in elves.iter().zip(&sim.elves) {
+            assert!(after == (x, y) || after == (x, y - 1));
+        }
+
+        // A proposal that doesn't look where it's going still can't land on an elf
+        let rules = Rules {
+            proposals: vec![Proposal { step: (0, -1), clear: vec![] }],
+            rotate: false,
+            ..Rules::puzzle()
+        };
+        let mut sparse = Automaton::<HashSet<Pos>>::new(elves.clone(), rules.clone());
+        let mut dense = Automaton::<Dense>::new(elves.clone(), rules);
+        for _ in 0..10 {
+            assert_eq!(sparse.step(), dense.step());
+            assert_eq!(sparse.elves, dense.elves);
+        }
+        let tiles: HashSet<_> = dense.elves.iter().copied().collect();
+        assert_eq!(tiles.len(), elves.len());
+        assert_eq!(dense.field.elves().collect::<HashSet<_>>(), tiles);
+        Ok(())
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = SMALL_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(solve(lines.as_slice(), 10).0, 25);
+        assert_eq!(part_a(lines.as_slice())?, 25);
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(solve(lines.as_slice(), 10).0, 110);
+        assert_eq!(part_a(lines.as_slice())?, 110);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(solve(lines.as_slice(), usize::MAX).1.unwrap(), 20);
+        assert_eq!(part_b(lines.as_slice())?, 20);
+        Ok(())
     }
 }
@@ -1,12 +1,12 @@
+use advent_common::{
+    math::lcm,
+    parsing::{grid_of, in_file, read_lines, ParseError},
+    search::astar,
+};
+use anyhow::anyhow;
 use humantime::format_duration;
 use regex::Regex;
-use std::{
-    collections::HashSet,
-    fs::File,
-    io::{BufRead, BufReader},
-    iter::zip,
-    time::Instant,
-};
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
 
@@ -22,188 +22,239 @@ const WEST: u8 = 1;
 const EMPTY: u8 = 0;
 const WALL: u8 = 128;
 
-#[derive(Debug, Hash, PartialEq, Eq)]
-struct State {
-    row: usize,
-    col: usize,
-    depth: usize,
+type Coord = (usize, usize);
+
+fn parse(lines: &[String]) -> AResult<Valley> {
+    let valley = grid_of(lines, "a wall, an empty tile or a blizzard", |c| match c {
+        '^' => Some(NORTH),
+        'v' => Some(SOUTH),
+        '>' => Some(EAST),
+        '<' => Some(WEST),
+        '.' => Some(EMPTY),
+        '#' => Some(WALL),
+        _ => None,
+    })?;
+
+    // The way in is at the top left and the way out at the bottom right
+    if valley.len() < 3 {
+        Err(ParseError::end_of_input(lines.len(), "a row of the valley"))?;
+    }
+    let width = valley[0].len();
+    for (ri, ci) in [(0, 1), (valley.len() - 1, width.saturating_sub(2))] {
+        if valley[ri].get(ci) != Some(&EMPTY) {
+            let found = lines[ri]
+                .chars()
+                .nth(ci)
+                .map_or("end of line".to_string(), |c| format!("{c:?}"));
+            Err(ParseError::new(
+                ri + 1,
+                ci + 1,
+                "the way through the wall",
+                found,
+            ))?;
+        }
+    }
+
+    Ok(valley)
 }
 
-fn parse(lines: &[String]) -> Valley {
-    let mut out = Vec::with_capacity(lines[0].len());
-    for line in lines {
-        let row = line
-            .chars()
-            .map(|c| -> u8 {
-                match c {
-                    '^' => NORTH,
-                    'v' => SOUTH,
-                    '>' => EAST,
-                    '<' => WEST,
-                    '.' => EMPTY,
-                    '#' => WALL,
-                    _ => panic!("unsupported character {c}"),
-                }
-            })
-            .collect();
-        out.push(row);
+/// Draw the valley the way the puzzle does, with the expedition (if any) as `E`
+fn display(valley: &Valley, expedition: Option<Coord>) -> String {
+    let mut out = String::new();
+    for (ri, row) in valley.iter().enumerate() {
+        for (ci, v) in row.iter().enumerate() {
+            out.push(match *v {
+                _ if expedition == Some((ri, ci)) => 'E',
+                WALL => '#',
+                EMPTY => '.',
+                NORTH => '^',
+                SOUTH => 'v',
+                EAST => '>',
+                WEST => '<',
+                x if x.count_ones() > 1 => (x.count_ones() + 48).try_into().unwrap(),
+                _ => panic!(),
+            });
+        }
+        out.push('\n');
     }
     out
 }
 
-fn next_step(previous: &Valley) -> Valley {
-    // Mutate the valley into it's next incarnation
-
-    let row_len = previous[0].len();
-    let mut next = Vec::with_capacity(previous.len());
-    next.push(previous[0].clone());
-
-    for ri in 1..previous.len() - 1 {
-        let mut row: Vec<u8> = std::iter::repeat(0).take(row_len - 1).collect();
-        row[0] = WALL;
-        for (ci, cv) in row[1..].iter_mut().enumerate() {
-            let ci = ci + 1;
-            *cv |= previous[ri - 1][ci] & SOUTH; // south wind coming down
-            *cv |= previous[ri + 1][ci] & NORTH; // north wind coming up
-            *cv |= previous[ri][ci + 1] & WEST; // west wind blowing left
-            *cv |= previous[ri][ci - 1] & EAST; // east wind blowing right
-        }
+/// A fixed-size set of small numbers, one bit each
+#[derive(Debug, Clone)]
+struct Bits(Vec<u64>);
 
-        row.push(WALL);
-        next.push(row);
+impl Bits {
+    fn new(len: usize) -> Bits {
+        Bits(vec![0; len.div_ceil(64)])
     }
 
-    // Deal with wrapping
-    // for the bottom row, wrap south winds around to the top row
-    for (ci, v) in previous[previous.len() - 2].iter().enumerate() {
-        next[1][ci] |= v & SOUTH;
+    fn set(&mut self, i: usize) {
+        self.0[i / 64] |= 1 << (i % 64);
     }
-    // for the top row, wrap north winds around to the bottom
-    for (ci, v) in previous[1].iter().enumerate() {
-        next[previous.len() - 2][ci] |= v & NORTH;
-    }
-    // left/right wrapping
-    for (p_row, n_row) in zip(previous, &mut next) {
-        n_row[row_len - 2] |= p_row[1] & WEST;
-        n_row[1] |= p_row[row_len - 2] & EAST;
+
+    fn get(&self, i: usize) -> bool {
+        self.0[i / 64] & 1 << (i % 64) != 0
     }
+}
 
-    // Push the end row and return
-    next.push(previous[previous.len() - 1].clone());
-    next
+/// The valley with its blizzards as bitsets of where they start along each row
+/// and down each column. Blizzards wrap around inside the walls, so where they
+/// are repeats every lcm(width, height) minutes.
+struct Basin {
+    initial: Valley,
+    // The inside of the valley, without the walls
+    height: usize,
+    width: usize,
+    period: usize,
+    // Indexed by inside row
+    east: Vec<Bits>,
+    west: Vec<Bits>,
+    // Indexed by inside column
+    north: Vec<Bits>,
+    south: Vec<Bits>,
+    entrance: Coord,
+    exit: Coord,
 }
 
-fn _display(valley: &Valley) {
-    for row in valley {
-        for v in row {
-            print!(
-                "{}",
-                match *v {
-                    WALL => '#',
-                    EMPTY => '.',
-                    NORTH => '^',
-                    SOUTH => 'v',
-                    EAST => '>',
-                    WEST => '<',
-                    x if x.count_ones() > 1 => (x.count_ones() + 48).try_into().unwrap(),
-                    _ => panic!(),
-                }
-            );
+impl Basin {
+    fn new(initial: Valley) -> AResult<Basin> {
+        let (height, width) = (initial.len() - 2, initial[0].len() - 2);
+        if height == 0 || width == 0 {
+            return Err(anyhow!("the valley has no room inside its walls"));
+        }
+
+        let along = |flag| -> Vec<Bits> {
+            (0..height)
+                .map(|r| {
+                    let mut bits = Bits::new(width);
+                    (0..width)
+                        .filter(|&c| initial[r + 1][c + 1] & flag != 0)
+                        .for_each(|c| bits.set(c));
+                    bits
+                })
+                .collect()
+        };
+        let down = |flag| -> Vec<Bits> {
+            (0..width)
+                .map(|c| {
+                    let mut bits = Bits::new(height);
+                    (0..height)
+                        .filter(|&r| initial[r + 1][c + 1] & flag != 0)
+                        .for_each(|r| bits.set(r));
+                    bits
+                })
+                .collect()
+        };
+
+        Ok(Basin {
+            entrance: (0, 1),
+            exit: (height + 1, width),
+            period: lcm(width, height),
+            east: along(EAST),
+            west: along(WEST),
+            north: down(NORTH),
+            south: down(SOUTH),
+            initial,
+            height,
+            width,
+        })
+    }
+
+    /// Whether the expedition can stand at `(row, col)` at minute `t`
+    fn is_clear(&self, (row, col): Coord, t: usize) -> bool {
+        if (row, col) == self.entrance || (row, col) == self.exit {
+            return true;
+        }
+        let (h, w) = (self.height, self.width);
+        if !(1..=h).contains(&row) || !(1..=w).contains(&col) {
+            return false;
         }
-        println!();
+
+        // Look back along each row and column for a blizzard that would be here now
+        let (r, c) = (row - 1, col - 1);
+        let (tr, tc) = (t % h, t % w);
+        !(self.east[r].get((c + w - tc) % w)
+            || self.west[r].get((c + tc) % w)
+            || self.south[c].get((r + h - tr) % h)
+            || self.north[c].get((r + tr) % h))
     }
-}
 
-fn solve(initial: Valley, the_start: (usize, usize), the_end: (usize, usize)) -> (usize, Valley) {
-    let max_row = std::cmp::max(the_start.0, the_end.0);
-
-    let mut queue: Vec<_> = Vec::new();
-    queue.push(State {
-        row: the_start.0,
-        col: the_start.1,
-        depth: 0,
-    });
-    let mut p_valley = initial;
-
-    // loop
-    for _depth in 1..=1000 {
-        // Start a collection for the next level down
-        let mut next = HashSet::new();
-
-        // Create the next valley
-        let n_valley = next_step(&p_valley);
-
-        // For each state at this level - push the possible next states into the next set
-        while let Some(prev) = queue.pop() {
-            // Can we stand still?
-            if n_valley[prev.row][prev.col] == EMPTY {
-                next.insert(State {
-                    depth: prev.depth + 1,
-                    ..prev
-                });
-            }
-            // Can we move up?
-            if prev.row > 0 && n_valley[prev.row - 1][prev.col] == EMPTY {
-                next.insert(State {
-                    row: prev.row - 1,
-                    depth: prev.depth + 1,
-                    ..prev
-                });
-            }
-            // Can we move down?
-            if prev.row < max_row && n_valley[prev.row + 1][prev.col] == EMPTY {
-                next.insert(State {
-                    row: prev.row + 1,
-                    depth: prev.depth + 1,
-                    ..prev
-                });
-            }
-            // Can we move left?
-            if n_valley[prev.row][prev.col - 1] == EMPTY {
-                next.insert(State {
-                    col: prev.col - 1,
-                    depth: prev.depth + 1,
-                    ..prev
-                });
-            }
-            // Can we move right?
-            if n_valley[prev.row][prev.col + 1] == EMPTY {
-                next.insert(State {
-                    col: prev.col + 1,
-                    depth: prev.depth + 1,
-                    ..prev
-                });
+    /// The valley as it looks at minute `t`
+    fn valley_at(&self, t: usize) -> Valley {
+        let (h, w) = (self.height, self.width);
+        let mut valley = self.initial.clone();
+        for row in &mut valley[1..=h] {
+            row[1..=w].fill(EMPTY);
+        }
+        let moves = [
+            (NORTH, (h - t % h, 0)),
+            (SOUTH, (t % h, 0)),
+            (EAST, (0, t % w)),
+            (WEST, (0, w - t % w)),
+        ];
+        for r in 0..h {
+            for c in 0..w {
+                for (flag, (dr, dc)) in moves {
+                    if self.initial[r + 1][c + 1] & flag != 0 {
+                        valley[1 + (r + dr) % h][1 + (c + dc) % w] |= flag;
+                    }
+                }
             }
         }
+        valley
+    }
 
-        // Check if we're done
-        if let Some(s) = next.iter().find(|s| (s.row, s.col) == the_end) {
-            return (s.depth, n_valley);
+    /// The quickest walk through each of `waypoints` in turn, as where the
+    /// expedition is at each minute from the start
+    fn route(&self, waypoints: &[Coord]) -> AResult<Vec<Coord>> {
+        let Some(&first) = waypoints.first() else {
+            return Ok(vec![]);
+        };
+        let mut route = vec![first];
+        for &goal in &waypoints[1..] {
+            let from = route[route.len() - 1];
+            let (path, _) = astar(
+                (from, (route.len() - 1) % self.period),
+                |&((r, c), t)| {
+                    let t = (t + 1) % self.period;
+                    [(0, 0), (-1, 0), (1, 0), (0, -1), (0, 1)]
+                        .into_iter()
+                        .filter_map(move |(dr, dc)| {
+                            let next = (r.checked_add_signed(dr)?, c.checked_add_signed(dc)?);
+                            self.is_clear(next, t).then_some(((next, t), 1))
+                        })
+                },
+                |&((r, c), _)| r.abs_diff(goal.0) + c.abs_diff(goal.1),
+                |&(at, _)| at == goal,
+            )
+            .ok_or_else(|| anyhow!("there is no way from {from:?} to {goal:?}"))?;
+            route.extend(path.into_iter().skip(1).map(|(at, _)| at));
         }
-
-        // Get ready for the next loop
-        queue.extend(next);
-        p_valley = n_valley;
+        Ok(route)
     }
 
-    panic!("Route not found within 1000 iterations");
+    /// Every minute of `route`, drawn with the blizzards
+    fn replay(&self, route: &[Coord]) -> String {
+        route
+            .iter()
+            .enumerate()
+            .map(|(t, &at)| format!("Minute {t}:\n{}", display(&self.valley_at(t), Some(at))))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 }
 
-fn part_a(lines: &[String]) -> usize {
-    let initial = parse(lines);
-    let the_start = (0, 1);
-    let the_end: (usize, usize) = (initial.len() - 1, initial[0].len() - 2);
-    solve(initial, the_start, the_end).0
+fn part_a(lines: &[String]) -> AResult<usize> {
+    let basin = Basin::new(parse(lines)?)?;
+    Ok(basin.route(&[basin.entrance, basin.exit])?.len() - 1)
 }
 
-fn part_b(lines: &[String]) -> usize {
-    let initial = parse(lines);
-    let the_start = (0, 1);
-    let the_end: (usize, usize) = (initial.len() - 1, initial[0].len() - 2);
-
-    let (l1, initial) = solve(initial, the_start, the_end); // Fwd
-    let (l2, initial) = solve(initial, the_end, the_start); // Bkd
-    l1 + l2 + solve(initial, the_start, the_end).0 // Fwd
+fn part_b(lines: &[String]) -> AResult<usize> {
+    // Back for the snacks
+    let basin = Basin::new(parse(lines)?)?;
+    let (there, back) = (basin.exit, basin.entrance);
+    Ok(basin.route(&[basin.entrance, there, back, there])?.len() - 1)
 }
 
 fn main() -> AResult<()> {
@@ -214,16 +265,35 @@ fn main() -> AResult<()> {
         .find(name)
         .expect("binary name should contain a number")
         .as_str();
-    println!("Running code for Day {ex}.");
+    // `day_24 --replay` draws each minute of the quickest way across
+    let replay = std::env::args().any(|arg| arg == "--replay");
+    if !replay {
+        println!("Running code for Day {ex}.");
+    }
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
+
+    if replay {
+        let basin = Basin::new(parse(lines.as_slice()).map_err(in_file(&path))?)?;
+        print!(
+            "{}",
+            basin.replay(&basin.route(&[basin.entrance, basin.exit])?)
+        );
+        return Ok(());
+    }
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -243,14 +313,33 @@ mod tests {
     ######.#";
 
     #[test]
-    fn test_next() {
+    fn test_parse_errors() {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        let v0 = &parse(lines.as_slice());
-        let v1 = &next_step(v0);
-        let v2 = &next_step(v1);
 
-        _display(v0);
-        _display(v1);
+        let mut broken = lines.clone();
+        broken[2] = "#.<.x<<#".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 3, column 5: expected a wall, an empty tile or a blizzard, found 'x'"
+        );
+
+        let mut broken = lines;
+        broken[5] = "########".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 6, column 7: expected the way through the wall, found '#'"
+        );
+    }
+
+    #[test]
+    fn test_next() -> AResult<()> {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        let basin = Basin::new(parse(lines.as_slice())?)?;
+        let (v0, v1, v2) = (
+            &basin.valley_at(0),
+            &basin.valley_at(1),
+            &basin.valley_at(2),
+        );
 
         for v in [v0, v1, v2] {
             assert_eq!(v[0][0], WALL);
@@ -279,17 +368,60 @@ mod tests {
             v2[3],
             [WALL, EMPTY, EAST, EAST | WEST, EMPTY, NORTH, EAST, WALL]
         );
+
+        // The precomputed blizzards agree with the drawn ones, and repeat
+        assert_eq!(basin.period, 12);
+        assert_eq!(basin.valley_at(12), *v0);
+        for t in 0..basin.period {
+            let valley = basin.valley_at(t);
+            for (r, row) in valley.iter().enumerate() {
+                for (c, &v) in row.iter().enumerate() {
+                    assert_eq!(basin.is_clear((r, c), t), v == EMPTY, "{r},{c} at {t}");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_replay() -> AResult<()> {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        let basin = Basin::new(parse(lines.as_slice())?)?;
+        let route = basin.route(&[basin.entrance, basin.exit])?;
+        assert_eq!(route.len(), 19);
+
+        let replay = basin.replay(&route);
+        assert!(replay.starts_with("Minute 0:\n#E######\n#>>.<^<#\n"));
+        assert!(replay
+            .ends_with("Minute 18:\n#.######\n#>2.<.<#\n#.2v^2<#\n#>..>2>#\n#<....>#\n######E#\n"));
+
+        // Stopping off on the way
+        let route = basin.route(&[basin.entrance, (2, 3), basin.exit])?;
+        assert!(route.contains(&(2, 3)));
+        assert!(route.len() >= 19);
+
+        // A wall isn't somewhere to stop
+        assert_eq!(
+            basin
+                .route(&[basin.entrance, (0, 3)])
+                .unwrap_err()
+                .to_string(),
+            "there is no way from (0, 1) to (0, 3)"
+        );
+        Ok(())
     }
 
     #[test]
-    fn test_a() {
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 18);
+        assert_eq!(part_a(lines.as_slice())?, 18);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(lines.as_slice()), 54);
+        assert_eq!(part_b(lines.as_slice())?, 54);
+        Ok(())
     }
 }
@@ -1,77 +1,257 @@
-use core::panic;
+use advent_common::parsing::{in_file, map_lines, parse_all, read_lines, ParseError};
+use anyhow::anyhow;
 use humantime::format_duration;
+use nom::{character::complete::one_of, combinator::map, error::context, multi::fold_many1};
 use regex::Regex;
 use std::{
-    fs::File,
-    io::{BufRead, BufReader},
+    cmp::Ordering,
+    collections::VecDeque,
+    fmt::{self, Debug, Display},
+    hash::Hash,
+    iter::Sum,
+    marker::PhantomData,
+    ops::{Add, Mul, Neg, Sub},
+    str::FromStr,
     time::Instant,
 };
 
 type AResult<T> = anyhow::Result<T>;
 
-fn from_snafu(s: &str) -> isize {
-    let mut digits = vec![];
-    for c in s.chars() {
-        let d = match c {
-            '2' => 2,
-            '1' => 1,
-            '0' => 0,
-            '-' => -1,
-            '=' => -2,
-            _ => panic!(),
-        };
-        digits.push(d);
+/// The digit characters of a balanced number system
+trait Digits: Clone + Copy + Debug + Default + PartialEq + Eq + Hash {
+    /// From the most negative digit to the most positive - there must be an odd number
+    /// (at least three) of distinct ASCII characters, and the middle one is zero
+    const DIGITS: &'static str;
+    /// What a number is called in parse errors
+    const NAME: &'static str;
+    /// The digits as listed in parse errors
+    const EXPECTED: &'static str;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+struct Quinary;
+
+impl Digits for Quinary {
+    const DIGITS: &'static str = "=-012";
+    const NAME: &'static str = "a SNAFU number";
+    const EXPECTED: &'static str = "2, 1, 0, - or =";
+}
+
+/// An arbitrarily large integer in a balanced base, where every digit lies in
+/// `-(base / 2)..=base / 2` so negative numbers need no sign
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+struct Balanced<D: Digits> {
+    // Least significant first, with no trailing zeros (so zero has no digits at all)
+    digits: Vec<i8>,
+    system: PhantomData<D>,
+}
+
+type Snafu = Balanced<Quinary>;
+
+/// Whether `digits` can be the digits of a balanced number system: an odd number of
+/// distinct ASCII characters, at least three so the base can carry, and few enough
+/// that each fits in an `i8`
+const fn valid_digits(digits: &str) -> bool {
+    let bytes = digits.as_bytes();
+    let len = bytes.len();
+    if !digits.is_ascii() || len.is_multiple_of(2) || len < 3 || len >= 256 {
+        return false;
+    }
+    let mut i = 0;
+    while i < len {
+        let mut j = i + 1;
+        while j < len {
+            if bytes[i] == bytes[j] {
+                return false;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    true
+}
+
+impl<D: Digits> Balanced<D> {
+    // Checked when the number system is first used, so a bad digit set won't compile
+    #[allow(clippy::cast_possible_wrap)]
+    const BASE: i64 = {
+        assert!(
+            valid_digits(D::DIGITS),
+            "balanced digits must be an odd number (at least three) of distinct ASCII characters that fit in an i8"
+        );
+        D::DIGITS.len() as i64
+    };
+    const HALF: i64 = Self::BASE / 2;
+
+    /// Build a number from (least significant first) column totals of any size,
+    /// carrying whatever is out of the balanced range into the next column
+    #[allow(clippy::cast_possible_truncation)]
+    fn from_columns(columns: impl IntoIterator<Item = i64>) -> Self {
+        let mut digits = vec![];
+        let mut carry = 0;
+        let mut columns = columns.into_iter();
+        loop {
+            let total = match columns.next() {
+                Some(c) => c + carry,
+                None if carry != 0 => carry,
+                None => break,
+            };
+            carry = (total + Self::HALF).div_euclid(Self::BASE);
+            digits.push((total - carry * Self::BASE) as i8);
+        }
+
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        Self { digits, system: PhantomData }
+    }
+
+    fn signum(&self) -> i8 {
+        self.digits.last().map_or(0, |d| d.signum())
     }
+}
 
-    let mut acc = 0;
-    for d in digits {
-        acc *= 5;
-        acc += d;
+impl<D: Digits> From<i64> for Balanced<D> {
+    fn from(mut n: i64) -> Self {
+        // Plain base-b digits all carry the sign of n, from_columns then balances them
+        let mut columns = vec![];
+        while n != 0 {
+            columns.push(n % Self::BASE);
+            n /= Self::BASE;
+        }
+        Self::from_columns(columns)
     }
-    acc
 }
 
-#[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
-fn to_snafu(mut n: isize) -> String {
-    let mut digits = vec![];
+impl<D: Digits> TryFrom<&Balanced<D>> for i64 {
+    type Error = anyhow::Error;
 
-    // Regular b5 digits
-    while n > 0 {
-        digits.insert(0, n % 5);
-        n /= 5;
+    fn try_from(value: &Balanced<D>) -> Result<Self, Self::Error> {
+        value
+            .digits
+            .iter()
+            .rev()
+            .try_fold(0i64, |acc, &d| {
+                acc.checked_mul(Balanced::<D>::BASE)?.checked_add(d.into())
+            })
+            .ok_or_else(|| anyhow!("{value} does not fit in an i64"))
     }
+}
+
+impl<D: Digits> FromStr for Balanced<D> {
+    type Err = ParseError;
 
-    // Add some extras in case of overflow later
-    (0..5).for_each(|_| digits.insert(0, 0));
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        #[allow(clippy::cast_possible_wrap)]
+        let digit = map(context(D::EXPECTED, one_of(D::DIGITS)), |c| {
+            D::DIGITS.find(c).unwrap() as i64 - Self::HALF
+        });
+        // Most significant digit first, so push each one in front of the rest
+        let columns = parse_all(
+            s,
+            context(
+                D::NAME,
+                fold_many1(digit, VecDeque::new, |mut acc, d| {
+                    acc.push_front(d);
+                    acc
+                }),
+            ),
+        )?;
+        Ok(Self::from_columns(columns))
+    }
+}
+
+impl<D: Digits> Display for Balanced<D> {
+    #[allow(clippy::cast_sign_loss)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.digits.is_empty() {
+            return write!(
+                f,
+                "{}",
+                &D::DIGITS[Self::HALF as usize..=Self::HALF as usize]
+            );
+        }
+        for &d in self.digits.iter().rev() {
+            let idx = (i64::from(d) + Self::HALF) as usize;
+            write!(f, "{}", &D::DIGITS[idx..=idx])?;
+        }
+        Ok(())
+    }
+}
 
-    // Apply the "smearing"
-    let nd = digits.len();
-    for i in (0..nd).rev() {
-        if digits[i] > 2 {
-            digits[i] -= 5;
-            digits[i - 1] += 1;
+impl<D: Digits> Ord for Balanced<D> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // A longer number outweighs everything a shorter one can reach, so it's bigger
+        // exactly when its leading digit is positive. Equal lengths compare digit by digit.
+        match self.digits.len().cmp(&other.digits.len()) {
+            Ordering::Equal => self.digits.iter().rev().cmp(other.digits.iter().rev()),
+            Ordering::Greater => self.signum().cmp(&0),
+            Ordering::Less => 0.cmp(&other.signum()),
         }
     }
+}
 
-    // Render digits into the string
-    digits
-        .iter()
-        .map(|c| match c {
-            -2 => '=',
-            -1 => '-',
-            0 => '0',
-            1 => '1',
-            2 => '2',
-            _ => panic!("unknown c {c}"),
-        })
-        .collect::<String>()
-        .trim_start_matches('0')
-        .to_string()
+impl<D: Digits> PartialOrd for Balanced<D> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
-fn part_a(lines: &[String]) -> String {
-    let tot = lines.iter().map(|s| from_snafu(s)).sum();
-    to_snafu(tot)
+impl<D: Digits> Neg for Balanced<D> {
+    type Output = Self;
+
+    fn neg(mut self) -> Self {
+        // Every digit range is symmetric, so negating is digit by digit with no carries
+        self.digits.iter_mut().for_each(|d| *d = -*d);
+        self
+    }
+}
+
+impl<D: Digits> Add for Balanced<D> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        let len = self.digits.len().max(rhs.digits.len());
+        let column = |n: &Self, i: usize| n.digits.get(i).copied().map_or(0, i64::from);
+        Self::from_columns((0..len).map(|i| column(&self, i) + column(&rhs, i)))
+    }
+}
+
+impl<D: Digits> Sub for Balanced<D> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self + -rhs
+    }
+}
+
+impl<D: Digits> Mul for Balanced<D> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        // Long multiplication - collect every digit product in its column then carry
+        if self.digits.is_empty() || rhs.digits.is_empty() {
+            return Self::default();
+        }
+        let mut columns = vec![0; self.digits.len() + rhs.digits.len() - 1];
+        for (i, &a) in self.digits.iter().enumerate() {
+            for (j, &b) in rhs.digits.iter().enumerate() {
+                columns[i + j] += i64::from(a) * i64::from(b);
+            }
+        }
+        Self::from_columns(columns)
+    }
+}
+
+impl<D: Digits> Sum for Balanced<D> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+fn part_a(lines: &[String]) -> AResult<String> {
+    let total: Snafu = map_lines(lines, str::parse)?.into_iter().sum();
+    Ok(total.to_string())
 }
 
 fn main() -> AResult<()> {
@@ -85,12 +265,15 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -102,6 +285,17 @@ fn main() -> AResult<()> {
 mod tests {
     use super::*;
 
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+    struct Ternary;
+
+    impl Digits for Ternary {
+        const DIGITS: &'static str = "-0+";
+        const NAME: &'static str = "a balanced ternary number";
+        const EXPECTED: &'static str = "+, 0 or -";
+    }
+
+    type BalancedTernary = Balanced<Ternary>;
+
     const TEST_INPUT: &str = "1=-0-2
         12111
         2=0=
@@ -116,7 +310,7 @@ mod tests {
         1=
         122";
 
-    const examples: [(isize, &'static str); 15] = [
+    const EXAMPLES: [(i64, &str); 15] = [
         (1, "1"),
         (2, "2"),
         (3, "1="),
@@ -135,22 +329,102 @@ mod tests {
     ];
 
     #[test]
-    fn test_from_snafu() {
-        for (dec, snaf) in examples {
-            assert_eq!(dec, from_snafu(snaf), "{snaf}");
+    fn test_parse_errors() {
+        let lines = ["1=-0-2".to_string(), "12131".to_string()];
+        assert_eq!(
+            part_a(&lines).unwrap_err().to_string(),
+            "line 2, column 4: expected end of line, found \"31\""
+        );
+
+        let lines = ["1=-0-2".to_string(), String::new()];
+        assert_eq!(
+            part_a(&lines).unwrap_err().to_string(),
+            "line 2, column 1: expected a SNAFU number, found end of line"
+        );
+    }
+
+    #[test]
+    fn test_valid_digits() {
+        assert!(valid_digits(Quinary::DIGITS));
+        assert!(valid_digits(Ternary::DIGITS));
+
+        // Too few to carry, which would leave conversions looping forever
+        assert!(!valid_digits("0"));
+        assert!(!valid_digits(""));
+        // Repeats, which would make the same character stand for two digits
+        assert!(!valid_digits("=-0-2"));
+        assert!(!valid_digits("000"));
+        // An even number, non-ASCII characters, or too many for an i8
+        assert!(!valid_digits("-01+"));
+        assert!(!valid_digits("\u{2212}0+"));
+        let all: String = (0..=u8::MAX)
+            .map(char::from)
+            .filter(char::is_ascii)
+            .collect();
+        assert!(valid_digits(&all[..127]));
+        assert!(!valid_digits(&all));
+    }
+
+    #[test]
+    fn test_from_snafu() -> AResult<()> {
+        for (dec, snaf) in EXAMPLES {
+            assert_eq!(dec, i64::try_from(&snaf.parse::<Snafu>()?)?, "{snaf}");
         }
+        Ok(())
     }
 
     #[test]
     fn test_to_snafu() {
-        for (dec, snaf) in examples {
-            assert_eq!(to_snafu(dec), snaf, "{dec}");
+        for (dec, snaf) in EXAMPLES {
+            assert_eq!(Snafu::from(dec).to_string(), snaf, "{dec}");
+            assert_eq!(Snafu::from(-dec), -Snafu::from(dec));
+        }
+        assert_eq!(Snafu::from(0).to_string(), "0");
+    }
+
+    #[test]
+    fn test_arithmetic() -> AResult<()> {
+        for a in -40..40 {
+            for b in -40..40 {
+                let (sa, sb) = (Snafu::from(a), Snafu::from(b));
+                assert_eq!(sa.clone() + sb.clone(), Snafu::from(a + b), "{a} + {b}");
+                assert_eq!(sa.clone() - sb.clone(), Snafu::from(a - b), "{a} - {b}");
+                assert_eq!(sa.clone() * sb.clone(), Snafu::from(a * b), "{a} * {b}");
+                assert_eq!(sa.cmp(&sb), a.cmp(&b), "{a} cmp {b}");
+
+                let (ta, tb) = (BalancedTernary::from(a), BalancedTernary::from(b));
+                assert_eq!(i64::try_from(&(ta.clone() * tb.clone()))?, a * b);
+                assert_eq!(i64::try_from(&(ta.clone() - tb.clone()))?, a - b);
+                assert_eq!(ta.cmp(&tb), a.cmp(&b), "{a} cmp {b}");
+            }
         }
+        assert_eq!(BalancedTernary::from(8).to_string(), "+0-");
+        assert_eq!(BalancedTernary::from(-8).to_string(), "-0+");
+        assert_eq!("+-0".parse::<BalancedTernary>()?, BalancedTernary::from(6));
+
+        // Well beyond i64, but still exact
+        let big: Snafu = "2".repeat(40).parse()?;
+        assert!(i64::try_from(&big).is_err());
+        assert!(big > Snafu::from(i64::MAX));
+        assert!(-big.clone() < Snafu::from(i64::MIN));
+        assert_eq!((big.clone() + big.clone()) - big.clone(), big);
+        assert_eq!(
+            big.clone() * big.clone() - big.clone() * big.clone(),
+            Snafu::default()
+        );
+        assert_eq!(
+            [big.clone(), big.clone(), big.clone()]
+                .into_iter()
+                .sum::<Snafu>(),
+            big.clone() * Snafu::from(3)
+        );
+        Ok(())
     }
 
     #[test]
-    fn test_a() {
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), "2=-1=0");
+        assert_eq!(part_a(lines.as_slice())?, "2=-1=0");
+        Ok(())
     }
 }
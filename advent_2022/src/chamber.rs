@@ -0,0 +1,373 @@
+//! Rocks falling into a narrow chamber while jets of gas push them side to side, as in
+//! the pyroclastic flow of 2022 day 17
+//!
+//! Each row of the chamber is a `u8` with bit `i` set where column `i` (from the left
+//! wall) holds rock, so chambers can be up to 8 wide. Rocks appear two columns in from
+//! the left wall with three empty rows below them, and are pushed by a jet and then
+//! fall one row, in turn, until they can't fall any further.
+
+use anyhow::anyhow;
+use std::collections::HashMap;
+
+/// How far down the skyline looks for the top of each column
+const SKYLINE_DEPTH: usize = 64;
+
+/// A rock's shape as rows of bits, bottom row first, with bit `i` set where it fills
+/// column `i` counting from its left edge
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Rock {
+    rows: Vec<u8>,
+    width: usize,
+}
+
+impl Rock {
+    /// A rock drawn as in the puzzle, top row first, with `#` for rock and `.` for
+    /// gaps.
+    ///
+    /// # Errors
+    ///
+    /// If the drawing has no rock in it, is wider than 8 columns or has anything
+    /// other than `#`, `.` and line breaks.
+    pub fn from_picture(picture: &str) -> anyhow::Result<Rock> {
+        let mut rows = Vec::new();
+        let mut width = 0;
+        for line in picture.lines().map(str::trim).rev() {
+            let mut row = 0;
+            for (i, c) in line.chars().enumerate() {
+                match c {
+                    '#' if i < 8 => row |= 1 << i,
+                    '#' | '.' if i >= 8 => return Err(anyhow!("rocks can be at most 8 wide")),
+                    '.' => {}
+                    _ => return Err(anyhow!("expected '#' or '.' in a rock, found {c:?}")),
+                }
+            }
+            width = width.max(line.len());
+            rows.push(row);
+        }
+        if rows.iter().all(|&r| r == 0) {
+            return Err(anyhow!("a rock needs at least one '#'"));
+        }
+        Ok(Rock { rows, width })
+    }
+
+    /// The five rocks from the puzzle, in the order they fall
+    #[must_use]
+    pub fn puzzle_rocks() -> Vec<Rock> {
+        [
+            "####",
+            ".#.\n###\n.#.",
+            "..#\n..#\n###",
+            "#\n#\n#\n#",
+            "##\n##",
+        ]
+        .into_iter()
+        .map(|p| Rock::from_picture(p).expect("the puzzle's rocks should be valid"))
+        .collect()
+    }
+
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    #[must_use]
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Jet {
+    Left,
+    Right,
+}
+
+impl TryFrom<char> for Jet {
+    type Error = char;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        match c {
+            '<' => Ok(Jet::Left),
+            '>' => Ok(Jet::Right),
+            _ => Err(c),
+        }
+    }
+}
+
+/// The chamber and the rocks that have settled in it so far
+#[derive(Debug, Clone)]
+pub struct Chamber {
+    width: usize,
+    rocks: Vec<Rock>,
+    jets: Vec<Jet>,
+    // From the floor up, without any empty rows on top
+    rows: Vec<u8>,
+    dropped: u64,
+    // The next jet to push
+    jet: usize,
+}
+
+impl Chamber {
+    /// An empty chamber that `rocks` will fall into in turn, pushed by `jets` in turn.
+    ///
+    /// # Errors
+    ///
+    /// If the chamber isn't 1 to 8 wide, there are no rocks or no jets, or a rock
+    /// doesn't fit where it appears.
+    pub fn new(width: usize, rocks: Vec<Rock>, jets: Vec<Jet>) -> anyhow::Result<Chamber> {
+        if !(1..=8).contains(&width) {
+            return Err(anyhow!("a chamber has to be 1 to 8 wide, not {width}"));
+        }
+        if rocks.is_empty() || jets.is_empty() {
+            return Err(anyhow!("a chamber needs some rocks and some jets"));
+        }
+        if let Some(rock) = rocks.iter().find(|r| r.width + 2 > width) {
+            return Err(anyhow!(
+                "a rock {} wide won't fit two in from the wall of a chamber {width} wide",
+                rock.width
+            ));
+        }
+        Ok(Chamber {
+            width,
+            rocks,
+            jets,
+            rows: Vec::new(),
+            dropped: 0,
+            jet: 0,
+        })
+    }
+
+    /// How tall the tower of rocks is
+    #[must_use]
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// How many rocks have come to rest
+    #[must_use]
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    fn fits(&self, rock: &Rock, x: usize, y: usize) -> bool {
+        rock.rows
+            .iter()
+            .enumerate()
+            .all(|(i, &row)| self.rows.get(y + i).is_none_or(|&r| r & row << x == 0))
+    }
+
+    /// Let the next rock fall until it comes to rest
+    pub fn drop_rock(&mut self) {
+        let idx = usize::try_from(self.dropped % self.rocks.len() as u64).unwrap_or_default();
+        let rock = &self.rocks[idx];
+        let (mut x, mut y) = (2_usize, self.rows.len() + 3);
+        loop {
+            let pushed = match self.jets[self.jet] {
+                Jet::Left => x.checked_sub(1),
+                Jet::Right => Some(x + 1).filter(|&x| x + rock.width <= self.width),
+            };
+            self.jet = (self.jet + 1) % self.jets.len();
+            if let Some(nx) = pushed.filter(|&nx| self.fits(rock, nx, y)) {
+                x = nx;
+            }
+            if y == 0 || !self.fits(rock, x, y - 1) {
+                break;
+            }
+            y -= 1;
+        }
+
+        for (i, &row) in rock.rows.iter().enumerate() {
+            if y + i == self.rows.len() {
+                self.rows.push(0);
+            }
+            self.rows[y + i] |= row << x;
+        }
+        // Rocks with empty rows on top shouldn't count towards the height
+        while self.rows.last() == Some(&0) {
+            self.rows.pop();
+        }
+        self.dropped += 1;
+    }
+
+    /// How far down from the top of the tower each column's highest rock is, treating
+    /// anything deeper than `SKYLINE_DEPTH` as out of reach so a column that never
+    /// fills can't keep the state from repeating
+    fn skyline(&self) -> Vec<usize> {
+        (0..self.width)
+            .map(|c| {
+                self.rows
+                    .iter()
+                    .rev()
+                    .take(SKYLINE_DEPTH)
+                    .position(|r| r & 1 << c != 0)
+                    .unwrap_or(SKYLINE_DEPTH)
+            })
+            .collect()
+    }
+
+    /// How tall the tower will be once `rocks` more have fallen. Once the next rock,
+    /// the next jet and the skyline repeat, so does the growth of the tower, so this
+    /// only simulates until that happens.
+    #[must_use]
+    pub fn height_after(&self, rocks: u64) -> u64 {
+        let mut chamber = self.clone();
+        let target = self.dropped + rocks;
+        let rock_count = self.rocks.len() as u64;
+
+        // The first time each state was seen, as an index into `heights`
+        let mut seen: HashMap<(u64, usize, Vec<usize>), usize> = HashMap::new();
+        let mut heights = Vec::new();
+        while chamber.dropped < target {
+            let key = (chamber.dropped % rock_count, chamber.jet, chamber.skyline());
+            let height = chamber.height() as u64;
+            if let Some(&start) = seen.get(&key) {
+                let period = (heights.len() - start) as u64;
+                let left = target - chamber.dropped;
+                let partial = start + usize::try_from(left % period).unwrap_or_default();
+                return height
+                    + left / period * (height - heights[start])
+                    + (heights[partial] - heights[start]);
+            }
+            seen.insert(key, heights.len());
+            heights.push(height);
+            chamber.drop_rock();
+        }
+        chamber.height() as u64
+    }
+
+    /// The top `rows` rows of the tower drawn as in the puzzle, with the floor if it's
+    /// in view
+    #[must_use]
+    pub fn render(&self, rows: usize) -> String {
+        let mut lines: Vec<String> = self
+            .rows
+            .iter()
+            .rev()
+            .take(rows)
+            .map(|&r| {
+                let cells: String = (0..self.width)
+                    .map(|c| if r & 1 << c == 0 { '.' } else { '#' })
+                    .collect();
+                format!("|{cells}|")
+            })
+            .collect();
+        if rows > self.rows.len() {
+            lines.push(format!("+{}+", "-".repeat(self.width)));
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JETS: &str = ">>><<><>><<<>><>>><<<>>><<<><<<>><>><<>>";
+
+    fn example() -> Chamber {
+        let jets = JETS.chars().map(|c| Jet::try_from(c).unwrap()).collect();
+        Chamber::new(7, Rock::puzzle_rocks(), jets).unwrap()
+    }
+
+    #[test]
+    fn test_rocks() {
+        let rocks = Rock::puzzle_rocks();
+        assert_eq!(rocks[1].rows, [0b010, 0b111, 0b010]);
+        assert_eq!(rocks[2].rows, [0b111, 0b100, 0b100]);
+        assert_eq!((rocks[3].width(), rocks[3].height()), (1, 4));
+
+        assert_eq!(
+            Rock::from_picture("#.#\n.x.").unwrap_err().to_string(),
+            "expected '#' or '.' in a rock, found 'x'"
+        );
+        assert_eq!(
+            Rock::from_picture("...").unwrap_err().to_string(),
+            "a rock needs at least one '#'"
+        );
+        assert_eq!(
+            Rock::from_picture("#########").unwrap_err().to_string(),
+            "rocks can be at most 8 wide"
+        );
+        assert_eq!(
+            Chamber::new(5, Rock::puzzle_rocks(), vec![Jet::Left])
+                .unwrap_err()
+                .to_string(),
+            "a rock 4 wide won't fit two in from the wall of a chamber 5 wide"
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let mut chamber = example();
+        chamber.drop_rock();
+        assert_eq!(chamber.render(5), "|..####.|\n+-------+");
+        chamber.drop_rock();
+        assert_eq!(
+            chamber.render(5),
+            "|...#...|\n|..###..|\n|...#...|\n|..####.|\n+-------+"
+        );
+        for _ in 2..10 {
+            chamber.drop_rock();
+        }
+        assert_eq!(
+            chamber.render(17),
+            "|....#..|
+|....#..|
+|....##.|
+|##..##.|
+|######.|
+|.###...|
+|..#....|
+|.####..|
+|....##.|
+|....##.|
+|....#..|
+|..#.#..|
+|..#.#..|
+|#####..|
+|..###..|
+|...#...|
+|..####.|"
+        );
+        assert_eq!(chamber.render(2), "|....#..|\n|....#..|");
+    }
+
+    #[test]
+    fn test_height_after() {
+        let chamber = example();
+        assert_eq!(chamber.height_after(2022), 3068);
+        assert_eq!(chamber.height_after(1_000_000_000_000), 1_514_285_714_288);
+
+        // Skipping ahead agrees with dropping every rock, from any starting point
+        let mut brute = example();
+        for n in 0..400 {
+            assert_eq!(chamber.height_after(n), brute.height() as u64);
+            assert_eq!(brute.height_after(n % 37), {
+                let mut ahead = brute.clone();
+                (0..n % 37).for_each(|_| ahead.drop_rock());
+                ahead.height() as u64
+            });
+            brute.drop_rock();
+        }
+    }
+
+    #[test]
+    fn test_custom_chamber() -> anyhow::Result<()> {
+        // Single blocks blown right pile up against the right hand wall
+        let block = Rock::from_picture("#")?;
+        let mut chamber = Chamber::new(4, vec![block], vec![Jet::Right])?;
+        for _ in 0..3 {
+            chamber.drop_rock();
+        }
+        assert_eq!(chamber.render(4), "|...#|\n|...#|\n|...#|\n+----+");
+        assert_eq!(chamber.height_after(1_000_000), 1_000_003);
+
+        // Alternating pushes leave them where they started
+        let block = Rock::from_picture("#")?;
+        let mut chamber = Chamber::new(4, vec![block], vec![Jet::Right, Jet::Left])?;
+        chamber.drop_rock();
+        chamber.drop_rock();
+        assert_eq!(chamber.render(3), "|..#.|\n|..#.|\n+----+");
+        Ok(())
+    }
+}
@@ -0,0 +1,5 @@
+//! Types shared beyond a single 2022 solution
+
+pub mod chamber;
+pub mod packet;
+pub mod voxels;
@@ -0,0 +1,288 @@
+//! The distress signal packets from 2022 day 13 - nested lists of non-negative integers
+//!
+//! Packets are written like JSON arrays (`[1,[2,[]],3]`), so as well as the puzzle's own
+//! syntax they convert to and from [`serde_json::Value`].
+
+use advent_common::parsing::{parse_all, uint, PResult, ParseError};
+use anyhow::anyhow;
+use nom::{
+    branch::alt,
+    character::complete::char,
+    combinator::{cut, map},
+    error::context,
+    multi::separated_list0,
+    sequence::delimited,
+};
+use serde_json::Value;
+use std::{cmp::Ordering, fmt, io::BufRead, iter::zip, str::FromStr};
+
+/// A packet, or any of the elements inside one
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Packet {
+    List(Vec<Packet>),
+    Num(u64),
+}
+
+use Packet::{List, Num};
+
+impl Packet {
+    /// The puzzle's comparison: integers by value, lists element by element and then
+    /// by length, and an integer against a list as if it were a list of just itself.
+    ///
+    /// This treats packets such as `1`, `[1]` and `[[1]]` as equal - `Ord` breaks those
+    /// ties so that it agrees with `Eq`.
+    #[must_use]
+    pub fn compare(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Num(l), Num(r)) => l.cmp(r),
+            (List(l), List(r)) => zip(l, r)
+                .map(|(l, r)| l.compare(r))
+                .find(|&o| o != Ordering::Equal)
+                .unwrap_or_else(|| l.len().cmp(&r.len())),
+            (List(_), Num(r)) => self.compare(&List(vec![Num(*r)])),
+            (Num(l), List(_)) => List(vec![Num(*l)]).compare(other),
+        }
+    }
+
+    // Order packets the puzzle can't tell apart by their structure, integers first
+    fn structure(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Num(l), Num(r)) => l.cmp(r),
+            (List(l), List(r)) => zip(l, r)
+                .map(|(l, r)| l.structure(r))
+                .find(|&o| o != Ordering::Equal)
+                .unwrap_or_else(|| l.len().cmp(&r.len())),
+            (Num(_), List(_)) => Ordering::Less,
+            (List(_), Num(_)) => Ordering::Greater,
+        }
+    }
+}
+
+impl Ord for Packet {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.compare(other).then_with(|| self.structure(other))
+    }
+}
+
+impl PartialOrd for Packet {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Packet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Num(n) => write!(f, "{n}"),
+            List(items) => {
+                write!(f, "[")?;
+                for (idx, item) in items.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+fn number(input: &str) -> PResult<'_, Packet> {
+    map(uint, Num)(input)
+}
+
+fn list(input: &str) -> PResult<'_, Packet> {
+    map(
+        delimited(
+            char('['),
+            separated_list0(char(','), packet),
+            cut(context("',' or ']'", char(']'))),
+        ),
+        List,
+    )(input)
+}
+
+/// Parse a packet (or any element of one) written in the puzzle's syntax
+///
+/// # Errors
+///
+/// Fails if the input doesn't start with a number or a list
+pub fn packet(input: &str) -> PResult<'_, Packet> {
+    context("a number or a list", alt((number, list)))(input)
+}
+
+impl FromStr for Packet {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(s, packet)
+    }
+}
+
+impl From<Packet> for Value {
+    fn from(packet: Packet) -> Self {
+        match packet {
+            Num(n) => Value::from(n),
+            List(items) => Value::Array(items.into_iter().map(Value::from).collect()),
+        }
+    }
+}
+
+impl TryFrom<Value> for Packet {
+    type Error = anyhow::Error;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Number(ref n) => n
+                .as_u64()
+                .map(Num)
+                .ok_or_else(|| anyhow!("expected a non-negative integer or a list, found {value}")),
+            Value::Array(items) => Ok(List(
+                items
+                    .into_iter()
+                    .map(Packet::try_from)
+                    .collect::<Result<_, _>>()?,
+            )),
+            _ => Err(anyhow!(
+                "expected a non-negative integer or a list, found {value}"
+            )),
+        }
+    }
+}
+
+/// Read packets one line at a time, so the whole file is never held in memory.
+/// Blank lines (such as those between pairs of packets) are skipped.
+///
+/// # Errors
+///
+/// Each item is an error if its line couldn't be read or isn't a packet
+pub fn read_packets<R: BufRead>(reader: R) -> impl Iterator<Item = anyhow::Result<Packet>> {
+    reader
+        .lines()
+        .enumerate()
+        .filter_map(|(idx, line)| match line {
+            Err(e) => Some(Err(e.into())),
+            Ok(line) if line.trim().is_empty() => None,
+            Ok(line) => Some(
+                line.trim_end()
+                    .parse()
+                    .map_err(|e: ParseError| e.offset_by(idx).into()),
+            ),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn any_packet() -> impl Strategy<Value = Packet> {
+        // Small numbers so that the comparisons often have to look deeper
+        (0u64..4).prop_map(Num).prop_recursive(4, 32, 4, |inner| {
+            prop::collection::vec(inner, 0..4).prop_map(List)
+        })
+    }
+
+    fn p(text: &str) -> Packet {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_display() {
+        for text in [
+            "7",
+            "[]",
+            "[[]]",
+            "[1,[2,[3,[4,[5,6,7]]]],8,9]",
+            "[[],[10]]",
+        ] {
+            assert_eq!(p(text).to_string(), text);
+        }
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(p("[1,1,3,1,1]").compare(&p("[1,1,5,1,1]")), Ordering::Less);
+        assert_eq!(p("[[1],[2,3,4]]").compare(&p("[[1],4]")), Ordering::Less);
+        assert_eq!(p("[9]").compare(&p("[[8,7,6]]")), Ordering::Greater);
+        assert_eq!(p("[[[]]]").compare(&p("[[]]")), Ordering::Greater);
+
+        // The puzzle can't tell these apart, but they aren't equal
+        assert_eq!(p("[1]").compare(&p("[[1]]")), Ordering::Equal);
+        assert_eq!(p("[1]").cmp(&p("[[1]]")), Ordering::Less);
+        assert_ne!(p("[1]"), p("[[1]]"));
+    }
+
+    #[test]
+    fn test_json() -> anyhow::Result<()> {
+        let json: Value = serde_json::from_str("[1, [2, []], 3]")?;
+        assert_eq!(Packet::try_from(json)?, p("[1,[2,[]],3]"));
+        assert_eq!(Value::from(p("[[4],5]")), serde_json::json!([[4], 5]));
+
+        for bad in ["[1, -2]", "[1.5]", "{\"a\": 1}", "[\"x\"]"] {
+            let err = Packet::try_from(serde_json::from_str::<Value>(bad)?).unwrap_err();
+            assert!(err
+                .to_string()
+                .starts_with("expected a non-negative integer or a list"));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_packets() {
+        let text = "[1,2]\n[[3]]\n\n4\n[5,\n";
+        let packets: Vec<_> = read_packets(text.as_bytes())
+            .map(|r| r.map_err(|e| e.to_string()))
+            .collect();
+        assert_eq!(
+            packets,
+            [
+                Ok(p("[1,2]")),
+                Ok(p("[[3]]")),
+                Ok(p("4")),
+                Err("line 5, column 3: expected ',' or ']', found \",\"".to_string()),
+            ]
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_round_trip(packet in any_packet()) {
+            let text = packet.to_string();
+            prop_assert_eq!(p(&text), packet.clone());
+
+            // The puzzle syntax is compact JSON
+            let json = Value::from(packet.clone());
+            prop_assert_eq!(json.to_string(), text);
+            prop_assert_eq!(Packet::try_from(json).unwrap(), packet);
+        }
+
+        #[test]
+        fn prop_total_order(a in any_packet(), b in any_packet(), c in any_packet()) {
+            prop_assert_eq!(a.cmp(&b), b.cmp(&a).reverse());
+            prop_assert_eq!(a.cmp(&b) == Ordering::Equal, a == b);
+            if a <= b && b <= c {
+                prop_assert!(a <= c);
+            }
+            prop_assert_eq!(a.cmp(&a), Ordering::Equal);
+        }
+
+        #[test]
+        fn prop_puzzle_order(a in any_packet(), b in any_packet(), n in 0u64..4) {
+            // Ord only ever refines the puzzle's comparison
+            if a.compare(&b) != Ordering::Equal {
+                prop_assert_eq!(a.cmp(&b), a.compare(&b));
+            }
+            // An integer compares as a list holding just that integer
+            prop_assert_eq!(a.compare(&Num(n)), a.compare(&List(vec![Num(n)])));
+            // Lists compare as their first difference, or else by length
+            let (l, r) = (List(vec![a.clone()]), List(vec![a.clone(), b.clone()]));
+            prop_assert_eq!(l.compare(&r), Ordering::Less);
+            let (l, r) = (List(vec![a.clone(), Num(n)]), List(vec![b.clone()]));
+            if a.compare(&b) != Ordering::Equal {
+                prop_assert_eq!(l.compare(&r), a.compare(&b));
+            }
+        }
+    }
+}
@@ -0,0 +1,335 @@
+//! Shapes built from unit cubes on an integer grid, like the lava droplet of 2022 day 18
+//!
+//! A voxel `(x, y, z)` fills the cube from `(x, y, z)` to `(x + 1, y + 1, z + 1)`, and
+//! voxels are connected when they share a face. Surfaces can be written out as ASCII
+//! STL or Wavefront OBJ meshes, which most 3D viewers can open.
+
+use advent_common::search::bfs_all;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+pub type Voxel = (i32, i32, i32);
+
+/// The six directions out of a voxel, one for each of its faces
+const SIDES: [Voxel; 6] = [
+    (1, 0, 0),
+    (-1, 0, 0),
+    (0, 1, 0),
+    (0, -1, 0),
+    (0, 0, 1),
+    (0, 0, -1),
+];
+
+/// The corners of the face on each side of the unit cube, anticlockwise when seen
+/// from outside so the mesh faces point outwards
+const CORNERS: [[Voxel; 4]; 6] = [
+    [(1, 0, 0), (1, 1, 0), (1, 1, 1), (1, 0, 1)],
+    [(0, 0, 0), (0, 0, 1), (0, 1, 1), (0, 1, 0)],
+    [(0, 1, 0), (0, 1, 1), (1, 1, 1), (1, 1, 0)],
+    [(0, 0, 0), (1, 0, 0), (1, 0, 1), (0, 0, 1)],
+    [(0, 0, 1), (1, 0, 1), (1, 1, 1), (0, 1, 1)],
+    [(0, 0, 0), (0, 1, 0), (1, 1, 0), (1, 0, 0)],
+];
+
+fn add((x, y, z): Voxel, (dx, dy, dz): Voxel) -> Voxel {
+    (x + dx, y + dy, z + dz)
+}
+
+fn neighbours(voxel: Voxel) -> impl Iterator<Item = Voxel> {
+    SIDES.into_iter().map(move |side| add(voxel, side))
+}
+
+/// Split `voxels` into the groups connected through shared faces, each group sorted
+/// and the groups in order of their smallest voxel
+fn connected(voxels: &HashSet<Voxel>) -> Vec<Shape> {
+    let mut todo: Vec<_> = voxels.iter().copied().collect();
+    todo.sort_unstable();
+    let mut seen = HashSet::new();
+    let mut groups = Vec::new();
+    for start in todo {
+        if seen.contains(&start) {
+            continue;
+        }
+        let group = bfs_all(start, |&v| neighbours(v).filter(|n| voxels.contains(n)));
+        seen.extend(group.keys().copied());
+        groups.push(Shape::new(group.into_keys()));
+    }
+    groups
+}
+
+/// A set of voxels
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Shape {
+    voxels: HashSet<Voxel>,
+}
+
+impl FromIterator<Voxel> for Shape {
+    fn from_iter<T: IntoIterator<Item = Voxel>>(iter: T) -> Self {
+        Shape::new(iter)
+    }
+}
+
+impl Shape {
+    pub fn new(voxels: impl IntoIterator<Item = Voxel>) -> Shape {
+        Shape {
+            voxels: voxels.into_iter().collect(),
+        }
+    }
+
+    /// How many voxels there are, which is also the volume
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.voxels.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.voxels.is_empty()
+    }
+
+    #[must_use]
+    pub fn contains(&self, voxel: Voxel) -> bool {
+        self.voxels.contains(&voxel)
+    }
+
+    /// The voxels in order
+    #[must_use]
+    pub fn voxels(&self) -> Vec<Voxel> {
+        let mut voxels: Vec<_> = self.voxels.iter().copied().collect();
+        voxels.sort_unstable();
+        voxels
+    }
+
+    /// The lowest and highest voxel positions along each axis, or `None` if the shape
+    /// is empty
+    #[must_use]
+    pub fn bounds(&self) -> Option<(Voxel, Voxel)> {
+        let mut voxels = self.voxels.iter();
+        let &first = voxels.next()?;
+        Some(voxels.fold((first, first), |(lo, hi), &(x, y, z)| {
+            (
+                (lo.0.min(x), lo.1.min(y), lo.2.min(z)),
+                (hi.0.max(x), hi.1.max(y), hi.2.max(z)),
+            )
+        }))
+    }
+
+    /// Every face that isn't against another voxel, as the voxel and an index into
+    /// `SIDES`
+    fn faces(&self) -> impl Iterator<Item = (Voxel, usize)> + '_ {
+        self.voxels().into_iter().flat_map(move |v| {
+            (0..SIDES.len())
+                .filter(move |&s| !self.voxels.contains(&add(v, SIDES[s])))
+                .map(move |s| (v, s))
+        })
+    }
+
+    /// The area of every face not against another voxel, including those facing in
+    /// to air pockets
+    #[must_use]
+    pub fn surface_area(&self) -> usize {
+        self.faces().count()
+    }
+
+    /// The air around the shape that can be reached from outside it, found by one
+    /// flood fill through a box one bigger than the shape all round
+    fn outside(&self) -> HashSet<Voxel> {
+        let Some((lo, hi)) = self.bounds() else {
+            return HashSet::new();
+        };
+        let (lo, hi) = (add(lo, (-1, -1, -1)), add(hi, (1, 1, 1)));
+        let inside = |(x, y, z): &Voxel| {
+            (lo.0..=hi.0).contains(x) && (lo.1..=hi.1).contains(y) && (lo.2..=hi.2).contains(z)
+        };
+        bfs_all(lo, |&v| {
+            neighbours(v).filter(|n| inside(n) && !self.voxels.contains(n))
+        })
+        .into_keys()
+        .collect()
+    }
+
+    /// The area of the faces that can be reached from outside the shape
+    #[must_use]
+    pub fn exterior_surface_area(&self) -> usize {
+        let outside = self.outside();
+        self.faces()
+            .filter(|&(v, s)| outside.contains(&add(v, SIDES[s])))
+            .count()
+    }
+
+    /// The parts of the shape that are connected through shared faces
+    #[must_use]
+    pub fn components(&self) -> Vec<Shape> {
+        connected(&self.voxels)
+    }
+
+    /// The pockets of air shut inside the shape, with no way out to the outside
+    #[must_use]
+    pub fn air_pockets(&self) -> Vec<Shape> {
+        let Some((lo, hi)) = self.bounds() else {
+            return Vec::new();
+        };
+        let outside = self.outside();
+        let air: HashSet<Voxel> = (lo.0..=hi.0)
+            .flat_map(|x| (lo.1..=hi.1).flat_map(move |y| (lo.2..=hi.2).map(move |z| (x, y, z))))
+            .filter(|v| !self.voxels.contains(v) && !outside.contains(v))
+            .collect();
+        connected(&air)
+    }
+
+    /// The surface as an ASCII STL mesh called `name`, with two triangles for each
+    /// face
+    #[must_use]
+    pub fn to_stl(&self, name: &str) -> String {
+        let mut out = format!("solid {name}\n");
+        for (v, s) in self.faces() {
+            let (nx, ny, nz) = SIDES[s];
+            let c = CORNERS[s].map(|corner| add(v, corner));
+            for [a, b, c] in [[c[0], c[1], c[2]], [c[0], c[2], c[3]]] {
+                let _ = writeln!(out, "  facet normal {nx} {ny} {nz}\n    outer loop");
+                for (x, y, z) in [a, b, c] {
+                    let _ = writeln!(out, "      vertex {x} {y} {z}");
+                }
+                let _ = writeln!(out, "    endloop\n  endfacet");
+            }
+        }
+        let _ = writeln!(out, "endsolid {name}");
+        out
+    }
+
+    /// The surface as a Wavefront OBJ mesh, with a square for each face and corners
+    /// shared between the faces that meet there
+    #[must_use]
+    pub fn to_obj(&self) -> String {
+        let mut vertices: HashMap<Voxel, usize> = HashMap::new();
+        let (mut verts, mut faces) = (String::new(), String::new());
+        for (v, s) in self.faces() {
+            let ids = CORNERS[s].map(|corner| {
+                let at = add(v, corner);
+                let next = vertices.len() + 1;
+                *vertices.entry(at).or_insert_with(|| {
+                    let _ = writeln!(verts, "v {} {} {}", at.0, at.1, at.2);
+                    next
+                })
+            });
+            let _ = writeln!(faces, "f {} {} {} {}", ids[0], ids[1], ids[2], ids[3]);
+        }
+        verts + &faces
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The example droplet from the puzzle
+    const DROPLET: [Voxel; 13] = [
+        (2, 2, 2),
+        (1, 2, 2),
+        (3, 2, 2),
+        (2, 1, 2),
+        (2, 3, 2),
+        (2, 2, 1),
+        (2, 2, 3),
+        (2, 2, 4),
+        (2, 2, 6),
+        (1, 2, 5),
+        (3, 2, 5),
+        (2, 1, 5),
+        (2, 3, 5),
+    ];
+
+    /// A hollow `size` cube with walls one voxel thick
+    fn hollow(size: i32) -> Shape {
+        let edge = |n| n == 0 || n == size - 1;
+        (0..size)
+            .flat_map(|x| (0..size).flat_map(move |y| (0..size).map(move |z| (x, y, z))))
+            .filter(|&(x, y, z)| edge(x) || edge(y) || edge(z))
+            .collect()
+    }
+
+    #[test]
+    fn test_corners_face_outwards() {
+        for (side, corners) in SIDES.iter().zip(CORNERS) {
+            let [a, b, c, _] = corners;
+            let (u, v) = (
+                (b.0 - a.0, b.1 - a.1, b.2 - a.2),
+                (c.0 - a.0, c.1 - a.1, c.2 - a.2),
+            );
+            let normal = (
+                u.1 * v.2 - u.2 * v.1,
+                u.2 * v.0 - u.0 * v.2,
+                u.0 * v.1 - u.1 * v.0,
+            );
+            assert_eq!(&normal, side);
+        }
+    }
+
+    #[test]
+    fn test_surface_areas() {
+        let droplet = Shape::new(DROPLET);
+        assert_eq!(droplet.surface_area(), 64);
+        assert_eq!(droplet.exterior_surface_area(), 58);
+        assert_eq!(Shape::new(DROPLET[..2].to_vec()).surface_area(), 10);
+
+        let cube = hollow(5);
+        assert_eq!(cube.surface_area(), 6 * 25 + 6 * 9);
+        assert_eq!(cube.exterior_surface_area(), 6 * 25);
+        assert_eq!(Shape::default().exterior_surface_area(), 0);
+    }
+
+    #[test]
+    fn test_components_and_pockets() {
+        let droplet = Shape::new(DROPLET);
+        let parts = droplet.components();
+        // The empty pocket at (2, 2, 5) leaves the five cubes around it as separate parts
+        let sizes: Vec<_> = parts.iter().map(Shape::len).collect();
+        assert_eq!(sizes, [8, 1, 1, 1, 1, 1]);
+        assert_eq!(parts[3].voxels(), [(2, 2, 6)]);
+
+        let pockets = droplet.air_pockets();
+        assert_eq!(pockets.len(), 1);
+        assert_eq!(pockets[0].voxels(), [(2, 2, 5)]);
+
+        // Two hollow cubes side by side, one with a hole in its wall
+        let mut pair = hollow(5);
+        pair.voxels
+            .extend(hollow(4).voxels().into_iter().map(|v| add(v, (10, 0, 0))));
+        assert_eq!(pair.components().len(), 2);
+        let volumes: Vec<_> = pair.air_pockets().iter().map(Shape::len).collect();
+        assert_eq!(volumes, [27, 8]);
+        pair.voxels.remove(&(10, 1, 1));
+        assert_eq!(pair.air_pockets().len(), 1);
+    }
+
+    #[test]
+    fn test_meshes() {
+        let cube = Shape::new([(0, 0, 0)]);
+        let stl = cube.to_stl("cube");
+        assert!(stl.starts_with(
+            "solid cube
+  facet normal 1 0 0
+    outer loop
+      vertex 1 0 0
+      vertex 1 1 0
+      vertex 1 1 1
+    endloop
+  endfacet
+"
+        ));
+        assert!(stl.ends_with("endsolid cube\n"));
+        assert_eq!(stl.matches("facet normal").count(), 12);
+
+        let obj = cube.to_obj();
+        assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), 8);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), 6);
+        assert!(obj.starts_with("v 1 0 0\nv 1 1 0\nv 1 1 1\nv 1 0 1\nv 0 0 0\n"));
+
+        // Faces in the pocket are part of the mesh too, but shared corners aren't
+        // repeated
+        let droplet = Shape::new(DROPLET).to_obj();
+        assert_eq!(droplet.lines().filter(|l| l.starts_with("f ")).count(), 64);
+    }
+}
@@ -1,103 +1,54 @@
+use advent_common::parsing::{in_file, read_lines, ParseError};
 use humantime::format_duration;
 use regex::Regex;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
 
-fn part_a(lines: &[String]) -> usize {
-    let p = Regex::new(r"^\D*(\d)?.*(\d)\D*$").unwrap();
-    lines
-        .iter()
-        .map(|l| {
-            let c = p.captures(l);
-            if let Some(caps) = c {
-                let m1 = caps.get(1);
-                let m2 = caps.get(2);
-
-                match (m1, m2) {
-                    (Some(d1), Some(d2)) => {
-                        (10 * d1.as_str().parse::<usize>().unwrap())
-                            + d2.as_str().parse::<usize>().unwrap()
-                    }
-                    (None, Some(d1)) => {
-                        (10 * d1.as_str().parse::<usize>().unwrap())
-                            + d1.as_str().parse::<usize>().unwrap()
-                    }
-                    _ => panic!("Something unexpected happened"),
-                }
-            } else {
-                0
-            }
-        })
-        .sum()
-}
-
-fn part_b(lines: &[String]) -> usize {
-    let mut total = 0;
-    for line in lines {
-        // Find the first digit through forward search
-        let mut d1 = '!';
-        for offset in 0..line.len() {
-            let segment = &line[offset..];
-
-            let found = match segment {
-                s if s.starts_with(|c: char| c.is_ascii_digit()) => segment.chars().next(),
-                s if s.starts_with("one") => Some('1'),
-                s if s.starts_with("two") => Some('2'),
-                s if s.starts_with("three") => Some('3'),
-                s if s.starts_with("four") => Some('4'),
-                s if s.starts_with("five") => Some('5'),
-                s if s.starts_with("six") => Some('6'),
-                s if s.starts_with("seven") => Some('7'),
-                s if s.starts_with("eight") => Some('8'),
-                s if s.starts_with("nine") => Some('9'),
-                _ => None,
-            };
-
-            if let Some(d) = found {
-                d1 = d;
-                break;
-            }
-        }
+const NAMES: [&str; 9] = [
+    "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+];
 
-        assert_ne!(d1, '!', "The first digit was not found");
-
-        // Search backwards for the last one
-        let mut d2 = '!';
-        for offset in (1..=line.len()).rev() {
-            let segment = &line[0..offset];
-
-            let found = match segment {
-                s if s.ends_with(|c: char| c.is_ascii_digit()) => segment.chars().last(),
-                s if s.ends_with("one") => Some('1'),
-                s if s.ends_with("two") => Some('2'),
-                s if s.ends_with("three") => Some('3'),
-                s if s.ends_with("four") => Some('4'),
-                s if s.ends_with("five") => Some('5'),
-                s if s.ends_with("six") => Some('6'),
-                s if s.ends_with("seven") => Some('7'),
-                s if s.ends_with("eight") => Some('8'),
-                s if s.ends_with("nine") => Some('9'),
-                _ => None,
-            };
-
-            if let Some(d) = found {
-                d2 = d;
-                break;
-            }
+/// The first and last digits in a line as a two digit number, also counting the
+/// names of digits if `names` is set
+fn calibration(li: usize, line: &str, names: bool) -> AResult<usize> {
+    let mut digits = line.char_indices().filter_map(|(i, c)| {
+        if c.is_ascii_digit() {
+            return Some(c as usize - '0' as usize);
         }
+        // Every index from `char_indices` is on a character boundary
+        let rest = &line[i..];
+        NAMES
+            .iter()
+            .position(|name| names && rest.starts_with(name))
+            .map(|n| n + 1)
+    });
+
+    let Some(first) = digits.next() else {
+        let expected = if names {
+            "a digit or the name of one"
+        } else {
+            "a digit"
+        };
+        Err(ParseError::new(li + 1, 1, expected, format!("{line:?}")))?
+    };
+    Ok(10 * first + digits.next_back().unwrap_or(first))
+}
 
-        assert_ne!(d2, '!', "The last digit was not found");
-
-        let num = format!("{d1}{d2}");
-        total += num.parse::<usize>().unwrap();
+fn part_a(lines: &[String]) -> AResult<usize> {
+    let mut total = 0;
+    for (li, line) in lines.iter().enumerate() {
+        total += calibration(li, line, false)?;
     }
+    Ok(total)
+}
 
-    total
+fn part_b(lines: &[String]) -> AResult<usize> {
+    let mut total = 0;
+    for (li, line) in lines.iter().enumerate() {
+        total += calibration(li, line, true)?;
+    }
+    Ok(total)
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -112,13 +63,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -131,18 +88,43 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines = ["two1nine".to_string(), "abcdef".to_string()];
+        assert_eq!(
+            part_b(&lines).unwrap_err().to_string(),
+            "line 2, column 1: expected a digit or the name of one, found \"abcdef\""
+        );
+        assert_eq!(
+            part_a(&lines).unwrap_err().to_string(),
+            "line 2, column 1: expected a digit, found \"abcdef\""
+        );
+
+        // Only ASCII digits count, and any other characters are skipped over
+        let lines = ["two\u{663}".to_string()];
+        assert_eq!(
+            part_a(&lines).unwrap_err().to_string(),
+            "line 1, column 1: expected a digit, found \"two\u{663}\""
+        );
+        assert_eq!(part_b(&lines).unwrap(), 22);
+        let lines = ["\u{e9}t\u{e9}7one\u{663}".to_string()];
+        assert_eq!(part_a(&lines).unwrap(), 77);
+        assert_eq!(part_b(&lines).unwrap(), 71);
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         const TEST_INPUT: &str = "1abc2
     pqr3stu8vwx
     a1b2c3d4e5f
     treb7uchet";
 
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 142);
+        assert_eq!(part_a(lines.as_slice())?, 142);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         const TEST_INPUT: &str = "two1nine
         eightwothree
         abcone2threexyz
@@ -152,6 +134,7 @@ mod tests {
         7pqrstsixteen";
 
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(lines.as_slice()), 281);
+        assert_eq!(part_b(lines.as_slice())?, 281);
+        Ok(())
     }
 }
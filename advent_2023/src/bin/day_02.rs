@@ -1,20 +1,16 @@
+use advent_common::parsing::{in_file, parse_lines, read_lines, uint, PResult};
 use humantime::format_duration;
 use nom::{
     branch::alt,
     bytes::complete::tag,
-    character::complete::{digit1, multispace1},
-    combinator::{map, map_res},
-    multi::separated_list0,
+    character::complete::multispace1,
+    combinator::{cut, map},
+    error::context,
+    multi::separated_list1,
     sequence::{self, delimited, separated_pair},
-    IResult,
 };
 use regex::Regex;
-use std::{
-    cmp,
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::{cmp, time::Instant};
 
 type AResult<T> = anyhow::Result<T>;
 
@@ -33,20 +29,20 @@ struct Draw {
 
 // Lowest level is a colour name paired with a number
 // Returns in (col, n) form
-fn colour_pair(input: &str) -> IResult<&str, (&str, usize)> {
-    map_res(
+fn colour_pair(input: &str) -> PResult<'_, (&str, usize)> {
+    map(
         separated_pair(
-            digit1,
+            uint,
             multispace1,
-            alt((tag("red"), tag("green"), tag("blue"))),
+            context("a colour", alt((tag("red"), tag("green"), tag("blue")))),
         ),
-        |res: (&str, &str)| res.0.parse::<usize>().map(|v| (res.1, v)),
+        |(v, col)| (col, v),
     )(input)
 }
 
 // Then draws are built of comma separated color pairs
-fn draw(input: &str) -> IResult<&str, Draw> {
-    map(separated_list0(tag(", "), colour_pair), |pairs| {
+fn draw(input: &str) -> PResult<'_, Draw> {
+    map(separated_list1(tag(", "), cut(colour_pair)), |pairs| {
         let mut out = Draw { red: 0, green: 0, blue: 0 };
 
         for pair in pairs {
@@ -62,30 +58,26 @@ fn draw(input: &str) -> IResult<&str, Draw> {
     })(input)
 }
 
-fn game(input: &str) -> IResult<&str, Game> {
+fn game(input: &str) -> PResult<'_, Game> {
     map(
         sequence::tuple((
             delimited(
-                tag("Game "),
-                map_res(digit1, str::parse::<usize>),
-                tag(": "),
+                context("\"Game \"", tag("Game ")),
+                uint,
+                context("\": \"", tag(": ")),
             ),
-            separated_list0(tag("; "), draw),
+            separated_list1(tag("; "), cut(draw)),
         )),
         |(id, draws)| Game { id, draws },
     )(input)
 }
 
-fn parse(lines: &[String]) -> Vec<Game> {
-    lines
-        .iter()
-        .map(|l| game(l).map(|(_, g)| g))
-        .map(Result::unwrap)
-        .collect()
+fn parse(lines: &[String]) -> AResult<Vec<Game>> {
+    Ok(parse_lines(lines, game)?)
 }
 
-fn part_a(lines: &[String]) -> usize {
-    parse(lines)
+fn part_a(lines: &[String]) -> AResult<usize> {
+    Ok(parse(lines)?
         .iter()
         .filter_map(|g| {
             if g.draws
@@ -97,11 +89,11 @@ fn part_a(lines: &[String]) -> usize {
                 None
             }
         })
-        .sum()
+        .sum())
 }
 
-fn part_b(lines: &[String]) -> usize {
-    parse(lines)
+fn part_b(lines: &[String]) -> AResult<usize> {
+    Ok(parse(lines)?
         .iter()
         .map(|game| {
             let mut minimal = Draw { red: 0, green: 0, blue: 0 };
@@ -115,7 +107,7 @@ fn part_b(lines: &[String]) -> usize {
             minimal
         })
         .map(|draw| draw.red * draw.green * draw.blue)
-        .sum()
+        .sum())
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -130,13 +122,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -154,6 +152,30 @@ mod tests {
     Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
     Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green";
 
+    #[test]
+    fn test_parse_errors() {
+        let lines = ["Game 1: 3 blue, 4 purple".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 19: expected a colour, found \"purple\""
+        );
+
+        let lines = [
+            "Game 1: 3 blue".to_string(),
+            "Game 2: 1 red; ; 2 green".to_string(),
+        ];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 16: expected unsigned integer, found \"; 2 green\""
+        );
+
+        let lines = ["Game one: 3 blue".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 6: expected unsigned integer, found \"one: 3 blue\""
+        );
+    }
+
     #[test]
     fn test_color_pair() -> AResult<()> {
         assert_eq!(colour_pair("1 red")?, ("", ("red", 1)));
@@ -161,14 +183,16 @@ mod tests {
     }
 
     #[test]
-    fn test_a() {
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 8);
+        assert_eq!(part_a(lines.as_slice())?, 8);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(lines.as_slice()), 2286);
+        assert_eq!(part_b(lines.as_slice())?, 2286);
+        Ok(())
     }
 }
@@ -1,15 +1,22 @@
+use advent_common::parsing::{grid_of, in_file, read_lines};
 use humantime::format_duration;
 use regex::Regex;
-use std::{
-    cmp,
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::{cmp, time::Instant};
 
 type AResult<T> = anyhow::Result<T>;
 
+// The parts index straight into the lines so check that the schematic is a
+// rectangle of ASCII symbols before starting
+fn check_schematic(lines: &[String]) -> AResult<()> {
+    grid_of(lines, "a digit, '.' or a symbol", |c| {
+        c.is_ascii_graphic().then_some(c)
+    })?;
+    Ok(())
+}
+
 fn part_a(lines: &[String]) -> AResult<u32> {
+    check_schematic(lines)?;
+
     // Check all the numbers for symbols around them (this'll avoid double counting
     // a single number if it happens to have 2 symbols near it)
 
@@ -60,7 +67,7 @@ fn part_a(lines: &[String]) -> AResult<u32> {
         }
 
         // Check the row below
-        if row_id < lines.len() {
+        if row_id + 1 < lines.len() {
             let next_line = &lines[row_id + 1].as_str();
             let search_start = range.start.saturating_sub(1);
             let search_end = (range.end + 1).clamp(0, lines[0].len());
@@ -128,6 +135,8 @@ fn part_b(lines: &[String]) -> AResult<u32> {
     // Do this it the opposite way around - find the *'s and then look for surrounding numbers
     // Rather than looking for the numbers and then checking the surrounds
 
+    check_schematic(lines)?;
+
     let pattern = Regex::new(r"\*")?;
     let mut gears = vec![];
     for (row_id, line) in lines.iter().enumerate() {
@@ -172,13 +181,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice())?);
-    println!("Part B result = {}", part_b(lines.as_slice())?);
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -201,6 +216,21 @@ mod tests {
     ...$.*....
     .664.598..";
 
+    #[test]
+    fn test_parse_errors() {
+        let lines = ["467..".to_string(), "..* .".to_string()];
+        assert_eq!(
+            part_a(&lines).unwrap_err().to_string(),
+            "line 2, column 4: expected a digit, '.' or a symbol, found ' '"
+        );
+
+        let lines = ["467..".to_string(), "...*".to_string()];
+        assert_eq!(
+            part_b(&lines).unwrap_err().to_string(),
+            "line 2, column 5: expected a row of 5 cells, found 4 cells"
+        );
+    }
+
     #[test]
     fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
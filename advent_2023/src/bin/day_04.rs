@@ -1,17 +1,16 @@
+use advent_common::parsing::{in_file, parse_lines, read_lines, uint, PResult};
 use humantime::format_duration;
 use nom::{
     bytes::complete::tag,
-    character::complete::{digit1, multispace0, multispace1},
-    combinator::{map, map_res},
+    character::complete::{multispace0, multispace1},
+    combinator::map,
+    error::context,
     multi::separated_list1,
     sequence::{delimited, tuple},
-    IResult,
 };
 use regex::Regex;
 use std::{
     collections::{BTreeSet, HashMap},
-    fs::File,
-    io::{BufRead, BufReader},
     time::Instant,
 };
 
@@ -39,43 +38,42 @@ impl Card {
     }
 }
 
-fn number_set(l: &str) -> IResult<&str, BTreeSet<usize>> {
-    map(
-        separated_list1(multispace1, map_res(digit1, |s: &str| s.parse::<usize>())),
-        |l| l.into_iter().collect::<BTreeSet<usize>>(),
-    )(l)
+fn number_set(l: &str) -> PResult<'_, BTreeSet<usize>> {
+    map(separated_list1(multispace1, uint), |l| {
+        l.into_iter().collect::<BTreeSet<usize>>()
+    })(l)
 }
 
-fn card_header(l: &str) -> IResult<&str, usize> {
+fn card_header(l: &str) -> PResult<'_, usize> {
     delimited(
-        tuple((tag("Card"), multispace1)),
-        map_res(digit1, |s: &str| s.parse::<usize>()),
-        tuple((tag(":"), multispace1)),
+        tuple((context("\"Card\"", tag("Card")), multispace1)),
+        uint,
+        tuple((context("':'", tag(":")), multispace1)),
     )(l)
 }
 
-fn parse_card(l: &str) -> Card {
-    let (_, (id, winners, _, mine)) = tuple((
-        card_header,
-        number_set,
-        tuple((multispace0, tag("|"), multispace0)),
-        number_set,
-    ))(l)
-    .unwrap_or_else(|_| panic!("Can't parse {l} as a card"));
-
-    Card { id, winners, mine }
+fn card(l: &str) -> PResult<'_, Card> {
+    map(
+        tuple((
+            card_header,
+            number_set,
+            tuple((multispace0, context("'|'", tag("|")), multispace0)),
+            number_set,
+        )),
+        |(id, winners, _, mine)| Card { id, winners, mine },
+    )(l)
 }
 
-fn parse(lines: &[String]) -> Vec<Card> {
-    lines.iter().map(|s| parse_card(s)).collect()
+fn parse(lines: &[String]) -> AResult<Vec<Card>> {
+    Ok(parse_lines(lines, card)?)
 }
 
-fn part_a(lines: &[String]) -> usize {
-    parse(lines).iter().map(Card::points).sum()
+fn part_a(lines: &[String]) -> AResult<usize> {
+    Ok(parse(lines)?.iter().map(Card::points).sum())
 }
 
-fn part_b(lines: &[String]) -> usize {
-    let cards = parse(lines);
+fn part_b(lines: &[String]) -> AResult<usize> {
+    let cards = parse(lines)?;
     let mut copies: HashMap<usize, usize> = cards.iter().map(|c| (c.id, 1)).collect();
 
     for card in cards {
@@ -87,7 +85,7 @@ fn part_b(lines: &[String]) -> usize {
         }
     }
 
-    copies.values().sum()
+    Ok(copies.values().sum())
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -102,13 +100,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -128,14 +132,34 @@ mod tests {
     Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines = ["Card 1: 41 48 x | 83 86".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 15: expected '|', found \"x | 83 86\""
+        );
+
+        let lines = [
+            "Card 1: 41 48 | 83 86".to_string(),
+            "Card 2 13 32 | 61 30".to_string(),
+        ];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 7: expected ':', found \" 13 32 | 61 30\""
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 13);
+        assert_eq!(part_a(lines.as_slice())?, 13);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(lines.as_slice()), 30);
+        assert_eq!(part_b(lines.as_slice())?, 30);
+        Ok(())
     }
 }
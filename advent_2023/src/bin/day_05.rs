@@ -1,22 +1,16 @@
+use advent_common::parsing::{in_file, read_lines, sections, uint, PResult, ParseError};
 use anyhow::anyhow;
 use humantime::format_duration;
 use indicatif::ParallelProgressIterator;
 use nom::{
-    bytes::complete::tag,
-    character::complete::{digit1, multispace0, multispace1, newline, none_of, space0},
-    combinator::{map, map_res},
-    multi::{fold_many1, many_m_n, separated_list1},
-    sequence::{pair, preceded, terminated, tuple},
-    IResult,
+    character::complete::{space0, space1},
+    combinator::map,
+    multi::{many_m_n, separated_list1},
+    sequence::terminated,
 };
 use rayon::prelude::*;
 use regex::Regex;
-use std::{
-    cmp,
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::{cmp, time::Instant};
 
 type AResult<T> = anyhow::Result<T>;
 
@@ -41,52 +35,78 @@ impl IntervalMap {
     }
 }
 
-fn interval(input: &str) -> IResult<&str, (usize, usize, usize)> {
-    map(
-        many_m_n(
-            3,
-            3,
-            terminated(map_res(digit1, |v: &str| v.parse::<usize>()), space0),
-        ),
-        |v| (v[0], v[1], v[2]),
-    )(input)
-}
-
-fn name(input: &str) -> IResult<&str, String> {
-    fold_many1(none_of(" "), String::new, |mut out, v| {
-        out.push(v);
-        out
+fn interval(input: &str) -> PResult<'_, (usize, usize, usize)> {
+    map(many_m_n(3, 3, terminated(uint, space0)), |v| {
+        (v[0], v[1], v[2])
     })(input)
 }
 
-fn map_block(input: &str) -> IResult<&str, IntervalMap> {
-    map(
-        tuple((
-            terminated(name, tuple((tag(" map:"), multispace0))),
-            separated_list1(newline, interval),
-        )),
-        |(bits, ranges)| IntervalMap { _name: bits, ranges },
-    )(input)
-}
+// The maps in the order they are applied
+const MAPS: [&str; 7] = [
+    "seed-to-soil",
+    "soil-to-fertilizer",
+    "fertilizer-to-water",
+    "water-to-light",
+    "light-to-temperature",
+    "temperature-to-humidity",
+    "humidity-to-location",
+];
+
+fn parse(lines: &[String]) -> AResult<(Vec<usize>, Vec<IntervalMap>)> {
+    let sections = sections(lines)?;
+    let Some((seeds, maps)) = sections.split_first() else {
+        Err(ParseError::end_of_input(lines.len(), "the seeds"))?
+    };
+
+    if seeds.key != "seeds" {
+        Err(ParseError::new(
+            seeds.header.start + 1,
+            1,
+            "\"seeds\"",
+            format!("{:?}", seeds.key),
+        ))?;
+    }
 
-fn block_list(input: &str) -> IResult<&str, Vec<IntervalMap>> {
-    separated_list1(pair(newline, newline), map_block)(input)
-}
+    if let Some(extra) = maps.get(MAPS.len()) {
+        Err(ParseError::new(
+            extra.header.start + 1,
+            1,
+            "end of input",
+            format!("{:?}", extra.key),
+        ))?;
+    }
 
-fn seeds(input: &str) -> IResult<&str, Vec<usize>> {
-    preceded(
-        tag("seeds: "),
-        separated_list1(multispace1, map_res(digit1, |v: &str| v.parse::<usize>())),
-    )(input)
-}
+    let seeds = seeds.parse_value(separated_list1(space1, uint))?;
+    let maps = MAPS
+        .iter()
+        .enumerate()
+        .map(|(idx, name)| {
+            let expected = format!("{name} map");
+            let s = maps
+                .get(idx)
+                .ok_or_else(|| ParseError::end_of_input(lines.len(), format!("the {expected}")))?;
+            if s.key != expected {
+                Err(ParseError::new(
+                    s.header.start + 1,
+                    1,
+                    format!("{expected:?}"),
+                    format!("{:?}", s.key),
+                ))?;
+            }
+
+            let ranges = s.body.parse_lines(interval)?;
+            Ok(IntervalMap {
+                _name: name.to_string(),
+                ranges,
+            })
+        })
+        .collect::<AResult<_>>()?;
 
-fn parse(input: &str) -> IResult<&str, (Vec<usize>, Vec<IntervalMap>)> {
-    tuple((terminated(seeds, multispace0), block_list))(input)
+    Ok((seeds, maps))
 }
 
-#[allow(clippy::redundant_closure_for_method_calls)]
-fn part_a(lines: &str) -> AResult<usize> {
-    let (_, (seeds, intervals)) = parse(lines).map_err(|e| e.to_owned())?;
+fn part_a(lines: &[String]) -> AResult<usize> {
+    let (seeds, intervals) = parse(lines)?;
 
     let mut min = usize::MAX;
     for seed in seeds {
@@ -112,9 +132,13 @@ fn part_a(lines: &str) -> AResult<usize> {
 // I may revisit this in the future and implement the significantly more
 // efficient range manipulation approach.
 //
-#[allow(clippy::redundant_closure_for_method_calls)]
-fn part_b(lines: &str) -> AResult<usize> {
-    let (_, (raw_seed_ranges, intervals)) = parse(lines).map_err(|e| e.to_owned())?;
+fn part_b(lines: &[String]) -> AResult<usize> {
+    let (raw_seed_ranges, intervals) = parse(lines)?;
+    if !raw_seed_ranges.len().is_multiple_of(2) {
+        return Err(anyhow!(
+            "the seed ranges are not all start and length pairs"
+        ));
+    }
 
     let seed_ranges = raw_seed_ranges.chunks(2).map(|p| p[0]..p[0] + p[1]);
 
@@ -152,14 +176,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
-    let all_lines = lines.join("\n");
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(&all_lines)?);
-    println!("Part B result = {}", part_b(&all_lines)?);
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -205,15 +234,46 @@ humidity-to-location map:
 60 56 37
 56 93 4";
 
+    #[test]
+    fn test_parse_errors() {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+
+        assert_eq!(
+            parse(&lines[2..]).unwrap_err().to_string(),
+            "line 1, column 1: expected \"seeds\", found \"seed-to-soil map\""
+        );
+
+        assert_eq!(
+            parse(&lines[..28]).unwrap_err().to_string(),
+            "line 29, column 1: expected the humidity-to-location map, found end of input"
+        );
+
+        let mut swapped = lines.clone();
+        swapped.swap(2, 6);
+        assert_eq!(
+            parse(&swapped).unwrap_err().to_string(),
+            "line 3, column 1: expected \"seed-to-soil map\", found \"soil-to-fertilizer map\""
+        );
+
+        let mut short = lines.clone();
+        short[4] = "52 50".to_string();
+        assert_eq!(
+            parse(&short).unwrap_err().to_string(),
+            "line 5, column 6: expected unsigned integer, found end of line"
+        );
+    }
+
     #[test]
     fn test_a() -> AResult<()> {
-        assert_eq!(part_a(TEST_INPUT)?, 35);
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        assert_eq!(part_a(lines.as_slice())?, 35);
         Ok(())
     }
 
     #[test]
     fn test_b() -> AResult<()> {
-        assert_eq!(part_b(TEST_INPUT)?, 46);
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        assert_eq!(part_b(lines.as_slice())?, 46);
         Ok(())
     }
 }
@@ -1,32 +1,48 @@
+use advent_common::parsing::{
+    exact_lines, in_file, parse_all, read_lines, uint, PResult, ParseError,
+};
 use humantime::format_duration;
-use regex::Regex;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
+use nom::{
+    bytes::complete::tag,
+    character::complete::space1,
+    error::context,
+    multi::separated_list1,
+    sequence::{pair, preceded},
 };
+use regex::Regex;
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
 
-fn parse(lines: &[String]) -> [Vec<usize>; 2] {
-    [
-        lines[0]
-            .split_whitespace()
-            .skip(1)
-            .map(|x| x.parse().unwrap())
-            .collect(),
-        lines[1]
-            .split_whitespace()
-            .skip(1)
-            .map(|x| x.parse().unwrap())
-            .collect(),
-    ]
+// `label` (given with quotes for error messages) followed by a list of numbers
+fn numbers<'a>(label: &'static str) -> impl FnMut(&'a str) -> PResult<'a, Vec<usize>> {
+    preceded(
+        pair(context(label, tag(label.trim_matches('"'))), space1),
+        separated_list1(space1, uint),
+    )
+}
+
+fn parse(lines: &[String]) -> AResult<[Vec<usize>; 2]> {
+    let [times, distances] = exact_lines::<2>(lines, "the record distances")?;
+    let times = parse_all(times, numbers("\"Time:\""))?;
+    let distances = parse_all(distances, numbers("\"Distance:\"")).map_err(|e| e.offset_by(1))?;
+
+    if distances.len() != times.len() {
+        Err(ParseError::new(
+            2,
+            1,
+            format!("{} distances", times.len()),
+            format!("{}", distances.len()),
+        ))?;
+    }
+
+    Ok([times, distances])
 }
 
-fn part_a(lines: &[String]) -> usize {
+fn part_a(lines: &[String]) -> AResult<usize> {
     // Lets just do a simple iterator on this part :)
 
-    let [times, distances] = parse(lines);
+    let [times, distances] = parse(lines)?;
 
     let mut acc = 1;
     for (total_time, max_distance) in times.into_iter().zip(distances) {
@@ -39,34 +55,32 @@ fn part_a(lines: &[String]) -> usize {
         }
         acc *= std::cmp::max(1, wins);
     }
-    acc
+    Ok(acc)
 }
 
 #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
-fn part_b(lines: &[String]) -> usize {
+fn part_b(lines: &[String]) -> AResult<usize> {
     // Solution is the roots of the equation
     // h(t - h) - d = 0
     // h = hold time, t = total_time, d = distance to beat
 
-    let total_time = lines[0]
-        .chars()
-        .filter(char::is_ascii_digit)
-        .collect::<String>()
-        .parse::<f64>()
-        .unwrap();
+    // The spaces between the numbers are just bad kerning
+    let [times, distances] = parse(lines)?;
+    let unkern = |ns: Vec<usize>| {
+        ns.iter()
+            .map(ToString::to_string)
+            .collect::<String>()
+            .parse::<f64>()
+    };
 
-    let max_distance = lines[1]
-        .chars()
-        .filter(char::is_ascii_digit)
-        .collect::<String>()
-        .parse::<f64>()
-        .unwrap();
+    let total_time = unkern(times)?;
+    let max_distance = unkern(distances)?;
 
     // Rolling back the years to GCSE Maths 🙂
     let r1 = (-total_time + (total_time.powf(2.0) - (4.0 * max_distance)).sqrt()) / -2.0;
     let r2 = (-total_time - (total_time.powf(2.0) - (4.0 * max_distance)).sqrt()) / -2.0;
 
-    (r2.floor() - r1.ceil()) as usize + 1
+    Ok((r2.floor() - r1.ceil()) as usize + 1)
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -81,13 +95,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -103,14 +123,52 @@ mod tests {
     Distance:  9  40  200";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines = ["Time:      7  15   30".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 1: expected the record distances, found end of input"
+        );
+
+        let lines = [
+            "Time:      7  15   30".to_string(),
+            "Distance:  9  forty  200".to_string(),
+        ];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 13: expected end of line, found \"  forty  200\""
+        );
+
+        let lines = [
+            "Distance:  9  40  200".to_string(),
+            "Time:      7  15   30".to_string(),
+        ];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 1: expected \"Time:\", found \"Distance:  9  40  20\"..."
+        );
+
+        let lines = [
+            "Time:      7  15   30".to_string(),
+            "Distance:  9  40".to_string(),
+        ];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 1: expected 3 distances, found 2"
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 288);
+        assert_eq!(part_a(lines.as_slice())?, 288);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(lines.as_slice()), 71503);
+        assert_eq!(part_b(lines.as_slice())?, 71503);
+        Ok(())
     }
 }
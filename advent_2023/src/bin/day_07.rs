@@ -1,240 +1,189 @@
+use advent_common::parsing::{in_file, parse_lines, read_lines, uint, PResult, ParseError};
+use anyhow::anyhow;
 use humantime::format_duration;
-use regex::Regex;
-use std::{
-    cmp::{Ordering, Reverse},
-    collections::{HashMap, HashSet, VecDeque},
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
+use nom::{
+    character::complete::{alphanumeric1, space1},
+    error::context,
+    sequence::{pair, preceded},
 };
+use regex::Regex;
+use std::{cmp::Reverse, collections::HashMap, time::Instant};
 
 type AResult<T> = anyhow::Result<T>;
 
-//
-// Part specific card orderings
-//
-const CARD_ORDER: [char; 13] = [
-    'A', 'K', 'Q', 'J', 'T', '9', '8', '7', '6', '5', '4', '3', '2',
-];
+/// A kind of hand, from the sizes of its largest groups of matching cards
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Category {
+    name: &'static str,
+    // Largest first - a hand with groups at least this big (in some order) qualifies
+    groups: &'static [usize],
+}
 
-const CARD_ORDER_B: [char; 13] = [
-    'A', 'K', 'Q', 'T', '9', '8', '7', '6', '5', '4', '3', '2', 'J',
+/// The usual hand categories, weakest first
+const POKER: [Category; 7] = [
+    Category {
+        name: "high card",
+        groups: &[1],
+    },
+    Category { name: "one pair", groups: &[2] },
+    Category {
+        name: "two pair",
+        groups: &[2, 2],
+    },
+    Category {
+        name: "three of a kind",
+        groups: &[3],
+    },
+    Category {
+        name: "full house",
+        groups: &[3, 2],
+    },
+    Category {
+        name: "four of a kind",
+        groups: &[4],
+    },
+    Category {
+        name: "five of a kind",
+        groups: &[5],
+    },
 ];
 
-//
-// Define HandType
-//
-
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-enum HandType {
-    FiveKind,
-    FourKind,
-    FullHouse,
-    ThreeKind,
-    TwoPair,
-    OnePair,
-    HighCard,
+/// How a game of Camel Cards is played
+#[derive(Debug, Clone)]
+struct Rules {
+    // Weakest first, which breaks ties between hands of the same category
+    cards: &'static str,
+    // Stands in for whichever card makes the best hand
+    wild: Option<char>,
+    hand_size: usize,
+    // Weakest first
+    categories: Vec<Category>,
 }
 
-impl HandType {
-    // Need this because Rust doesn't have Sorted Enums
-    fn discriminant(self) -> u8 {
-        match self {
-            HandType::FiveKind => 7,
-            HandType::FourKind => 6,
-            HandType::FullHouse => 5,
-            HandType::ThreeKind => 4,
-            HandType::TwoPair => 3,
-            HandType::OnePair => 2,
-            HandType::HighCard => 1,
+impl Rules {
+    fn standard() -> Self {
+        Rules {
+            cards: "23456789TJQKA",
+            wild: None,
+            hand_size: 5,
+            categories: POKER.to_vec(),
         }
     }
-}
-
-impl From<[char; 5]> for HandType {
-    fn from(cards: [char; 5]) -> Self {
-        let mut card_counts: HashMap<char, usize> = HashMap::new();
-        for c in cards {
-            card_counts.entry(c).and_modify(|e| *e += 1).or_insert(1);
-        }
 
-        let mut card_counts: Vec<_> = card_counts.iter().collect();
-        card_counts.sort_by_key(|cc| Reverse((cc.1, cc.0)));
-
-        match card_counts.as_slice() {
-            [(_, 5)] => HandType::FiveKind,
-            [(_, 4), _] => HandType::FourKind,
-            [(_, 3), (_, 2)] => HandType::FullHouse,
-            [(_, 3), (_, 1), (_, 1)] => HandType::ThreeKind,
-            [(_, 2), (_, 2), (_, 1)] => HandType::TwoPair,
-            [(_, 2), (_, 1), (_, 1), (_, 1)] => HandType::OnePair,
-            _ => HandType::HighCard,
+    /// Jokers are wild, but are the weakest card when breaking ties
+    fn jokers() -> Self {
+        Rules {
+            cards: "J23456789TQKA",
+            wild: Some('J'),
+            ..Rules::standard()
         }
     }
-}
 
-impl PartialOrd for HandType {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+    /// The index of the strongest category that `cards` can make, if there is one
+    fn classify(&self, cards: &[char]) -> Option<usize> {
+        let mut counts: HashMap<char, usize> = HashMap::new();
+        for &c in cards.iter().filter(|&&c| Some(c) != self.wild) {
+            *counts.entry(c).or_default() += 1;
+        }
+        let wilds = cards.len() - counts.values().sum::<usize>();
+        let mut groups: Vec<_> = counts.into_values().collect();
+        groups.sort_unstable_by_key(|&g| Reverse(g));
+
+        // Pairing the largest groups with the largest wanted needs the fewest wild cards
+        self.categories.iter().rposition(|category| {
+            let needed: usize = category
+                .groups
+                .iter()
+                .enumerate()
+                .map(|(idx, &want)| want.saturating_sub(groups.get(idx).copied().unwrap_or(0)))
+                .sum();
+            needed <= wilds
+        })
     }
-}
 
-impl Ord for HandType {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        // Delegate to the custom discriminant
-        self.discriminant().cmp(&other.discriminant())
+    /// The category, then each card in turn - stronger hands sort after weaker ones
+    fn strength(&self, cards: &[char]) -> AResult<(usize, Vec<usize>)> {
+        let category = self.classify(cards).ok_or_else(|| {
+            anyhow!(
+                "{} fits none of the hand categories",
+                cards.iter().collect::<String>()
+            )
+        })?;
+        let ranks = cards
+            .iter()
+            .map(|&c| self.cards.find(c).unwrap_or_default())
+            .collect();
+        Ok((category, ranks))
     }
 }
 
-//
-// Define a Hand
-//
-
 #[derive(Debug)]
 struct Hand {
-    cards: [char; 5],
+    cards: Vec<char>,
     bid: usize,
-    h_type: HandType,
 }
 
-impl Hand {
-    fn best_hand_type(&self) -> HandType {
-        // Work out the best hand type for this hand given the part-b joker rule
-        let num_js = self.cards.iter().filter(|&&c| c == 'J').count();
-
-        // If there is at least on Joker, but not all the cards are jokers...
-        if num_js > 0 && num_js < 5 {
-            // Generate possible joker replacements - but only those that might improve the hand
-            // we do this my only considering joker substitutions with cards that are already in
-            // the hand
-            let mut candidates = vec![];
-
-            let mut to_expand: VecDeque<_> = VecDeque::new();
-            to_expand.push_front(self.cards);
-
-            while !to_expand.is_empty() {
-                let source: [char; 5] = to_expand.pop_front().unwrap();
-                let unique: HashSet<_> = source.iter().filter(|&c| c != &'J').collect();
-
-                let first_j = source.iter().position(|c| c == &'J');
-                match first_j {
-                    Some(idx) => {
-                        let mut new_cards = source;
-                        for new_card in unique {
-                            new_cards[idx] = *new_card;
-                            to_expand.push_back(new_cards);
-                        }
-                    }
-                    None => candidates.push(HandType::from(source)),
-                }
-            }
-
-            // Now we've generated all the candidates - return the best hand type
-            *candidates
-                .iter()
-                .max()
-                .unwrap_or_else(|| panic!("no best type for card {self:?}"))
-        } else {
-            // 0 or 5 jokers - the hand will not change from it's current type
-            self.h_type
-        }
-    }
-}
-
-impl From<&String> for Hand {
-    fn from(value: &String) -> Self {
-        let mut bits = value.split_whitespace();
-        let cards: [char; 5] = bits
-            .next()
-            .unwrap()
-            .chars()
-            .collect::<Vec<char>>()
-            .try_into()
-            .unwrap();
-        let bid = bits.next().unwrap().parse().unwrap();
-
-        let h_type = HandType::from(cards);
-
-        Hand { cards, bid, h_type }
-    }
-}
-
-//
-// Comparator Functions
-//
-
-fn cmp_a(this: &Hand, other: &Hand) -> Ordering {
-    // Separate comparator function for part_a
-    // IMPORTANT: This is an ascending order sort (ie. best hand last)
-
-    let order = this.h_type.cmp(&other.h_type);
-    if order == Ordering::Equal {
-        return this
-            .cards
-            .iter()
-            .zip(other.cards.iter())
-            .find_map(|(x, y)| {
-                let xi = CARD_ORDER.iter().rev().position(|c| c == x).unwrap();
-                let yi = CARD_ORDER.iter().rev().position(|c| c == y).unwrap();
-                if xi == yi {
-                    None
-                } else {
-                    Some(xi.cmp(&yi))
-                }
-            })
-            .unwrap();
-    }
-    order
+fn hand(input: &str) -> PResult<'_, (&str, usize)> {
+    pair(
+        context("some cards", alphanumeric1),
+        context("a bid", preceded(space1, uint)),
+    )(input)
 }
 
-fn cmp_b(this: &Hand, other: &Hand) -> Ordering {
-    // Separate comparator function for part_b
-    // IMPORTANT: This is an ascending order sort (ie. best hand last)
+fn parse(lines: &[String], rules: &Rules) -> AResult<Vec<Hand>> {
+    let hands = parse_lines(lines, hand)?;
 
-    let order = this.best_hand_type().cmp(&other.best_hand_type());
-    if order == Ordering::Equal {
-        return this
-            .cards
-            .iter()
-            .zip(other.cards.iter())
-            .find_map(|(x, y)| {
-                let xi = CARD_ORDER_B.iter().rev().position(|c| c == x).unwrap();
-                let yi = CARD_ORDER_B.iter().rev().position(|c| c == y).unwrap();
-                if xi == yi {
-                    None
-                } else {
-                    Some(xi.cmp(&yi))
-                }
+    // The cards have to come from the deck, and make up a whole hand
+    hands
+        .into_iter()
+        .enumerate()
+        .map(|(idx, (cards, bid))| {
+            if let Some(pos) = cards.find(|c| !rules.cards.contains(c)) {
+                Err(ParseError::new(
+                    idx + 1,
+                    pos + 1,
+                    format!("one of the cards {}", rules.cards),
+                    format!("{:?}", &cards[pos..pos + 1]),
+                ))?;
+            }
+            if cards.len() != rules.hand_size {
+                Err(ParseError::new(
+                    idx + 1,
+                    1,
+                    format!("{} cards", rules.hand_size),
+                    format!("{cards:?}"),
+                ))?;
+            }
+            Ok(Hand {
+                cards: cards.chars().collect(),
+                bid,
             })
-            .unwrap();
-    }
-    order
-}
-
-fn parse(lines: &[String]) -> Vec<Hand> {
-    lines.iter().map(Hand::from).collect()
+        })
+        .collect()
 }
 
-fn part_a(lines: &[String]) -> usize {
-    let mut hands = parse(lines);
-    hands.sort_by(cmp_a);
+/// Each hand's bid times its rank, from 1 for the weakest hand
+fn winnings(lines: &[String], rules: &Rules) -> AResult<usize> {
+    let hands = parse(lines, rules)?;
+    let mut ranked = hands
+        .iter()
+        .map(|h| Ok((rules.strength(&h.cards)?, h.bid)))
+        .collect::<AResult<Vec<_>>>()?;
+    ranked.sort_unstable();
 
-    hands
+    Ok(ranked
         .iter()
         .enumerate()
-        .map(|(rank, h)| h.bid * (rank + 1))
-        .sum()
+        .map(|(rank, (_, bid))| bid * (rank + 1))
+        .sum())
 }
 
-fn part_b(lines: &[String]) -> usize {
-    let mut hands = parse(lines);
-    hands.sort_by(cmp_b);
+fn part_a(lines: &[String]) -> AResult<usize> {
+    winnings(lines, &Rules::standard())
+}
 
-    hands
-        .iter()
-        .enumerate()
-        .map(|(rank, h)| h.bid * (rank + 1))
-        .sum()
+fn part_b(lines: &[String]) -> AResult<usize> {
+    winnings(lines, &Rules::jokers())
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -249,13 +198,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -274,14 +229,159 @@ mod tests {
     QQQJA 483";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let rules = Rules::standard();
+        let lines = ["32T3K 765".to_string(), "T55X5 684".to_string()];
+        assert_eq!(
+            parse(&lines, &rules).unwrap_err().to_string(),
+            "line 2, column 4: expected one of the cards 23456789TJQKA, found \"X\""
+        );
+
+        let lines = ["32T3 765".to_string()];
+        assert_eq!(
+            parse(&lines, &rules).unwrap_err().to_string(),
+            "line 1, column 1: expected 5 cards, found \"32T3\""
+        );
+
+        let lines = ["32T3K".to_string()];
+        assert_eq!(
+            parse(&lines, &rules).unwrap_err().to_string(),
+            "line 1, column 6: expected a bid, found end of line"
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 6440);
+        assert_eq!(part_a(lines.as_slice())?, 6440);
+        Ok(())
+    }
+
+    #[test]
+    fn test_b() -> AResult<()> {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        assert_eq!(part_b(lines.as_slice())?, 5905);
+        Ok(())
+    }
+
+    #[test]
+    fn test_classify() {
+        let name = |rules: &Rules, cards: &str| {
+            let cards: Vec<_> = cards.chars().collect();
+            rules.classify(&cards).map(|idx| rules.categories[idx].name)
+        };
+
+        let standard = Rules::standard();
+        assert_eq!(name(&standard, "32T3K"), Some("one pair"));
+        assert_eq!(name(&standard, "KTJJT"), Some("two pair"));
+        assert_eq!(name(&standard, "QQQJA"), Some("three of a kind"));
+        assert_eq!(name(&standard, "23332"), Some("full house"));
+
+        let jokers = Rules::jokers();
+        assert_eq!(name(&jokers, "KTJJT"), Some("four of a kind"));
+        assert_eq!(name(&jokers, "2345J"), Some("one pair"));
+        assert_eq!(name(&jokers, "2233J"), Some("full house"));
+        assert_eq!(name(&jokers, "JJJJJ"), Some("five of a kind"));
+
+        // Jokers are weakest when breaking ties
+        assert!(
+            jokers.strength(&['J', 'K', 'K', 'K', '2']).unwrap()
+                < jokers.strength(&['Q', 'Q', 'Q', 'Q', '2']).unwrap()
+        );
+
+        // Hands that fit no category can't be ranked
+        let pairs_only = Rules {
+            categories: POKER[1..].to_vec(),
+            ..Rules::standard()
+        };
+        assert_eq!(name(&pairs_only, "23456"), None);
+        assert_eq!(
+            pairs_only
+                .strength(&['2', '3', '4', '5', '6'])
+                .unwrap_err()
+                .to_string(),
+            "23456 fits none of the hand categories"
+        );
     }
 
     #[test]
-    fn test_b() {
+    fn test_variants() -> AResult<()> {
+        // Deuces wild - 32T3K becomes three of a kind, the lowest of the three
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(lines.as_slice()), 5905);
+        let deuces = Rules {
+            cards: "23456789TJQKA",
+            wild: Some('2'),
+            ..Rules::standard()
+        };
+        assert_eq!(
+            winnings(&lines, &deuces)?,
+            220 + 28 * 2 + 765 * 3 + 684 * 4 + 483 * 5
+        );
+        assert_eq!(
+            winnings(&["2222A 1".to_string(), "AAAAK 2".to_string()], &deuces)?,
+            2 + 2
+        );
+
+        // Seven card hands with more categories
+        let seven = Rules {
+            hand_size: 7,
+            wild: Some('J'),
+            cards: "J23456789TQKA",
+            categories: vec![
+                POKER[0],
+                POKER[1],
+                POKER[2],
+                Category {
+                    name: "three pair",
+                    groups: &[2, 2, 2],
+                },
+                POKER[3],
+                POKER[4],
+                Category {
+                    name: "two triples",
+                    groups: &[3, 3],
+                },
+                POKER[5],
+                Category {
+                    name: "four and three",
+                    groups: &[4, 3],
+                },
+                POKER[6],
+                Category {
+                    name: "six of a kind",
+                    groups: &[6],
+                },
+                Category {
+                    name: "seven of a kind",
+                    groups: &[7],
+                },
+            ],
+        };
+        let name = |cards: &str| {
+            let cards: Vec<_> = cards.chars().collect();
+            seven.classify(&cards).map(|idx| seven.categories[idx].name)
+        };
+        assert_eq!(name("2233445"), Some("three pair"));
+        assert_eq!(name("223344J"), Some("full house"));
+        assert_eq!(name("2223334"), Some("two triples"));
+        assert_eq!(name("222334J"), Some("four of a kind"));
+        assert_eq!(name("2222333"), Some("four and three"));
+        assert_eq!(name("22233JJ"), Some("five of a kind"));
+        assert_eq!(name("2345JJJ"), Some("four of a kind"));
+        assert_eq!(name("JJJJJJ2"), Some("seven of a kind"));
+
+        let lines = [
+            "2233445 10".to_string(),
+            "22233JJ 1".to_string(),
+            "2345678 100".to_string(),
+        ];
+        assert_eq!(winnings(&lines, &seven)?, 100 + 10 * 2 + 3);
+        assert_eq!(
+            winnings(&lines[..1], &Rules::jokers())
+                .unwrap_err()
+                .to_string(),
+            "line 1, column 1: expected 5 cards, found \"2233445\""
+        );
+        Ok(())
     }
 }
@@ -1,35 +1,74 @@
+use advent_common::{
+    math::lcm_all,
+    parsing::{exact_blocks, exact_lines, in_file, parse_all, read_lines, PResult, ParseError},
+};
+use anyhow::anyhow;
 use humantime::format_duration;
-use regex::Regex;
-use std::{
-    collections::HashMap,
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
+use nom::{
+    bytes::complete::{is_a, tag},
+    character::complete::alphanumeric1,
+    error::context,
+    sequence::{delimited, separated_pair},
 };
+use regex::Regex;
+use std::{collections::HashMap, time::Instant};
 
 type AResult<T> = anyhow::Result<T>;
+type Network<'a> = HashMap<&'a str, (&'a str, &'a str)>;
 
-fn parse(lines: &[String]) -> (&str, HashMap<&str, (&str, &str)>) {
-    let pattern = Regex::new(r"^(\w{3}) = \((\w{3}), (\w{3})\)$").unwrap();
-    let instr = lines[0].as_str();
-    let edges: HashMap<_, _> = lines[2..]
-        .iter()
-        .map(|l| {
-            let m = pattern.captures(l).unwrap();
-            (
-                m.get(1).unwrap().as_str(),
-                (m.get(2).unwrap().as_str(), m.get(3).unwrap().as_str()),
-            )
-        })
-        .collect();
+fn directions(input: &str) -> PResult<'_, &str> {
+    context("L or R", is_a("LR"))(input)
+}
 
-    (instr, edges)
+fn edge(input: &str) -> PResult<'_, (&str, (&str, &str))> {
+    separated_pair(
+        context("a node", alphanumeric1),
+        context("\" = \"", tag(" = ")),
+        delimited(
+            context("'('", tag("(")),
+            separated_pair(
+                context("a node", alphanumeric1),
+                context("\", \"", tag(", ")),
+                context("a node", alphanumeric1),
+            ),
+            context("')'", tag(")")),
+        ),
+    )(input)
 }
 
-fn part_a(lines: &[String]) -> usize {
+fn parse(lines: &[String]) -> AResult<(&str, Network<'_>)> {
+    let [instr, network_block] = exact_blocks::<2>(lines, "the network")?;
+    let [instr] = exact_lines::<1>(instr.lines, "the directions")?;
+    let instr = parse_all(instr, directions)?;
+    let edges = network_block.parse_lines(edge)?;
+
+    // Every edge has to lead to a node in the network
+    let network: Network<'_> = edges.iter().copied().collect();
+    for (idx, (node, (left, right))) in edges.iter().enumerate() {
+        let left_col = node.len() + 5;
+        let right_col = left_col + left.len() + 2;
+        for (col, dest) in [(left_col, left), (right_col, right)] {
+            if !network.contains_key(dest) {
+                Err(ParseError::new(
+                    network_block.start + idx + 1,
+                    col,
+                    "a known node",
+                    format!("{dest:?}"),
+                ))?;
+            }
+        }
+    }
+
+    Ok((instr, network))
+}
+
+fn part_a(lines: &[String]) -> AResult<usize> {
     // Straightforward path traversal
 
-    let (instr, edges) = parse(lines);
+    let (instr, edges) = parse(lines)?;
+    if !edges.contains_key("AAA") {
+        return Err(anyhow!("there is no node AAA to start from"));
+    }
 
     let mut pos = "AAA";
     let mut steps = 0;
@@ -45,10 +84,10 @@ fn part_a(lines: &[String]) -> usize {
         steps += 1;
     }
 
-    steps
+    Ok(steps)
 }
 
-fn part_b(lines: &[String]) -> usize {
+fn part_b(lines: &[String]) -> AResult<usize> {
     // Find the paths followed from multiple-start to multiple-ends
     //
     // Assumption: Each path is actually cycle of computable length
@@ -56,7 +95,7 @@ fn part_b(lines: &[String]) -> usize {
     // Therefore we can determine the length of each cycle and then find the lowest
     // common multiple of all lengths to find the first point where all paths sync up.
 
-    let (instr, edges) = parse(lines);
+    let (instr, edges) = parse(lines)?;
     let starts: Vec<&str> = edges.keys().copied().filter(|n| n.ends_with('A')).collect();
     let cycle_lengths: Vec<usize> = starts
         .iter()
@@ -78,8 +117,10 @@ fn part_b(lines: &[String]) -> usize {
         })
         .collect();
 
-    // Since LCM is commutative can simply reduce over the lcm method
-    cycle_lengths.into_iter().reduce(num::integer::lcm).unwrap()
+    if cycle_lengths.is_empty() {
+        return Err(anyhow!("no start nodes found"));
+    }
+    lcm_all(cycle_lengths).ok_or_else(|| anyhow!("the lcm of the cycle lengths overflows"))
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -94,13 +135,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -129,15 +176,47 @@ mod tests {
     ZZZ = (ZZZ, ZZZ)";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines: Vec<_> = TEST_INPUT_2.lines().map(|l| l.trim().to_string()).collect();
+
+        assert_eq!(
+            parse(&lines[..1]).unwrap_err().to_string(),
+            "line 2, column 1: expected the network, found end of input"
+        );
+
+        let mut broken = lines.clone();
+        broken[0] = "LLX".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 1, column 3: expected end of line, found \"X\""
+        );
+
+        let mut broken = lines.clone();
+        broken[3] = "BBB = (AAA ZZZ)".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 4, column 11: expected \", \", found \" ZZZ)\""
+        );
+
+        let mut broken = lines.clone();
+        broken[3] = "BBB = (AAA, YYY)".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 4, column 13: expected a known node, found \"YYY\""
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT_1.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 2);
+        assert_eq!(part_a(lines.as_slice())?, 2);
         let lines: Vec<_> = TEST_INPUT_2.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 6);
+        assert_eq!(part_a(lines.as_slice())?, 6);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = "LR
 
         11A = (11B, XXX)
@@ -151,6 +230,7 @@ mod tests {
             .lines()
             .map(|l| l.trim().to_string())
             .collect();
-        assert_eq!(part_b(lines.as_slice()), 6);
+        assert_eq!(part_b(lines.as_slice())?, 6);
+        Ok(())
     }
 }
@@ -1,18 +1,13 @@
+use advent_common::parsing::{in_file, int, parse_lines, read_lines};
 use humantime::format_duration;
+use nom::{character::complete::space1, multi::separated_list1};
 use regex::Regex;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
 
-fn parse(lines: &[String]) -> Vec<Vec<isize>> {
-    lines
-        .iter()
-        .map(|l| l.split_whitespace().map(|s| s.parse().unwrap()).collect())
-        .collect()
+fn parse(lines: &[String]) -> AResult<Vec<Vec<isize>>> {
+    Ok(parse_lines(lines, separated_list1(space1, int))?)
 }
 
 fn next_value(seq: &[isize]) -> isize {
@@ -32,18 +27,18 @@ fn next_value(seq: &[isize]) -> isize {
     seq.last().unwrap() + delta
 }
 
-fn part_a(lines: &[String]) -> isize {
-    parse(lines).iter().map(|s| next_value(s)).sum()
+fn part_a(lines: &[String]) -> AResult<isize> {
+    Ok(parse(lines)?.iter().map(|s| next_value(s)).sum())
 }
 
-fn part_b(lines: &[String]) -> isize {
-    parse(lines)
+fn part_b(lines: &[String]) -> AResult<isize> {
+    Ok(parse(lines)?
         .iter()
         .map(|s| {
             let reversed: Vec<_> = s.iter().copied().rev().collect();
             next_value(reversed.as_slice())
         })
-        .sum()
+        .sum())
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -58,13 +53,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -81,15 +82,32 @@ mod tests {
     10 13 16 21 30 45";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines = ["0 3 6 9 12 15".to_string(), "1 3 six 10".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 4: expected end of line, found \" six 10\""
+        );
+
+        let lines = ["".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 1: expected integer, found end of line"
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 114);
+        assert_eq!(part_a(lines.as_slice())?, 114);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(&lines[2..]), 5);
-        assert_eq!(part_b(lines.as_slice()), 2);
+        assert_eq!(part_b(&lines[2..])?, 5);
+        assert_eq!(part_b(lines.as_slice())?, 2);
+        Ok(())
     }
 }
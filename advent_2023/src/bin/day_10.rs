@@ -1,62 +1,83 @@
+use advent_common::parsing::{grid_of, in_file, read_lines, ParseError};
+use anyhow::anyhow;
 use humantime::format_duration;
 use regex::Regex;
-use std::{
-    collections::HashSet,
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::{collections::HashSet, time::Instant};
 
 type AResult<T> = anyhow::Result<T>;
 
-fn parse(lines: &[String]) -> Vec<Vec<char>> {
-    lines.iter().map(|l| l.chars().collect()).collect()
+fn parse(lines: &[String]) -> AResult<Vec<Vec<char>>> {
+    let grid = grid_of(lines, "a pipe, '.' or 'S'", |c| {
+        "|-LJ7F.S".contains(c).then_some(c)
+    })?;
+
+    let starts = grid.iter().flatten().filter(|&&c| c == 'S').count();
+    if starts != 1 {
+        Err(ParseError::new(
+            1,
+            1,
+            "exactly one starting position",
+            format!("{starts}"),
+        ))?;
+    }
+
+    Ok(grid)
+}
+
+// The cell `delta` away from (row, col), if it is on the grid
+fn step(
+    grid: &[Vec<char>],
+    (row, col): (usize, usize),
+    delta: (isize, isize),
+) -> Option<(usize, usize)> {
+    let row = row.checked_add_signed(delta.0)?;
+    let col = col.checked_add_signed(delta.1)?;
+    grid.get(row)?.get(col).map(|_| (row, col))
 }
 
-fn determine_type(grid: &[Vec<char>], row: usize, col: usize) -> char {
+fn determine_type(grid: &[Vec<char>], row: usize, col: usize) -> AResult<char> {
+    // Anything off the edge of the grid is ground
+    let at = |delta| step(grid, (row, col), delta).map_or('.', |(r, c)| grid[r][c]);
+    let (north, south, west, east) = (at((-1, 0)), at((1, 0)), at((0, -1)), at((0, 1)));
+
     // | is a vertical pipe connecting north and south.
-    if row > 0 && "|7F".contains(grid[row - 1][col]) && "|LJ".contains(grid[row + 1][col]) {
-        '|'
-    } else if col > 0 && "-LF".contains(grid[row][col - 1]) && "-J7".contains(grid[row][col + 1]) {
+    if "|7F".contains(north) && "|LJ".contains(south) {
+        Ok('|')
+    } else if "-LF".contains(west) && "-J7".contains(east) {
         // - is a horizontal pipe connecting east and west.
-        '-'
-    } else if row > 0 && "|7F".contains(grid[row - 1][col]) && "-J7".contains(grid[row][col + 1]) {
+        Ok('-')
+    } else if "|7F".contains(north) && "-J7".contains(east) {
         // L is a 90-degree bend connecting north and east.
-        'L'
-    } else if row > 0
-        && col > 0
-        && "|7F".contains(grid[row - 1][col])
-        && "-LF".contains(grid[row][col - 1])
-    {
+        Ok('L')
+    } else if "|7F".contains(north) && "-LF".contains(west) {
         // J is a 90-degree bend connecting north and west.
-        'J'
-    } else if col > 0 && "|LJ".contains(grid[row + 1][col]) && "-LF".contains(grid[row][col - 1]) {
+        Ok('J')
+    } else if "|LJ".contains(south) && "-LF".contains(west) {
         // 7 is a 90-degree bend connecting south and west.
-        '7'
-    } else if "|LJ".contains(grid[row + 1][col]) && "-J7".contains(grid[row][col + 1]) {
+        Ok('7')
+    } else if "|LJ".contains(south) && "-J7".contains(east) {
         // F is a 90-degree bend connecting south and east.
-        'F'
+        Ok('F')
     } else {
-        panic!("Cannot determine underlying type");
+        Err(anyhow!("cannot determine the pipe under S"))
     }
 }
 
-#[allow(clippy::match_on_vec_items)]
-fn find_loop(grid: &[Vec<char>]) -> (Vec<(usize, usize)>, char) {
+fn find_loop(grid: &[Vec<char>]) -> AResult<(Vec<(usize, usize)>, char)> {
     let Some(start) = grid.iter().enumerate().find_map(|(ri, row)| {
         row.iter()
             .enumerate()
             .find_map(|(ci, &c)| if c == 'S' { Some((ri, ci)) } else { None })
     }) else {
-        panic!("Couldn't find starting position.")
+        return Err(anyhow!("couldn't find the starting position"));
     };
 
     // Determine what the S should actually be
-    let s_type = determine_type(grid, start.0, start.1);
+    let s_type = determine_type(grid, start.0, start.1)?;
 
     let mut pos = match s_type {
         '|' | 'F' | '7' => (start.0 + 1, start.1), // Start south in these cases
-        x => panic!("No start rule defined for {x}"),
+        x => return Err(anyhow!("no start rule defined for {x}")),
     };
 
     let mut path = vec![start, pos];
@@ -66,14 +87,27 @@ fn find_loop(grid: &[Vec<char>]) -> (Vec<(usize, usize)>, char) {
         let (p_row, p_col) = pos;
 
         // Determine where we go next (there will be only two options)
-        let (opt1, opt2) = match grid[p_row][p_col] {
-            '|' => ((p_row - 1, p_col), (p_row + 1, p_col)),
-            '-' => ((p_row, p_col - 1), (p_row, p_col + 1)),
-            'L' => ((p_row - 1, p_col), (p_row, p_col + 1)),
-            'J' => ((p_row - 1, p_col), (p_row, p_col - 1)),
-            '7' => ((p_row + 1, p_col), (p_row, p_col - 1)),
-            'F' => ((p_row + 1, p_col), (p_row, p_col + 1)),
-            x => panic!("No directions known for {x}"),
+        let deltas = match grid[p_row][p_col] {
+            '|' => [(-1, 0), (1, 0)],
+            '-' => [(0, -1), (0, 1)],
+            'L' => [(-1, 0), (0, 1)],
+            'J' => [(-1, 0), (0, -1)],
+            '7' => [(1, 0), (0, -1)],
+            'F' => [(1, 0), (0, 1)],
+            x => {
+                return Err(anyhow!(
+                    "the loop is broken by {x:?} at line {}, column {}",
+                    p_row + 1,
+                    p_col + 1
+                ))
+            }
+        };
+        let [Some(opt1), Some(opt2)] = deltas.map(|d| step(grid, pos, d)) else {
+            return Err(anyhow!(
+                "the loop leaves the grid at line {}, column {}",
+                p_row + 1,
+                p_col + 1
+            ));
         };
 
         pos = if history.contains(&opt1) && history.contains(&opt2) {
@@ -90,18 +124,18 @@ fn find_loop(grid: &[Vec<char>]) -> (Vec<(usize, usize)>, char) {
         history.insert(pos);
     }
 
-    (path, s_type)
+    Ok((path, s_type))
 }
 
-fn part_a(lines: &[String]) -> usize {
-    let grid = parse(lines);
-    let (path, _) = find_loop(&grid);
-    path.len() / 2
+fn part_a(lines: &[String]) -> AResult<usize> {
+    let grid = parse(lines)?;
+    let (path, _) = find_loop(&grid)?;
+    Ok(path.len() / 2)
 }
 
-fn part_b(lines: &[String]) -> usize {
-    let mut grid = parse(lines);
-    let (path, s_type) = find_loop(&grid);
+fn part_b(lines: &[String]) -> AResult<usize> {
+    let mut grid = parse(lines)?;
+    let (path, s_type) = find_loop(&grid)?;
 
     // Any cells that are not part of the grid can be considered ground (as they're junk)
     for (ri, row) in grid.iter_mut().enumerate() {
@@ -114,9 +148,9 @@ fn part_b(lines: &[String]) -> usize {
 
     // https://en.wikipedia.org/wiki/Point_in_polygon
 
-    let l7 = Regex::new(r"L-*7").unwrap();
-    let f7 = Regex::new(r"F-*7").unwrap();
-    let fj = Regex::new(r"F-*J").unwrap();
+    let l7 = Regex::new(r"L-*7")?;
+    let f7 = Regex::new(r"F-*7")?;
+    let fj = Regex::new(r"F-*J")?;
 
     let mut inside_count = 0;
     for row in grid {
@@ -148,7 +182,7 @@ fn part_b(lines: &[String]) -> usize {
         }
     }
 
-    inside_count
+    Ok(inside_count)
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -163,13 +197,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -236,38 +276,69 @@ mod tests {
     L7JLJL-JLJLJL--JLJ.L";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines: Vec<_> = SIMPLEST_INPUT
+            .lines()
+            .map(|l| l.trim().to_string())
+            .collect();
+
+        let mut broken = lines.clone();
+        broken[2] = ".|x|.".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 3, column 3: expected a pipe, '.' or 'S', found 'x'"
+        );
+
+        let mut broken = lines.clone();
+        broken[1] = ".F-7.".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 1, column 1: expected exactly one starting position, found 0"
+        );
+
+        let mut broken = lines.clone();
+        broken[3] = ".L.J.".to_string();
+        assert_eq!(
+            part_a(&broken).unwrap_err().to_string(),
+            "the loop is broken by '.' at line 4, column 3"
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = SIMPLEST_INPUT
             .lines()
             .map(|l| l.trim().to_string())
             .collect();
-        assert_eq!(part_a(lines.as_slice()), 4);
+        assert_eq!(part_a(lines.as_slice())?, 4);
 
         let lines: Vec<_> = LARGER_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 8);
+        assert_eq!(part_a(lines.as_slice())?, 8);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = SIMPLE_LOOP_WITH_GAP
             .lines()
             .map(|l| l.trim().to_string())
             .collect();
-        assert_eq!(part_b(lines.as_slice()), 4);
+        assert_eq!(part_b(lines.as_slice())?, 4);
 
         let lines: Vec<_> = SIMPLE_LOOP_NO_GAP
             .lines()
             .map(|l| l.trim().to_string())
             .collect();
-        assert_eq!(part_b(lines.as_slice()), 4);
+        assert_eq!(part_b(lines.as_slice())?, 4);
 
         let lines: Vec<_> = LARGER_LOOP.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(lines.as_slice()), 8);
+        assert_eq!(part_b(lines.as_slice())?, 8);
 
         let lines: Vec<_> = LARGER_WITH_JUNK
             .lines()
             .map(|l| l.trim().to_string())
             .collect();
-        assert_eq!(part_b(lines.as_slice()), 10);
+        assert_eq!(part_b(lines.as_slice())?, 10);
+        Ok(())
     }
 }
@@ -1,16 +1,17 @@
+use advent_common::parsing::{grid_of, in_file, read_lines, ParseError};
 use humantime::format_duration;
 use regex::Regex;
-use std::{
-    collections::BTreeSet,
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::{collections::BTreeSet, time::Instant};
 
 type AResult<T> = anyhow::Result<T>;
 
-fn parse(lines: &[String]) -> Vec<Vec<char>> {
-    lines.iter().map(|l| l.chars().collect()).collect()
+fn parse(lines: &[String]) -> AResult<Vec<Vec<char>>> {
+    if lines.is_empty() {
+        Err(ParseError::end_of_input(0, "a row of the image"))?;
+    }
+    Ok(grid_of(lines, "'#' or '.'", |c| {
+        "#.".contains(c).then_some(c)
+    })?)
 }
 
 fn expand(source: &mut [Vec<char>], factor: usize) -> BTreeSet<(usize, usize)> {
@@ -64,8 +65,8 @@ fn calculate_distance(a: &(usize, usize), b: &(usize, usize)) -> usize {
     a_row.abs_diff(b_row) + a_col.abs_diff(b_col)
 }
 
-fn part_a(lines: &[String]) -> usize {
-    let mut grid = parse(lines);
+fn part_a(lines: &[String]) -> AResult<usize> {
+    let mut grid = parse(lines)?;
     let galaxies = expand(&mut grid, 2);
 
     let mut acc = 0;
@@ -75,11 +76,11 @@ fn part_a(lines: &[String]) -> usize {
         }
     }
 
-    acc
+    Ok(acc)
 }
 
-fn part_b(lines: &[String], factor: usize) -> usize {
-    let mut grid = parse(lines);
+fn part_b(lines: &[String], factor: usize) -> AResult<usize> {
+    let mut grid = parse(lines)?;
     let galaxies = expand(&mut grid, factor);
 
     let mut acc = 0;
@@ -89,7 +90,7 @@ fn part_b(lines: &[String], factor: usize) -> usize {
         }
     }
 
-    acc
+    Ok(acc)
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -104,13 +105,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice(), 1_000_000));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice(), 1_000_000).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -134,15 +141,31 @@ mod tests {
     #...#.....";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines = ["...#...".to_string(), "..*....".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 3: expected '#' or '.', found '*'"
+        );
+
+        assert_eq!(
+            parse(&[]).unwrap_err().to_string(),
+            "line 1, column 1: expected a row of the image, found end of input"
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 374);
+        assert_eq!(part_a(lines.as_slice())?, 374);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(lines.as_slice(), 10), 1030);
-        assert_eq!(part_b(lines.as_slice(), 100), 8410);
+        assert_eq!(part_b(lines.as_slice(), 10)?, 1030);
+        assert_eq!(part_b(lines.as_slice(), 100)?, 8410);
+        Ok(())
     }
 }
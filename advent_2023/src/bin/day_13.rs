@@ -1,18 +1,19 @@
+use advent_common::parsing::{blocks, grid_of, in_file, read_lines};
+use anyhow::anyhow;
 use humantime::format_duration;
 use regex::Regex;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
 
-fn parse(lines: &[String]) -> Vec<Vec<Vec<char>>> {
-    lines
-        .split(String::is_empty)
-        .map(|group| group.iter().map(|s| s.chars().collect()).collect())
-        .collect()
+fn parse(lines: &[String]) -> AResult<Vec<Vec<Vec<char>>>> {
+    Ok(blocks(lines)
+        .iter()
+        .map(|b| {
+            grid_of(b.lines, "'#' or '.'", |c| "#.".contains(c).then_some(c))
+                .map_err(|e| e.offset_by(b.start))
+        })
+        .collect::<Result<_, _>>()?)
 }
 
 fn transpose(grid: &[Vec<char>]) -> Vec<Vec<char>> {
@@ -51,27 +52,27 @@ fn find_horizontal_lor(grid: &[Vec<char>], is_b: bool) -> Option<usize> {
     None
 }
 
-fn part_a(lines: &[String]) -> usize {
+fn part_a(lines: &[String]) -> AResult<usize> {
     let mut left_of_vertical = 0usize;
     let mut above_horizontal = 0usize;
 
-    for grid in parse(lines) {
+    for (idx, grid) in parse(lines)?.into_iter().enumerate() {
         if let Some(n) = find_vertical_lor(&grid, false) {
             left_of_vertical += n;
         } else if let Some(n) = find_horizontal_lor(&grid, false) {
             above_horizontal += n;
         } else {
-            unreachable!();
+            return Err(anyhow!("pattern {} has no line of reflection", idx + 1));
         }
     }
 
-    left_of_vertical + (100 * above_horizontal)
+    Ok(left_of_vertical + (100 * above_horizontal))
 }
 
-fn part_b(lines: &[String]) -> usize {
+fn part_b(lines: &[String]) -> AResult<usize> {
     let mut left_of_vertical = 0usize;
     let mut above_horizontal = 0usize;
-    for grid in parse(lines) {
+    for (idx, grid) in parse(lines)?.into_iter().enumerate() {
         // Determine the part_a answer (so we know what to avoid)
         let a_h = find_horizontal_lor(&grid, false);
         let a_v = find_vertical_lor(&grid, false);
@@ -87,11 +88,11 @@ fn part_b(lines: &[String]) -> usize {
         } else if let Some(n) = b_h {
             above_horizontal += n;
         } else {
-            unreachable!();
+            return Err(anyhow!("pattern {} has no line of reflection", idx + 1));
         }
     }
 
-    left_of_vertical + (100 * above_horizontal)
+    Ok(left_of_vertical + (100 * above_horizontal))
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -106,13 +107,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -141,14 +148,42 @@ mod tests {
     #....#..#";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+
+        let mut broken = lines.clone();
+        broken[10] = "..##..#O#".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 11, column 8: expected '#' or '.', found 'O'"
+        );
+
+        let mut broken = lines.clone();
+        broken[12] = "#####.##".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 13, column 9: expected a row of 9 cells, found 8 cells"
+        );
+
+        assert_eq!(
+            part_a(&["#..".to_string(), "..#".to_string()])
+                .unwrap_err()
+                .to_string(),
+            "pattern 1 has no line of reflection"
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 405);
+        assert_eq!(part_a(lines.as_slice())?, 405);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(lines.as_slice()), 400);
+        assert_eq!(part_b(lines.as_slice())?, 400);
+        Ok(())
     }
 }
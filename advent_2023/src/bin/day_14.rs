@@ -1,18 +1,22 @@
+use advent_common::parsing::{grid_of, in_file, read_lines, ParseError};
+use anyhow::anyhow;
 use humantime::format_duration;
 use regex::Regex;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
 
-fn parse(lines: &[String]) -> Vec<Vec<char>> {
-    lines.iter().map(|l| l.chars().collect()).collect()
+fn parse(lines: &[String]) -> AResult<Vec<Vec<char>>> {
+    if lines.is_empty() {
+        Err(ParseError::end_of_input(0, "a row of the platform"))?;
+    }
+    Ok(grid_of(lines, "'O', '#' or '.'", |c| {
+        "O#.".contains(c).then_some(c)
+    })?)
 }
 
-fn roll_north(grid: &mut Vec<Vec<char>>) {
+#[allow(clippy::needless_range_loop)] // reason: the rows above and below are both indexed
+fn roll_north(grid: &mut [Vec<char>]) {
     let grid_len = grid.len();
     let row_len = grid[0].len();
 
@@ -33,7 +37,8 @@ fn roll_north(grid: &mut Vec<Vec<char>>) {
     }
 }
 
-fn roll_south(grid: &mut Vec<Vec<char>>) {
+#[allow(clippy::needless_range_loop)] // reason: the rows above and below are both indexed
+fn roll_south(grid: &mut [Vec<char>]) {
     let grid_len = grid.len();
     let row_len = grid[0].len();
 
@@ -54,7 +59,7 @@ fn roll_south(grid: &mut Vec<Vec<char>>) {
     }
 }
 
-fn roll_west(grid: &mut Vec<Vec<char>>) {
+fn roll_west(grid: &mut [Vec<char>]) {
     let row_len = grid[0].len();
 
     let mut changes = 1;
@@ -76,7 +81,7 @@ fn roll_west(grid: &mut Vec<Vec<char>>) {
     }
 }
 
-fn roll_east(grid: &mut Vec<Vec<char>>) {
+fn roll_east(grid: &mut [Vec<char>]) {
     let row_len = grid[0].len();
 
     let mut changes = 1;
@@ -142,17 +147,17 @@ fn brent(scores: &[usize]) -> Option<(usize, usize)> {
     Some((lam, mu)) // (loop_len, offset)
 }
 
-fn part_a(lines: &[String]) -> usize {
-    let mut grid = parse(lines);
+fn part_a(lines: &[String]) -> AResult<usize> {
+    let mut grid = parse(lines)?;
     roll_north(&mut grid);
-    grid_score(&grid)
+    Ok(grid_score(&grid))
 }
 
-fn part_b(lines: &[String]) -> usize {
+fn part_b(lines: &[String]) -> AResult<usize> {
     const SEED_ITERS: usize = 250;
     const TARGET_ITERS: usize = 1_000_000_000;
 
-    let mut previous_grid = parse(lines);
+    let mut previous_grid = parse(lines)?;
 
     // Run forward for some number of items to seed the sequence
     let mut scores = vec![];
@@ -173,10 +178,12 @@ fn part_b(lines: &[String]) -> usize {
     if let Some((len, offset)) = brent(&scores) {
         let repeated_section: Vec<_> = scores.iter().skip(offset).take(len).collect();
         let idx = TARGET_ITERS - offset - 1;
-        return *repeated_section[idx % len];
+        return Ok(*repeated_section[idx % len]);
     }
 
-    panic!("No cycle detected in scores, perhaps you need to increase SEED_ITERS")
+    Err(anyhow!(
+        "no cycle detected in scores, perhaps you need to increase SEED_ITERS"
+    ))
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -191,13 +198,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -220,6 +233,20 @@ mod tests {
     #....###..
     #OO..#....";
 
+    #[test]
+    fn test_parse_errors() {
+        let lines = ["O....#....".to_string(), "O.OO#...X#".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 9: expected 'O', '#' or '.', found 'X'"
+        );
+
+        assert_eq!(
+            parse(&[]).unwrap_err().to_string(),
+            "line 1, column 1: expected a row of the platform, found end of input"
+        );
+    }
+
     #[test]
     fn test_brent() {
         assert_eq!(
@@ -234,14 +261,16 @@ mod tests {
     }
 
     #[test]
-    fn test_a() {
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 136);
+        assert_eq!(part_a(lines.as_slice())?, 136);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(lines.as_slice()), 64);
+        assert_eq!(part_b(lines.as_slice())?, 64);
+        Ok(())
     }
 }
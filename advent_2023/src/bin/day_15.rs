@@ -1,15 +1,22 @@
+use advent_common::parsing::{exact_lines, in_file, parse_all, read_lines, uint, PResult};
 use humantime::format_duration;
-use regex::Regex;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::alpha1,
+    combinator::{cut, map},
+    error::context,
+    multi::separated_list1,
+    sequence::{separated_pair, terminated},
 };
+use regex::Regex;
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
 
-fn part_a(lines: &[String]) -> usize {
-    lines[0].split(',').map(hash).sum()
+fn part_a(lines: &[String]) -> AResult<usize> {
+    let [sequence] = exact_lines::<1>(lines, "the initialization sequence")?;
+    Ok(sequence.split(',').map(hash).sum())
 }
 
 fn hash(s: &str) -> usize {
@@ -27,23 +34,40 @@ fn hash(s: &str) -> usize {
 #[derive(Debug)]
 struct Lens<'a>(&'a str, usize);
 
-fn part_b(lines: &[String]) -> usize {
-    let instructions = lines[0].split(',');
-    let mut boxes: Vec<Vec<Lens>> = (0..256).map(|_| Vec::new()).collect();
+#[derive(Debug, PartialEq)]
+enum Step<'a> {
+    Remove(&'a str),
+    Insert(&'a str, usize),
+}
 
-    let pattern = Regex::new(r"(\w+)([=-])(\d*)").unwrap();
+fn step(input: &str) -> PResult<'_, Step<'_>> {
+    context(
+        "a step",
+        alt((
+            map(terminated(alpha1, tag("-")), Step::Remove),
+            map(separated_pair(alpha1, tag("="), uint), |(label, fl)| {
+                Step::Insert(label, fl)
+            }),
+        )),
+    )(input)
+}
 
-    for ins in instructions {
-        let (_, groups): (_, [&str; 3]) = pattern.captures(ins).expect("a match").extract();
+fn parse(lines: &[String]) -> AResult<Vec<Step<'_>>> {
+    let [sequence] = exact_lines::<1>(lines, "the initialization sequence")?;
+    Ok(parse_all(sequence, separated_list1(tag(","), cut(step)))?)
+}
 
-        match groups {
-            [label, "-", ""] => {
+fn part_b(lines: &[String]) -> AResult<usize> {
+    let mut boxes: Vec<Vec<Lens>> = (0..256).map(|_| Vec::new()).collect();
+
+    for ins in parse(lines)? {
+        match ins {
+            Step::Remove(label) => {
                 let box_id = hash(label);
                 boxes[box_id].retain(|l| l.0 != label);
             }
-            [label, "=", fl] => {
+            Step::Insert(label, fl) => {
                 let box_id = hash(label);
-                let fl = fl.parse::<usize>().unwrap();
 
                 let existing = boxes[box_id].iter_mut().find(|l| l.0 == label);
                 match existing {
@@ -53,11 +77,10 @@ fn part_b(lines: &[String]) -> usize {
                     None => boxes[box_id].push(Lens(label, fl)),
                 }
             }
-            _ => unreachable!(),
         }
     }
 
-    boxes
+    Ok(boxes
         .into_iter()
         .enumerate()
         .map(|(box_id, b)| {
@@ -66,7 +89,7 @@ fn part_b(lines: &[String]) -> usize {
                 .map(|(idx, l)| (box_id + 1) * (idx + 1) * l.1)
                 .sum::<usize>()
         })
-        .sum()
+        .sum())
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -81,13 +104,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -102,14 +131,42 @@ mod tests {
     const TEST_INPUT: &str = "rn=1,cm-,qp=3,cm=2,qp-,pc=4,ot=9,ab=5,pc-,pc=6,ot=7";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines = ["rn=1,cm-,qp=x,cm=2".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 13: expected unsigned integer, found \"x,cm=2\""
+        );
+
+        let lines = ["rn=1,cm-,qp".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 12: expected a step, found end of line"
+        );
+
+        let lines = ["rn=1,cm-".to_string(), "qp=3".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 1: expected end of input, found \"qp=3\""
+        );
+
+        assert_eq!(
+            part_a(&[]).unwrap_err().to_string(),
+            "line 1, column 1: expected the initialization sequence, found end of input"
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(&lines), 1320);
+        assert_eq!(part_a(&lines)?, 1320);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(&lines), 145);
+        assert_eq!(part_b(&lines)?, 145);
+        Ok(())
     }
 }
@@ -1,12 +1,7 @@
+use advent_common::parsing::{grid_of, in_file, read_lines, ParseError};
 use humantime::format_duration;
 use regex::Regex;
-use std::{
-    collections::HashSet,
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-    usize,
-};
+use std::{collections::HashSet, time::Instant};
 
 type AResult<T> = anyhow::Result<T>;
 type Grid = Vec<Vec<char>>;
@@ -14,8 +9,13 @@ type Grid = Vec<Vec<char>>;
 #[derive(Debug, Hash, PartialEq, Eq)]
 struct Beam(char, (usize, usize));
 
-fn parse(lines: &[String]) -> Grid {
-    lines.iter().map(|l| l.chars().collect()).collect()
+fn parse(lines: &[String]) -> AResult<Grid> {
+    if lines.is_empty() {
+        Err(ParseError::end_of_input(0, "a row of the contraption"))?;
+    }
+    Ok(grid_of(lines, "a mirror, a splitter or '.'", |c| {
+        r"./\|-".contains(c).then_some(c)
+    })?)
 }
 
 fn next_beams(dir: char, tile: char, n_coords: (usize, usize)) -> Vec<Beam> {
@@ -81,13 +81,13 @@ fn count_engergised(grid: &Grid, start_dir: char, start_pos: (usize, usize)) ->
     history.iter().map(|b| b.1).collect::<HashSet<_>>().len()
 }
 
-fn part_a(lines: &[String]) -> usize {
-    let grid = parse(lines);
-    count_engergised(&grid, 'R', (0, 0))
+fn part_a(lines: &[String]) -> AResult<usize> {
+    let grid = parse(lines)?;
+    Ok(count_engergised(&grid, 'R', (0, 0)))
 }
 
-fn part_b(lines: &[String]) -> usize {
-    let grid = parse(lines);
+fn part_b(lines: &[String]) -> AResult<usize> {
+    let grid = parse(lines)?;
     let last_col = grid[0].len() - 1;
     let last_row = grid.len() - 1;
 
@@ -107,24 +107,26 @@ fn part_b(lines: &[String]) -> usize {
         count_engergised(&grid, 'U', (last_row, last_col)),
     ];
 
-    // Now do rest of the sides - starting on the top row
-    lengths
-        .into_iter()
-        .chain((1..last_col).map(|col| count_engergised(&grid, 'D', (0, col))))
-        .chain(
-            // Left hand side
-            (1..last_row).map(|row| count_engergised(&grid, 'R', (row, 0))),
-        )
-        .chain(
-            // Right hand side
-            (1..last_row).map(|row| count_engergised(&grid, 'L', (row, last_col))),
-        )
-        .chain(
-            // Bottom Row
-            (1..last_col).map(|col| count_engergised(&grid, 'U', (last_row, col))),
-        )
-        .max()
-        .unwrap()
+    Ok(
+        // Now do rest of the sides - starting on the top row
+        lengths
+            .into_iter()
+            .chain((1..last_col).map(|col| count_engergised(&grid, 'D', (0, col))))
+            .chain(
+                // Left hand side
+                (1..last_row).map(|row| count_engergised(&grid, 'R', (row, 0))),
+            )
+            .chain(
+                // Right hand side
+                (1..last_row).map(|row| count_engergised(&grid, 'L', (row, last_col))),
+            )
+            .chain(
+                // Bottom Row
+                (1..last_col).map(|col| count_engergised(&grid, 'U', (last_row, col))),
+            )
+            .max()
+            .unwrap(),
+    )
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -139,13 +141,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -169,14 +177,31 @@ mod tests {
     ..//.|....";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines = [r".|...\....".to_string(), r"|.-.#.....".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 5: expected a mirror, a splitter or '.', found '#'"
+        );
+
+        let lines = [r".|...\....".to_string(), r"|.-.\".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 6: expected a row of 10 cells, found 5 cells"
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 46);
+        assert_eq!(part_a(lines.as_slice())?, 46);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(&lines), 51);
+        assert_eq!(part_b(&lines)?, 51);
+        Ok(())
     }
 }
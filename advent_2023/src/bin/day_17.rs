@@ -1,238 +1,213 @@
+use advent_2023::grid_string;
+use advent_common::{
+    parsing::{digit_grid, in_file, read_lines, ParseError},
+    search::dijkstra,
+};
+use anyhow::anyhow;
 use humantime::format_duration;
 use regex::Regex;
-use std::{
-    collections::{BTreeMap, BinaryHeap},
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::time::Instant;
 
 type Grid = Vec<Vec<usize>>;
 type Coord = (usize, usize);
 type AResult<T> = anyhow::Result<T>;
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
-struct State {
-    row: usize,
-    col: usize,
-    dir: char,
-    path: Vec<Coord>,
-    heat_loss: usize,
+fn parse(lines: &[String]) -> AResult<Grid> {
+    if lines.is_empty() {
+        Err(ParseError::end_of_input(0, "a row of the city"))?;
+    }
+    if let Some(idx) = lines.iter().position(String::is_empty) {
+        Err(ParseError::new(
+            idx + 1,
+            1,
+            "a row of the city",
+            "end of line",
+        ))?;
+    }
+    Ok(digit_grid(lines)?)
 }
 
-impl Ord for State {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        // Ordering is for the binary heap only
-        // Favor lower heat_loss states
-        self.heat_loss.cmp(&other.heat_loss).reverse()
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Dir {
+    Up,
+    Right,
+    Down,
+    Left,
 }
 
-impl PartialOrd for State {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+impl Dir {
+    const ALL: [Dir; 4] = [Dir::Up, Dir::Right, Dir::Down, Dir::Left];
+
+    fn step(self, (row, col): Coord, (rows, cols): (usize, usize)) -> Option<Coord> {
+        let next = match self {
+            Dir::Up => (row.checked_sub(1)?, col),
+            Dir::Right => (row, col + 1),
+            Dir::Down => (row + 1, col),
+            Dir::Left => (row, col.checked_sub(1)?),
+        };
+        (next.0 < rows && next.1 < cols).then_some(next)
     }
-}
 
-fn parse(lines: &[String]) -> Grid {
-    lines
-        .iter()
-        .map(|l| {
-            l.chars()
-                .map(|c| c.to_digit(10).unwrap() as usize)
-                .collect()
-        })
-        .collect()
-}
+    fn turn(self, turn: Turn) -> Dir {
+        let quarters = match turn {
+            Turn::Right => 1,
+            Turn::Back => 2,
+            Turn::Left => 3,
+        };
+        Dir::ALL[(self as usize + quarters) % 4]
+    }
 
-//
-// Functions to generate successor states in the specified direction
-//
-
-fn left(original: &State, min: usize, max: usize, grid: &Grid) -> Vec<State> {
-    let mut out = Vec::with_capacity(max - min);
-    for candidate_offset in min..=max {
-        if let Some(n_col) = original.col.checked_sub(candidate_offset) {
-            if !original.path.contains(&(original.row, n_col)) {
-                let mut np = original.path.clone();
-                np.extend((n_col..original.col).rev().map(|nc| (original.row, nc)));
-
-                let np_loss: usize = (n_col..original.col).map(|i| grid[original.row][i]).sum();
-
-                out.push(State {
-                    col: n_col,
-                    dir: 'L',
-                    path: np,
-                    heat_loss: original.heat_loss + np_loss,
-                    ..*original
-                });
-            }
+    /// How the puzzle draws a step this way
+    fn arrow(self) -> char {
+        match self {
+            Dir::Up => '^',
+            Dir::Right => '>',
+            Dir::Down => 'v',
+            Dir::Left => '<',
         }
     }
-    out
 }
 
-fn right(original: &State, min: usize, max: usize, grid: &Grid) -> Vec<State> {
-    let mut out = Vec::with_capacity(max - min);
-    for candidate_offset in min..=max {
-        if (original.col + candidate_offset) < grid[0].len() {
-            let n_col = original.col + candidate_offset;
-
-            if !original.path.contains(&(original.row, n_col)) {
-                let mut np = original.path.clone();
-                np.extend((original.col..n_col).map(|nc| (original.row, nc + 1)));
-
-                let np_loss: usize = (original.col..n_col)
-                    .map(|i| grid[original.row][i + 1])
-                    .sum();
-
-                out.push(State {
-                    col: n_col,
-                    dir: 'R',
-                    path: np,
-                    heat_loss: original.heat_loss + np_loss,
-                    ..*original
-                });
-            }
-        }
-    }
-    out
+/// A change of direction at the end of a straight run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Turn {
+    Left,
+    Right,
+    Back,
 }
 
-fn up(original: &State, min: usize, max: usize, grid: &Grid) -> Vec<State> {
-    let mut out = Vec::with_capacity(max - min);
-    for candidate_offset in min..=max {
-        if let Some(n_row) = original.row.checked_sub(candidate_offset) {
-            if !original.path.contains(&(n_row, original.col)) {
-                let mut np = original.path.clone();
-                np.extend((n_row..original.row).rev().map(|nr| (nr, original.col)));
-
-                let np_loss: usize = (n_row..original.row).map(|i| grid[i][original.col]).sum();
-
-                out.push(State {
-                    row: n_row,
-                    dir: 'U',
-                    path: np,
-                    heat_loss: original.heat_loss + np_loss,
-                    ..*original
-                });
-            }
-        }
-    }
-    out
+/// The rules a crucible moves by
+struct Policy<C> {
+    turns: Vec<Turn>,
+    // How many blocks it has to move, and can move, before turning
+    min_run: usize,
+    max_run: usize,
+    // Whether it can only stop at the goal once it has gone `min_run` blocks
+    stop_needs_min_run: bool,
+    // The cost of moving into a block heading a given way, as the given block of
+    // a straight run. `None` if it can't move there like that.
+    cost: C,
 }
 
-fn down(original: &State, min: usize, max: usize, grid: &Grid) -> Vec<State> {
-    let mut out = Vec::with_capacity(max - min);
-    for candidate_offset in min..=max {
-        if (original.row + candidate_offset) < grid.len() {
-            let n_row = original.row + candidate_offset;
-            if !original.path.contains(&(n_row, original.col)) {
-                let mut np = original.path.clone();
-                np.extend((original.row..n_row).map(|nr| (nr + 1, original.col)));
-
-                let np_loss: usize = (original.row..n_row)
-                    .map(|i| grid[i + 1][original.col])
-                    .sum();
-
-                out.push(State {
-                    row: n_row,
-                    dir: 'D',
-                    path: np,
-                    heat_loss: original.heat_loss + np_loss,
-                    ..*original
-                });
-            }
-        }
-    }
-    out
+/// Moving into a block costs its heat loss
+fn heat_loss(grid: &Grid) -> impl Fn(Coord, Dir, usize) -> Option<usize> + '_ {
+    |(row, col), _, _| Some(grid[row][col])
 }
 
-//
-// End direction functions
-//
-
-fn solve(lines: &[String], min_move: usize, max_move: usize) -> usize {
-    let grid = parse(lines);
-    let mut best = usize::MAX;
-    let l_row = grid.len() - 1;
-    let l_col = grid[0].len() - 1;
-
-    let mut heap: BinaryHeap<State> = BinaryHeap::new();
-    heap.push(State {
-        row: 0,
-        col: 0,
-        dir: 'U',
-        path: vec![],
-        heat_loss: 0,
-    });
-
-    let mut best_losses = BTreeMap::new();
-
-    while !heap.is_empty() {
-        let original = heap.pop().unwrap();
-        if (original.row == l_row) && (original.col == l_col) {
-            // Reached the end
-            if best > original.heat_loss {
-                best = original.heat_loss;
-            }
-            continue;
+impl<C> Policy<C> {
+    fn crucible(cost: C) -> Self {
+        Policy {
+            turns: vec![Turn::Left, Turn::Right],
+            min_run: 1,
+            max_run: 3,
+            stop_needs_min_run: false,
+            cost,
         }
+    }
 
-        // If we've been at this position before with a better heat loss score - bail out
-        let best_entry = best_losses.get(&(original.row, original.col, original.dir));
-        if let Some(&best_to_here) = best_entry {
-            if best_to_here <= original.heat_loss {
-                continue;
-            }
-            best_losses.insert(
-                (original.row, original.col, original.dir),
-                original.heat_loss,
-            );
-        } else {
-            best_losses.insert(
-                (original.row, original.col, original.dir),
-                original.heat_loss,
-            );
+    fn ultra_crucible(cost: C) -> Self {
+        Policy {
+            turns: vec![Turn::Left, Turn::Right],
+            min_run: 4,
+            max_run: 10,
+            stop_needs_min_run: true,
+            cost,
         }
+    }
+}
 
-        match original.dir {
-            'U' => {
-                heap.extend(left(&original, min_move, max_move, &grid));
-                heap.extend(right(&original, min_move, max_move, &grid));
-                heap.extend(down(&original, min_move, max_move, &grid));
-            }
-            'R' => {
-                heap.extend(left(&original, min_move, max_move, &grid));
-                heap.extend(up(&original, min_move, max_move, &grid));
-                heap.extend(down(&original, min_move, max_move, &grid));
-            }
-            'D' => {
-                heap.extend(left(&original, min_move, max_move, &grid));
-                heap.extend(right(&original, min_move, max_move, &grid));
-                heap.extend(up(&original, min_move, max_move, &grid));
-            }
-            'L' => {
-                heap.extend(up(&original, min_move, max_move, &grid));
-                heap.extend(right(&original, min_move, max_move, &grid));
-                heap.extend(down(&original, min_move, max_move, &grid));
+/// The cheapest way found, and each block moved into on the way
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Route {
+    cost: usize,
+    steps: Vec<(Coord, Dir)>,
+}
+
+/// The cheapest route across a `size` grid from `start` to `goal` under `policy`.
+/// The first move can be in any direction. The start only counts as the goal if no
+/// run is needed to stop there.
+fn find_path<C>(
+    size: (usize, usize),
+    start: Coord,
+    goal: Coord,
+    policy: &Policy<C>,
+) -> Option<Route>
+where
+    C: Fn(Coord, Dir, usize) -> Option<usize>,
+{
+    // Where the crucible is, which way it's heading and how far it's come that way
+    type State = (Coord, Option<Dir>, usize);
+
+    let neighbours = |&(at, dir, run): &State| {
+        let heading = match dir {
+            // Straight on if it can, or turn if it's gone far enough
+            Some(d) => {
+                let straight = (run < policy.max_run).then_some(d);
+                let turns = policy.turns.iter().map(move |&t| d.turn(t));
+                straight
+                    .into_iter()
+                    .chain(turns.filter(|_| run >= policy.min_run))
+                    .collect()
             }
-            _ => unreachable!(),
-        }
+            None => Dir::ALL.to_vec(),
+        };
+        heading
+            .into_iter()
+            .filter_map(|d| {
+                let next = d.step(at, size)?;
+                let run = if dir == Some(d) { run + 1 } else { 1 };
+                let cost = (policy.cost)(next, d, run)?;
+                Some(((next, Some(d), run), cost))
+            })
+            .collect::<Vec<_>>()
+    };
+    let is_goal =
+        |&(at, _, run): &State| at == goal && (!policy.stop_needs_min_run || run >= policy.min_run);
+
+    let (states, cost) = dijkstra((start, None, 0), neighbours, is_goal)?;
+    let steps = states
+        .into_iter()
+        .filter_map(|(at, dir, _)| Some((at, dir?)))
+        .collect();
+    Some(Route { cost, steps })
+}
 
-        // Only keep those states that can be better than the current best
-        heap.retain(|i| (i.heat_loss + ((l_row - i.row) + (l_col - i.col))) <= best);
+/// Draw the grid with the route's steps as arrows, as the puzzle does
+fn render(grid: &Grid, route: &Route) -> String {
+    let mut out: Vec<Vec<char>> = grid
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|&h| char::from_digit(u32::try_from(h).unwrap_or(u32::MAX), 10).unwrap_or('?'))
+                .collect()
+        })
+        .collect();
+    for &((row, col), dir) in &route.steps {
+        out[row][col] = dir.arrow();
     }
+    grid_string(&out)
+}
 
-    best
+/// The cheapest way from the top left to the bottom right
+fn crossing<C>(grid: &Grid, policy: &Policy<C>) -> AResult<Route>
+where
+    C: Fn(Coord, Dir, usize) -> Option<usize>,
+{
+    let size = (grid.len(), grid[0].len());
+    find_path(size, (0, 0), (size.0 - 1, size.1 - 1), policy)
+        .ok_or_else(|| anyhow!("there is no way to the bottom right"))
 }
 
-fn part_a(lines: &[String]) -> usize {
-    solve(lines, 1, 3)
+fn part_a(lines: &[String]) -> AResult<usize> {
+    let grid = parse(lines)?;
+    let route = crossing(&grid, &Policy::crucible(heat_loss(&grid)))?;
+    Ok(route.cost)
 }
 
-fn part_b(lines: &[String]) -> usize {
-    solve(lines, 4, 10)
+fn part_b(lines: &[String]) -> AResult<usize> {
+    let grid = parse(lines)?;
+    let route = crossing(&grid, &Policy::ultra_crucible(heat_loss(&grid)))?;
+    Ok(route.cost)
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -244,16 +219,47 @@ fn main() -> AResult<()> {
         .find(name)
         .expect("binary name should contain a number")
         .as_str();
-    println!("Running code for Day {ex}.");
+    // `day_17 --draw` shows the routes both crucibles take, and `--draw --reverse`
+    // lets them turn back on themselves at the end of a run as well
+    let draw = std::env::args().any(|arg| arg == "--draw");
+    let reverse = std::env::args().any(|arg| arg == "--reverse");
+    if !draw {
+        println!("Running code for Day {ex}.");
+    }
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
+
+    if draw {
+        let grid = parse(lines.as_slice()).map_err(in_file(&path))?;
+        let policies = [
+            Policy::crucible(heat_loss(&grid)),
+            Policy::ultra_crucible(heat_loss(&grid)),
+        ];
+        for (i, mut policy) in policies.into_iter().enumerate() {
+            if reverse {
+                policy.turns.push(Turn::Back);
+            }
+            let route = crossing(&grid, &policy)?;
+            if i > 0 {
+                println!();
+            }
+            println!("{}", render(&grid, &route));
+        }
+        return Ok(());
+    }
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -280,180 +286,111 @@ mod tests {
     4322674655533";
 
     #[test]
-    fn test_dir_left() {
-        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        let grid = parse(&lines);
-
-        let orig = State {
-            col: 3,
-            row: 0,
-            dir: 'X',
-            path: vec![],
-            heat_loss: 0,
-        };
-        let t1 = State {
-            col: 2,
-            dir: 'L',
-            path: vec![(0, 2)],
-            heat_loss: 1,
-            ..orig.clone()
-        };
-        assert_eq!(left(&orig, 1, 1, &grid), &[t1.clone()]);
-
-        let t2 = State {
-            col: 1,
-            dir: 'L',
-            path: vec![(0, 2), (0, 1)],
-            heat_loss: 5,
-            ..orig.clone()
-        };
-        assert_eq!(left(&orig, 1, 2, &grid), &[t1.clone(), t2.clone()]);
-
-        let t3 = State {
-            row: 0,
-            col: 0,
-            dir: 'L',
-            path: vec![(0, 2), (0, 1), (0, 0)],
-            heat_loss: 7,
-        };
+    fn test_parse_errors() {
+        let lines = ["2413".to_string(), "32x5".to_string()];
         assert_eq!(
-            left(&orig, 1, 10, &grid),
-            &[t1.clone(), t2.clone(), t3.clone()]
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 3: expected a digit, found 'x'"
         );
 
-        assert_eq!(left(&orig, 2, 10, &grid), &[t2.clone(), t3.clone()]);
-
-        assert_eq!(left(&orig, 5, 5, &grid), &[]);
-    }
-
-    #[test]
-    fn test_dir_right() {
-        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        let grid = parse(&lines);
-
-        let orig = State {
-            col: 10,
-            row: 0,
-            dir: 'X',
-            path: vec![],
-            heat_loss: 0,
-        };
-        let t1 = State {
-            col: 11,
-            dir: 'R',
-            path: vec![(0, 11)],
-            heat_loss: 2,
-            ..orig.clone()
-        };
-        assert_eq!(right(&orig, 1, 1, &grid), &[t1.clone()]);
-
-        let t2 = State {
-            col: 12,
-            dir: 'R',
-            path: vec![(0, 11), (0, 12)],
-            heat_loss: 5,
-            ..orig.clone()
-        };
         assert_eq!(
-            right(&orig, 1, 2, &grid),
-            &[t1.clone(), t2.clone()],
-            "simple two test"
+            parse(&[]).unwrap_err().to_string(),
+            "line 1, column 1: expected a row of the city, found end of input"
         );
-
         assert_eq!(
-            right(&orig, 1, 10, &grid),
-            &[t1.clone(), t2.clone()],
-            "overflow max test"
+            parse(&[String::new()]).unwrap_err().to_string(),
+            "line 1, column 1: expected a row of the city, found end of line"
         );
-
-        assert_eq!(right(&orig, 2, 2, &grid), &[t2.clone()], "min length test");
-
-        assert_eq!(right(&orig, 5, 5, &grid), &[], "too many test");
     }
 
     #[test]
-    fn test_dir_up() {
+    fn test_render() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        let grid = parse(&lines);
-
-        let orig = State {
-            col: 0,
-            row: 2,
-            dir: 'X',
-            path: vec![],
-            heat_loss: 0,
-        };
-        let t1 = State {
-            row: 1,
-            dir: 'U',
-            path: vec![(1, 0)],
-            heat_loss: 3,
-            ..orig.clone()
-        };
-        assert_eq!(up(&orig, 1, 1, &grid), &[t1.clone()]);
-
-        let t2 = State {
-            row: 0,
-            dir: 'U',
-            path: vec![(1, 0), (0, 0)],
-            heat_loss: 5,
-            ..orig.clone()
-        };
-        assert_eq!(up(&orig, 1, 2, &grid), &[t1.clone(), t2.clone()]);
-
-        assert_eq!(up(&orig, 1, 10, &grid), &[t1.clone(), t2.clone()]);
+        let grid = parse(&lines)?;
 
-        assert_eq!(up(&orig, 2, 10, &grid), &[t2.clone()]);
+        let route = crossing(&grid, &Policy::crucible(heat_loss(&grid)))?;
+        assert_eq!(route.cost, 102);
+        assert_eq!(route.steps.len(), 28);
+        assert_eq!(
+            render(&grid, &route),
+            "2>>34^>>>1323
+32v>>>35v5623
+32552456v>>54
+3446585845v52
+4546657867v>6
+14385987984v4
+44578769877v6
+36378779796v>
+465496798688v
+456467998645v
+12246868655<v
+25465488877v5
+43226746555v>"
+        );
 
-        assert_eq!(up(&orig, 5, 5, &grid), &[]);
+        let route = crossing(&grid, &Policy::ultra_crucible(heat_loss(&grid)))?;
+        assert_eq!(
+            render(&grid, &route),
+            "2>>>>>>>>1323
+32154535v5623
+32552456v4254
+34465858v5452
+45466578v>>>>
+143859879845v
+445787698776v
+363787797965v
+465496798688v
+456467998645v
+122468686556v
+254654888773v
+432267465553v"
+        );
+        Ok(())
     }
 
     #[test]
-    fn test_dir_down() {
+    fn test_policy() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        let grid = parse(&lines);
-
-        let orig = State {
-            col: 0,
-            row: 10,
-            dir: 'X',
-            path: vec![],
-            heat_loss: 0,
-        };
-        let t1 = State {
-            row: 11,
-            dir: 'D',
-            path: vec![(11, 0)],
-            heat_loss: 2,
-            ..orig.clone()
+        let grid = parse(&lines)?;
+
+        // Counting blocks rather than heat, zig-zagging in threes is as short as it gets
+        let route = crossing(&grid, &Policy::crucible(|_, _, _| Some(1)))?;
+        assert_eq!(route.cost, 24);
+        let route = crossing(&grid, &Policy::crucible(|_, _, _| None));
+        assert!(route.is_err());
+
+        // Doubling back is only possible if U-turns are allowed
+        let line = parse(&["123".to_string()])?;
+        let mut policy = Policy {
+            turns: vec![Turn::Left, Turn::Right],
+            min_run: 2,
+            max_run: 2,
+            stop_needs_min_run: true,
+            cost: heat_loss(&line),
         };
-        assert_eq!(down(&orig, 1, 1, &grid), &[t1.clone()]);
-
-        let t2 = State {
-            row: 12,
-            dir: 'D',
-            path: vec![(11, 0), (12, 0)],
-            heat_loss: 6,
-            ..orig.clone()
-        };
-        assert_eq!(down(&orig, 1, 3, &grid), &[t1.clone(), t2.clone()]);
-
-        assert_eq!(down(&orig, 1, 10, &grid), &[t1.clone(), t2.clone()]);
-
-        assert_eq!(down(&orig, 2, 10, &grid), &[t2.clone()]);
-
-        assert_eq!(down(&orig, 5, 5, &grid), &[]);
+        assert_eq!(find_path((1, 3), (0, 0), (0, 0), &policy), None);
+        policy.turns.push(Turn::Back);
+        let route = find_path((1, 3), (0, 0), (0, 0), &policy).unwrap();
+        assert_eq!(route.cost, 2 + 3 + 2 + 1);
+        assert_eq!(render(&line, &route), "<<>");
+
+        // Without needing a run to stop, it's there already
+        policy.stop_needs_min_run = false;
+        assert_eq!(find_path((1, 3), (0, 0), (0, 0), &policy).unwrap().cost, 0);
+        Ok(())
     }
 
     #[test]
-    fn test_a() {
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 102);
+        assert_eq!(part_a(lines.as_slice())?, 102);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(&lines), 94);
+        assert_eq!(part_b(&lines)?, 94);
+        Ok(())
     }
 }
@@ -1,15 +1,51 @@
+use advent_common::parsing::{in_file, parse_lines, read_lines, uint, PResult};
 use humantime::format_duration;
-use regex::Regex;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
+use nom::{
+    bytes::complete::{tag, take_while_m_n},
+    character::complete::{one_of, space1},
+    combinator::map_res,
+    error::context,
+    sequence::{delimited, pair, tuple},
 };
+use regex::Regex;
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
 
+#[derive(Debug)]
 struct Direction(char, isize);
 
+// Each line gives the plan for part a and, hidden in the colour, the plan for part b
+fn dig_step(input: &str) -> PResult<'_, (Direction, Direction)> {
+    let (rest, (dir, _, dist, _, (hex_dist, hex_dir))) = tuple((
+        context("a direction", one_of("RDLU")),
+        space1,
+        uint,
+        space1,
+        delimited(
+            context("\"(#\"", tag("(#")),
+            pair(
+                context(
+                    "5 hex digits",
+                    map_res(take_while_m_n(5, 5, |c: char| c.is_ascii_hexdigit()), |h| {
+                        isize::from_str_radix(h, 16)
+                    }),
+                ),
+                context("a direction digit", one_of("0123")),
+            ),
+            context("')'", tag(")")),
+        ),
+    ))(input)?;
+
+    let lookup: Vec<_> = "RDLU".chars().collect();
+    let hex_dir = lookup[hex_dir.to_digit(10).unwrap() as usize];
+    Ok((rest, (Direction(dir, dist), Direction(hex_dir, hex_dist))))
+}
+
+fn parse(lines: &[String]) -> AResult<(Vec<Direction>, Vec<Direction>)> {
+    Ok(parse_lines(lines, dig_step)?.into_iter().unzip())
+}
+
 fn calculate_area(directions: &[Direction]) -> isize {
     let mut vertices = vec![(0, 0)];
     let mut length = 0isize;
@@ -38,37 +74,13 @@ fn calculate_area(directions: &[Direction]) -> isize {
     1 + (area.abs() + length) / 2
 }
 
-fn part_a(lines: &[String]) -> isize {
-    let directions: Vec<_> = lines
-        .iter()
-        .filter_map(|l| {
-            let mut bits = l.split_whitespace();
-            Some(Direction(
-                bits.next()?.chars().next()?,
-                bits.next()?.parse().ok()?,
-            ))
-        })
-        .collect();
-
-    calculate_area(&directions)
+fn part_a(lines: &[String]) -> AResult<isize> {
+    let (directions, _) = parse(lines)?;
+    Ok(calculate_area(&directions))
 }
 
 fn part_b(lines: &[String]) -> AResult<isize> {
-    let hex_pattern = Regex::new(r"\(#(\w{5})(\w)\)")?;
-    let lookup: Vec<_> = "RDLU".chars().collect();
-    let directions: Vec<_> = lines
-        .iter()
-        .filter_map(|l| {
-            let caps = hex_pattern.captures(l)?;
-            let dist_str = caps.get(1)?.as_str();
-            let dir_str = caps.get(2)?.as_str();
-
-            Some(Direction(
-                lookup[dir_str.parse::<usize>().ok()?],
-                isize::from_str_radix(dist_str, 16).ok()?,
-            ))
-        })
-        .collect();
+    let (_, directions) = parse(lines)?;
     Ok(calculate_area(&directions))
 }
 
@@ -84,13 +96,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice())?);
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -118,9 +136,37 @@ mod tests {
     U 2 (#7a21e3)";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines = ["R 6 (#70c710)".to_string(), "X 5 (#0dc571)".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 1: expected a direction, found \"X 5 (#0dc571)\""
+        );
+
+        let lines = ["R 6 (70c710)".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 5: expected \"(#\", found \"(70c710)\""
+        );
+
+        let lines = ["R 6 (#70c7g0)".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 7: expected 5 hex digits, found \"70c7g0)\""
+        );
+
+        let lines = ["R 6 (#70c714)".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 12: expected a direction digit, found \"4)\""
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 62);
+        assert_eq!(part_a(lines.as_slice())?, 62);
+        Ok(())
     }
 
     #[test]
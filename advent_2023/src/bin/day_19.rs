@@ -1,245 +1,390 @@
+use advent_common::parsing::{exact_blocks, in_file, read_lines, uint, PResult, ParseError};
+use anyhow::anyhow;
 use humantime::format_duration;
-use lazy_static::lazy_static;
 use nom::{
     branch::alt,
     bytes::complete::tag,
-    character::complete::{alpha1, digit1, newline},
-    combinator::map,
-    combinator::map_res,
+    character::complete::alpha1,
+    combinator::{cut, map, value},
+    error::context,
     multi::separated_list1,
     sequence::{delimited, separated_pair, tuple},
-    IResult,
 };
 use regex::Regex;
 use std::{
-    collections::HashMap,
-    fmt::Debug,
-    fs::File,
-    io::{BufRead, BufReader},
+    collections::{BTreeSet, HashMap, HashSet},
     time::Instant,
 };
 
 type AResult<T> = anyhow::Result<T>;
 
-lazy_static! {
-    static ref RULE_PATTERN: Regex = Regex::new(r"(\w+)([<>])(\d+)").unwrap();
+/// How a rule compares one of a part's ratings with its value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cmp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
 }
 
-#[derive(Debug, Clone)]
-struct Workflow(String, Vec<Instruction>);
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Condition {
+    dim: String,
+    cmp: Cmp,
+    value: usize,
+}
+
+impl Condition {
+    /// The inclusive range of ratings that pass, if any can
+    fn range(&self) -> Option<(usize, usize)> {
+        match self.cmp {
+            Cmp::Lt => Some((0, self.value.checked_sub(1)?)),
+            Cmp::Le => Some((0, self.value)),
+            Cmp::Gt => Some((self.value.checked_add(1)?, usize::MAX)),
+            Cmp::Ge => Some((self.value, usize::MAX)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    // No condition always sends the part to the target
+    condition: Option<Condition>,
+    target: String,
+}
+
+impl std::fmt::Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(Condition { dim, cmp, value }) = &self.condition {
+            let cmp = match cmp {
+                Cmp::Lt => "<",
+                Cmp::Le => "<=",
+                Cmp::Gt => ">",
+                Cmp::Ge => ">=",
+            };
+            write!(f, "{dim}{cmp}{value}:")?;
+        }
+        write!(f, "{}", self.target)
+    }
+}
 
 #[derive(Debug, Clone)]
-#[allow(clippy::upper_case_acronyms)]
-enum Instruction {
-    LT(String, usize, String),
-    GT(String, usize, String),
-    JMP(String),
+struct Workflow(String, Vec<Rule>);
+
+/// An inclusive range of ratings for each dimension, in the engine's order
+type Rect = Vec<(usize, usize)>;
+
+fn volume(rect: &Rect) -> usize {
+    rect.iter().map(|(lo, hi)| hi - lo + 1).product()
 }
 
+/// The workflows, along with every dimension that they or the parts rate
 #[derive(Debug)]
-struct Item {
-    fields: HashMap<String, usize>,
+struct Engine {
+    dims: Vec<String>,
+    workflows: HashMap<String, Workflow>,
 }
 
 //
 // Nom Parsing Functions
 //
 
-fn parse_rule(input: &str) -> IResult<&str, Instruction> {
-    map_res(
+fn parse_rule(input: &str) -> PResult<'_, Rule> {
+    map(
         separated_pair(
-            tuple((alpha1::<&str, _>, alt((tag("<"), tag(">"))), digit1)),
-            tag(":"),
-            alpha1,
+            tuple((
+                alpha1,
+                alt((
+                    value(Cmp::Le, tag("<=")),
+                    value(Cmp::Ge, tag(">=")),
+                    value(Cmp::Lt, tag("<")),
+                    value(Cmp::Gt, tag(">")),
+                )),
+                uint,
+            )),
+            context("':'", tag(":")),
+            context("a workflow name", alpha1),
         ),
-        |((field, op, val_str), target)| {
-            val_str.parse().map(|val| match op {
-                "<" => Instruction::LT(field.to_string(), val, target.to_string()),
-                ">" => Instruction::GT(field.to_string(), val, target.to_string()),
-                _ => unreachable!(),
-            })
+        |((dim, cmp, value), target)| Rule {
+            condition: Some(Condition {
+                dim: dim.to_string(),
+                cmp,
+                value,
+            }),
+            target: target.to_string(),
         },
     )(input)
 }
 
-fn parse_jmp(input: &str) -> IResult<&str, Instruction> {
-    map(alpha1, |s: &str| Instruction::JMP(s.to_string()))(input)
+fn parse_jmp(input: &str) -> PResult<'_, Rule> {
+    map(context("a workflow name", alpha1), |s: &str| Rule {
+        condition: None,
+        target: s.to_string(),
+    })(input)
 }
 
-fn parse_workflow(input: &str) -> IResult<&str, Workflow> {
+fn parse_workflow(input: &str) -> PResult<'_, Workflow> {
     map(
         tuple((
-            alpha1,
+            context("a workflow name", alpha1),
             delimited(
-                tag("{"),
-                separated_list1(tag(","), alt((parse_rule, parse_jmp))),
-                tag("}"),
+                context("'{'", tag("{")),
+                separated_list1(tag(","), cut(alt((parse_rule, parse_jmp)))),
+                context("',' or '}'", tag("}")),
             ),
         )),
-        |(name, instrs)| Workflow(name.to_string(), instrs),
+        |(name, rules)| Workflow(name.to_string(), rules),
     )(input)
 }
 
-fn parse_workflows(input: &str) -> IResult<&str, Vec<Workflow>> {
-    separated_list1(newline, parse_workflow)(input)
-}
-
-fn parse_item(input: &str) -> IResult<&str, Item> {
-    map(
-        delimited(
-            tag("{"),
-            separated_list1(
-                tag(","),
-                separated_pair(
-                    alpha1,
-                    tag("="),
-                    map_res(digit1, |s: &str| s.parse::<usize>()),
-                ),
-            ),
-            tag("}"),
+fn parse_item(input: &str) -> PResult<'_, Vec<(&str, usize)>> {
+    delimited(
+        context("'{'", tag("{")),
+        separated_list1(
+            tag(","),
+            cut(separated_pair(
+                context("a rating name", alpha1),
+                context("'='", tag("=")),
+                uint,
+            )),
         ),
-        |input| {
-            let mut fields = HashMap::new();
-            for (n, v) in input {
-                fields.insert(n.to_string(), v);
-            }
-            Item { fields }
-        },
+        context("',' or '}'", tag("}")),
     )(input)
 }
 
-fn parse_items(input: &str) -> IResult<&str, Vec<Item>> {
-    separated_list1(newline, parse_item)(input)
-}
-
 //
 // End of nom parsing functions
 //
 
-fn parse(lines: &[String]) -> (HashMap<String, Workflow>, Vec<Item>) {
-    let mut s = lines.split(String::is_empty);
-    let wf_lines = s.next().unwrap().join("\n");
-    let (_, wf_list) = parse_workflows(&wf_lines).unwrap();
-    let mut workflows = HashMap::new();
-    for wf in wf_list {
-        workflows.insert(wf.0.clone(), wf);
-    }
-
-    let i_lines = s.next().unwrap().join("\n");
-    let (_, items) = parse_items(&i_lines).unwrap();
-
-    (workflows, items)
-}
+/// The workflows and the parts, each rated in the engine's order of dimensions
+fn parse(lines: &[String]) -> AResult<(Engine, Vec<Vec<usize>>)> {
+    let [wf_block, item_block] = exact_blocks::<2>(lines, "the parts")?;
 
-fn execute(workflows: &HashMap<String, Workflow>, wf_name: &str, item: &Item) -> bool {
-    if wf_name == "A" {
-        return true;
-    } else if wf_name == "R" {
-        return false;
+    let parsed = wf_block.parse_lines(parse_workflow)?;
+    let mut workflows = HashMap::new();
+    for wf in &parsed {
+        workflows.insert(wf.0.clone(), wf.clone());
     }
 
-    if let Some(Workflow(_, instructions)) = workflows.get(wf_name) {
-        for instr in instructions {
-            match instr {
-                Instruction::LT(f, v, t) => {
-                    if let Some(curr) = item.fields.get(f) {
-                        if curr < v {
-                            return execute(workflows, t, item);
-                        }
-                    }
-                }
-                Instruction::GT(f, v, t) => {
-                    if let Some(curr) = item.fields.get(f) {
-                        if curr > v {
-                            return execute(workflows, t, item);
-                        }
-                    }
-                }
-                Instruction::JMP(t) => return execute(workflows, t, item),
+    // Every rule has to send parts somewhere, and they all start at "in"
+    for (idx, Workflow(_, rules)) in parsed.iter().enumerate() {
+        let line = &wf_block.lines[idx];
+        for Rule { target: t, .. } in rules {
+            if t != "A" && t != "R" && !workflows.contains_key(t) {
+                Err(ParseError::new(
+                    wf_block.start + idx + 1,
+                    line.rfind(t.as_str()).unwrap_or_default() + 1,
+                    "a known workflow, A or R",
+                    format!("{t:?}"),
+                ))?;
             }
         }
     }
-    false
-}
-
-fn part_a(lines: &[String]) -> usize {
-    let (workflows, items) = parse(lines);
-    let mut acc = 0;
-    for item in items {
-        if execute(&workflows, "in", &item) {
-            acc += item.fields.values().sum::<usize>();
-        }
+    if !workflows.contains_key("in") {
+        return Err(anyhow!("there is no workflow named in to start from"));
     }
 
-    acc
-}
+    let items = item_block.parse_lines(parse_item)?;
+
+    // The dimensions are every rating named anywhere, in alphabetical order
+    let dims: BTreeSet<_> = parsed
+        .iter()
+        .flat_map(|Workflow(_, rules)| rules.iter().filter_map(|r| r.condition.as_ref()))
+        .map(|c| c.dim.as_str())
+        .chain(items.iter().flatten().map(|(dim, _)| *dim))
+        .collect();
+    let dims: Vec<_> = dims.into_iter().map(str::to_string).collect();
+
+    let items = items
+        .iter()
+        .enumerate()
+        .map(|(idx, ratings)| {
+            let mut sorted = ratings.clone();
+            sorted.sort_unstable();
+            if !sorted.iter().map(|(dim, _)| dim).eq(&dims) {
+                Err(ParseError::new(
+                    item_block.start + idx + 1,
+                    1,
+                    format!("one rating for each of {}", dims.join(", ")),
+                    format!("{:?}", item_block.lines[idx]),
+                ))?;
+            }
+            Ok(sorted.into_iter().map(|(_, v)| v).collect())
+        })
+        .collect::<AResult<_>>()?;
 
-fn combinations(ranges: &HashMap<String, (usize, usize)>) -> usize {
-    let (x_min, x_max) = ranges["x"];
-    let (m_min, m_max) = ranges["m"];
-    let (a_min, a_max) = ranges["a"];
-    let (s_min, s_max) = ranges["s"];
-    (1 + x_max - x_min) * (1 + m_max - m_min) * (1 + a_max - a_min) * (1 + s_max - s_min)
+    Ok((Engine::new(dims, workflows)?, items))
 }
 
-fn count_combinations(
-    workflows: &HashMap<String, Workflow>,
-    wf_name: &str,
-    ranges: HashMap<String, (usize, usize)>,
-) -> usize {
-    if wf_name == "R" {
-        return 0;
-    }
-
-    if wf_name == "A" {
-        return combinations(&ranges);
+impl Engine {
+    fn new(dims: Vec<String>, workflows: HashMap<String, Workflow>) -> AResult<Self> {
+        let engine = Engine { dims, workflows };
+        if let Some(cycle) = engine.find_cycle() {
+            return Err(anyhow!(
+                "workflow {} sends parts back to itself: {}",
+                cycle[0],
+                cycle.join(" -> ")
+            ));
+        }
+        Ok(engine)
     }
 
-    let mut n_comb = 0;
-    let wf = workflows[wf_name].clone();
-    let mut remaining = ranges;
+    /// A loop of workflows that could send parts round forever, if there is one
+    fn find_cycle(&self) -> Option<Vec<&str>> {
+        // Depth first from each workflow, looking for one already on the path
+        fn visit<'a>(
+            engine: &'a Engine,
+            name: &'a str,
+            path: &mut Vec<&'a str>,
+            done: &mut HashSet<&'a str>,
+        ) -> Option<Vec<&'a str>> {
+            if let Some(pos) = path.iter().position(|&p| p == name) {
+                let mut cycle = path[pos..].to_vec();
+                cycle.push(name);
+                return Some(cycle);
+            }
+            let Workflow(_, rules) = engine.workflows.get(name)?;
+            if !done.insert(name) {
+                return None;
+            }
+            path.push(name);
+            let cycle = rules
+                .iter()
+                .find_map(|r| visit(engine, &r.target, path, done));
+            path.pop();
+            cycle
+        }
 
-    for instr in wf.1 {
-        n_comb += match instr {
-            Instruction::LT(f, v, t) => {
-                let c_range = remaining[&f];
+        let mut names: Vec<_> = self.workflows.keys().collect();
+        names.sort_unstable();
+        let mut done = HashSet::new();
+        names
+            .into_iter()
+            .find_map(|name| visit(self, name, &mut Vec::new(), &mut done))
+    }
 
-                // The New Ranges (n_ranges) are those which pass the condition
-                let mut n_ranges = remaining.clone();
-                n_ranges.insert(f.clone(), (c_range.0, v - 1));
+    fn dim(&self, name: &str) -> usize {
+        self.dims.iter().position(|d| d == name).unwrap()
+    }
 
-                // Update the remaining ranges with what's left in preparation for the next loop
-                remaining.insert(f, (v, c_range.1));
+    /// Whether a single part ends up accepted
+    fn accepts(&self, part: &[usize]) -> bool {
+        let mut name = "in";
+        while let Some(Workflow(_, rules)) = self.workflows.get(name) {
+            // A part that matches no rule at all is rejected
+            let Some(rule) = rules.iter().find(|r| {
+                r.condition.as_ref().is_none_or(|c| {
+                    c.range()
+                        .is_some_and(|(lo, hi)| (lo..=hi).contains(&part[self.dim(&c.dim)]))
+                })
+            }) else {
+                return false;
+            };
+            name = &rule.target;
+        }
+        name == "A"
+    }
 
-                // Finally, count the combinations for the selected branch over n_ranges
-                count_combinations(workflows, &t, n_ranges)
+    /// Send every part within `bounds` through the workflows, calling `fired` with each
+    /// rule that some of them match and the parts that it matches
+    fn route<'a>(&'a self, bounds: Rect, mut fired: impl FnMut(&'a str, usize, &Rect)) {
+        let mut queue = vec![("in", bounds)];
+        while let Some((name, mut rest)) = queue.pop() {
+            let Some(Workflow(_, rules)) = self.workflows.get(name) else {
+                continue;
+            };
+
+            for (idx, rule) in rules.iter().enumerate() {
+                let (matched, unmatched) = match &rule.condition {
+                    None => (Some(rest.clone()), None),
+                    Some(c) => self.split(&rest, c),
+                };
+                if let Some(matched) = matched {
+                    fired(name, idx, &matched);
+                    queue.push((&rule.target, matched));
+                }
+                let Some(unmatched) = unmatched else { break };
+                rest = unmatched;
             }
-            Instruction::GT(f, v, t) => {
-                let c_range = remaining[&f];
+        }
+    }
 
-                // New Ranges
-                let mut n_ranges = remaining.clone();
-                n_ranges.insert(f.clone(), (v + 1, c_range.1));
+    /// Split `rect` into the parts that pass `condition` and those that don't
+    fn split(&self, rect: &Rect, condition: &Condition) -> (Option<Rect>, Option<Rect>) {
+        let dim = self.dim(&condition.dim);
+        let (lo, hi) = rect[dim];
+        let with = |lo: usize, hi: usize| {
+            (lo <= hi).then(|| {
+                let mut rect = rect.clone();
+                rect[dim] = (lo, hi);
+                rect
+            })
+        };
+
+        let Some((from, to)) = condition.range() else {
+            return (None, Some(rect.clone()));
+        };
+        // Every condition's range runs to one end or the other, so what's left is one piece
+        let rest = if from > 0 {
+            with(lo, hi.min(from - 1))
+        } else if to < usize::MAX {
+            with(lo.max(to + 1), hi)
+        } else {
+            None
+        };
+        (with(lo.max(from), hi.min(to)), rest)
+    }
 
-                // Update the remaining ranges
-                remaining.insert(f, (c_range.0, v));
-                count_combinations(workflows, &t, n_ranges)
+    /// The disjoint hyper-rectangles of parts within `bounds` that are accepted
+    fn accepted(&self, bounds: Rect) -> Vec<Rect> {
+        let mut accepted = Vec::new();
+        self.route(bounds, |name, idx, rect| {
+            if self.workflows[name].1[idx].target == "A" {
+                accepted.push(rect.clone());
             }
-            Instruction::JMP(t) => count_combinations(workflows, &t, remaining.clone()),
-        }
+        });
+        accepted
     }
 
-    n_comb
+    /// Each workflow's rules that no part within `bounds` can ever reach and match
+    fn dead_rules(&self, bounds: Rect) -> Vec<(&str, &Rule)> {
+        let mut fired = HashSet::new();
+        self.route(bounds, |name, idx, _| {
+            fired.insert((name, idx));
+        });
+
+        let mut dead: Vec<_> = self
+            .workflows
+            .iter()
+            .flat_map(|(name, Workflow(_, rules))| {
+                rules
+                    .iter()
+                    .enumerate()
+                    .filter(|(idx, _)| !fired.contains(&(name.as_str(), *idx)))
+                    .map(|(idx, rule)| (name.as_str(), idx, rule))
+            })
+            .collect();
+        dead.sort_unstable_by_key(|&(name, idx, _)| (name, idx));
+        dead.into_iter()
+            .map(|(name, _, rule)| (name, rule))
+            .collect()
+    }
+}
+
+fn part_a(lines: &[String]) -> AResult<usize> {
+    let (engine, items) = parse(lines)?;
+    Ok(items
+        .iter()
+        .filter(|item| engine.accepts(item))
+        .map(|item| item.iter().sum::<usize>())
+        .sum())
 }
 
-fn part_b(lines: &[String]) -> usize {
+fn part_b(lines: &[String]) -> AResult<usize> {
     // Lets walk the workflows with ranges...
-    let (workflows, _) = parse(lines);
-    let mut ranges = HashMap::new();
-    for c in "xmas".chars() {
-        ranges.insert(format!("{c}"), (1, 4000));
-    }
-    count_combinations(&workflows, "in", ranges)
+    let (engine, _) = parse(lines)?;
+    let bounds = vec![(1, 4000); engine.dims.len()];
+    Ok(engine.accepted(bounds).iter().map(volume).sum())
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -251,16 +396,35 @@ fn main() -> AResult<()> {
         .find(name)
         .expect("binary name should contain a number")
         .as_str();
-    println!("Running code for Day {ex}.");
+    // `day_19 --lint` lists the rules that no part can ever match instead of solving
+    let lint = std::env::args().any(|arg| arg == "--lint");
+    if !lint {
+        println!("Running code for Day {ex}.");
+    }
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
+
+    if lint {
+        let (engine, _) = parse(lines.as_slice()).map_err(in_file(&path))?;
+        let bounds = vec![(1, 4000); engine.dims.len()];
+        for (name, rule) in engine.dead_rules(bounds) {
+            println!("{name}: {rule} can never fire");
+        }
+        return Ok(());
+    }
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -271,6 +435,7 @@ fn main() -> AResult<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::iter::zip;
 
     const TEST_INPUT: &str = "px{a<2006:qkq,m>2090:A,rfg}
     pv{a>1716:R,A}
@@ -291,14 +456,133 @@ mod tests {
     {x=2127,m=1623,a=2188,s=1013}";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 19114);
+
+        let mut broken = lines.clone();
+        broken[0] = "px{a<2006:qkq,m=2090:A,rfg}".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 1, column 16: expected ',' or '}', found \"=2090:A,rfg}\""
+        );
+
+        let mut broken = lines.clone();
+        broken[14] = "{x=1679,m=44,a=2067}".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 15, column 1: expected one rating for each of a, m, s, x, found \"{x=1679,m=44,a=2067}\""
+        );
+
+        let mut broken = lines.clone();
+        broken[1] = "pv{a>1716:R,A".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 2, column 14: expected ',' or '}', found end of line"
+        );
+
+        let mut broken = lines.clone();
+        broken[1] = "pv{a>1716:R,xyz}".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 2, column 13: expected a known workflow, A or R, found \"xyz\""
+        );
+
+        let mut broken = lines.clone();
+        broken[13] = "{x=787,m:2655,a=1222,s=2876}".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 14, column 9: expected '=', found \":2655,a=1222,s=2876}\""
+        );
+
+        assert_eq!(
+            parse(&lines[..11]).unwrap_err().to_string(),
+            "line 12, column 1: expected the parts, found end of input"
+        );
     }
 
     #[test]
-    fn test_b() {
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(&lines), 167_409_079_868_000);
+        assert_eq!(part_a(lines.as_slice())?, 19114);
+        Ok(())
+    }
+
+    #[test]
+    fn test_b() -> AResult<()> {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        assert_eq!(part_b(&lines)?, 167_409_079_868_000);
+        Ok(())
+    }
+
+    fn engine_from(workflows: &str) -> AResult<Engine> {
+        let mut lines: Vec<_> = workflows.lines().map(|l| l.trim().to_string()).collect();
+        lines.extend(["".to_string(), "{h=1,w=1}".to_string()]);
+        Ok(parse(&lines)?.0)
+    }
+
+    #[test]
+    fn test_accepted() -> AResult<()> {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        let (engine, _) = parse(&lines)?;
+        assert_eq!(engine.dims, ["a", "m", "s", "x"]);
+
+        // The accepted parts never overlap, so their volumes add up
+        let accepted = engine.accepted(vec![(1, 4000); 4]);
+        let overlaps = |a: &Rect, b: &Rect| zip(a, b).all(|(a, b)| a.0 <= b.1 && b.0 <= a.1);
+        for (i, a) in accepted.iter().enumerate() {
+            assert!(accepted[i + 1..].iter().all(|b| !overlaps(a, b)));
+        }
+
+        // Inclusive and exclusive comparisons in other dimensions
+        let engine = engine_from(
+            "in{w<=10:A,h>=5:big,R}
+            big{w>=20:A,w<20:R}",
+        )?;
+        let mut accepted = engine.accepted(vec![(1, 30); 2]);
+        accepted.sort_unstable();
+        assert_eq!(accepted, [vec![(1, 30), (1, 10)], vec![(5, 30), (20, 30)]]);
+        assert!(engine.accepts(&[1, 10]));
+        assert!(!engine.accepts(&[4, 11]));
+        assert!(engine.accepts(&[5, 20]));
+        assert!(!engine.accepts(&[5, 19]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_dead_rules() -> AResult<()> {
+        let engine = engine_from(
+            "in{w<10:small,w>4000:R,A}
+            small{w<20:A,h<5:R,R}
+            unused{A}",
+        )?;
+        let dead: Vec<_> = engine
+            .dead_rules(vec![(1, 4000); 2])
+            .into_iter()
+            .map(|(name, rule)| format!("{name}: {rule}"))
+            .collect();
+        assert_eq!(
+            dead,
+            ["in: w>4000:R", "small: h<5:R", "small: R", "unused: A"]
+        );
+
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        let (engine, _) = parse(&lines)?;
+        assert!(engine.dead_rules(vec![(1, 4000); 4]).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cycles() {
+        assert_eq!(
+            engine_from(
+                "in{w<10:a,A}
+                a{h<=5:R,b}
+                b{c}
+                c{w>3:a,R}"
+            )
+            .unwrap_err()
+            .to_string(),
+            "workflow a sends parts back to itself: a -> b -> c -> a"
+        );
     }
 }
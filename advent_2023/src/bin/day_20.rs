@@ -1,10 +1,22 @@
+use advent_common::{
+    math::lcm_all,
+    parsing::{in_file, parse_lines, read_lines, PResult},
+};
+use anyhow::anyhow;
 use humantime::format_duration;
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{alpha1, one_of},
+    combinator::{cut, map, success},
+    error::context,
+    multi::separated_list1,
+    sequence::{pair, preceded},
+};
 use regex::Regex;
 use std::{
     collections::{HashMap, VecDeque},
     fmt::Debug,
-    fs::File,
-    io::{BufRead, BufReader},
     time::Instant,
 };
 
@@ -32,31 +44,40 @@ impl Debug for Send {
     }
 }
 
-fn parse(lines: &[String]) -> HashMap<String, Module> {
+fn module(input: &str) -> PResult<'_, Module> {
+    let (rest, (kind, name)) = context(
+        "a module",
+        alt((
+            // The broadcaster is the only module without a type prefix
+            pair(success(' '), tag("broadcaster")),
+            pair(one_of("%&"), alpha1),
+        )),
+    )(input)?;
+    let (rest, target_names) = preceded(
+        context("\" -> \"", tag(" -> ")),
+        separated_list1(
+            tag(", "),
+            cut(map(context("a module name", alpha1), str::to_string)),
+        ),
+    )(rest)?;
+
+    let name = name.to_string();
+    let module = match kind {
+        '%' => Module::FlipFlop(name, target_names, false),
+        '&' => Module::Conjunction(name, target_names, HashMap::new()),
+        _ => Module::Broadcast(name, target_names),
+    };
+    Ok((rest, module))
+}
+
+fn parse(lines: &[String]) -> AResult<HashMap<String, Module>> {
     let mut modules = HashMap::new();
 
-    for line in lines {
-        let mut line_split = line.split(" -> ");
-        let lhs = line_split.next().unwrap();
-        let rhs = line_split.next().unwrap();
-
-        let target_names = rhs.split(", ").map(str::to_string).collect();
-
-        if lhs == ("broadcaster") {
-            let this = Module::Broadcast("broadcaster".to_string(), target_names);
-            modules.insert("broadcaster".to_string(), this);
-        } else if lhs.starts_with('%') {
-            let name: String = lhs.chars().skip(1).collect();
-            let this = Module::FlipFlop(name.to_string(), target_names, false);
-            modules.insert(name, this);
-        } else if lhs.starts_with('&') {
-            let name: String = lhs.chars().skip(1).collect();
-            let inputs = HashMap::new();
-            let this = Module::Conjunction(name.to_string(), target_names, inputs);
-            modules.insert(name, this);
-        } else {
-            panic!("Can't parse {line}");
-        }
+    for module in parse_lines(lines, module)? {
+        let (Module::FlipFlop(name, _, _)
+        | Module::Conjunction(name, _, _)
+        | Module::Broadcast(name, _)) = &module;
+        modules.insert(name.clone(), module);
     }
 
     let all_modules: Vec<Module> = modules.values().cloned().collect();
@@ -83,162 +104,154 @@ fn parse(lines: &[String]) -> HashMap<String, Module> {
         }
     }
 
-    modules
+    Ok(modules)
+}
+
+/// Press the button once, calling `watch` with every pulse as it is delivered
+fn press(modules: &mut HashMap<String, Module>, mut watch: impl FnMut(&Send)) {
+    let mut queue = VecDeque::new();
+    queue.push_back(Send(
+        "button".to_string(),
+        "broadcaster".to_string(),
+        Signal::Low,
+    ));
+
+    while let Some(s) = queue.pop_front() {
+        watch(&s);
+        let Send(source, target_name, signal) = s;
+
+        modules.entry(target_name).and_modify(|target| {
+            let (name, targets, out) = match (target, signal) {
+                (Module::FlipFlop(name, targets, on), Signal::Low) => {
+                    *on = !*on;
+                    (name, targets, if *on { Signal::High } else { Signal::Low })
+                }
+                (Module::FlipFlop(_, _, _), Signal::High) => return,
+                (Module::Conjunction(name, targets, inputs), signal) => {
+                    inputs.insert(source, signal);
+                    if inputs.values().all(|s| s == &Signal::High) {
+                        (name, targets, Signal::Low)
+                    } else {
+                        (name, targets, Signal::High)
+                    }
+                }
+                (Module::Broadcast(name, targets), signal) => (name, targets, signal),
+            };
+            queue.extend(
+                targets
+                    .iter()
+                    .map(|tn| Send(name.clone(), tn.clone(), out.clone())),
+            );
+        });
+    }
 }
 
-fn part_a(lines: &[String]) -> usize {
-    let modules = &mut parse(lines);
+fn part_a(lines: &[String]) -> AResult<usize> {
+    let modules = &mut parse(lines)?;
 
     let mut pulse_count_low = 0;
     let mut pulse_count_high = 0;
 
     for _ in 0..1000 {
-        let mut queue = VecDeque::new();
-        queue.push_back(Send(
-            "button".to_string(),
-            "broadcaster".to_string(),
-            Signal::Low,
-        ));
-
-        while let Some(s) = queue.pop_front() {
-            let Send(source, target_name, signal) = s;
-
-            if signal == Signal::High {
+        press(modules, |Send(_, _, signal)| {
+            if signal == &Signal::High {
                 pulse_count_high += 1;
             } else {
                 pulse_count_low += 1;
             }
-
-            modules.entry(target_name.to_string()).and_modify(|target| {
-                match (source, target, signal) {
-                    (_, Module::FlipFlop(name, targets, on), Signal::Low) => {
-                        *on = !*on;
-                        if *on {
-                            queue.extend(
-                                targets.iter().map(|tn| {
-                                    Send((*name).to_string(), tn.to_string(), Signal::High)
-                                }),
-                            );
-                        } else {
-                            queue.extend(
-                                targets.iter().map(|tn| {
-                                    Send((*name).to_string(), tn.to_string(), Signal::Low)
-                                }),
-                            );
-                        }
-                    }
-                    (_, Module::FlipFlop(_, _, _), Signal::High) => (),
-                    (source, Module::Conjunction(name, targets, inputs), signal) => {
-                        inputs.insert(source.to_string(), signal);
-                        if inputs.values().all(|s| s == &Signal::High) {
-                            queue.extend(
-                                targets.iter().map(|tn| {
-                                    Send((*name).to_string(), tn.to_string(), Signal::Low)
-                                }),
-                            );
-                        } else {
-                            queue.extend(
-                                targets.iter().map(|tn| {
-                                    Send((*name).to_string(), tn.to_string(), Signal::High)
-                                }),
-                            );
-                        }
-                    }
-                    (_, Module::Broadcast(name, target_names), Signal::Low) => {
-                        queue.extend(
-                            target_names
-                                .iter()
-                                .map(|tn| Send((*name).to_string(), tn.to_string(), Signal::Low)),
-                        );
-                    }
-                    _ => unreachable!(),
-                }
-            });
-        }
+        });
     }
 
-    pulse_count_low * pulse_count_high
+    Ok(pulse_count_low * pulse_count_high)
 }
 
-fn part_b(lines: &[String]) -> usize {
-    // If you draw the digraph for the modules and connections then you'll find
-    // there are 4 major components. (jp -> pg, bx -> sp, jq -> sv, nv -> qs)
+fn part_b(lines: &[String]) -> AResult<usize> {
+    // rx is fed by a single conjunction, so it gets a Low once every input of that
+    // conjunction has sent a High during the same press. Each input is the end of a
+    // separate sub-circuit (run with --dot to see them) that sends a High every
+    // so many presses - so the answer is the LCM of those periods.
     //
-    // These can all modelled separately to see what the periodicity of
-    // emitting high would be for each component.  All the ends of the pairs
-    // above are inverters so this is the same as them sending Low.
-    //
-    // We can then use the LCM of those button push periods to determine the
-    // first period when all components would all line up together and cause
-    // gf to send a Low to rx
+    // The periods aren't worked out from the shape of the sub-circuits, they're
+    // measured by pressing the button up to MAX times and noting when each input
+    // sends its first two Highs. That assumes each sub-circuit repeats from the very
+    // first press, so the second High has to come at exactly twice the presses of the
+    // first, and any input without two Highs within MAX presses is an error.
+    const MAX: usize = 1 << 16;
+    let modules = &mut parse(lines)?;
+
+    let feeders: Vec<_> = modules
+        .values()
+        .filter(|m| {
+            let (Module::FlipFlop(_, targets, _)
+            | Module::Conjunction(_, targets, _)
+            | Module::Broadcast(_, targets)) = m;
+            targets.iter().any(|t| t == "rx")
+        })
+        .collect();
+    let (gate, inputs) = match feeders.as_slice() {
+        [Module::Conjunction(gate, _, inputs)] => (gate.clone(), inputs.len()),
+        [] => return Err(anyhow!("no module sends pulses to rx")),
+        _ => return Err(anyhow!("rx has to be fed by a single conjunction")),
+    };
+
+    // First and second press at which each input sends a High to the gate
+    let mut highs: HashMap<String, Vec<usize>> = HashMap::new();
+    for push_count in 1..=MAX {
+        press(modules, |Send(source, target, signal)| {
+            if target == &gate && signal == &Signal::High {
+                let seen = highs.entry(source.clone()).or_default();
+                if seen.last() != Some(&push_count) && seen.len() < 2 {
+                    seen.push(push_count);
+                }
+            }
+        });
 
-    const MAX: usize = 5000;
-    let modules = &mut parse(lines);
+        if highs.len() == inputs && highs.values().all(|seen| seen.len() == 2) {
+            break;
+        }
+    }
 
     let mut push_counts = vec![];
-
-    'outer: for (start, end) in [("jp", "pg"), ("bx", "sp"), ("jq", "sv"), ("nv", "qs")] {
-        for push_count in 0..MAX {
-            let mut queue = VecDeque::new();
-
-            // Push the start node directly rather than the button by pretending to be the broadcaster
-            queue.push_back(Send(
-                "broadcaster".to_string(),
-                start.to_string(),
-                Signal::Low,
-            ));
-
-            while let Some(s) = queue.pop_front() {
-                let Send(source, target_name, signal) = s;
-
-                // We've found the period of this start/end pair - move onto the next one.
-                if target_name == end && signal == Signal::Low && push_count > 0 {
-                    push_counts.push(push_count + 1);
-                    continue 'outer;
-                }
-
-                modules.entry(target_name.to_string()).and_modify(|target| {
-                    match (source, target, signal) {
-                        (_, Module::FlipFlop(name, targets, on), Signal::Low) => {
-                            *on = !*on;
-                            if *on {
-                                queue.extend(targets.iter().map(|tn| {
-                                    Send((*name).to_string(), tn.to_string(), Signal::High)
-                                }));
-                            } else {
-                                queue.extend(targets.iter().map(|tn| {
-                                    Send((*name).to_string(), tn.to_string(), Signal::Low)
-                                }));
-                            }
-                        }
-                        (_, Module::FlipFlop(_, _, _), Signal::High) => (),
-                        (source, Module::Conjunction(name, targets, inputs), signal) => {
-                            inputs.insert(source.to_string(), signal);
-                            if inputs.values().all(|s| s == &Signal::High) {
-                                queue.extend(targets.iter().map(|tn| {
-                                    Send((*name).to_string(), tn.to_string(), Signal::Low)
-                                }));
-                            } else {
-                                queue.extend(targets.iter().map(|tn| {
-                                    Send((*name).to_string(), tn.to_string(), Signal::High)
-                                }));
-                            }
-                        }
-                        (_, Module::Broadcast(name, target_names), Signal::Low) => {
-                            queue.extend(
-                                target_names.iter().map(|tn| {
-                                    Send((*name).to_string(), tn.to_string(), Signal::Low)
-                                }),
-                            );
-                        }
-                        _ => unreachable!(),
-                    }
-                });
+    for (source, seen) in &highs {
+        match *seen.as_slice() {
+            [first, second] if second == 2 * first => push_counts.push(first),
+            [first, second] => {
+                return Err(anyhow!(
+                    "{source} first sends a high to {gate} after {first} presses, \
+                     but then again after {second}"
+                ))
             }
+            _ => return Err(anyhow!("{source} has no period within {MAX} presses")),
         }
     }
 
-    push_counts.into_iter().reduce(num::integer::lcm).unwrap()
+    if push_counts.len() < inputs {
+        return Err(anyhow!(
+            "not every input to {gate} has a period within {MAX} presses"
+        ));
+    }
+    lcm_all(push_counts).ok_or_else(|| anyhow!("the lcm of the module periods overflows"))
+}
+
+/// The module graph in Graphviz DOT format
+fn to_dot(modules: &HashMap<String, Module>) -> String {
+    let mut names: Vec<_> = modules.keys().collect();
+    names.sort_unstable();
+
+    let mut dot = String::from("digraph modules {\n");
+    for name in names {
+        let (shape, targets) = match &modules[name] {
+            Module::FlipFlop(_, targets, _) => ("box", targets),
+            Module::Conjunction(_, targets, _) => ("invtriangle", targets),
+            Module::Broadcast(_, targets) => ("doublecircle", targets),
+        };
+        dot.push_str(&format!("    {name} [shape={shape}];\n"));
+        for target in targets {
+            dot.push_str(&format!("    {name} -> {target};\n"));
+        }
+    }
+    dot.push_str("}\n");
+    dot
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -250,16 +263,34 @@ fn main() -> AResult<()> {
         .find(name)
         .expect("binary name should contain a number")
         .as_str();
-    println!("Running code for Day {ex}.");
+    // `day_20 --dot | dot -Tsvg` draws the module graph instead of solving
+    let dot = std::env::args().any(|arg| arg == "--dot");
+    if !dot {
+        println!("Running code for Day {ex}.");
+    }
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
+
+    if dot {
+        print!(
+            "{}",
+            to_dot(&parse(lines.as_slice()).map_err(in_file(&path))?)
+        );
+        return Ok(());
+    }
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -284,13 +315,87 @@ mod tests {
     &con -> output";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines = ["broadcaster -> a".to_string(), "#a -> b".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 1: expected a module, found \"#a -> b\""
+        );
+
+        let lines = ["broadcaster -> a".to_string(), "%a => b".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 3: expected \" -> \", found \" => b\""
+        );
+
+        let lines = ["broadcaster -> a, ".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 19: expected a module name, found end of line"
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT_1.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 32_000_000);
+        assert_eq!(part_a(lines.as_slice())?, 32_000_000);
         let lines: Vec<_> = TEST_INPUT_2.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 11_687_500);
+        assert_eq!(part_a(lines.as_slice())?, 11_687_500);
+        Ok(())
     }
 
+    // gate's inputs send a High every 2 and every 4 presses
+    const TEST_INPUT_3: &str = "broadcaster -> a, b
+    %a -> inva
+    &inva -> gate
+    %b -> c
+    %c -> invc
+    &invc -> gate
+    &gate -> rx";
+
     #[test]
-    fn test_b() {}
+    fn test_b() -> AResult<()> {
+        let lines: Vec<_> = TEST_INPUT_3.lines().map(|l| l.trim().to_string()).collect();
+        assert_eq!(part_b(lines.as_slice())?, 4);
+
+        let lines: Vec<_> = TEST_INPUT_2.lines().map(|l| l.trim().to_string()).collect();
+        assert_eq!(
+            part_b(lines.as_slice()).unwrap_err().to_string(),
+            "no module sends pulses to rx"
+        );
+
+        // x never sends anything, so the button is pressed MAX times without finding
+        // a period for it
+        let lines: Vec<_> = ["broadcaster -> con", "%x -> con", "&con -> rx"]
+            .map(String::from)
+            .to_vec();
+        assert_eq!(
+            part_b(lines.as_slice()).unwrap_err().to_string(),
+            "not every input to con has a period within 65536 presses"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_dot() -> AResult<()> {
+        let lines: Vec<_> = TEST_INPUT_2.lines().map(|l| l.trim().to_string()).collect();
+        assert_eq!(
+            to_dot(&parse(&lines)?),
+            "digraph modules {
+    a [shape=box];
+    a -> inv;
+    a -> con;
+    b [shape=box];
+    b -> con;
+    broadcaster [shape=doublecircle];
+    broadcaster -> a;
+    con [shape=invtriangle];
+    con -> output;
+    inv [shape=invtriangle];
+    inv -> b;
+}
+"
+        );
+        Ok(())
+    }
 }
@@ -1,46 +1,51 @@
+use advent_common::parsing::{grid_of, in_file, read_lines, ParseError};
+use anyhow::anyhow;
 use humantime::format_duration;
 use indicatif::ProgressIterator;
 use regex::Regex;
-use std::{
-    collections::BTreeSet,
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::{collections::BTreeSet, time::Instant};
 
 type AResult<T> = anyhow::Result<T>;
+type Coord = (isize, isize);
 
 #[allow(clippy::cast_possible_wrap)]
-fn parse(lines: &[String]) -> ((isize, isize), BTreeSet<(isize, isize)>) {
-    let start = lines
+fn parse(lines: &[String]) -> AResult<(Coord, BTreeSet<Coord>)> {
+    let grid = grid_of(lines, "'.', '#' or 'S'", |c| ".#S".contains(c).then_some(c))?;
+
+    let starts: Vec<_> = grid
         .iter()
         .enumerate()
-        .find_map(|(rid, row)| {
-            row.char_indices().find_map(|(cid, c)| {
-                if c == 'S' {
-                    Some((rid as isize, cid as isize))
-                } else {
-                    None
-                }
-            })
+        .flat_map(|(rid, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|&(_, &c)| c == 'S')
+                .map(move |(cid, _)| (rid as isize, cid as isize))
         })
-        .unwrap();
+        .collect();
+    let &[start] = starts.as_slice() else {
+        Err(ParseError::new(
+            1,
+            1,
+            "exactly one starting position",
+            format!("{}", starts.len()),
+        ))?
+    };
 
     let mut rocks = BTreeSet::new();
-    for (rid, row) in lines.iter().enumerate() {
-        for (cid, c) in row.char_indices() {
+    for (rid, row) in grid.iter().enumerate() {
+        for (cid, &c) in row.iter().enumerate() {
             if c == '#' {
                 rocks.insert((rid as isize, cid as isize));
             }
         }
     }
 
-    (start, rocks)
+    Ok((start, rocks))
 }
 
 #[allow(clippy::cast_possible_wrap)]
-fn solve(lines: &[String], steps: &[usize]) -> Vec<usize> {
-    let (start, rocks) = parse(lines);
+fn solve(lines: &[String], steps: &[usize]) -> AResult<Vec<usize>> {
+    let (start, rocks) = parse(lines)?;
     let rows = lines.len() as isize;
     let cols = lines[0].len() as isize;
 
@@ -75,11 +80,11 @@ fn solve(lines: &[String], steps: &[usize]) -> Vec<usize> {
         }
     }
 
-    outputs
+    Ok(outputs)
 }
 
-fn part_a(lines: &[String]) -> usize {
-    solve(lines, &[64])[0]
+fn part_a(lines: &[String]) -> AResult<usize> {
+    Ok(solve(lines, &[64])?[0])
 }
 
 #[allow(clippy::similar_names)]
@@ -107,7 +112,7 @@ fn part_b(lines: &[String]) -> AResult<i128> {
         offset + (lines.len() * 2),
         offset + (lines.len() * 3),
     ];
-    let results = solve(lines, &to_calc);
+    let results = solve(lines, &to_calc)?;
 
     println!(
         "Now visit: https://www.wolframalpha.com/ and input the following:
@@ -125,12 +130,26 @@ fn part_b(lines: &[String]) -> AResult<i128> {
     println!("Enter the constant term (with the - if appropriate):");
     std::io::stdin().read_line(&mut c_str)?;
 
-    let x2: i128 = x2_str.trim().parse().expect("A positive integer x^2 term");
-    let x: i128 = x_str.trim().parse().expect("An integer x term");
-    let c: i128 = c_str.trim().parse().expect("An integer constant term");
+    let x2: i128 = x2_str
+        .trim()
+        .parse()
+        .map_err(|e| anyhow!("expected a positive integer x^2 term: {e}"))?;
+    let x: i128 = x_str
+        .trim()
+        .parse()
+        .map_err(|e| anyhow!("expected an integer x term: {e}"))?;
+    let c: i128 = c_str
+        .trim()
+        .parse()
+        .map_err(|e| anyhow!("expected an integer constant term: {e}"))?;
 
     // Gotcha: ^ is not the operator for powers in rust 🤦 (it's Bitwise exclusive OR)
-    Ok((x2 * (whole_periods.pow(2))) + (x * whole_periods) + c)
+    whole_periods
+        .checked_pow(2)
+        .and_then(|p2| x2.checked_mul(p2))
+        .zip(x.checked_mul(whole_periods))
+        .and_then(|(a, b)| a.checked_add(b)?.checked_add(c))
+        .ok_or_else(|| anyhow!("the quadratic overflows an i128 at x = {whole_periods}"))
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -145,13 +164,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice())?);
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -176,8 +201,28 @@ mod tests {
     ...........";
 
     #[test]
-    fn test_solve() {
+    fn test_parse_errors() {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+
+        let mut broken = lines.clone();
+        broken[3] = "..#.#..O#..".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 4, column 8: expected '.', '#' or 'S', found 'O'"
+        );
+
+        let mut broken = lines.clone();
+        broken[0] = ".....S.....".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 1, column 1: expected exactly one starting position, found 2"
+        );
+    }
+
+    #[test]
+    fn test_solve() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(solve(&lines, &[6, 10, 50, 100]), [16, 50, 1594, 6536]);
+        assert_eq!(solve(&lines, &[6, 10, 50, 100])?, [16, 50, 1594, 6536]);
+        Ok(())
     }
 }
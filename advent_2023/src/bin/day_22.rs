@@ -1,14 +1,13 @@
+use advent_common::parsing::{in_file, parse_lines, read_lines, uint, PResult, ParseError};
+use anyhow::anyhow;
 use humantime::format_duration;
-use indicatif::ParallelProgressIterator;
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use regex::Regex;
-use std::{
-    cmp::Ordering,
-    collections::{HashMap, HashSet},
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
+use nom::{
+    bytes::complete::tag,
+    error::context,
+    sequence::{preceded, separated_pair, tuple},
 };
+use regex::Regex;
+use std::{collections::HashMap, time::Instant};
 
 type AResult<T> = anyhow::Result<T>;
 type Coord = (usize, usize, usize);
@@ -36,177 +35,231 @@ impl Brick {
     }
 }
 
-fn parse(lines: &[String]) -> Vec<Brick> {
-    let patterns = Regex::new(r"^(\d+),(\d+),(\d+)~(\d+),(\d+),(\d+)$").unwrap();
-    let mut bricks = vec![];
-    for (idx, line) in lines.iter().enumerate() {
-        let caps = patterns.captures(line).unwrap();
-        let (_, bits): (_, [&str; 6]) = caps.extract();
-        let nums: Vec<usize> = bits
+fn coord(input: &str) -> PResult<'_, Coord> {
+    let comma = || context("','", tag(","));
+    tuple((uint, preceded(comma(), uint), preceded(comma(), uint)))(input)
+}
+
+fn parse(lines: &[String]) -> AResult<Vec<Brick>> {
+    let ends = parse_lines(
+        lines,
+        separated_pair(coord, context("'~'", tag("~")), coord),
+    )?;
+
+    // The settling code relies on every brick being a straight line given from
+    // its lowest corner
+    for (idx, &(start, end)) in ends.iter().enumerate() {
+        let axes_used = [start.0 != end.0, start.1 != end.1, start.2 != end.2]
             .into_iter()
-            .map(str::parse)
-            .map(Result::unwrap)
-            .collect();
-        bricks.push(Brick {
-            id: idx,
-            start: (nums[0], nums[1], nums[2]),
-            end: (nums[3], nums[4], nums[5]),
-        });
+            .filter(|&d| d)
+            .count();
+        if axes_used > 1 || start > end {
+            Err(ParseError::new(
+                idx + 1,
+                1,
+                "a straight brick, lowest corner first",
+                format!("{:?}", lines[idx]),
+            ))?;
+        }
     }
-    bricks
+
+    Ok(ends
+        .into_iter()
+        .enumerate()
+        .map(|(id, (start, end))| Brick { id, start, end })
+        .collect())
 }
 
-fn settle(lines: &[String]) -> Vec<Brick> {
-    let mut last_bricks = parse(lines);
-    last_bricks.sort_by(|b1, b2| {
-        let mut ret = b1.start.2.cmp(&b2.start.2);
-        if ret == Ordering::Equal {
-            ret = b1.start.cmp(&b2.start);
+/// The bricks once they have all come to rest, and which rests on which
+#[derive(Debug)]
+struct Stack {
+    // Indexed by brick ID
+    bricks: Vec<Brick>,
+    below: Vec<Vec<usize>>,
+    above: Vec<Vec<usize>>,
+    // The brick every route down to the ground passes through first, if any. Take
+    // that away and this one falls too.
+    dominator: Vec<Option<usize>>,
+    // How many other bricks fall if this one is taken away
+    falls: Vec<usize>,
+}
+
+impl Stack {
+    fn settle(mut bricks: Vec<Brick>) -> Stack {
+        let n = bricks.len();
+        let mut order: Vec<_> = (0..n).collect();
+        order.sort_by_key(|&id| (bricks[id].start.2, bricks[id].start));
+
+        // The highest brick (and its top) over each column fallen through so far
+        let mut tops: HashMap<(usize, usize), (usize, usize)> = HashMap::new();
+        let mut below = vec![vec![]; n];
+        let mut above = vec![vec![]; n];
+        let mut dominator = vec![None; n];
+        let mut depth = vec![0; n];
+        for &id in &order {
+            let Brick { start, end, .. } = bricks[id];
+            let columns: Vec<_> = (start.0..=end.0)
+                .flat_map(|x| (start.1..=end.1).map(move |y| (x, y)))
+                .collect();
+
+            let floor = columns
+                .iter()
+                .filter_map(|c| tops.get(c))
+                .map(|&(z, _)| z)
+                .max()
+                .unwrap_or(0);
+            let mut resting: Vec<_> = columns
+                .iter()
+                .filter_map(|c| tops.get(c))
+                .filter(|&&(z, _)| z == floor)
+                .map(|&(_, b)| b)
+                .collect();
+            resting.sort_unstable();
+            resting.dedup();
+
+            let brick = &mut bricks[id];
+            brick.end.2 -= brick.start.2 - (floor + 1);
+            brick.start.2 = floor + 1;
+            for c in columns {
+                tops.insert(c, (brick.end.2, id));
+            }
+
+            // Bricks settle bottom up, so everything below already has its dominator
+            // and the nearest one common to all of them is this brick's
+            let mut dom = resting.first().copied();
+            for &b in &resting {
+                above[b].push(id);
+                dom = meet(&dominator, &depth, dom, Some(b));
+            }
+            dominator[id] = dom;
+            depth[id] = dom.map_or(1, |d| depth[d] + 1);
+            below[id] = resting;
+        }
+
+        // Everything a brick dominates falls with it, so count up the dominator tree
+        let mut falls = vec![0; n];
+        for &id in order.iter().rev() {
+            if let Some(d) = dominator[id] {
+                falls[d] += falls[id] + 1;
+            }
         }
-        ret
-    });
-
-    let mut iterations = 0;
-    loop {
-        let bricks = drop(&last_bricks, &mut HashSet::new());
-        if bricks == last_bricks {
-            last_bricks = bricks;
-            break;
+
+        Stack {
+            bricks,
+            below,
+            above,
+            dominator,
+            falls,
         }
-        last_bricks = bricks;
-        iterations += 1;
     }
 
-    println!("Iterations required to initially settle = {iterations}");
-    last_bricks
+    /// The bricks directly underneath `id`, holding it up
+    fn rests_on(&self, id: usize) -> &[usize] {
+        &self.below[id]
+    }
+
+    /// The bricks resting directly on `id`
+    fn supports(&self, id: usize) -> &[usize] {
+        &self.above[id]
+    }
+
+    /// Whether `id` can go without anything else falling
+    fn is_safe(&self, id: usize) -> bool {
+        self.supports(id)
+            .iter()
+            .all(|&b| self.rests_on(b).len() > 1)
+    }
+
+    /// How many other bricks fall if `id` is disintegrated
+    fn falls(&self, id: usize) -> usize {
+        self.falls[id]
+    }
+
+    /// The other bricks that fall if `id` is disintegrated
+    fn falling(&self, id: usize) -> Vec<usize> {
+        (0..self.bricks.len())
+            .filter(|&b| {
+                std::iter::successors(self.dominator[b], |&d| self.dominator[d]).any(|d| d == id)
+            })
+            .collect()
+    }
 }
 
-fn drop(before: &Vec<Brick>, dropped: &mut HashSet<usize>) -> Vec<Brick> {
-    let occupied: HashSet<Coord> = before.iter().flat_map(Brick::all_blocks).collect();
-    let mut after = Vec::with_capacity(before.len());
-    let mut moved = false;
-    for brick in before {
-        if moved || (brick.start.2 == 1) || (brick.end.2 == 1) {
-            // This brick is on the ground as (one-of) it's z-coords is 1
-            // OR we've moved another block already this turn
-            after.push(*brick);
-            continue;
+/// The nearest common dominator of `a` and `b`, where `None` is the ground
+fn meet(
+    dominator: &[Option<usize>],
+    depth: &[usize],
+    mut a: Option<usize>,
+    mut b: Option<usize>,
+) -> Option<usize> {
+    let depth = |n: Option<usize>| n.map_or(0, |n| depth[n]);
+    while a != b {
+        if depth(a) >= depth(b) {
+            a = a.and_then(|n| dominator[n]);
+        } else {
+            b = b.and_then(|n| dominator[n]);
         }
+    }
+    a
+}
 
-        // Which blocks to check depends on orientation
-        let &Brick {
-            id: _,
-            start: (x1, y1, z1),
-            end: (x2, y2, z2),
-        } = brick;
-
-        let (footprint_size, to_check): (usize, HashSet<Coord>) = if x1 == x2 && y1 == y2 {
-            // For a Z-block we only need to check the 1 block below it
-            // Z-blocks only have a 1 block x/y footprint
-            (1, (1..z1).map(|z| (x1, y1, z)).collect())
-        } else if x1 == x2 && z1 == z2 {
-            // For a y-block we need to check the z-1's of all Y's
-            (
-                1 + y2 - y1,
-                (y1..=y2)
-                    .flat_map(|y| (1..z1).map(|z| (x1, y, z)).collect::<Vec<_>>())
-                    .collect(),
-            )
-        } else if y1 == y2 && z1 == z2 {
-            // For a x-block we need to check the z-1's of all X's
-            (
-                1 + x2 - x1,
-                (x1..=x2)
-                    .flat_map(|x| (1..z1).map(|z| (x, y1, z)).collect::<Vec<_>>())
-                    .collect(),
-            )
-        } else {
-            panic!("Found a brick that isn't a stick {brick:?}")
-        };
-
-        // The disjoint set between occupied and to_check is the set of blocks
-        // that we can fall through
-        let available: HashMap<usize, Vec<Coord>> =
-            to_check
-                .difference(&occupied)
-                .fold(HashMap::new(), |mut map, &(x, y, z)| {
-                    map.entry(z)
-                        .and_modify(|v| v.push((x, y, z)))
-                        .or_insert(vec![(x, y, z)]);
-
-                    map
-                });
-
-        // Find the smallest appropriate row
-        let mut new_z = None;
-        for candidate_z in (1..brick.start.2).rev() {
-            if available.get(&candidate_z).unwrap_or(&vec![]).len() == footprint_size {
-                new_z = Some(candidate_z);
-            } else {
-                break; // We've hit a row we can't fall through
-            }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Axis {
+    X,
+    Y,
+}
+
+/// The bricks seen from the side, looking along the other horizontal axis, as
+/// drawn in the puzzle. Bricks are lettered by ID (wrapping after Z), and `?`
+/// marks where more than one brick is in view.
+fn side_view(bricks: &[Brick], across: Axis) -> String {
+    let pick = |(x, y, _): Coord| if across == Axis::X { x } else { y };
+    let width = bricks.iter().map(|b| pick(b.end) + 1).max().unwrap_or(1);
+    let height = bricks.iter().map(|b| b.end.2).max().unwrap_or(0);
+
+    let mut seen: HashMap<(usize, usize), Option<usize>> = HashMap::new();
+    for b in bricks {
+        for block in b.all_blocks() {
+            seen.entry((pick(block), block.2))
+                .and_modify(|id| {
+                    if *id != Some(b.id) {
+                        *id = None;
+                    }
+                })
+                .or_insert(Some(b.id));
         }
+    }
 
-        if let Some(z) = new_z {
-            // Can drop!
-            let Brick {
-                id,
-                start: (x_min, y_min, z_min),
-                end: (x_max, y_max, z_max),
-            } = *brick;
-            let z_diff = z_max - z_min;
-            after.push(Brick {
-                id,
-                start: (x_min, y_min, z),
-                end: (x_max, y_max, z + z_diff),
+    let label = if across == Axis::X { 'x' } else { 'y' };
+    let mut out = format!("{}{label}\n", " ".repeat(width / 2));
+    out.extend((0..width).map(|i| char::from(b'0' + (i % 10) as u8)));
+    out.push('\n');
+    for z in (1..=height).rev() {
+        for i in 0..width {
+            out.push(match seen.get(&(i, z)) {
+                None => '.',
+                Some(None) => '?',
+                Some(&Some(id)) => char::from(b'A' + (id % 26) as u8),
             });
-            moved = true;
-            dropped.insert(id);
-        } else {
-            // Can't drop this brick as it's blocked by something else - leave it where it is
-            after.push(*brick);
         }
+        out += &format!(" {z}");
+        if z == height.div_ceil(2) {
+            out += " z";
+        }
+        out.push('\n');
     }
-
-    assert_eq!(before.len(), after.len());
-    after
+    out += &format!("{} 0", "-".repeat(width));
+    out
 }
 
-fn part_a(settled: &Vec<Brick>) -> usize {
-    // Check if there is movement when each brick is removed in turn
-    settled
-        .par_iter()
-        .filter_map(|b| {
-            let before: Vec<_> = settled.iter().filter(|&x| x != b).copied().collect();
-            assert_eq!(before.len(), settled.len() - 1);
-            let after: Vec<_> = drop(&before, &mut HashSet::new());
-            assert_eq!(before.len(), after.len());
-
-            if before == after {
-                Some(1)
-            } else {
-                None
-            }
-        })
-        .sum()
+fn part_a(stack: &Stack) -> usize {
+    (0..stack.bricks.len())
+        .filter(|&id| stack.is_safe(id))
+        .count()
 }
 
-fn part_b(settled: &[Brick]) -> usize {
-    settled
-        .par_iter()
-        .progress()
-        .filter_map(|b| {
-            let mut dropped = HashSet::new();
-            let mut before: Vec<_> = settled.iter().filter(|&x| x != b).copied().collect();
-            loop {
-                let after: Vec<_> = drop(&before, &mut dropped);
-                if before == after {
-                    return Some(dropped.len());
-                }
-                before = after;
-            }
-        })
-        .sum()
+fn part_b(stack: &Stack) -> usize {
+    (0..stack.bricks.len()).map(|id| stack.falls(id)).sum()
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -218,18 +271,46 @@ fn main() -> AResult<()> {
         .find(name)
         .expect("binary name should contain a number")
         .as_str();
-    println!("Running code for Day {ex}.");
+    // `day_22 --views` draws the settled stack from the side, and `day_22 --brick N`
+    // describes brick N (counting input lines from 0)
+    let args: Vec<_> = std::env::args().collect();
+    let views = args.iter().any(|arg| arg == "--views");
+    let brick = match args.iter().position(|arg| arg == "--brick") {
+        Some(i) => Some(
+            args.get(i + 1)
+                .and_then(|id| id.parse::<usize>().ok())
+                .ok_or_else(|| anyhow!("--brick needs a brick ID"))?,
+        ),
+        None => None,
+    };
+    if !views && brick.is_none() {
+        println!("Running code for Day {ex}.");
+    }
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Waiting for bricks to settle...");
-    let settled = settle(&lines);
-    println!("Part A result = {}", part_a(&settled));
-    println!("Part B result = {}", part_b(&settled));
+    let stack = Stack::settle(parse(&lines).map_err(in_file(&path))?);
+    if views {
+        println!("{}\n", side_view(&stack.bricks, Axis::X));
+        println!("{}", side_view(&stack.bricks, Axis::Y));
+        return Ok(());
+    }
+    if let Some(id) = brick {
+        if id >= stack.bricks.len() {
+            return Err(anyhow!("there are only {} bricks", stack.bricks.len()));
+        }
+        println!("Brick {id} settles at {:?}", stack.bricks[id]);
+        println!("  rests on {:?}", stack.rests_on(id));
+        println!("  supports {:?}", stack.supports(id));
+        println!("  disintegrating it brings down {:?}", stack.falling(id));
+        return Ok(());
+    }
+    println!("Part A result = {}", part_a(&stack));
+    println!("Part B result = {}", part_b(&stack));
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -250,116 +331,136 @@ mod tests {
     1,1,8~1,1,9";
 
     #[test]
-    fn test_all_blocks() {
-        let bricks = parse(&["2,2,2~2,2,2".to_string()]);
+    fn test_parse_errors() {
+        let lines = ["1,0,1~1,2,1".to_string(), "0,0,2-2,0,2".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 6: expected '~', found \"-2,0,2\""
+        );
+
+        let lines = ["1,0,1~1,2".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 10: expected ',', found end of line"
+        );
+
+        let lines = ["1,0,1~1,2,1".to_string(), "0,0,2~2,1,2".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 1: expected a straight brick, lowest corner first, found \"0,0,2~2,1,2\""
+        );
+
+        let lines = ["1,2,1~1,0,1".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 1: expected a straight brick, lowest corner first, found \"1,2,1~1,0,1\""
+        );
+    }
+
+    #[test]
+    fn test_all_blocks() -> AResult<()> {
+        let bricks = parse(&["2,2,2~2,2,2".to_string()])?;
         assert_eq!(bricks[0].all_blocks(), vec![(2, 2, 2)]);
 
-        let bricks = parse(&["0,0,10~1,0,10".to_string()]);
+        let bricks = parse(&["0,0,10~1,0,10".to_string()])?;
         assert_eq!(bricks[0].all_blocks(), vec![(0, 0, 10), (1, 0, 10)]);
 
-        let bricks = parse(&["0,0,10~0,1,10".to_string()]);
+        let bricks = parse(&["0,0,10~0,1,10".to_string()])?;
         assert_eq!(bricks[0].all_blocks(), vec![(0, 0, 10), (0, 1, 10)]);
 
-        let bricks = parse(&["0,1,6~2,1,6".to_string()]);
+        let bricks = parse(&["0,1,6~2,1,6".to_string()])?;
         assert_eq!(
             bricks[0].all_blocks(),
             vec![(0, 1, 6), (1, 1, 6), (2, 1, 6)]
         );
+        Ok(())
+    }
+
+    fn settled(lines: &[&str]) -> AResult<Vec<Coord>> {
+        let lines: Vec<_> = lines.iter().map(ToString::to_string).collect();
+        let stack = Stack::settle(parse(&lines)?);
+        Ok(stack.bricks.iter().map(|b| b.start).collect())
     }
 
     #[test]
-    fn test_drop() {
-        let before = parse(&["5,5,1~5,6,1".to_string()]);
-        let after = drop(&before, &mut HashSet::new());
-        assert_eq!(before, after);
-
-        let before = parse(&["0,2,1~0,2,5".to_string()]);
-        let after = drop(&before, &mut HashSet::new());
-        assert_eq!(before, after);
-
-        let before = parse(&["3,3,20~3,3,21".to_string()]);
-        let after = drop(&before, &mut HashSet::new());
-        assert_ne!(before, after);
-        assert_eq!(after[0].all_blocks(), vec![(3, 3, 1), (3, 3, 2)]);
-
-        let before = parse(&["1,3,20~3,3,20".to_string()]);
-        let after = drop(&before, &mut HashSet::new());
-        assert_ne!(before, after, "no change for x-block");
+    fn test_settle() -> AResult<()> {
+        assert_eq!(settled(&["5,5,1~5,6,1"])?, [(5, 5, 1)]);
+        assert_eq!(settled(&["0,2,1~0,2,5"])?, [(0, 2, 1)]);
+        assert_eq!(settled(&["3,3,20~3,3,21"])?, [(3, 3, 1)]);
+        assert_eq!(settled(&["1,3,20~3,3,20"])?, [(1, 3, 1)]);
+        assert_eq!(settled(&["3,1,20~3,3,20"])?, [(3, 1, 1)]);
         assert_eq!(
-            after[0].all_blocks(),
-            vec![(1, 3, 1), (2, 3, 1), (3, 3, 1)],
-            "wrong change for x-block"
+            settled(&["1,1,1~3,1,1", "1,1,10~1,1,10"])?,
+            [(1, 1, 1), (1, 1, 2)]
         );
-
-        let before = parse(&["3,1,20~3,3,20".to_string()]);
-        let after = drop(&before, &mut HashSet::new());
-        assert_ne!(before, after, "no change for y-block");
         assert_eq!(
-            after[0].all_blocks(),
-            vec![(3, 1, 1), (3, 2, 1), (3, 3, 1)],
-            "wrong change for y-block"
+            settled(&["1,1,20~1,1,20", "1,1,10~1,1,10"])?,
+            [(1, 1, 2), (1, 1, 1)]
         );
 
-        let before = parse(&["1,1,1~3,1,1".to_string(), "1,1,10~1,1,10".to_string()]);
-        let after = drop(&before, &mut HashSet::new());
-        assert_ne!(before, after, "no change for blocking test");
-        assert_eq!(
-            after[1].all_blocks(),
-            vec![(1, 1, 2)],
-            "wrong change for blocking test"
-        );
+        // A tall brick lands on top of whatever is highest under it
+        let lines = ["0,0,1~0,0,3", "1,0,1~1,0,1", "0,0,9~1,0,9"];
+        assert_eq!(settled(&lines)?, [(0, 0, 1), (1, 0, 1), (0, 0, 4)]);
+        Ok(())
+    }
 
-        let before = parse(&["1,1,20~1,1,20".to_string(), "1,1,10~1,1,10".to_string()]);
-        let after = drop(&before, &mut HashSet::new());
-        assert_ne!(before, after, "no change for block with gap test - step 1");
-        assert_eq!(
-            after[0].all_blocks(),
-            vec![(1, 1, 11)],
-            "wrong change for with gap test"
-        );
-        let before = after;
-        let after = drop(&before, &mut HashSet::new());
-        assert_ne!(before, after, "no change for block with gap test - step 2");
-        assert_eq!(
-            after[0].all_blocks(),
-            vec![(1, 1, 11)],
-            "wrong change for with gap test - s2"
-        );
+    #[test]
+    fn test_queries() -> AResult<()> {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        let stack = Stack::settle(parse(&lines)?);
+
+        // A holds up B and C, which both hold up D and E
+        assert_eq!(stack.supports(0), [1, 2]);
+        assert_eq!(stack.rests_on(3), [1, 2]);
+        assert!(!stack.is_safe(0));
+        assert!(stack.is_safe(1));
+        assert_eq!(stack.falling(0), [1, 2, 3, 4, 5, 6]);
+        assert_eq!(stack.falling(5), [6]);
+        assert_eq!(stack.falls(5), 1);
+
+        // Safe bricks are just those nothing else falls with
+        for id in 0..lines.len() {
+            assert_eq!(stack.is_safe(id), stack.falls(id) == 0);
+            assert_eq!(stack.falling(id).len(), stack.falls(id));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_side_view() -> AResult<()> {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        let bricks = parse(&lines)?;
         assert_eq!(
-            after[1].all_blocks(),
-            vec![(1, 1, 1)],
-            "wrong change for with gap test - s2"
+            side_view(&bricks, Axis::X),
+            " x\n012\n.G. 9\n.G. 8\n... 7\nFFF 6\n..E 5 z\nD.. 4\nCCC 3\nBBB 2\n.A. 1\n--- 0"
         );
-        let before = after;
-        let after = drop(&before, &mut HashSet::new());
-        assert_ne!(before, after, "no change for block with gap test - step 2");
         assert_eq!(
-            after[0].all_blocks(),
-            vec![(1, 1, 2)],
-            "wrong change for with gap test - s3"
+            side_view(&bricks, Axis::Y),
+            " y\n012\n.G. 9\n.G. 8\n... 7\n.F. 6\nEEE 5 z\nDDD 4\n..C 3\nB.. 2\nAAA 1\n--- 0"
         );
+
+        // Once settled, B and C (and D and E) hide each other along one axis
+        let stack = Stack::settle(bricks);
         assert_eq!(
-            after[1].all_blocks(),
-            vec![(1, 1, 1)],
-            "wrong change for with gap test - s3"
+            side_view(&stack.bricks, Axis::X),
+            " x\n012\n.G. 6\n.G. 5\nFFF 4\nD.E 3 z\n??? 2\n.A. 1\n--- 0"
         );
-
-        let before = after;
-        let after = drop(&before, &mut HashSet::new());
-        assert_eq!(before, after);
+        Ok(())
     }
 
     #[test]
-    fn test_a() {
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        let settled = settle(&lines);
-        assert_eq!(part_a(&settled), 5);
+        let stack = Stack::settle(parse(&lines)?);
+        assert_eq!(part_a(&stack), 5);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        let settled = settle(&lines);
-        assert_eq!(part_b(&settled), 7);
+        let stack = Stack::settle(parse(&lines)?);
+        assert_eq!(part_b(&stack), 7);
+        Ok(())
     }
 }
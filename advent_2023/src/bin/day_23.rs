@@ -1,165 +1,54 @@
+use advent_2023::maze::{Coord, Maze, Slopes};
+use advent_common::parsing::{grid_of, in_file, read_lines, ParseError};
+use anyhow::anyhow;
 use humantime::format_duration;
-use rayon::prelude::*;
 use regex::Regex;
-use std::{
-    collections::{HashMap, HashSet, VecDeque},
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::time::Instant;
 
 type AResult<T> = anyhow::Result<T>;
-type Coord = (usize, usize);
 
-fn parse(lines: &[String]) -> Vec<Vec<char>> {
-    lines.iter().map(|l| l.chars().collect()).collect()
-}
-
-#[allow(clippy::match_on_vec_items, clippy::similar_names)]
-fn find_neighbours(grid: &[Vec<char>], (rid, cid): Coord) -> HashSet<Coord> {
-    match grid[rid][cid] {
-        // If this position is a slope - there is only 1 neighbour which is in the
-        // direction of the slope
-        '^' if grid[rid - 1][cid] != '#' => vec![(rid - 1, cid)],
-        '>' if grid[rid][cid + 1] != '#' => vec![(rid, cid + 1)],
-        'v' if grid[rid + 1][cid] != '#' => vec![(rid + 1, cid)],
-        '<' if grid[rid][cid + 1] != '#' => vec![(rid, cid - 1)],
-        // We're on a path tile
-        '.' => {
-            let mut n = vec![];
-            if rid > 0 && !"#v".contains(grid[rid - 1][cid]) {
-                n.push((rid - 1, cid)); // Up
-            }
-            if cid < grid.len() - 1 && !"#<".contains(grid[rid][cid + 1]) {
-                n.push((rid, cid + 1)); // Right
-            }
-            if rid < grid.len() - 1 && !"#^".contains(grid[rid + 1][cid]) {
-                n.push((rid + 1, cid)); // Down
-            }
-            if cid > 0 && !"#>".contains(grid[rid][cid - 1]) {
-                n.push((rid, cid - 1)); // Left
-            }
-            n
-        }
-        _ => vec![],
-    }
-    .into_iter()
-    .collect()
-}
-
-fn create_edge_map(grid: &[Vec<char>]) -> HashMap<Coord, Vec<(Coord, usize)>> {
-    let mut all_edges = HashMap::new();
-
-    // Generate all possible edges
-    for (rid, row) in grid.iter().enumerate() {
-        for (cid, _) in row.iter().enumerate() {
-            let neighbours = find_neighbours(grid, (rid, cid));
-            for n in neighbours {
-                all_edges
-                    .entry((rid, cid))
-                    .and_modify(|l: &mut Vec<_>| l.push(n))
-                    .or_insert(vec![n]);
-            }
-        }
+fn parse(lines: &[String]) -> AResult<Vec<Vec<char>>> {
+    if lines.len() < 2 {
+        Err(ParseError::end_of_input(lines.len(), "a row of the map"))?;
     }
-
-    // Compress the set of edges down to the junction points only
-    let mut junction_edges = HashMap::new();
-    let mut checked = HashSet::new();
-    let mut to_check = VecDeque::new();
-    to_check.push_back((0, 1));
-
-    // We'll loop over a queue of junction points - which is added too as we discover them
-    while let Some(start) = to_check.pop_front() {
-        if checked.contains(&start) {
-            continue;
-        }
-        checked.insert(start);
-
-        let possible_nexts = all_edges.get(&start).unwrap().clone();
-        for next in possible_nexts {
-            let mut history: HashSet<_> = [start, next].into_iter().collect();
-            let mut end = next;
-            let mut out_edges: Vec<_> = all_edges
-                .get(&next)
-                .unwrap()
-                .iter()
-                .filter(|e| !history.contains(e))
-                .copied()
-                .collect();
-
-            // Keep fast-forwarding until we get to the next junction point
-            while out_edges.len() == 1 {
-                history.insert(end);
-                end = out_edges[0];
-
-                out_edges = all_edges
-                    .get(&end)
-                    .unwrap()
-                    .iter()
-                    .filter(|e| !history.contains(e))
-                    .copied()
-                    .collect();
-            }
-
-            // We've now reached the next junction point - set the edge & cost
-            junction_edges
-                .entry(start)
-                .and_modify(|l: &mut Vec<_>| l.push((end, history.len())))
-                .or_insert(vec![(end, history.len())]);
-
-            // Add this junction point to the queue for us to check in the future
-            to_check.push_back(end);
+    let grid = grid_of(lines, "a path, a forest or a slope", |c| {
+        ".#^>v<".contains(c).then_some(c)
+    })?;
+
+    // The walk starts in the top row and ends in the bottom row
+    let width = grid[0].len();
+    for (ri, ci) in [(0, 1), (grid.len() - 1, width.saturating_sub(2))] {
+        if grid[ri].get(ci) != Some(&'.') {
+            let found = grid[ri]
+                .get(ci)
+                .map_or("end of line".to_string(), |c| format!("{c:?}"));
+            Err(ParseError::new(
+                ri + 1,
+                ci + 1,
+                "a path through the edge",
+                found,
+            ))?;
         }
     }
 
-    junction_edges
+    Ok(grid)
 }
 
-fn solve_with_edges(
-    edges: &HashMap<Coord, Vec<(Coord, usize)>>,
-    pos: Coord,
-    target: Coord,
-    history: &HashSet<Coord>,
-    cost: usize,
-) -> usize {
-    if pos == target {
-        return cost;
-    }
-
-    edges
-        .get(&pos)
-        .unwrap()
-        .par_iter() // Exploit Rayon to parallelise the recursive checks
-        .map(|(next, w)| {
-            if history.contains(next) {
-                return 0;
-            }
-
-            let mut new_hist = history.clone();
-            new_hist.insert(*next);
-            solve_with_edges(edges, *next, target, &new_hist, cost + w)
-        })
-        .max()
-        .unwrap()
+/// The tiles of the longest hike from the top row to the bottom row
+fn hike(lines: &[String], slopes: Slopes) -> AResult<Vec<Coord>> {
+    let grid = parse(lines)?;
+    let end = (grid.len() - 1, grid[0].len() - 2);
+    Maze::new(&grid, (0, 1), end, slopes)?
+        .longest_path()
+        .ok_or_else(|| anyhow!("there is no way from the top row to the bottom row"))
 }
 
-fn solve(lines: &[String]) -> usize {
-    let grid = parse(lines);
-    let edges = create_edge_map(&grid);
-    let start = (0, 1);
-    let target = (grid.len() - 1, grid[0].len() - 2);
-    let history: HashSet<_> = [start].into_iter().collect();
-    solve_with_edges(&edges, start, target, &history, 0)
+fn part_a(lines: &[String]) -> AResult<usize> {
+    Ok(hike(lines, Slopes::OneWay)?.len() - 1)
 }
 
-fn part_a(lines: &[String]) -> usize {
-    solve(lines)
-}
-
-fn part_b(lines: &[String]) -> usize {
-    let altered_lines: Vec<_> = lines.iter().map(|l| l.replace(['v', '>'], ".")).collect();
-    solve(&altered_lines)
+fn part_b(lines: &[String]) -> AResult<usize> {
+    Ok(hike(lines, Slopes::Flat)?.len() - 1)
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -174,13 +63,19 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
-    println!("Part B result = {}", part_b(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -217,14 +112,53 @@ mod tests {
     #####################.#";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+
+        let mut broken = lines.clone();
+        broken[1] = "#.......#########...#x#".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 2, column 22: expected a path, a forest or a slope, found 'x'"
+        );
+
+        let mut broken = lines.clone();
+        broken[0] = "#######################".to_string();
+        assert_eq!(
+            parse(&broken).unwrap_err().to_string(),
+            "line 1, column 2: expected a path through the edge, found '#'"
+        );
+    }
+
+    #[test]
+    fn test_hike() -> AResult<()> {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        let path = hike(&lines, Slopes::OneWay)?;
+        assert_eq!(path.first(), Some(&(0, 1)));
+        assert_eq!(path.last(), Some(&(22, 21)));
+        // The slopes send it down the right hand side early on
+        assert!(path.contains(&(3, 10)));
+
+        let mut lines = lines;
+        lines[21] = "#.....###...###...#.###".to_string();
+        assert_eq!(
+            hike(&lines, Slopes::Flat).unwrap_err().to_string(),
+            "there is no way from the top row to the bottom row"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 94);
+        assert_eq!(part_a(lines.as_slice())?, 94);
+        Ok(())
     }
 
     #[test]
-    fn test_b() {
+    fn test_b() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_b(&lines), 154);
+        assert_eq!(part_b(&lines)?, 154);
+        Ok(())
     }
 }
@@ -0,0 +1,276 @@
+use advent_common::{
+    math::solve_linear,
+    parsing::{in_file, int, parse_lines, read_lines, PResult},
+};
+use anyhow::anyhow;
+use humantime::format_duration;
+use nom::{
+    character::complete::{char, space0},
+    combinator::map,
+    error::context,
+    sequence::{preceded, separated_pair, tuple},
+};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use regex::Regex;
+use std::time::Instant;
+
+type AResult<T> = anyhow::Result<T>;
+
+type Vec3 = [i64; 3];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Hailstone {
+    pos: Vec3,
+    vel: Vec3,
+}
+
+fn vec3(input: &str) -> PResult<'_, Vec3> {
+    let comma = || tuple((space0, context("','", char(',')), space0));
+    map(
+        tuple((int, preceded(comma(), int), preceded(comma(), int))),
+        |(x, y, z)| [x, y, z],
+    )(input)
+}
+
+fn hailstone(input: &str) -> PResult<'_, Hailstone> {
+    map(
+        separated_pair(
+            vec3,
+            tuple((space0, context("'@'", char('@')), space0)),
+            vec3,
+        ),
+        |(pos, vel)| Hailstone { pos, vel },
+    )(input)
+}
+
+fn parse(lines: &[String]) -> AResult<Vec<Hailstone>> {
+    Ok(parse_lines(lines, hailstone)?)
+}
+
+fn ratio(v: impl Into<BigInt>) -> BigRational {
+    BigRational::from_integer(v.into())
+}
+
+/// Where the paths of `a` and `b` cross in x and y, if they do so in the future for both
+fn crossing(a: &Hailstone, b: &Hailstone) -> Option<(BigRational, BigRational)> {
+    // a.pos + a.vel * t == b.pos + b.vel * s - parallel paths have no single solution
+    let [t, s] = <[_; 2]>::try_from(solve_linear(
+        vec![
+            vec![ratio(a.vel[0]), ratio(-b.vel[0])],
+            vec![ratio(a.vel[1]), ratio(-b.vel[1])],
+        ],
+        vec![ratio(b.pos[0] - a.pos[0]), ratio(b.pos[1] - a.pos[1])],
+    )?)
+    .ok()?;
+    if t < BigRational::ZERO || s < BigRational::ZERO {
+        return None;
+    }
+    Some((
+        ratio(a.pos[0]) + ratio(a.vel[0]) * &t,
+        ratio(a.pos[1]) + ratio(a.vel[1]) * &t,
+    ))
+}
+
+/// The number of pairs of hailstones whose paths cross inside the square `lo..=hi`
+fn crossings_within(hailstones: &[Hailstone], lo: i64, hi: i64) -> usize {
+    let area = ratio(lo)..=ratio(hi);
+    hailstones
+        .iter()
+        .enumerate()
+        .flat_map(|(i, a)| hailstones[i + 1..].iter().map(move |b| (a, b)))
+        .filter_map(|(a, b)| crossing(a, b))
+        .filter(|(x, y)| area.contains(x) && area.contains(y))
+        .count()
+}
+
+fn cross(a: [i128; 3], b: [i128; 3]) -> [i128; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+/// The rock's starting position and velocity, so that it hits every hailstone
+fn throw(hailstones: &[Hailstone]) -> AResult<Hailstone> {
+    // The rock at P + V t hits hailstone i if p_i - P and v_i - V are parallel, so
+    // (p_i - P) × (v_i - V) = 0. The only part that isn't linear, P × V, is the same for
+    // every hailstone, so subtracting the first hailstone's equation from the others
+    // leaves P × (v_i - v_0) + (p_i - p_0) × V = p_i × v_i - p_0 × v_0
+    let widen = |v: Vec3| v.map(i128::from);
+    let first = hailstones
+        .first()
+        .ok_or_else(|| anyhow!("there are no hailstones"))?;
+    let (p0, v0) = (widen(first.pos), widen(first.vel));
+
+    let (mut a, mut b) = (Vec::new(), Vec::new());
+    for h in &hailstones[1..] {
+        let (p, v) = (widen(h.pos), widen(h.vel));
+        let d: [i128; 3] = std::array::from_fn(|i| v[i] - v0[i]);
+        let e: [i128; 3] = std::array::from_fn(|i| p[i] - p0[i]);
+        let (pv, pv0) = (cross(p, v), cross(p0, v0));
+
+        // Coefficients of P then V for each component of the cross products
+        let rows = [
+            [0, d[2], -d[1], 0, -e[2], e[1]],
+            [-d[2], 0, d[0], e[2], 0, -e[0]],
+            [d[1], -d[0], 0, -e[1], e[0], 0],
+        ];
+        for (i, row) in rows.into_iter().enumerate() {
+            a.push(row.map(ratio).to_vec());
+            b.push(ratio(pv[i] - pv0[i]));
+        }
+    }
+
+    let solution = solve_linear(a, b)
+        .ok_or_else(|| anyhow!("there isn't exactly one throw that hits every hailstone"))?;
+    let whole = solution
+        .iter()
+        .map(|v| {
+            v.is_integer()
+                .then(|| i64::try_from(v.to_integer()).ok())
+                .flatten()
+                .ok_or_else(|| anyhow!("the rock would have to be thrown from {v}"))
+        })
+        .collect::<AResult<Vec<_>>>()?;
+
+    Ok(Hailstone {
+        pos: [whole[0], whole[1], whole[2]],
+        vel: [whole[3], whole[4], whole[5]],
+    })
+}
+
+fn part_a(lines: &[String]) -> AResult<usize> {
+    Ok(crossings_within(
+        &parse(lines)?,
+        200_000_000_000_000,
+        400_000_000_000_000,
+    ))
+}
+
+fn part_b(lines: &[String]) -> AResult<i64> {
+    let rock = throw(&parse(lines)?)?;
+    Ok(rock.pos.iter().sum())
+}
+
+#[cfg(not(tarpaulin_include))]
+fn main() -> AResult<()> {
+    // Figure out which exercise we are so we can load the correct input file
+    let pattern = Regex::new("[0-9]+$")?;
+    let name = &std::env::args().next().expect("binary name not found.");
+    let ex = pattern
+        .find(name)
+        .expect("binary name should contain a number")
+        .as_str();
+    println!("Running code for Day {ex}.");
+
+    // Load the appropriate input text
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
+
+    // Run the solutions
+    let start = Instant::now();
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
+    println!(
+        "Part B result = {}",
+        part_b(lines.as_slice()).map_err(in_file(&path))?
+    );
+    let end = Instant::now();
+
+    println!("Run took {}", format_duration(end - start));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_INPUT: &str = "19, 13, 30 @ -2,  1, -2
+    18, 19, 22 @ -1, -1, -2
+    20, 25, 34 @ -2, -2, -4
+    12, 31, 28 @ -1, -2, -1
+    20, 19, 15 @  1, -5, -3";
+
+    #[test]
+    fn test_parse_errors() {
+        let lines = [
+            "19, 13, 30 @ -2,  1, -2".to_string(),
+            "18, 19 @ -1, -1, -2".to_string(),
+        ];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 8: expected ',', found \"@ -1, -1, -2\""
+        );
+
+        let lines = ["19, 13, 30 -2,  1, -2".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 12: expected '@', found \"-2,  1, -2\""
+        );
+    }
+
+    #[test]
+    fn test_crossing() -> AResult<()> {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        let hailstones = parse(&lines)?;
+        let r = |n: i64, d: i64| BigRational::new(n.into(), d.into());
+
+        // x=14.333, y=15.333
+        assert_eq!(
+            crossing(&hailstones[0], &hailstones[1]),
+            Some((r(43, 3), r(46, 3)))
+        );
+        // Parallel
+        assert_eq!(crossing(&hailstones[1], &hailstones[2]), None);
+        // In the past for the first hailstone
+        assert_eq!(crossing(&hailstones[0], &hailstones[4]), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        assert_eq!(crossings_within(&parse(&lines)?, 7, 27), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_throw() -> AResult<()> {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        let hailstones = parse(&lines)?;
+        assert_eq!(
+            throw(&hailstones)?,
+            Hailstone {
+                pos: [24, 13, 10],
+                vel: [-3, 1, 2]
+            }
+        );
+
+        // Two hailstones can be hit by any number of throws
+        assert_eq!(
+            throw(&hailstones[..2]).unwrap_err().to_string(),
+            "there isn't exactly one throw that hits every hailstone"
+        );
+
+        // Nudging a hailstone means no single throw hits them all
+        let mut nudged = hailstones.clone();
+        nudged[4].pos[2] += 1;
+        assert_eq!(
+            throw(&nudged).unwrap_err().to_string(),
+            "there isn't exactly one throw that hits every hailstone"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_b() -> AResult<()> {
+        let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
+        assert_eq!(part_b(lines.as_slice())?, 47);
+        Ok(())
+    }
+}
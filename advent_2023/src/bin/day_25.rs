@@ -1,16 +1,17 @@
-use anyhow::Ok;
+use advent_common::parsing::{in_file, key_value, parse_lines, read_lines};
+use anyhow::{anyhow, Ok};
 use humantime::format_duration;
+use nom::{
+    character::complete::{alpha1, space1},
+    error::context,
+    multi::separated_list1,
+};
 use regex::Regex;
 use rustworkx_core::{
     connectivity::stoer_wagner_min_cut,
     petgraph::{graph::NodeIndex, graph::UnGraph},
 };
-use std::{
-    collections::HashMap,
-    fs::File,
-    io::{BufRead, BufReader},
-    time::Instant,
-};
+use std::{collections::HashMap, time::Instant};
 
 type AResult<T> = anyhow::Result<T>;
 
@@ -26,31 +27,35 @@ fn get_node(
     *node
 }
 
-fn part_a(lines: &[String]) -> usize {
+fn parse(lines: &[String]) -> AResult<Vec<(&str, Vec<&str>)>> {
+    Ok(parse_lines(
+        lines,
+        key_value(separated_list1(space1, context("a component name", alpha1))),
+    )?)
+}
+
+fn part_a(lines: &[String]) -> AResult<usize> {
     // It's Christmas Day - lets use rustworkx rather than implementing Stoer–Wagner ourselves
 
     let mut graph: UnGraph<String, usize, _> = UnGraph::new_undirected();
     let mut nodes = HashMap::new();
 
-    for line in lines {
-        let mut it = line.split(": ");
-        let source = it.next().unwrap();
+    for (source, targets) in parse(lines)? {
         let s_node = get_node(source, &mut graph, &mut nodes);
 
-        for target in it.next().unwrap().split_whitespace() {
+        for target in targets {
             let t_node = get_node(target, &mut graph, &mut nodes);
             graph.add_edge(s_node, t_node, 1);
         }
     }
 
-    let (_, left_nodes) = stoer_wagner_min_cut(&graph, |_| Ok(1usize))
-        .unwrap()
-        .unwrap();
+    let (_, left_nodes) = stoer_wagner_min_cut(&graph, |_| Ok(1usize))?
+        .ok_or_else(|| anyhow!("there are no wires to cut"))?;
 
     let mut right_nodes = graph.clone();
     right_nodes.retain_nodes(|_, b| !left_nodes.contains(&b));
 
-    left_nodes.len() * right_nodes.node_count()
+    Ok(left_nodes.len() * right_nodes.node_count())
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -65,12 +70,15 @@ fn main() -> AResult<()> {
     println!("Running code for Day {ex}.");
 
     // Load the appropriate input text
-    let file = File::open(format!("./data/day_{ex}.txt"))?;
-    let lines: Vec<String> = BufReader::new(file).lines().map(Result::unwrap).collect();
+    let path = format!("./data/day_{ex}.txt");
+    let lines = read_lines(&path)?;
 
     // Run the solutions
     let start = Instant::now();
-    println!("Part A result = {}", part_a(lines.as_slice()));
+    println!(
+        "Part A result = {}",
+        part_a(lines.as_slice()).map_err(in_file(&path))?
+    );
     let end = Instant::now();
 
     println!("Run took {}", format_duration(end - start));
@@ -97,8 +105,33 @@ mod tests {
     frs: qnr lhk lsr";
 
     #[test]
-    fn test_a() {
+    fn test_parse_errors() {
+        let lines = [
+            "jqt: rhn xhk nvd".to_string(),
+            "rsh frs pzl lsr".to_string(),
+        ];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 2, column 16: expected ':', found end of line"
+        );
+
+        let lines = ["jqt: rhn, xhk".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 9: expected end of line, found \", xhk\""
+        );
+
+        let lines = ["jqt:".to_string()];
+        assert_eq!(
+            parse(&lines).unwrap_err().to_string(),
+            "line 1, column 5: expected a component name, found end of line"
+        );
+    }
+
+    #[test]
+    fn test_a() -> AResult<()> {
         let lines: Vec<_> = TEST_INPUT.lines().map(|l| l.trim().to_string()).collect();
-        assert_eq!(part_a(lines.as_slice()), 54);
+        assert_eq!(part_a(lines.as_slice())?, 54);
+        Ok(())
     }
 }
@@ -1,3 +1,6 @@
+pub mod maze;
+pub mod nonogram;
+
 #[allow(clippy::must_use_candidate)]
 pub fn grid_string(grid: &[Vec<char>]) -> String {
     let s: String = grid
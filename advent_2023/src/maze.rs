@@ -0,0 +1,352 @@
+//! Longest simple paths through grid mazes, as in the hiking trails of day 23.
+//!
+//! A maze is a grid of walls (`#`) and open tiles (anything else). Slopes (`^`, `>`,
+//! `v` and `<`) can be one-way, in which case a walker on one must step downhill.
+//! Corridors between junctions are compressed into weighted edges, so the search
+//! only has to choose at the junctions.
+
+use anyhow::anyhow;
+
+pub type Coord = (usize, usize);
+
+/// Whether slopes can only be walked downhill, or are just more open ground
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Slopes {
+    OneWay,
+    Flat,
+}
+
+/// The compass step a slope forces, as `(row, column)` deltas
+fn slope(tile: char) -> Option<(isize, isize)> {
+    match tile {
+        '^' => Some((-1, 0)),
+        '>' => Some((0, 1)),
+        'v' => Some((1, 0)),
+        '<' => Some((0, -1)),
+        _ => None,
+    }
+}
+
+const STEPS: [(isize, isize); 4] = [(-1, 0), (0, 1), (1, 0), (0, -1)];
+
+#[derive(Debug, Clone)]
+struct Edge {
+    to: usize,
+    // The tiles walked, leaving out the junction the edge starts from
+    tiles: Vec<Coord>,
+}
+
+/// A maze compressed down to its junctions, the start and the end
+#[derive(Debug, Clone)]
+pub struct Maze {
+    junctions: Vec<Coord>,
+    edges: Vec<Vec<Edge>>,
+    start: usize,
+    end: usize,
+}
+
+impl Maze {
+    /// Compress `grid` into the corridors between its junctions.
+    ///
+    /// # Errors
+    ///
+    /// If `start` or `end` is not an open tile, or there are more than 128 junctions
+    /// to keep track of.
+    pub fn new(
+        grid: &[Vec<char>],
+        start: Coord,
+        end: Coord,
+        slopes: Slopes,
+    ) -> anyhow::Result<Self> {
+        let open = |(r, c): Coord| {
+            grid.get(r)
+                .and_then(|row| row.get(c))
+                .is_some_and(|&t| t != '#')
+        };
+        for (name, at) in [("start", start), ("end", end)] {
+            if !open(at) {
+                return Err(anyhow!("the {name} {at:?} is not an open tile"));
+            }
+        }
+        let step = |(r, c): Coord, (dr, dc): (isize, isize)| -> Option<Coord> {
+            let next = (r.checked_add_signed(dr)?, c.checked_add_signed(dc)?);
+            open(next).then_some(next)
+        };
+
+        // Junctions are where the walker has a choice of more than one way on
+        let mut junctions = vec![start, end];
+        for (r, row) in grid.iter().enumerate() {
+            for c in 0..row.len() {
+                let exits = STEPS.iter().filter_map(|&d| step((r, c), d)).count();
+                if open((r, c)) && exits > 2 && !junctions.contains(&(r, c)) {
+                    junctions.push((r, c));
+                }
+            }
+        }
+        if junctions.len() > 128 {
+            return Err(anyhow!(
+                "the maze has {} junctions, but at most 128 can be tracked",
+                junctions.len()
+            ));
+        }
+
+        // Stepping from one tile to the next has to go downhill off a slope, and can't
+        // go straight up one
+        let allowed = |(r, c): Coord, d: (isize, isize)| -> Option<Coord> {
+            let next = step((r, c), d)?;
+            if slopes == Slopes::OneWay {
+                let leaving = slope(grid[r][c]).is_none_or(|s| s == d);
+                let entering = slope(grid[next.0][next.1]).is_none_or(|s| s != (-d.0, -d.1));
+                if !(leaving && entering) {
+                    return None;
+                }
+            }
+            Some(next)
+        };
+
+        let mut edges = vec![Vec::new(); junctions.len()];
+        for (from, &at) in junctions.iter().enumerate() {
+            for d in STEPS {
+                let Some(mut next) = allowed(at, d) else {
+                    continue;
+                };
+                let (mut prev, mut tiles) = (at, vec![next]);
+                let to = loop {
+                    if let Some(to) = junctions.iter().position(|&j| j == next) {
+                        break Some(to);
+                    }
+                    // Corridors have just one way on, unless they dead-end
+                    let Some(on) = STEPS
+                        .iter()
+                        .filter_map(|&d| allowed(next, d))
+                        .find(|&n| n != prev)
+                    else {
+                        break None;
+                    };
+                    (prev, next) = (next, on);
+                    tiles.push(next);
+                };
+                if let Some(to) = to.filter(|&to| to != from) {
+                    edges[from].push(Edge { to, tiles });
+                }
+            }
+        }
+
+        Ok(Maze {
+            junctions,
+            edges,
+            start: 0,
+            end: 1,
+        })
+    }
+
+    /// The start, the end and then every junction, in the order they are numbered
+    #[must_use]
+    pub fn junctions(&self) -> &[Coord] {
+        &self.junctions
+    }
+
+    /// The tiles of the longest walk from the start to the end that never visits a
+    /// tile twice, starting with the start. `None` if the end can't be reached.
+    #[must_use]
+    pub fn longest_path(&self) -> Option<Vec<Coord>> {
+        let n = self.junctions.len();
+        let mut search = Search {
+            edges: &self.edges,
+            end: self.end,
+            best_in: vec![0; n],
+            into_end: None,
+            route: Vec::new(),
+            best: None,
+        };
+
+        // Each junction still to visit can add at most its longest way in
+        for edge in self.edges.iter().flatten() {
+            search.best_in[edge.to] = search.best_in[edge.to].max(edge.tiles.len());
+        }
+
+        // If only one junction leads to the end then that has to be the last stop, and
+        // from there it's straight to the end
+        let mut into_end = (0..n).flat_map(|from| {
+            let end = self.end;
+            self.edges[from]
+                .iter()
+                .enumerate()
+                .filter(move |(_, e)| e.to == end)
+                .map(move |(i, _)| (from, i))
+        });
+        if let (Some(only), None) = (into_end.next(), into_end.next()) {
+            search.into_end = Some(only);
+        }
+
+        search.dfs(self.start, 1 << self.start, 0);
+
+        let (_, route) = search.best?;
+        let mut path = vec![self.junctions[self.start]];
+        for (from, i) in route {
+            path.extend(&self.edges[from][i].tiles);
+        }
+        Some(path)
+    }
+}
+
+/// Depth-first search over the junctions, visiting each at most once
+struct Search<'a> {
+    edges: &'a [Vec<Edge>],
+    end: usize,
+    best_in: Vec<usize>,
+    // The one edge into the end, if there is just one, as `(from, edge index)`
+    into_end: Option<(usize, usize)>,
+    route: Vec<(usize, usize)>,
+    best: Option<(usize, Vec<(usize, usize)>)>,
+}
+
+impl Search<'_> {
+    fn dfs(&mut self, at: usize, visited: u128, length: usize) {
+        if at == self.end {
+            if self.best.as_ref().is_none_or(|(best, _)| length > *best) {
+                self.best = Some((length, self.route.clone()));
+            }
+            return;
+        }
+        let Some(bound) = self.bound(at, visited) else {
+            return;
+        };
+        if self
+            .best
+            .as_ref()
+            .is_some_and(|(best, _)| length + bound <= *best)
+        {
+            return;
+        }
+
+        let forced = self.into_end.filter(|&(from, _)| from == at);
+        for (i, edge) in self.edges[at].iter().enumerate() {
+            if visited & 1 << edge.to != 0 || forced.is_some_and(|f| f != (at, i)) {
+                continue;
+            }
+            self.route.push((at, i));
+            self.dfs(edge.to, visited | 1 << edge.to, length + edge.tiles.len());
+            self.route.pop();
+        }
+    }
+
+    /// The most the walk could still add, from the junctions reachable without going
+    /// back through any already visited. `None` if the end isn't one of them.
+    fn bound(&self, at: usize, visited: u128) -> Option<usize> {
+        let (mut seen, mut todo) = (1_u128 << at, vec![at]);
+        let mut bound = 0;
+        while let Some(from) = todo.pop() {
+            for edge in &self.edges[from] {
+                let bit = 1 << edge.to;
+                if (visited | seen) & bit == 0 {
+                    seen |= bit;
+                    bound += self.best_in[edge.to];
+                    todo.push(edge.to);
+                }
+            }
+        }
+        (seen & 1 << self.end != 0).then_some(bound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(text: &str) -> Vec<Vec<char>> {
+        text.lines().map(|l| l.trim().chars().collect()).collect()
+    }
+
+    fn check(grid: &[Vec<char>], path: &[Coord], slopes: Slopes) {
+        for pair in path.windows(2) {
+            let ((r0, c0), (r1, c1)) = (pair[0], pair[1]);
+            assert_eq!(
+                r0.abs_diff(r1) + c0.abs_diff(c1),
+                1,
+                "{pair:?} should be adjacent"
+            );
+            assert_ne!(grid[r1][c1], '#');
+            if slopes == Slopes::OneWay {
+                let d = (r1 as isize - r0 as isize, c1 as isize - c0 as isize);
+                assert!(slope(grid[r0][c0]).is_none_or(|s| s == d));
+            }
+        }
+        let mut tiles = path.to_vec();
+        tiles.sort_unstable();
+        tiles.dedup();
+        assert_eq!(tiles.len(), path.len(), "no tile should be visited twice");
+    }
+
+    #[test]
+    fn test_open_room() -> anyhow::Result<()> {
+        // Every tile of a 3x3 room is a junction, and a snake can visit them all
+        let room = grid(
+            "#.###
+             #...#
+             #...#
+             #...#
+             ###.#",
+        );
+        let maze = Maze::new(&room, (0, 1), (4, 3), Slopes::Flat)?;
+        let path = maze.longest_path().unwrap();
+        check(&room, &path, Slopes::Flat);
+        assert_eq!(path.len(), 11);
+        assert_eq!(path.first(), Some(&(0, 1)));
+        assert_eq!(path.last(), Some(&(4, 3)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_slopes() -> anyhow::Result<()> {
+        // The long way round is uphill, so only counts when slopes are flat
+        let hill = grid(
+            "#.#######
+             #.>.....#
+             #^#####v#
+             #.#...#.#
+             #.#.#.#.#
+             #...#...#
+             #######.#",
+        );
+        let maze = Maze::new(&hill, (0, 1), (6, 7), Slopes::OneWay)?;
+        let path = maze.longest_path().unwrap();
+        check(&hill, &path, Slopes::OneWay);
+        assert_eq!(path.len(), 13);
+
+        let maze = Maze::new(&hill, (0, 1), (6, 7), Slopes::Flat)?;
+        let path = maze.longest_path().unwrap();
+        check(&hill, &path, Slopes::Flat);
+        assert_eq!(path.len(), 17);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unreachable() -> anyhow::Result<()> {
+        let blocked = grid(
+            "#.###
+             #.#.#
+             ###.#",
+        );
+        assert_eq!(
+            Maze::new(&blocked, (0, 1), (2, 3), Slopes::Flat)?.longest_path(),
+            None
+        );
+
+        let downhill = grid(
+            "#.#
+             #^#
+             #.#",
+        );
+        let maze = Maze::new(&downhill, (0, 1), (2, 1), Slopes::OneWay)?;
+        assert_eq!(maze.longest_path(), None);
+
+        assert_eq!(
+            Maze::new(&blocked, (0, 0), (2, 3), Slopes::Flat)
+                .unwrap_err()
+                .to_string(),
+            "the start (0, 0) is not an open tile"
+        );
+        Ok(())
+    }
+}
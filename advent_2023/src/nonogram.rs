@@ -0,0 +1,345 @@
+//! Run-length constraints along lines of cells, as in nonograms or the damaged spring
+//! records of day 12.
+//!
+//! A line's clue is the length of each run of filled cells along it, in order. Runs are
+//! at least one cell long and are kept apart by at least one empty cell.
+
+use std::iter::zip;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Cell {
+    Filled,
+    Empty,
+    Unknown,
+}
+
+impl From<Cell> for char {
+    fn from(cell: Cell) -> Self {
+        match cell {
+            Cell::Filled => '#',
+            Cell::Empty => '.',
+            Cell::Unknown => '?',
+        }
+    }
+}
+
+impl TryFrom<char> for Cell {
+    type Error = char;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        match c {
+            '#' => Ok(Cell::Filled),
+            '.' => Ok(Cell::Empty),
+            '?' => Ok(Cell::Unknown),
+            _ => Err(c),
+        }
+    }
+}
+
+/// Show cells the way the puzzle does - `#`, `.` and `?`
+#[must_use]
+pub fn render(cells: &[Cell]) -> String {
+    cells.iter().copied().map(char::from).collect()
+}
+
+/// The ways of placing `runs` along `cells` that agree with whichever cells are known
+#[derive(Debug)]
+pub struct Line<'a> {
+    cells: &'a [Cell],
+    runs: &'a [usize],
+    // empties[i] - how many of the cells before `i` are known to be empty
+    empties: Vec<usize>,
+    // ways[i][j] - the arrangements of `runs[j..]` along `cells[i..]`, where cell `i`
+    // follows an empty cell (or is the first)
+    ways: Vec<Vec<usize>>,
+}
+
+impl<'a> Line<'a> {
+    #[must_use]
+    pub fn new(cells: &'a [Cell], runs: &'a [usize]) -> Self {
+        let n = cells.len();
+        let mut empties = vec![0; n + 1];
+        for (i, &cell) in cells.iter().enumerate() {
+            empties[i + 1] = empties[i] + usize::from(cell == Cell::Empty);
+        }
+
+        let mut line = Line {
+            cells,
+            runs,
+            empties,
+            ways: vec![vec![0; runs.len() + 1]; n + 1],
+        };
+        line.ways[n][runs.len()] = 1;
+        for i in (0..n).rev() {
+            for j in 0..=runs.len() {
+                let ways = line
+                    .moves(i, j)
+                    .map(|(_, (i, j))| line.ways[i][j])
+                    .fold(0, usize::saturating_add);
+                line.ways[i][j] = ways;
+            }
+        }
+        line
+    }
+
+    // From cell `i` with `runs[j..]` still to place, either leave the cell empty or start
+    // the next run on it. Gives the run started (if any) and where that leads.
+    fn moves(&self, i: usize, j: usize) -> impl Iterator<Item = (Option<usize>, (usize, usize))> {
+        let n = self.cells.len();
+        let empty = (self.cells[i] != Cell::Filled).then_some((None, (i + 1, j)));
+        let run = self
+            .runs
+            .get(j)
+            .copied()
+            .filter(|&len| {
+                i + len <= n
+                    && self.empties[i + len] == self.empties[i]
+                    && self.cells.get(i + len) != Some(&Cell::Filled)
+            })
+            .map(|len| (Some(len), ((i + len + 1).min(n), j + 1)));
+        empty.into_iter().chain(run)
+    }
+
+    /// The number of arrangements, saturating at `usize::MAX`
+    #[must_use]
+    pub fn count(&self) -> usize {
+        self.ways[0][0]
+    }
+
+    /// Each arrangement in turn, with every cell either filled or empty. The first has
+    /// the runs as far to the start as they'll go.
+    #[must_use]
+    pub fn arrangements(&self) -> Arrangements<'_, 'a> {
+        Arrangements {
+            line: self,
+            stack: if self.count() > 0 {
+                vec![(0, 0, Vec::new())]
+            } else {
+                Vec::new()
+            },
+        }
+    }
+
+    /// The cells that every arrangement agrees on, with `Unknown` wherever they differ -
+    /// or `None` if there aren't any arrangements
+    #[must_use]
+    pub fn forced(&self) -> Option<Vec<Cell>> {
+        if self.count() == 0 {
+            return None;
+        }
+
+        // Follow every move that leads on to a full arrangement from the start
+        let (n, k) = (self.cells.len(), self.runs.len());
+        let (mut can_fill, mut can_empty) = (vec![false; n], vec![false; n]);
+        let mut reached = vec![vec![false; k + 1]; n + 1];
+        reached[0][0] = true;
+        for i in 0..n {
+            for j in 0..=k {
+                if !reached[i][j] {
+                    continue;
+                }
+                for (run, (ni, nj)) in self.moves(i, j) {
+                    if self.ways[ni][nj] == 0 {
+                        continue;
+                    }
+                    reached[ni][nj] = true;
+                    match run {
+                        None => can_empty[i] = true,
+                        Some(len) => {
+                            can_fill[i..i + len].fill(true);
+                            if let Some(after) = can_empty.get_mut(i + len) {
+                                *after = true;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Some(
+            zip(can_fill, can_empty)
+                .map(|fill_or_empty| match fill_or_empty {
+                    (true, false) => Cell::Filled,
+                    (false, true) => Cell::Empty,
+                    _ => Cell::Unknown,
+                })
+                .collect(),
+        )
+    }
+}
+
+/// The arrangements of a [`Line`], worked out as they are needed
+#[derive(Debug)]
+pub struct Arrangements<'l, 'a> {
+    line: &'l Line<'a>,
+    // Partial arrangements - only ever ones that can be finished
+    stack: Vec<(usize, usize, Vec<Cell>)>,
+}
+
+impl Iterator for Arrangements<'_, '_> {
+    type Item = Vec<Cell>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = self.line;
+        while let Some((i, j, cells)) = self.stack.pop() {
+            if i == line.cells.len() {
+                return Some(cells);
+            }
+
+            // The empty cell goes on the stack first, so that runs are placed as early
+            // as they can be before later arrangements shuffle them along
+            for (run, (ni, nj)) in line.moves(i, j) {
+                if line.ways[ni][nj] == 0 {
+                    continue;
+                }
+                let mut cells = cells.clone();
+                if let Some(len) = run {
+                    cells.resize(i + len, Cell::Filled);
+                }
+                cells.resize(ni, Cell::Empty);
+                self.stack.push((ni, nj, cells));
+            }
+        }
+        None
+    }
+}
+
+/// Fill in a nonogram from the runs along each of its rows and columns.
+///
+/// The cells forced along each line are passed on to the lines crossing them until
+/// nothing more changes, and then any cells still unknown are guessed at. Returns
+/// `None` if the clues can't all be met.
+#[must_use]
+pub fn solve_nonogram(rows: &[Vec<usize>], cols: &[Vec<usize>]) -> Option<Vec<Vec<Cell>>> {
+    search(
+        vec![vec![Cell::Unknown; cols.len()]; rows.len()],
+        rows,
+        cols,
+    )
+}
+
+fn search(
+    mut grid: Vec<Vec<Cell>>,
+    rows: &[Vec<usize>],
+    cols: &[Vec<usize>],
+) -> Option<Vec<Vec<Cell>>> {
+    propagate(&mut grid, rows, cols)?;
+
+    let Some((r, c)) = grid.iter().enumerate().find_map(|(r, row)| {
+        row.iter()
+            .position(|&cell| cell == Cell::Unknown)
+            .map(|c| (r, c))
+    }) else {
+        return Some(grid);
+    };
+    [Cell::Filled, Cell::Empty].into_iter().find_map(|guess| {
+        let mut grid = grid.clone();
+        grid[r][c] = guess;
+        search(grid, rows, cols)
+    })
+}
+
+// Fix the forced cells of every row and column until there are no more - or fail if a
+// line can't be arranged at all
+fn propagate(grid: &mut [Vec<Cell>], rows: &[Vec<usize>], cols: &[Vec<usize>]) -> Option<()> {
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (row, runs) in zip(grid.iter_mut(), rows) {
+            let forced = Line::new(row, runs).forced()?;
+            changed |= forced != *row;
+            *row = forced;
+        }
+        for (c, runs) in cols.iter().enumerate() {
+            let column: Vec<_> = grid.iter().map(|row| row[c]).collect();
+            let forced = Line::new(&column, runs).forced()?;
+            for (row, cell) in zip(grid.iter_mut(), forced) {
+                changed |= row[c] != cell;
+                row[c] = cell;
+            }
+        }
+    }
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(text: &str) -> Vec<Cell> {
+        text.chars().map(|c| Cell::try_from(c).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_count() {
+        assert_eq!(Line::new(&cells("???.###"), &[1, 1, 3]).count(), 1);
+        assert_eq!(Line::new(&cells(".??..??...?##."), &[1, 1, 3]).count(), 4);
+        assert_eq!(Line::new(&cells("?###????????"), &[3, 2, 1]).count(), 10);
+        assert_eq!(Line::new(&cells("#.#"), &[3]).count(), 0);
+        assert_eq!(Line::new(&cells("..."), &[]).count(), 1);
+        assert_eq!(Line::new(&[], &[]).count(), 1);
+        assert_eq!(Line::new(&[], &[1]).count(), 0);
+    }
+
+    #[test]
+    fn test_arrangements() {
+        let all: Vec<_> = Line::new(&cells("?###????????"), &[3, 2, 1])
+            .arrangements()
+            .map(|a| render(&a))
+            .collect();
+        assert_eq!(all.len(), 10);
+        assert_eq!(all[0], ".###.##.#...");
+        assert_eq!(all[9], ".###....##.#");
+
+        // Every arrangement is different and fits the clue and the known cells
+        let mut sorted = all.clone();
+        sorted.sort_unstable();
+        sorted.dedup();
+        assert_eq!(sorted.len(), 10);
+        for a in &all {
+            assert_eq!(Line::new(&cells(a), &[3, 2, 1]).count(), 1, "{a}");
+            assert_eq!(Line::new(&cells(a), &[3, 2]).count(), 0, "{a}");
+        }
+
+        assert_eq!(Line::new(&cells("#.#"), &[3]).arrangements().next(), None);
+    }
+
+    #[test]
+    fn test_forced() {
+        let forced = |text: &str, runs: &[usize]| {
+            Line::new(&cells(text), runs)
+                .forced()
+                .map(|cells| render(&cells))
+        };
+        assert_eq!(forced("??????????", &[8]).as_deref(), Some("??######??"));
+        assert_eq!(
+            forced("?###????????", &[3, 2, 1]).as_deref(),
+            Some(".###.???????")
+        );
+        assert_eq!(forced("???.###", &[1, 1, 3]).as_deref(), Some("#.#.###"));
+        assert_eq!(forced("?#??", &[1]).as_deref(), Some(".#.."));
+        assert_eq!(forced("#.#", &[3]), None);
+    }
+
+    #[test]
+    fn test_nonogram() {
+        let solve = |rows: &[Vec<usize>], cols: &[Vec<usize>]| {
+            solve_nonogram(rows, cols)
+                .map(|grid| grid.iter().map(|row| render(row)).collect::<Vec<_>>())
+        };
+
+        // Solved by propagation alone
+        let rows = [vec![3], vec![1, 1], vec![5], vec![1, 1], vec![1, 1]];
+        let cols = [vec![4], vec![1, 1], vec![1, 1], vec![1, 1], vec![4]];
+        assert_eq!(
+            solve(&rows, &cols).unwrap(),
+            [".###.", "#...#", "#####", "#...#", "#...#"]
+        );
+
+        // Two solutions - propagation can't choose, so the first guess wins
+        let ones = [vec![1], vec![1]];
+        assert_eq!(solve(&ones, &ones).unwrap(), ["#.", ".#"]);
+
+        assert_eq!(solve(&[vec![2]], &[vec![1], vec![]]), None);
+    }
+}
@@ -0,0 +1,5 @@
+//! Helpers shared between the yearly solution crates
+
+pub mod math;
+pub mod parsing;
+pub mod search;
@@ -0,0 +1,468 @@
+//! Exact number theory - gcd / lcm, modular arithmetic and the Chinese remainder theorem
+//!
+//! The primitive versions never silently wrap: anything that can overflow is either done
+//! in `u128` with an overflow-free multiply, or returns `None` so the caller can fall back
+//! to the `BigInt` / `BigUint` versions.
+//!
+//! Linear systems are solved over `BigRational`, so there is no rounding at all.
+
+use num_bigint::{BigInt, BigUint};
+use num_rational::BigRational;
+use std::ops::{Div, Mul, Rem};
+
+/// The primitive integer types accepted by [`gcd`] and [`lcm`]
+pub trait Integer:
+    Copy + Ord + Rem<Output = Self> + Div<Output = Self> + Mul<Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+    fn checked_abs(self) -> Option<Self>;
+}
+
+macro_rules! impl_integer {
+    (signed: $($t:ty),*) => {$(
+        impl Integer for $t {
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+
+            fn checked_mul(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_mul(self, rhs)
+            }
+
+            fn checked_abs(self) -> Option<Self> {
+                <$t>::checked_abs(self)
+            }
+        }
+    )*};
+    (unsigned: $($t:ty),*) => {$(
+        impl Integer for $t {
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+
+            fn checked_mul(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_mul(self, rhs)
+            }
+
+            fn checked_abs(self) -> Option<Self> {
+                Some(self)
+            }
+        }
+    )*};
+}
+
+impl_integer!(signed: i8, i16, i32, i64, i128, isize);
+impl_integer!(unsigned: u8, u16, u32, u64, u128, usize);
+
+/// Greatest common divisor - always non-negative, and `gcd(0, 0) == 0`.
+///
+/// Panics if the result is not representable (only `gcd(MIN, 0)` or `gcd(MIN, MIN)`
+/// for a signed type).
+pub fn gcd<T: Integer>(mut a: T, mut b: T) -> T {
+    while b != T::ZERO {
+        (a, b) = (b, a % b);
+    }
+    a.checked_abs().expect("gcd overflowed")
+}
+
+/// Least common multiple, or `None` if it does not fit in `T`. `lcm(x, 0) == 0`.
+pub fn checked_lcm<T: Integer>(a: T, b: T) -> Option<T> {
+    if a == T::ZERO || b == T::ZERO {
+        return Some(T::ZERO);
+    }
+    (a / gcd(a, b)).checked_mul(b)?.checked_abs()
+}
+
+/// Least common multiple - panics on overflow, use [`checked_lcm`] if that's possible.
+pub fn lcm<T: Integer>(a: T, b: T) -> T {
+    checked_lcm(a, b).expect("lcm overflowed")
+}
+
+/// Greatest common divisor of every value, `0` for no values
+pub fn gcd_all<T: Integer>(values: impl IntoIterator<Item = T>) -> T {
+    values.into_iter().fold(T::ZERO, gcd)
+}
+
+/// Least common multiple of every value (`1` for no values), or `None` if it does not
+/// fit in `T` - in which case [`lcm_big`] gives the exact answer.
+pub fn lcm_all<T: Integer>(values: impl IntoIterator<Item = T>) -> Option<T> {
+    values.into_iter().try_fold(T::ONE, checked_lcm)
+}
+
+/// Least common multiple of every value without any risk of overflow
+pub fn lcm_big<T: Into<BigUint>>(values: impl IntoIterator<Item = T>) -> BigUint {
+    values.into_iter().fold(BigUint::from(1u8), |acc, v| {
+        let v = v.into();
+        if acc == BigUint::ZERO || v == BigUint::ZERO {
+            BigUint::ZERO
+        } else {
+            let g = big_gcd(acc.clone(), v.clone());
+            acc / g * v
+        }
+    })
+}
+
+/// `(a * b) % m` for any `u128` values - falls back to shift-and-add when the
+/// product would overflow.
+///
+/// Panics if `m == 0`.
+pub fn mul_mod(a: u128, b: u128, m: u128) -> u128 {
+    let (mut a, mut b) = (a % m, b % m);
+    if let Some(p) = a.checked_mul(b) {
+        return p % m;
+    }
+
+    let mut out = 0;
+    while b > 0 {
+        if b & 1 == 1 {
+            out = add_mod(out, a, m);
+        }
+        a = add_mod(a, a, m);
+        b >>= 1;
+    }
+    out
+}
+
+/// `(a + b) % m` for `a < m` and `b <= m`
+fn add_mod(a: u128, b: u128, m: u128) -> u128 {
+    // a + b >= m  <=>  a >= m - b, which can't overflow
+    if a >= m - b {
+        a - (m - b)
+    } else {
+        a + b
+    }
+}
+
+/// `base.pow(exp) % m` by repeated squaring.
+///
+/// Panics if `m == 0`.
+pub fn mod_pow(base: u128, mut exp: u128, m: u128) -> u128 {
+    let mut base = base % m;
+    let mut out = 1 % m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            out = mul_mod(out, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    out
+}
+
+/// The `x` in `0..m` with `a * x ≡ 1 (mod m)`, or `None` if `a` and `m` share a factor.
+///
+/// Panics if `m == 0`.
+pub fn mod_inv(a: i128, m: u128) -> Option<u128> {
+    inv_mod(rem_i128(a, m), m)
+}
+
+/// `mod_inv` for `a < m`
+fn inv_mod(a: u128, m: u128) -> Option<u128> {
+    // Extended Euclid, keeping the Bézout coefficient for `a` reduced mod m so nothing
+    // can overflow
+    let (mut r0, mut r1) = (m, a);
+    let (mut t0, mut t1) = (0, 1 % m);
+    while r1 != 0 {
+        let q = r0 / r1;
+        (r0, r1) = (r1, r0 - q * r1);
+        (t0, t1) = (t1, add_mod(t0, m - mul_mod(q, t1, m), m));
+    }
+    (r0 == 1).then_some(t0)
+}
+
+/// Solve `a · x = b` exactly by Gaussian elimination, where `a` has a row for each
+/// equation. There can be more equations than unknowns as long as they all agree.
+///
+/// Returns `None` unless there is exactly one solution.
+pub fn solve_linear(
+    mut a: Vec<Vec<BigRational>>,
+    mut b: Vec<BigRational>,
+) -> Option<Vec<BigRational>> {
+    let unknowns = a.first().map_or(0, Vec::len);
+    assert!(
+        a.len() == b.len() && a.iter().all(|row| row.len() == unknowns),
+        "a linear system needs one right hand side and {unknowns} coefficients per equation"
+    );
+
+    for col in 0..unknowns {
+        // Any non-zero pivot will do, as nothing is ever rounded
+        let pivot = (col..a.len()).find(|&r| a[r][col] != BigRational::ZERO)?;
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+
+        let (pivot_row, pivot_rhs) = (a[col].clone(), b[col].clone());
+        for (r, (row, rhs)) in a.iter_mut().zip(b.iter_mut()).enumerate() {
+            if r == col || row[col] == BigRational::ZERO {
+                continue;
+            }
+            let factor = &row[col] / &pivot_row[col];
+            for (v, p) in row.iter_mut().zip(&pivot_row).skip(col) {
+                *v -= &factor * p;
+            }
+            *rhs -= &factor * &pivot_rhs;
+        }
+    }
+
+    // Any equations left over have to reduce to 0 = 0
+    if b[unknowns..].iter().any(|v| *v != BigRational::ZERO) {
+        return None;
+    }
+    Some((0..unknowns).map(|i| &b[i] / &a[i][i]).collect())
+}
+
+/// `a mod m` as a value in `0..m`
+fn rem_i128(a: i128, m: u128) -> u128 {
+    let r = a.unsigned_abs() % m;
+    if a < 0 && r != 0 {
+        m - r
+    } else {
+        r
+    }
+}
+
+/// Solves the simultaneous congruences `x ≡ residue (mod modulus)`, which need not have
+/// coprime moduli.
+///
+/// Returns the smallest non-negative `x` and the modulus it is unique under (the lcm of
+/// all the moduli). `None` if the congruences are inconsistent or that modulus does not
+/// fit in a `u128` - [`crt_big`] separates the two cases.
+///
+/// Panics if any modulus is `0`.
+pub fn crt(congruences: impl IntoIterator<Item = (i128, u128)>) -> Option<(u128, u128)> {
+    congruences
+        .into_iter()
+        .try_fold((0, 1), |(x, m), (residue, modulus)| {
+            // Merge x ≡ r1 (mod m1) and x ≡ r2 (mod m2): x = r1 + m1 * k where
+            // (m1 / g) * k ≡ (r2 - r1) / g  (mod m2 / g)
+            let r2 = rem_i128(residue, modulus);
+            let g = gcd(m, modulus);
+            let diff = add_mod(r2, modulus - x % modulus, modulus);
+            if !diff.is_multiple_of(g) {
+                return None;
+            }
+            let reduced = modulus / g;
+            let inv = inv_mod(m / g % reduced, reduced)?;
+            let k = mul_mod(diff / g, inv, reduced);
+            let lcm = m.checked_mul(reduced)?;
+            Some((add_mod(x, mul_mod(m, k, lcm), lcm), lcm))
+        })
+}
+
+/// As [`crt`] but without any risk of overflow, so `None` always means the congruences
+/// are inconsistent.
+pub fn crt_big<R, M>(congruences: impl IntoIterator<Item = (R, M)>) -> Option<(BigUint, BigUint)>
+where
+    R: Into<BigInt>,
+    M: Into<BigUint>,
+{
+    let (x, m) = congruences.into_iter().try_fold(
+        (BigInt::ZERO, BigInt::from(1u8)),
+        |(x, m), (residue, modulus)| {
+            let modulus = BigInt::from(modulus.into());
+            let (g, p, _) = big_ext_gcd(m.clone(), modulus.clone());
+            let diff = residue.into() - &x;
+            if &diff % &g != BigInt::ZERO {
+                return None;
+            }
+            let lcm = &m / &g * &modulus;
+            // p * m ≡ g (mod modulus), so scaling by diff / g lands on the second residue
+            let x = big_rem(x + &m * (diff / &g * p), &lcm);
+            Some((x, lcm))
+        },
+    )?;
+    Some((x.to_biguint()?, m.to_biguint()?))
+}
+
+/// `a mod m` as a value in `0..m`
+fn big_rem(a: BigInt, m: &BigInt) -> BigInt {
+    ((a % m) + m) % m
+}
+
+fn big_gcd(mut a: BigUint, mut b: BigUint) -> BigUint {
+    while b != BigUint::ZERO {
+        let r = &a % &b;
+        (a, b) = (b, r);
+    }
+    a
+}
+
+/// Returns `(g, p, q)` with `a * p + b * q == g == gcd(a, b)`
+fn big_ext_gcd(a: BigInt, b: BigInt) -> (BigInt, BigInt, BigInt) {
+    let (mut r0, mut r1) = (a, b);
+    let (mut p0, mut p1) = (BigInt::from(1u8), BigInt::ZERO);
+    let (mut q0, mut q1) = (BigInt::ZERO, BigInt::from(1u8));
+    while r1 != BigInt::ZERO {
+        let q = &r0 / &r1;
+        let r = &r0 - &q * &r1;
+        let p = &p0 - &q * &p1;
+        let s = &q0 - &q * &q1;
+        (r0, r1, p0, p1, q0, q1) = (r1, r, p1, p, q1, s);
+    }
+    (r0, p0, q0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_gcd_lcm() {
+        assert_eq!(gcd(12, 18), 6);
+        assert_eq!(gcd(-12i32, 18), 6);
+        assert_eq!(gcd(0u8, 0), 0);
+        assert_eq!(lcm(4, 6), 12);
+        assert_eq!(lcm(-4i64, 6), 12);
+        assert_eq!(lcm(0, 6), 0);
+        assert_eq!(gcd_all([12, 18, 27]), 3);
+        assert_eq!(lcm_all([2u64, 3, 4, 5]), Some(60));
+        assert_eq!(lcm_all(Vec::<u8>::new()), Some(1));
+        assert_eq!(lcm_all([16u8, 17]), None);
+        assert_eq!(lcm_big([16u8, 17]), BigUint::from(272u32));
+        assert_eq!(checked_lcm(i8::MIN, 1), None);
+    }
+
+    #[test]
+    fn test_modular() {
+        assert_eq!(mod_pow(2, 10, 1000), 24);
+        assert_eq!(mod_pow(5, 0, 1), 0);
+        assert_eq!(mod_inv(3, 7), Some(5));
+        assert_eq!(mod_inv(-3, 7), Some(2));
+        assert_eq!(mod_inv(4, 8), None);
+        assert_eq!(mod_inv(4, 1), Some(0));
+        assert_eq!(
+            mul_mod(u128::MAX - 1, u128::MAX - 1, u128::MAX),
+            1,
+            "(-1)^2 ≡ 1"
+        );
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(crt([(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+        // Non-coprime moduli
+        assert_eq!(crt([(2, 4), (4, 6)]), Some((10, 12)));
+        assert_eq!(crt([(1, 4), (2, 6)]), None);
+        assert_eq!(crt([(-1, 5)]), Some((4, 5)));
+        assert_eq!(crt(Vec::new()), Some((0, 1)));
+
+        // The combined modulus needs more than 128 bits
+        let big = [(1, u128::MAX), (0, u128::MAX - 1)];
+        assert_eq!(crt(big), None);
+        let (x, m) = crt_big(big).unwrap();
+        assert_eq!(m, BigUint::from(u128::MAX) * (u128::MAX - 1));
+        assert_eq!(&x % u128::MAX, BigUint::from(1u8));
+        assert_eq!(&x % (u128::MAX - 1), BigUint::ZERO);
+        assert_eq!(crt_big([(1, 4u8), (2, 6)]), None);
+    }
+
+    #[test]
+    fn test_solve_linear() {
+        let system = |rows: &[&[i64]]| {
+            let r = |v: i64| BigRational::from_integer(v.into());
+            let a = rows
+                .iter()
+                .map(|row| row[..row.len() - 1].iter().map(|&v| r(v)).collect());
+            let b = rows.iter().map(|row| r(row[row.len() - 1]));
+            solve_linear(a.collect(), b.collect())
+        };
+        let ratio = |n: i64, d: i64| BigRational::new(n.into(), d.into());
+
+        // 3y = 3, x + 2y = 3 and 3x - y = 2, with a zero to pivot around first
+        assert_eq!(
+            system(&[&[0, 3, 3], &[1, 2, 3], &[3, -1, 2]]),
+            Some(vec![ratio(1, 1), ratio(1, 1)])
+        );
+        assert_eq!(
+            system(&[&[2, 1, 1], &[1, 3, 1]]),
+            Some(vec![ratio(2, 5), ratio(1, 5)])
+        );
+        // Inconsistent, and then not enough independent equations
+        assert_eq!(system(&[&[1, 1, 1], &[1, 1, 2]]), None);
+        assert_eq!(system(&[&[1, 1, 1], &[2, 2, 2]]), None);
+        assert_eq!(system(&[&[1, 2, 5], &[3, -1, 1], &[1, 1, 4]]), None);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_gcd_lcm(a in any::<u64>(), b in any::<u64>()) {
+            let g = gcd(a, b);
+            if g != 0 {
+                prop_assert_eq!(a % g, 0);
+                prop_assert_eq!(b % g, 0);
+                prop_assert_eq!(gcd(a / g, b / g), 1);
+            }
+            let l = lcm(u128::from(a), u128::from(b));
+            prop_assert_eq!(u128::from(g) * l, u128::from(a) * u128::from(b));
+            prop_assert_eq!(gcd(i128::from(a), -i128::from(b)), u128::from(g) as i128);
+        }
+
+        #[test]
+        fn prop_lcm_all(values in prop::collection::vec(1..u128::MAX, 0..6)) {
+            let big = lcm_big(values.iter().copied());
+            match lcm_all(values.iter().copied()) {
+                Some(l) => prop_assert_eq!(BigUint::from(l), big),
+                None => prop_assert!(big > BigUint::from(u128::MAX)),
+            }
+        }
+
+        #[test]
+        fn prop_mul_pow_mod(a in any::<u128>(), b in any::<u128>(), e in any::<u128>(), m in 1..=u128::MAX) {
+            let m_big = BigUint::from(m);
+            prop_assert_eq!(
+                BigUint::from(mul_mod(a, b, m)),
+                BigUint::from(a) * b % &m_big
+            );
+            prop_assert_eq!(
+                BigUint::from(mod_pow(a, e, m)),
+                BigUint::from(a).modpow(&BigUint::from(e), &m_big)
+            );
+        }
+
+        #[test]
+        fn prop_mod_inv(a in any::<i128>(), m in 1..=u128::MAX) {
+            let coprime = gcd(a.unsigned_abs(), m) == 1;
+            match mod_inv(a, m) {
+                Some(inv) => {
+                    prop_assert!(coprime || m == 1);
+                    prop_assert!(inv < m);
+                    prop_assert_eq!(mul_mod(rem_i128(a, m), inv, m), 1 % m);
+                }
+                None => prop_assert!(!coprime),
+            }
+        }
+
+        #[test]
+        fn prop_crt(congruences in prop::collection::vec((-1000i128..1000, 1..100u128), 0..5)) {
+            let brute = (0..lcm_all(congruences.iter().map(|c| c.1)).unwrap()).find(|&x| {
+                congruences
+                    .iter()
+                    .all(|&(r, m)| x % m == rem_i128(r, m))
+            });
+            let solved = crt(congruences.iter().copied());
+            prop_assert_eq!(solved.map(|(x, _)| x), brute);
+            if let Some((x, m)) = solved {
+                prop_assert_eq!(Some(m), lcm_all(congruences.iter().map(|c| c.1)));
+                prop_assert_eq!(crt_big(congruences), Some((BigUint::from(x), BigUint::from(m))));
+            }
+        }
+
+        #[test]
+        fn prop_crt_large(
+            congruences in prop::collection::vec((any::<i128>(), 1..=u128::MAX), 0..4)
+        ) {
+            let big = crt_big(congruences.iter().copied());
+            if let Some((x, m)) = crt(congruences.iter().copied()) {
+                prop_assert_eq!(big, Some((BigUint::from(x), BigUint::from(m))));
+            } else if let Some((x, m)) = big {
+                // Only an overflowing modulus can make the primitive version give up
+                prop_assert!(m > BigUint::from(u128::MAX));
+                for (r, modulus) in congruences {
+                    let r = big_rem(BigInt::from(r), &BigInt::from(modulus));
+                    prop_assert_eq!(BigInt::from(&x % modulus), r);
+                }
+            }
+        }
+    }
+}
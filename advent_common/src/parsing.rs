@@ -0,0 +1,702 @@
+//! nom based building blocks for reading puzzle input
+//!
+//! Everything reports failures as a [`ParseError`] carrying the (1-based) line
+//! and column of the problem so a bad input file can be fixed without a debugger.
+//! The binaries attach the file name with [`in_file`] once the error reaches `main`.
+
+use nom::{
+    bytes::complete::take_till1,
+    character::complete::{char, digit1, one_of, space0},
+    combinator::{all_consuming, map_res, opt, recognize},
+    error::{context, ErrorKind, VerboseError, VerboseErrorKind},
+    sequence::{pair, separated_pair},
+    Finish, IResult, Offset,
+};
+use std::{
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader},
+    str::FromStr,
+};
+
+/// The result type for all of the nom parsers used with this module
+pub type PResult<'a, O> = IResult<&'a str, O, VerboseError<&'a str>>;
+
+/// Where and why some input could not be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The input file, once known
+    pub file: Option<String>,
+    pub line: usize,
+    pub column: usize,
+    pub expected: String,
+    pub found: String,
+}
+
+impl ParseError {
+    pub fn new(
+        line: usize,
+        column: usize,
+        expected: impl Into<String>,
+        found: impl Into<String>,
+    ) -> Self {
+        ParseError {
+            file: None,
+            line,
+            column,
+            expected: expected.into(),
+            found: found.into(),
+        }
+    }
+
+    /// The input stopped after `lines` lines when `expected` was still to come
+    pub fn end_of_input(lines: usize, expected: impl Into<String>) -> Self {
+        ParseError::new(lines + 1, 1, expected, "end of input")
+    }
+
+    /// Record the file the error was found in
+    #[must_use]
+    pub fn with_file(mut self, file: impl Into<String>) -> Self {
+        self.file = Some(file.into());
+        self
+    }
+
+    /// Move the error down by `lines` - for errors from a slice of the full input
+    #[must_use]
+    pub fn offset_by(mut self, lines: usize) -> Self {
+        self.line += lines;
+        self
+    }
+
+    /// Build an error for position `offset` (in bytes) of `input`
+    fn at_offset(input: &str, offset: usize, expected: impl Into<String>) -> Self {
+        let before = &input[..offset];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |p| p + 1);
+        let column = input[line_start..offset].chars().count() + 1;
+
+        let found = match &input[offset..] {
+            // Running out of a single line is the end of that line, not of the input
+            "" if !input.contains('\n') => "end of line".to_string(),
+            rest => describe(rest),
+        };
+
+        ParseError::new(line, column, expected, found)
+    }
+
+    fn from_verbose(input: &str, err: &VerboseError<&str>) -> Self {
+        // The first entry is where parsing actually stopped, later entries are the
+        // contexts it was wrapped in - use the innermost context as the expectation
+        let Some((rest, kind)) = err.errors.first() else {
+            return ParseError::new(1, 1, "valid input", describe(input));
+        };
+
+        let expected = err
+            .errors
+            .iter()
+            .find_map(|(_, k)| match k {
+                VerboseErrorKind::Context(c) => Some((*c).to_string()),
+                _ => None,
+            })
+            .unwrap_or_else(|| match kind {
+                VerboseErrorKind::Char(c) => format!("'{c}'"),
+                // Something was left over
+                VerboseErrorKind::Nom(ErrorKind::Eof) if input.contains('\n') => {
+                    "end of input".to_string()
+                }
+                VerboseErrorKind::Nom(ErrorKind::Eof) => "end of line".to_string(),
+                VerboseErrorKind::Nom(k) => k.description().to_lowercase(),
+                VerboseErrorKind::Context(c) => (*c).to_string(),
+            });
+
+        ParseError::at_offset(input, input.offset(rest), expected)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{file}: ")?;
+        }
+        write!(
+            f,
+            "line {}, column {}: expected {}, found {}",
+            self.line, self.column, self.expected, self.found
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Name `file` in an error coming back from a solution - parse errors get the
+/// file recorded against their position, anything else just gets it as context
+pub fn in_file(file: &str) -> impl Fn(anyhow::Error) -> anyhow::Error + '_ {
+    move |err| match err.downcast::<ParseError>() {
+        Ok(e) => e.with_file(file).into(),
+        Err(err) => err.context(file.to_string()),
+    }
+}
+
+/// Read all of the lines of an input file
+///
+/// # Errors
+///
+/// Returns an error naming the file if it can't be opened or isn't valid UTF-8
+pub fn read_lines(file: &str) -> anyhow::Result<Vec<String>> {
+    File::open(file)
+        .and_then(|f| BufReader::new(f).lines().collect())
+        .map_err(|e| anyhow::anyhow!("{file}: {e}"))
+}
+
+/// Show (the start of) the remaining text on the line for an error message
+fn describe(rest: &str) -> String {
+    let line = rest.lines().next().unwrap_or_default();
+    if line.is_empty() {
+        if rest.is_empty() {
+            "end of input".to_string()
+        } else {
+            "end of line".to_string()
+        }
+    } else if line.chars().count() > 20 {
+        format!("{:?}...", line.chars().take(20).collect::<String>())
+    } else {
+        format!("{line:?}")
+    }
+}
+
+//
+// Parsers
+//
+
+/// An unsigned integer
+pub fn uint<T: FromStr>(input: &str) -> PResult<'_, T> {
+    context("unsigned integer", map_res(digit1, str::parse))(input)
+}
+
+/// An integer with an optional leading sign
+pub fn int<T: FromStr>(input: &str) -> PResult<'_, T> {
+    context(
+        "integer",
+        map_res(recognize(pair(opt(one_of("+-")), digit1)), str::parse),
+    )(input)
+}
+
+/// `key: value` - the key is everything up to the first colon, spaces after the
+/// colon are skipped before `value` is applied
+pub fn key_value<'a, O, F>(value: F) -> impl FnMut(&'a str) -> PResult<'a, (&'a str, O)>
+where
+    F: FnMut(&'a str) -> PResult<'a, O>,
+{
+    separated_pair(
+        context("key", take_till1(|c| c == ':' || c == '\n')),
+        pair(char(':'), space0),
+        value,
+    )
+}
+
+//
+// Runners
+//
+
+/// Run `parser` over the whole of `input`, it is an error for anything to be left over
+///
+/// # Errors
+///
+/// Returns the position and expectation of the first thing that did not parse
+pub fn parse_all<'a, O, F>(input: &'a str, parser: F) -> Result<O, ParseError>
+where
+    F: FnMut(&'a str) -> PResult<'a, O>,
+{
+    all_consuming(parser)(input)
+        .finish()
+        .map(|(_, o)| o)
+        .map_err(|e| ParseError::from_verbose(input, &e))
+}
+
+/// Apply `f` to each line, fixing up the line number of any error
+///
+/// # Errors
+///
+/// Returns the first error from `f`
+pub fn map_lines<'a, O, F>(lines: &'a [String], mut f: F) -> Result<Vec<O>, ParseError>
+where
+    F: FnMut(&'a str) -> Result<O, ParseError>,
+{
+    lines
+        .iter()
+        .enumerate()
+        .map(|(idx, l)| f(l).map_err(|e| e.offset_by(idx)))
+        .collect()
+}
+
+/// Run `parser` over every line in turn
+///
+/// # Errors
+///
+/// Returns the first line that does not parse
+pub fn parse_lines<'a, O, F>(lines: &'a [String], mut parser: F) -> Result<Vec<O>, ParseError>
+where
+    F: FnMut(&'a str) -> PResult<'a, O>,
+{
+    map_lines(lines, |l| parse_all(l, &mut parser))
+}
+
+/// Every integer in `line`, in order. Anything else is skipped over.
+///
+/// A `-` is only taken as a sign when it is not preceded by a digit so that
+/// ranges such as `2-4` give `[2, 4]`.
+///
+/// # Errors
+///
+/// Returns an error if a number does not fit in `T`
+pub fn ints_in_line<T: FromStr>(line: &str) -> Result<Vec<T>, ParseError> {
+    let bytes = line.as_bytes();
+    let mut out = vec![];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let signed = bytes[i] == b'-'
+            && bytes.get(i + 1).is_some_and(u8::is_ascii_digit)
+            && (i == 0 || !bytes[i - 1].is_ascii_digit());
+
+        if signed || bytes[i].is_ascii_digit() {
+            let start = i;
+            i += 1;
+            while i < bytes.len() && bytes[i].is_ascii_digit() {
+                i += 1;
+            }
+            let n = line[start..i]
+                .parse()
+                .map_err(|_| ParseError::at_offset(line, start, "an integer in range"))?;
+            out.push(n);
+        } else {
+            i += 1;
+        }
+    }
+
+    Ok(out)
+}
+
+/// The input as exactly `N` lines - `expected` describes what is missing if there are fewer
+///
+/// # Errors
+///
+/// Returns an error at the end of the input if it is short, or at the first extra line
+pub fn exact_lines<'a, const N: usize>(
+    lines: &'a [String],
+    expected: &str,
+) -> Result<&'a [String; N], ParseError> {
+    match lines.get(N) {
+        Some(extra) => Err(ParseError::new(N + 1, 1, "end of input", describe(extra))),
+        None => lines
+            .try_into()
+            .map_err(|_| ParseError::end_of_input(lines.len(), expected)),
+    }
+}
+
+//
+// Multi-line structures
+//
+
+/// A run of lines from the input, remembering where it started
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Block<'a> {
+    /// Index (0-based) of the first line of the block in the full input
+    pub start: usize,
+    pub lines: &'a [String],
+}
+
+impl<'a> Block<'a> {
+    /// Run `parser` over each line of the block
+    ///
+    /// # Errors
+    ///
+    /// Returns the first line that does not parse
+    pub fn parse_lines<O, F>(&self, parser: F) -> Result<Vec<O>, ParseError>
+    where
+        F: FnMut(&'a str) -> PResult<'a, O>,
+    {
+        parse_lines(self.lines, parser).map_err(|e| e.offset_by(self.start))
+    }
+
+    /// Apply `f` to each line of the block
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from `f`
+    pub fn map_lines<O, F>(&self, f: F) -> Result<Vec<O>, ParseError>
+    where
+        F: FnMut(&'a str) -> Result<O, ParseError>,
+    {
+        map_lines(self.lines, f).map_err(|e| e.offset_by(self.start))
+    }
+
+    /// Run `parser` over the lines of the block joined with `\n`
+    ///
+    /// # Errors
+    ///
+    /// Returns the position of the first thing that does not parse
+    pub fn parse_joined<O, F>(&self, parser: F) -> Result<O, ParseError>
+    where
+        F: for<'b> FnMut(&'b str) -> PResult<'b, O>,
+    {
+        let joined = self.lines.join("\n");
+        parse_all(&joined, parser).map_err(|e| e.offset_by(self.start))
+    }
+}
+
+/// Split the input into blocks separated by blank lines
+pub fn blocks(lines: &[String]) -> Vec<Block<'_>> {
+    let mut out = vec![];
+    let mut start = 0;
+
+    for (idx, line) in lines.iter().chain([&String::new()]).enumerate() {
+        if line.trim().is_empty() {
+            if idx > start {
+                out.push(Block {
+                    start,
+                    lines: &lines[start..idx],
+                });
+            }
+            start = idx + 1;
+        }
+    }
+
+    out
+}
+
+/// Split the input into exactly `N` blocks - `expected` describes what is missing if
+/// there are fewer
+///
+/// # Errors
+///
+/// Returns an error at the end of the input if it is short, or at the start of the
+/// first extra block
+pub fn exact_blocks<'a, const N: usize>(
+    lines: &'a [String],
+    expected: &str,
+) -> Result<[Block<'a>; N], ParseError> {
+    let found = blocks(lines);
+    match found.get(N) {
+        Some(extra) => Err(ParseError::new(
+            extra.start + 1,
+            1,
+            "end of input",
+            describe(&extra.lines[0]),
+        )),
+        None => found
+            .try_into()
+            .map_err(|_| ParseError::end_of_input(lines.len(), expected)),
+    }
+}
+
+/// A block whose first line is a `key: value` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Section<'a> {
+    pub key: &'a str,
+    pub value: &'a str,
+    /// The header line in full
+    pub header: Block<'a>,
+    /// The lines after the header
+    pub body: Block<'a>,
+}
+
+impl<'a> Section<'a> {
+    /// Run `parser` over the value part of the header line
+    ///
+    /// # Errors
+    ///
+    /// Returns the position (within the header line) of the first thing that does not parse
+    pub fn parse_value<O, F>(&self, parser: F) -> Result<O, ParseError>
+    where
+        F: FnMut(&'a str) -> PResult<'a, O>,
+    {
+        let header = &self.header.lines[0];
+        let value_column = header[..header.len() - self.value.len()].chars().count();
+        parse_all(self.value, parser).map_err(|mut e| {
+            e.column += value_column;
+            e.offset_by(self.header.start)
+        })
+    }
+}
+
+/// Split the input into blank line separated sections, each headed by a
+/// `key: value` (or just `key:`) line
+///
+/// # Errors
+///
+/// Returns an error if a block does not start with a header line
+pub fn sections(lines: &[String]) -> Result<Vec<Section<'_>>, ParseError> {
+    blocks(lines)
+        .into_iter()
+        .map(|block| {
+            let (key, value) = parse_all(&block.lines[0], key_value(nom::combinator::rest))
+                .map_err(|e| e.offset_by(block.start))?;
+            Ok(Section {
+                key,
+                value,
+                header: Block {
+                    start: block.start,
+                    lines: &block.lines[..1],
+                },
+                body: Block {
+                    start: block.start + 1,
+                    lines: &block.lines[1..],
+                },
+            })
+        })
+        .collect()
+}
+
+/// A rectangular grid of characters
+///
+/// # Errors
+///
+/// Returns an error if the rows are not all the same width
+pub fn grid(lines: &[String]) -> Result<Vec<Vec<char>>, ParseError> {
+    grid_of(lines, "any character", Some)
+}
+
+/// A rectangular grid of single digits
+///
+/// # Errors
+///
+/// Returns an error for a non-digit cell or if the rows are not all the same width
+pub fn digit_grid<T: From<u8>>(lines: &[String]) -> Result<Vec<Vec<T>>, ParseError> {
+    #[allow(clippy::cast_possible_truncation)]
+    grid_of(lines, "a digit", |c| {
+        c.to_digit(10).map(|d| T::from(d as u8))
+    })
+}
+
+/// Rows of characters, each converted by `cell`. `expected` describes the valid
+/// characters for error messages.
+///
+/// # Errors
+///
+/// Returns the position of the first character that `cell` rejects
+pub fn rows_of<T, F>(
+    lines: &[String],
+    expected: &str,
+    mut cell: F,
+) -> Result<Vec<Vec<T>>, ParseError>
+where
+    F: FnMut(char) -> Option<T>,
+{
+    map_lines(lines, |line| {
+        line.chars()
+            .enumerate()
+            .map(|(ci, c)| {
+                cell(c).ok_or_else(|| ParseError::new(1, ci + 1, expected, format!("{c:?}")))
+            })
+            .collect()
+    })
+}
+
+/// A rectangular grid where each character is converted by `cell`.
+/// `expected` describes the valid characters for error messages.
+///
+/// # Errors
+///
+/// Returns an error if `cell` rejects a character or if the rows are not all the same width
+pub fn grid_of<T, F>(lines: &[String], expected: &str, cell: F) -> Result<Vec<Vec<T>>, ParseError>
+where
+    F: FnMut(char) -> Option<T>,
+{
+    let rows = rows_of(lines, expected, cell)?;
+    let width = rows.first().map_or(0, Vec::len);
+
+    match rows.iter().position(|row| row.len() != width) {
+        Some(ri) => Err(ParseError::new(
+            ri + 1,
+            rows[ri].len().min(width) + 1,
+            format!("a row of {width} cells"),
+            format!("{} cells", rows[ri].len()),
+        )),
+        None => Ok(rows),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::{
+        bytes::complete::tag, character::complete::char, combinator::cut, multi::separated_list1,
+    };
+
+    fn to_lines(s: &str) -> Vec<String> {
+        s.lines().map(|l| l.trim().to_string()).collect()
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(parse_all("123", uint::<u32>), Ok(123));
+        assert_eq!(parse_all("-123", int::<i64>), Ok(-123));
+        assert_eq!(parse_all("+7", int::<i8>), Ok(7));
+
+        let e = parse_all("-1", uint::<u32>).unwrap_err();
+        assert_eq!(
+            (e.line, e.column, e.expected.as_str()),
+            (1, 1, "unsigned integer")
+        );
+
+        // Doesn't fit in a u8
+        let e = parse_all("300", uint::<u8>).unwrap_err();
+        assert_eq!(e.expected, "unsigned integer");
+    }
+
+    #[test]
+    fn test_error_position() {
+        let list = |i| separated_list1(tag(", "), uint::<u32>)(i);
+        assert_eq!(parse_all("1, 2, 3", list), Ok(vec![1, 2, 3]));
+
+        let e = parse_all("1, 2, x", list).unwrap_err();
+        assert_eq!((e.line, e.column), (1, 5));
+        assert_eq!(
+            e.to_string(),
+            "line 1, column 5: expected end of line, found \", x\""
+        );
+
+        let e = parse_all("1, 2\n3, ", |i| {
+            separated_list1(char('\n'), separated_list1(tag(", "), cut(uint::<u32>)))(i)
+        })
+        .unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "line 2, column 4: expected unsigned integer, found end of input"
+        );
+        let e = parse_all("1, ", |i| separated_list1(tag(", "), cut(uint::<u32>))(i)).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "line 1, column 4: expected unsigned integer, found end of line"
+        );
+
+        let lines = to_lines("1, 2\n3, 4\n5; 6");
+        let e = parse_lines(&lines, list).unwrap_err();
+        assert_eq!((e.line, e.column), (3, 2));
+    }
+
+    #[test]
+    fn test_ints_in_line() {
+        assert_eq!(
+            ints_in_line::<i32>("Sensor at x=-2, y=15: closest beacon is at x=10, y=-16"),
+            Ok(vec![-2, 15, 10, -16])
+        );
+        assert_eq!(ints_in_line::<u32>("2-4,6-8"), Ok(vec![2, 4, 6, 8]));
+        assert_eq!(ints_in_line::<u32>("no numbers here"), Ok(vec![]));
+
+        let e = ints_in_line::<u8>("1 2 1000").unwrap_err();
+        assert_eq!(e.column, 5);
+    }
+
+    #[test]
+    fn test_blocks_and_sections() {
+        let lines = to_lines("seeds: 1 2 3\n\nsoil map:\n1 2 3\n4 5 6\n\n\nwater map:\n7 8 900");
+        let b = blocks(&lines);
+        assert_eq!(b.len(), 3);
+        assert_eq!(b[1].start, 2);
+        assert_eq!(b[2].lines, &["water map:", "7 8 900"]);
+
+        let s = sections(&lines).unwrap();
+        assert_eq!((s[0].key, s[0].value), ("seeds", "1 2 3"));
+        assert!(s[0].body.lines.is_empty());
+        assert_eq!((s[1].key, s[1].value), ("soil map", ""));
+
+        let rows = s[1].body.map_lines(ints_in_line::<u32>).unwrap();
+        assert_eq!(rows, vec![vec![1, 2, 3], vec![4, 5, 6]]);
+
+        let e = s[2]
+            .body
+            .parse_lines(|i| separated_list1(tag(" "), uint::<u8>)(i))
+            .unwrap_err();
+        assert_eq!(e.line, 9);
+
+        let e = s[0]
+            .parse_value(|i| separated_list1(tag(" "), uint::<u8>)(i))
+            .unwrap();
+        assert_eq!(e, vec![1, 2, 3]);
+
+        let lines = to_lines("\n\nseeds: 1 2 x");
+        let e = sections(&lines).unwrap()[0]
+            .parse_value(|i| separated_list1(tag(" "), uint::<u8>)(i))
+            .unwrap_err();
+        assert_eq!((e.line, e.column), (3, 11));
+
+        let lines = to_lines("no header here\n1 2 3");
+        assert_eq!(sections(&lines).unwrap_err().expected, "':'");
+    }
+
+    #[test]
+    fn test_exact() {
+        let lines = to_lines("a\nb");
+        assert_eq!(exact_lines::<2>(&lines, "x"), Ok(&["a".into(), "b".into()]));
+
+        let e = exact_lines::<3>(&lines, "a third wire").unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "line 3, column 1: expected a third wire, found end of input"
+        );
+
+        let e = exact_lines::<1>(&lines, "x").unwrap_err();
+        assert_eq!((e.line, e.expected.as_str()), (2, "end of input"));
+
+        let lines = to_lines("a\nb\n\nc\n\n\nd");
+        let [first, second] = exact_blocks::<2>(&lines[..4], "x").unwrap();
+        assert_eq!((first.start, second.start), (0, 3));
+
+        let e = exact_blocks::<2>(&lines, "x").unwrap_err();
+        assert_eq!((e.line, e.found.as_str()), (7, "\"d\""));
+
+        let e = exact_blocks::<2>(&lines[..2], "the folds").unwrap_err();
+        assert_eq!((e.line, e.expected.as_str()), (3, "the folds"));
+    }
+
+    #[test]
+    fn test_file_names() {
+        let e = parse_all("x", uint::<u32>)
+            .unwrap_err()
+            .with_file("day_01.txt");
+        assert_eq!(
+            e.to_string(),
+            "day_01.txt: line 1, column 1: expected unsigned integer, found \"x\""
+        );
+
+        let err = in_file("day_02.txt")(ParseError::end_of_input(3, "a move").into());
+        assert_eq!(
+            err.to_string(),
+            "day_02.txt: line 4, column 1: expected a move, found end of input"
+        );
+
+        let err = in_file("day_03.txt")(anyhow::anyhow!("no solution"));
+        assert_eq!(format!("{err:#}"), "day_03.txt: no solution");
+
+        let err = read_lines("no/such/file.txt").unwrap_err();
+        assert!(err.to_string().starts_with("no/such/file.txt: "));
+    }
+
+    #[test]
+    fn test_grids() {
+        let lines = to_lines("123\n456");
+        assert_eq!(
+            digit_grid::<u32>(&lines),
+            Ok(vec![vec![1, 2, 3], vec![4, 5, 6]])
+        );
+        assert_eq!(grid(&lines).unwrap()[1], vec!['4', '5', '6']);
+
+        let e = digit_grid::<u8>(&to_lines("123\n4x6")).unwrap_err();
+        assert_eq!((e.line, e.column, e.expected.as_str()), (2, 2, "a digit"));
+
+        let e = rows_of(&to_lines("()\n(x)"), "a bracket", |c| {
+            "()".contains(c).then_some(c)
+        })
+        .unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "line 2, column 2: expected a bracket, found 'x'"
+        );
+
+        let e = grid(&to_lines("123\n45")).unwrap_err();
+        assert_eq!((e.line, e.column), (2, 3));
+        assert_eq!(e.expected, "a row of 3 cells");
+    }
+}
@@ -0,0 +1,477 @@
+//! Generic graph searches driven by a `neighbours` closure
+//!
+//! States only need to be `Clone + Eq + Hash` - the priority queue orders by cost
+//! and an internal index so callers no longer have to write their own reversed `Ord`
+//! wrappers to get a min-heap out of `BinaryHeap`.
+
+use std::{
+    cmp::Reverse,
+    collections::{hash_map::Entry, BinaryHeap, HashMap, VecDeque},
+    hash::Hash,
+    ops::Add,
+};
+
+/// Anything that can be used as an edge weight - `Default` is taken to be zero
+pub trait Cost: Copy + Ord + Default + Add<Output = Self> {}
+
+impl<T: Copy + Ord + Default + Add<Output = T>> Cost for T {}
+
+/// Maps every reached state to its predecessor and the cost of reaching it.
+/// The start state is its own parent.
+pub type Parents<S, C> = HashMap<S, (S, C)>;
+
+/// Shortest path from `start` to the first state satisfying `is_goal`.
+///
+/// Returns the states along the path (including both ends) and the total cost.
+pub fn dijkstra<S, C, FN, IN, FG>(start: S, neighbours: FN, is_goal: FG) -> Option<(Vec<S>, C)>
+where
+    S: Clone + Eq + Hash,
+    C: Cost,
+    FN: FnMut(&S) -> IN,
+    IN: IntoIterator<Item = (S, C)>,
+    FG: FnMut(&S) -> bool,
+{
+    astar(start, neighbours, |_| C::default(), is_goal)
+}
+
+/// A* search - as `dijkstra` but guided by an admissible, consistent `heuristic`
+/// giving a lower bound on the remaining cost to a goal.
+pub fn astar<S, C, FN, IN, FH, FG>(
+    start: S,
+    mut neighbours: FN,
+    mut heuristic: FH,
+    mut is_goal: FG,
+) -> Option<(Vec<S>, C)>
+where
+    S: Clone + Eq + Hash,
+    C: Cost,
+    FN: FnMut(&S) -> IN,
+    IN: IntoIterator<Item = (S, C)>,
+    FH: FnMut(&S) -> C,
+    FG: FnMut(&S) -> bool,
+{
+    // Nodes are stored once and referred to by index everywhere else
+    let mut nodes: Vec<(S, usize, C)> = vec![(start.clone(), 0, C::default())];
+    let mut index: HashMap<S, usize> = HashMap::from([(start.clone(), 0)]);
+    let mut heap = BinaryHeap::from([Reverse((heuristic(&start), C::default(), 0))]);
+
+    while let Some(Reverse((_, cost, idx))) = heap.pop() {
+        if cost > nodes[idx].2 {
+            continue; // stale entry - a cheaper route has been found since
+        }
+
+        if is_goal(&nodes[idx].0) {
+            let mut path = vec![nodes[idx].0.clone()];
+            let mut current = idx;
+            while current != 0 {
+                current = nodes[current].1;
+                path.push(nodes[current].0.clone());
+            }
+            path.reverse();
+            return Some((path, cost));
+        }
+
+        for (next, step) in neighbours(&nodes[idx].0) {
+            let alt = cost + step;
+            let next_idx = match index.entry(next) {
+                Entry::Vacant(e) => {
+                    nodes.push((e.key().clone(), idx, alt));
+                    e.insert(nodes.len() - 1);
+                    nodes.len() - 1
+                }
+                Entry::Occupied(e) => {
+                    let n = &mut nodes[*e.get()];
+                    if alt >= n.2 {
+                        continue;
+                    }
+                    n.1 = idx;
+                    n.2 = alt;
+                    *e.get()
+                }
+            };
+            heap.push(Reverse((
+                alt + heuristic(&nodes[next_idx].0),
+                alt,
+                next_idx,
+            )));
+        }
+    }
+
+    None
+}
+
+/// Cheapest cost to every state reachable from `start`
+pub fn dijkstra_all<S, C, FN, IN>(start: S, mut neighbours: FN) -> Parents<S, C>
+where
+    S: Clone + Eq + Hash,
+    C: Cost,
+    FN: FnMut(&S) -> IN,
+    IN: IntoIterator<Item = (S, C)>,
+{
+    let mut parents: Parents<S, C> =
+        HashMap::from([(start.clone(), (start.clone(), C::default()))]);
+    let mut heap = BinaryHeap::from([Reverse((C::default(), 0usize))]);
+    let mut nodes = vec![start];
+
+    while let Some(Reverse((cost, idx))) = heap.pop() {
+        if cost > parents[&nodes[idx]].1 {
+            continue;
+        }
+
+        let current = nodes[idx].clone();
+        for (next, step) in neighbours(&current) {
+            let alt = cost + step;
+            match parents.get_mut(&next) {
+                Some(existing) if existing.1 <= alt => continue,
+                Some(existing) => *existing = (current.clone(), alt),
+                None => {
+                    parents.insert(next.clone(), (current.clone(), alt));
+                }
+            }
+            nodes.push(next);
+            heap.push(Reverse((alt, nodes.len() - 1)));
+        }
+    }
+
+    parents
+}
+
+/// Breadth first search - the shortest path in number of steps from `start` to
+/// the first state satisfying `is_goal`.
+pub fn bfs<S, FN, IN, FG>(start: S, mut neighbours: FN, mut is_goal: FG) -> Option<Vec<S>>
+where
+    S: Clone + Eq + Hash,
+    FN: FnMut(&S) -> IN,
+    IN: IntoIterator<Item = S>,
+    FG: FnMut(&S) -> bool,
+{
+    let mut parents: Parents<S, usize> = HashMap::from([(start.clone(), (start.clone(), 0))]);
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(current) = queue.pop_front() {
+        if is_goal(&current) {
+            return reconstruct_path(&parents, &current);
+        }
+
+        let steps = parents[&current].1 + 1;
+        for next in neighbours(&current) {
+            if let Entry::Vacant(e) = parents.entry(next.clone()) {
+                e.insert((current.clone(), steps));
+                queue.push_back(next);
+            }
+        }
+    }
+
+    None
+}
+
+/// Number of steps to every state reachable from `start`
+pub fn bfs_all<S, FN, IN>(start: S, mut neighbours: FN) -> Parents<S, usize>
+where
+    S: Clone + Eq + Hash,
+    FN: FnMut(&S) -> IN,
+    IN: IntoIterator<Item = S>,
+{
+    let mut parents: Parents<S, usize> = HashMap::from([(start.clone(), (start.clone(), 0))]);
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(current) = queue.pop_front() {
+        let steps = parents[&current].1 + 1;
+        for next in neighbours(&current) {
+            if let Entry::Vacant(e) = parents.entry(next.clone()) {
+                e.insert((current.clone(), steps));
+                queue.push_back(next);
+            }
+        }
+    }
+
+    parents
+}
+
+/// Walk the `parents` map back from `target` to the start of the search.
+///
+/// Returns `None` if `target` was never reached.
+pub fn reconstruct_path<S, C>(parents: &Parents<S, C>, target: &S) -> Option<Vec<S>>
+where
+    S: Clone + Eq + Hash,
+{
+    let mut path = vec![target.clone()];
+    let mut current = target;
+    loop {
+        let (parent, _) = parents.get(current)?;
+        if parent == current {
+            break;
+        }
+        path.push(parent.clone());
+        current = parent;
+    }
+    path.reverse();
+    Some(path)
+}
+
+/// Distances and routes between every pair of nodes in a graph
+#[derive(Debug, Clone)]
+pub struct AllPairs<N, C> {
+    nodes: Vec<N>,
+    index: HashMap<N, usize>,
+    dist: Vec<Vec<Option<C>>>,
+    next: Vec<Vec<Option<usize>>>,
+}
+
+impl<N, C> AllPairs<N, C>
+where
+    N: Clone + Eq + Hash,
+    C: Cost,
+{
+    /// The nodes of the graph, in the order they were supplied
+    pub fn nodes(&self) -> &[N] {
+        &self.nodes
+    }
+
+    /// Position of `node` in `nodes()`
+    pub fn index_of(&self, node: &N) -> Option<usize> {
+        self.index.get(node).copied()
+    }
+
+    /// Cost of the shortest route from `from` to `to`, if there is one
+    pub fn distance(&self, from: &N, to: &N) -> Option<C> {
+        self.dist[self.index_of(from)?][self.index_of(to)?]
+    }
+
+    /// The nodes along the shortest route from `from` to `to` (including both ends)
+    pub fn path(&self, from: &N, to: &N) -> Option<Vec<N>> {
+        let (mut i, j) = (self.index_of(from)?, self.index_of(to)?);
+        let mut path = vec![self.nodes[i].clone()];
+        while i != j {
+            i = self.next[i][j]?;
+            path.push(self.nodes[i].clone());
+        }
+        Some(path)
+    }
+}
+
+/// Floyd-Warshall all-pairs shortest paths over `nodes`
+pub fn floyd_warshall<N, C, FN, IN>(
+    nodes: impl IntoIterator<Item = N>,
+    mut neighbours: FN,
+) -> AllPairs<N, C>
+where
+    N: Clone + Eq + Hash,
+    C: Cost,
+    FN: FnMut(&N) -> IN,
+    IN: IntoIterator<Item = (N, C)>,
+{
+    let nodes: Vec<N> = nodes.into_iter().collect();
+    let index: HashMap<N, usize> = nodes
+        .iter()
+        .cloned()
+        .enumerate()
+        .map(|(i, n)| (n, i))
+        .collect();
+    let n = nodes.len();
+
+    let mut dist: Vec<Vec<Option<C>>> = vec![vec![None; n]; n];
+    let mut next: Vec<Vec<Option<usize>>> = vec![vec![None; n]; n];
+
+    for (u, node) in nodes.iter().enumerate() {
+        for (other, cost) in neighbours(node) {
+            if let Some(&v) = index.get(&other) {
+                if dist[u][v].is_none_or(|d| cost < d) {
+                    dist[u][v] = Some(cost);
+                    next[u][v] = Some(v);
+                }
+            }
+        }
+        dist[u][u] = Some(C::default());
+        next[u][u] = Some(u);
+    }
+
+    for k in 0..n {
+        for i in 0..n {
+            let Some(dik) = dist[i][k] else { continue };
+            for j in 0..n {
+                if let Some(dkj) = dist[k][j] {
+                    let alt = dik + dkj;
+                    if dist[i][j].is_none_or(|d| alt < d) {
+                        dist[i][j] = Some(alt);
+                        next[i][j] = next[i][k];
+                    }
+                }
+            }
+        }
+    }
+
+    AllPairs { nodes, index, dist, next }
+}
+
+/// Every path from `start` to a goal state that never visits a state twice
+pub fn all_simple_paths<S, FN, IN, FG>(start: S, neighbours: FN, is_goal: FG) -> Vec<Vec<S>>
+where
+    S: Clone + Eq + Hash,
+    FN: FnMut(&S) -> IN,
+    IN: IntoIterator<Item = S>,
+    FG: FnMut(&S) -> bool,
+{
+    all_paths_by(start, neighbours, is_goal, |path, next| {
+        !path.contains(next)
+    })
+}
+
+/// Every path from `start` to a goal state, where `can_visit(path, next)` decides
+/// whether the path so far may be extended with `next`.
+///
+/// Paths end as soon as they reach a goal. `can_visit` must eventually refuse
+/// every extension, otherwise the enumeration will never finish.
+pub fn all_paths_by<S, FN, IN, FG, FV>(
+    start: S,
+    mut neighbours: FN,
+    mut is_goal: FG,
+    mut can_visit: FV,
+) -> Vec<Vec<S>>
+where
+    S: Clone + Eq + Hash,
+    FN: FnMut(&S) -> IN,
+    IN: IntoIterator<Item = S>,
+    FG: FnMut(&S) -> bool,
+    FV: FnMut(&[S], &S) -> bool,
+{
+    let mut out = vec![];
+    let mut path = vec![start];
+    // Each level of the stack holds the not-yet-explored neighbours of that path element
+    let mut stack: Vec<Vec<S>> = vec![];
+
+    if is_goal(&path[0]) {
+        return vec![path];
+    }
+    stack.push(neighbours(&path[0]).into_iter().collect());
+
+    while let Some(options) = stack.last_mut() {
+        let Some(next) = options.pop() else {
+            stack.pop();
+            path.pop();
+            continue;
+        };
+
+        if !can_visit(&path, &next) {
+            continue;
+        }
+
+        path.push(next);
+        let last = &path[path.len() - 1];
+        if is_goal(last) {
+            out.push(path.clone());
+            path.pop();
+        } else {
+            stack.push(neighbours(last).into_iter().collect());
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Small weighted graph
+    //   a -1-> b -1-> c -1-> d
+    //   a -5-> d,  b -1-> e (dead end)
+    fn edges(n: &char) -> Vec<(char, u32)> {
+        match n {
+            'a' => vec![('b', 1), ('d', 5)],
+            'b' => vec![('c', 1), ('e', 1)],
+            'c' => vec![('d', 1)],
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn test_dijkstra() {
+        let (path, cost) = dijkstra('a', edges, |&n| n == 'd').unwrap();
+        assert_eq!(path, vec!['a', 'b', 'c', 'd']);
+        assert_eq!(cost, 3);
+
+        assert!(dijkstra('c', edges, |&n| n == 'a').is_none());
+        assert_eq!(dijkstra('a', edges, |&n| n == 'a'), Some((vec!['a'], 0)));
+    }
+
+    #[test]
+    fn test_astar_grid() {
+        // 5x5 open grid with a wall down column 2 except at the bottom
+        let open =
+            |(r, c): (i32, i32)| (0..5).contains(&r) && (0..5).contains(&c) && (c != 2 || r == 4);
+        let neighbours = |&(r, c): &(i32, i32)| {
+            [(r - 1, c), (r + 1, c), (r, c - 1), (r, c + 1)]
+                .into_iter()
+                .filter(|&p| open(p))
+                .map(|p| (p, 1))
+                .collect::<Vec<_>>()
+        };
+        let manhattan = |&(r, c): &(i32, i32)| r.abs_diff(0) + c.abs_diff(4);
+
+        let (path, cost) = astar((0, 0), neighbours, manhattan, |&p| p == (0, 4)).unwrap();
+        assert_eq!(cost, 12);
+        assert_eq!(path.len(), 13);
+        assert!(path.contains(&(4, 2)));
+
+        let (_, d_cost) = dijkstra((0, 0), neighbours, |&p| p == (0, 4)).unwrap();
+        assert_eq!(cost, d_cost);
+    }
+
+    #[test]
+    fn test_dijkstra_all() {
+        let parents = dijkstra_all('a', edges);
+        assert_eq!(parents[&'d'], ('c', 3));
+        assert_eq!(parents[&'e'].1, 2);
+        assert_eq!(
+            reconstruct_path(&parents, &'d').unwrap(),
+            vec!['a', 'b', 'c', 'd']
+        );
+        assert_eq!(reconstruct_path(&parents, &'z'), None);
+    }
+
+    #[test]
+    fn test_bfs() {
+        let unweighted = |n: &char| edges(n).into_iter().map(|(m, _)| m);
+        assert_eq!(bfs('a', unweighted, |&n| n == 'd').unwrap(), vec!['a', 'd']);
+        assert!(bfs('e', unweighted, |&n| n == 'a').is_none());
+
+        let all = bfs_all('a', unweighted);
+        assert_eq!(all.len(), 5);
+        assert_eq!(all[&'c'].1, 2);
+    }
+
+    #[test]
+    fn test_floyd_warshall() {
+        let ap = floyd_warshall("abcde".chars(), edges);
+        assert_eq!(ap.distance(&'a', &'d'), Some(3));
+        assert_eq!(ap.distance(&'d', &'a'), None);
+        assert_eq!(ap.distance(&'e', &'e'), Some(0));
+        assert_eq!(ap.path(&'a', &'d').unwrap(), vec!['a', 'b', 'c', 'd']);
+        assert_eq!(ap.path(&'c', &'a'), None);
+        assert_eq!(ap.index_of(&'c'), Some(2));
+    }
+
+    #[test]
+    fn test_all_simple_paths() {
+        // Undirected square with a diagonal
+        let adj = |n: &u8| match n {
+            0 => vec![1, 3, 2],
+            1 => vec![0, 2],
+            2 => vec![1, 3, 0],
+            3 => vec![0, 2],
+            _ => unreachable!(),
+        };
+        let mut paths = all_simple_paths(0u8, adj, |&n| n == 2);
+        paths.sort();
+        assert_eq!(paths, vec![vec![0, 1, 2], vec![0, 2], vec![0, 3, 2]]);
+
+        // Allow the start to be revisited once
+        let revisit_start = |p: &[u8], n: &u8| {
+            !p.contains(n) || (*n == 0 && p.iter().filter(|&&x| x == 0).count() < 2)
+        };
+        let paths = all_paths_by(0u8, adj, |&n| n == 2, revisit_start);
+        assert_eq!(paths.len(), 7);
+        assert!(paths.contains(&vec![0, 3, 0, 1, 2]));
+    }
+}